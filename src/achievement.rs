@@ -0,0 +1,249 @@
+//! Achievements listed in `achievements.toml`, unlocked by gameplay events
+//! and by how levels are finished, and kept across runs in
+//! `achievements.json`. Each unlock is announced by a toast shown over
+//! whatever screen is up.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{GameError, Result};
+use crate::events::GameEvent;
+use crate::game::Game;
+
+pub const ACHIEVEMENTS: &str = "achievements.toml";
+pub const UNLOCKED: &str = "achievements.json";
+/// How long each toast stays up.
+const TOAST_TIME: Duration = Duration::from_secs(3);
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Achievement {
+    /// Name it is kept under once unlocked.
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(flatten)]
+    pub condition: Condition,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "condition", rename_all = "snake_case")]
+pub enum Condition {
+    /// Finishing a level without dying in it.
+    NoDeaths,
+    /// Finishing a level with every coin in it picked up.
+    AllCoins,
+    /// Finishing a level within the par time set in its info file.
+    ParTime,
+    /// `count` events of one kind across every game played, by name as
+    /// passed to scripts, e.g. "EnemyDefeated".
+    Events { event: String, count: u32 },
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct AchievementFile {
+    #[serde(default, rename = "achievement")]
+    achievements: Vec<Achievement>,
+}
+
+/// What is kept across runs.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Progress {
+    /// Ids of the achievements unlocked.
+    pub unlocked: BTreeSet<String>,
+    /// Events counted toward achievements, by name.
+    pub counts: BTreeMap<String, u32>,
+}
+
+#[derive(Debug, Default)]
+pub struct Achievements {
+    pub definitions: Vec<Achievement>,
+    pub progress: Progress,
+    /// Whether a player died since the last level was finished.
+    died: bool,
+    /// Names of the achievements unlocked and not yet shown, the first one
+    /// showing since `toast_since`.
+    toasts: VecDeque<String>,
+    toast_since: Option<Instant>,
+}
+
+impl Achievements {
+    /// Reads the achievements at `definitions` and the progress toward
+    /// them at `progress`. Missing or unreadable files are left out rather
+    /// than keeping the game from starting.
+    pub fn load(definitions: &str, progress: &str) -> Self {
+        let file: AchievementFile = read(definitions, |contents| toml::from_str(contents).map_err(|error| error.to_string()));
+        Achievements {
+            definitions: file.achievements,
+            progress: read(progress, |contents| serde_json::from_str(contents).map_err(|error| error.to_string())),
+            ..Achievements::default()
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<()> {
+        let contents = serde_json::to_string_pretty(&self.progress)
+            .map_err(|error| GameError::InvalidSave(error.to_string()))?;
+        std::fs::write(path, contents)
+            .map_err(|source| GameError::Io { path: path.to_string(), source })
+    }
+
+    pub fn is_unlocked(&self, achievement: &Achievement) -> bool {
+        self.progress.unlocked.contains(&achievement.id)
+    }
+
+    /// Counts `event` toward the achievements waiting for it.
+    pub fn observe(&mut self, event: &GameEvent) {
+        if *event == GameEvent::PlayerDied {
+            self.died = true;
+        }
+        let name = event.name();
+        let counted = self.definitions.iter()
+            .any(|achievement| matches!(&achievement.condition, Condition::Events { event, .. } if event == name));
+        if !counted {
+            return;
+        }
+        let count = self.progress.counts.entry(name.to_string()).or_default();
+        *count += 1;
+        let count = *count;
+        self.unlock_where(|condition| matches!(condition, Condition::Events { event, count: needed } if event == name && count >= *needed));
+    }
+
+    /// Unlocks what finishing the level of `game` earned and starts
+    /// counting deaths afresh for the next one.
+    pub fn complete_level(&mut self, game: &Game) {
+        let flawless = !self.died;
+        let all_coins = game.total_coins > 0 && game.coins >= game.total_coins;
+        let par = game.info.par.is_some_and(|seconds| game.ticks <= seconds as u64 * 60);
+        self.unlock_where(|condition| match condition {
+            Condition::NoDeaths => { flawless }
+            Condition::AllCoins => { all_coins }
+            Condition::ParTime => { par }
+            Condition::Events { .. } => { false }
+        });
+        self.died = false;
+    }
+
+    /// Forgets deaths from an abandoned run.
+    pub fn start_run(&mut self) {
+        self.died = false;
+    }
+
+    fn unlock_where(&mut self, condition: impl Fn(&Condition) -> bool) {
+        for achievement in &self.definitions {
+            if condition(&achievement.condition) && self.progress.unlocked.insert(achievement.id.clone()) {
+                info!("Unlocked achievement '{}'", achievement.id);
+                self.toasts.push_back(achievement.name.clone());
+            }
+        }
+    }
+
+    /// Name of the achievement to announce now, if any, moving on to the
+    /// next one once a toast has been up long enough.
+    pub fn toast(&mut self) -> Option<&str> {
+        let now = Instant::now();
+        if self.toast_since.is_some_and(|since| now.duration_since(since) >= TOAST_TIME) {
+            self.toasts.pop_front();
+            self.toast_since = None;
+        }
+        if !self.toasts.is_empty() && self.toast_since.is_none() {
+            self.toast_since = Some(now);
+        }
+        self.toasts.front().map(String::as_str)
+    }
+}
+
+/// Parses the file at `path`, or starts from the defaults when it is
+/// missing or can't be read.
+fn read<T: Default>(path: &str, parse: impl Fn(&str) -> std::result::Result<T, String>) -> T {
+    if !Path::new(path).exists() {
+        return T::default();
+    }
+    match std::fs::read_to_string(path).map_err(|error| error.to_string()).and_then(|contents| parse(&contents)) {
+        Ok(value) => { value }
+        Err(error) => {
+            warn!("Ignoring '{}': {}", path, error);
+            T::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definitions::EntityDefinitions;
+    use crate::level;
+
+    fn achievements(toml: &str) -> Achievements {
+        let file: AchievementFile = toml::from_str(toml).unwrap();
+        Achievements { definitions: file.achievements, ..Achievements::default() }
+    }
+
+    fn game() -> Game {
+        Game::from_level(level::parse("...o\n@%%%").unwrap(), &EntityDefinitions::default(), 1)
+    }
+
+    fn unlocked(achievements: &Achievements) -> Vec<&str> {
+        achievements.progress.unlocked.iter().map(String::as_str).collect()
+    }
+
+    #[test]
+    fn parses_the_shipped_achievements() {
+        let file: AchievementFile = toml::from_str(include_str!("../assets/achievements.toml")).unwrap();
+        assert!(!file.achievements.is_empty());
+    }
+
+    #[test]
+    fn counts_events_across_games() {
+        let mut achievements = achievements(r#"
+            [[achievement]]
+            id = "rich"
+            name = "Rich"
+            condition = "events"
+            event = "CoinCollected"
+            count = 2
+        "#);
+        achievements.observe(&GameEvent::CoinCollected { x: 0, y: 0 });
+        achievements.observe(&GameEvent::PlayerJumped { player: 0 });
+        assert!(unlocked(&achievements).is_empty());
+        assert_eq!(achievements.toast(), None);
+        achievements.observe(&GameEvent::CoinCollected { x: 1, y: 0 });
+        assert_eq!(unlocked(&achievements), ["rich"]);
+        assert_eq!(achievements.progress.counts, BTreeMap::from([("CoinCollected".to_string(), 2)]));
+        assert_eq!(achievements.toast(), Some("Rich"));
+    }
+
+    #[test]
+    fn unlocks_for_how_levels_are_finished() {
+        let mut achievements = achievements(r#"
+            [[achievement]]
+            id = "flawless"
+            name = "Flawless"
+            condition = "no_deaths"
+
+            [[achievement]]
+            id = "collector"
+            name = "Collector"
+            condition = "all_coins"
+
+            [[achievement]]
+            id = "on-par"
+            name = "On Par"
+            condition = "par_time"
+        "#);
+        let mut game = game();
+        game.info.par = Some(1);
+        game.ticks = 61;
+        achievements.observe(&GameEvent::PlayerDied);
+        achievements.complete_level(&game);
+        assert!(unlocked(&achievements).is_empty());
+
+        game.coins = game.total_coins;
+        game.ticks = 60;
+        achievements.complete_level(&game);
+        assert_eq!(unlocked(&achievements), ["collector", "flawless", "on-par"]);
+    }
+}
@@ -0,0 +1,70 @@
+//! Achievements screen: lists every achievement, lighting up those
+//! unlocked, until any key goes back to the title screen.
+
+use crate::bindings::Press;
+use crate::context::Context;
+use crate::error::Result;
+use crate::mouse::Mouse;
+use crate::renderer::{Color, Renderer};
+use crate::state::{MenuState, State};
+use crate::text::text_size;
+
+const PIXEL: u32 = 3;
+const DESCRIPTION_PIXEL: u32 = 2;
+
+pub struct AchievementsState {
+    /// Name, description and whether it is unlocked, for every
+    /// achievement in the order they are listed.
+    rows: Vec<(String, String, bool)>,
+}
+
+impl AchievementsState {
+    pub fn new(context: &Context) -> Self {
+        let achievements = &context.achievements;
+        AchievementsState {
+            rows: achievements.definitions.iter()
+                .map(|achievement| (achievement.name.to_uppercase(), achievement.description.to_uppercase(), achievements.is_unlocked(achievement)))
+                .collect(),
+        }
+    }
+}
+
+impl State for AchievementsState {
+    fn name(&self) -> &'static str {
+        "achievements"
+    }
+
+    fn handle_press(self: Box<Self>, _context: &mut Context, _press: &Press) -> Result<Box<dyn State>> {
+        Ok(Box::new(MenuState::new()))
+    }
+
+    fn handle_mouse(self: Box<Self>, _context: &mut Context, mouse: &Mouse) -> Result<Box<dyn State>> {
+        if mouse.is_click() {
+            return Ok(Box::new(MenuState::new()));
+        }
+        Ok(self)
+    }
+
+    fn tick(self: Box<Self>, _context: &mut Context) -> Result<Box<dyn State>> {
+        Ok(self)
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer) -> Result<()> {
+        let (width, height) = renderer.output_size()?;
+        let name_height = text_size("X", PIXEL).1 as i32;
+        let description_height = text_size("X", DESCRIPTION_PIXEL).1 as i32;
+        let row_height = name_height + description_height + name_height;
+        let unlocked = self.rows.iter().filter(|(_, _, unlocked)| *unlocked).count();
+        let title = format!("ACHIEVEMENTS {}/{}", unlocked, self.rows.len());
+        let top = ((height as i32 - row_height * (self.rows.len() as i32 + 1)) / 2).max(4);
+        renderer.draw_text(&title, (width as i32 - text_size(&title, PIXEL).0 as i32) / 2, top, PIXEL, Color::WHITE)?;
+        let left = width as i32 / 6;
+        for (index, (name, description, unlocked)) in self.rows.iter().enumerate() {
+            let y = top + row_height * (index as i32 + 1);
+            let (name_color, description_color) = if *unlocked { (Color::YELLOW, Color::WHITE) } else { (Color::GRAY, Color::GRAY) };
+            renderer.draw_text(name, left, y, PIXEL, name_color)?;
+            renderer.draw_text(description, left, y + name_height + 2, DESCRIPTION_PIXEL, description_color)?;
+        }
+        Ok(())
+    }
+}
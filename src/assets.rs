@@ -0,0 +1,177 @@
+//! Sounds loaded once and shared through handles. Each load of a path
+//! counts as a reference; releasing the last one lets
+//! [`Assets::unload_unused`] free the data, which happens between levels
+//! and when the music changes. Paths go through the mods, so a mod can
+//! replace any asset.
+//!
+//! The audio converts what it plays into its own output format, so sound
+//! effects are released as soon as they are handed to it, and music is
+//! held only while its track plays.
+//!
+//! There are no image or font assets: everything is drawn with shapes and
+//! the built-in bitmap font, so the renderer never creates textures.
+
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::path::Path;
+use std::sync::Arc;
+
+use log::{debug, info};
+use sdl2::audio::{AudioFormat, AudioSpecWAV};
+
+use crate::error::{GameError, Result};
+use crate::mods::Mods;
+
+pub struct Sound {
+    pub freq: i32,
+    pub format: AudioFormat,
+    pub channels: u8,
+    pub samples: Vec<u8>,
+}
+
+pub struct Handle<T> {
+    index: u32,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+    }
+}
+
+impl<T> std::fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Handle({})", self.index)
+    }
+}
+
+struct Entry<T> {
+    path: String,
+    asset: Option<Arc<T>>,
+    references: u32,
+}
+
+/// Assets of one kind. Entries are never removed, so a handle stays tied
+/// to its path and loading the path again after an unload reuses it.
+pub struct Store<T> {
+    entries: Vec<Entry<T>>,
+    by_path: HashMap<String, u32>,
+}
+
+impl<T> Store<T> {
+    pub fn new() -> Self {
+        Store { entries: Vec::new(), by_path: HashMap::new() }
+    }
+
+    /// Returns the cached asset for `path`, calling `loader` only when it
+    /// is not in memory.
+    pub fn load(&mut self, path: &str, loader: impl FnOnce(&str) -> Result<T>) -> Result<Handle<T>> {
+        let index = match self.by_path.get(path) {
+            Some(&index) => { index }
+            None => {
+                let index = self.entries.len() as u32;
+                self.entries.push(Entry { path: path.to_string(), asset: None, references: 0 });
+                self.by_path.insert(path.to_string(), index);
+                index
+            }
+        };
+        let entry = &mut self.entries[index as usize];
+        if entry.asset.is_none() {
+            entry.asset = Some(Arc::new(loader(path)?));
+            debug!("Loaded asset '{}'", path);
+        }
+        entry.references += 1;
+        Ok(Handle { index, marker: PhantomData })
+    }
+
+    pub fn get(&self, handle: Handle<T>) -> Option<&Arc<T>> {
+        self.entries.get(handle.index as usize)?.asset.as_ref()
+    }
+
+    /// Gives up one reference taken by [`Store::load`].
+    pub fn release(&mut self, handle: Handle<T>) {
+        if let Some(entry) = self.entries.get_mut(handle.index as usize) {
+            entry.references = entry.references.saturating_sub(1);
+        }
+    }
+
+    /// Drops every asset nobody holds a reference to and returns how many.
+    pub fn unload_unused(&mut self) -> usize {
+        let mut unloaded = 0;
+        for entry in &mut self.entries {
+            if entry.references == 0 && entry.asset.take().is_some() {
+                debug!("Unloaded asset '{}'", entry.path);
+                unloaded += 1;
+            }
+        }
+        unloaded
+    }
+}
+
+impl<T> Default for Store<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Default)]
+pub struct Assets {
+    pub sounds: Store<Sound>,
+}
+
+impl Assets {
+    pub fn new() -> Self {
+        Assets::default()
+    }
+
+    /// Loads a WAV sound.
+    pub fn load_sound(&mut self, mods: &Mods, path: &str) -> Result<Handle<Sound>> {
+        self.sounds.load(&mods.resolve(path), |path| {
+            check_exists(path)?;
+            let wav = AudioSpecWAV::load_wav(path)
+                .map_err(|error| GameError::InvalidAsset(format!("{}: {}", path, error)))?;
+            Ok(Sound {
+                freq: wav.freq,
+                format: wav.format,
+                channels: wav.channels,
+                samples: wav.buffer().to_vec(),
+            })
+        })
+    }
+
+    /// Frees everything no longer referenced.
+    pub fn unload_unused(&mut self) {
+        let unloaded = self.sounds.unload_unused();
+        if unloaded > 0 {
+            info!("Unloaded {} unused assets", unloaded);
+        }
+    }
+}
+
+fn check_exists(path: &str) -> Result<()> {
+    if Path::new(path).exists() {
+        return Ok(());
+    }
+    Err(GameError::Io {
+        path: path.to_string(),
+        source: std::io::Error::from(std::io::ErrorKind::NotFound),
+    })
+}
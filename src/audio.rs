@@ -0,0 +1,675 @@
+//! Sound output. [`Audio`] mixes sounds itself on top of SDL's audio
+//! callback: one channel for music and [`SFX_CHANNELS`] for sound effects,
+//! all played from samples converted once to the device format. Sounds are
+//! loaded and played by id. Music crossfades from one track to the next,
+//! and its [`STEMS`], when a track has them, play over it louder the
+//! tenser the game gets. Without an audio device, or headless, there is
+//! no [`Audio`] at all.
+//!
+//! Gameplay never plays sounds itself: [`SoundCues`] listens to the game
+//! events and queues the sound of each, which [`Audio::play_cues`] plays
+//! once a frame.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use log::{info, warn};
+use sdl2::audio::{AudioCVT, AudioCallback, AudioDevice, AudioFormat, AudioSpecDesired};
+use sdl2::Sdl;
+use serde::Deserialize;
+
+use crate::assets::Sound;
+use crate::error::{GameError, Result};
+use crate::components::{Body, Position};
+use crate::events::{GameEvent, Subscriber};
+use crate::game::Game;
+use crate::enemy::Enemy;
+use crate::player::{Defeated, Health, Player};
+use crate::settings::AudioSettings;
+
+/// Sound effects of the game, by id: the WAV file and volume of each.
+pub const SOUNDS: &str = "sounds.toml";
+
+/// Sound effects that can play at once; the one furthest along stops
+/// for a new one past that.
+pub const SFX_CHANNELS: usize = 8;
+/// Share of its volume music keeps while ducked.
+pub const DUCKED_VOLUME: f32 = 0.3;
+/// Tiles from the listener past which sounds stop getting quieter.
+const HEARING_DISTANCE: f32 = 30.0;
+/// Share of their volume the furthest sounds keep.
+const FAR_VOLUME: f32 = 0.25;
+/// Layers a track of music can have over its base, as files named after
+/// it: `caves.danger.wav` over `caves.wav`. Danger grows as players run
+/// low on health or out of time, chase as enemies close in on them.
+pub const STEMS: [&str; 2] = ["danger", "chase"];
+/// Seconds a stem takes to fade fully in or out.
+const STEM_FADE: f32 = 0.5;
+/// Tiles between a player and an enemy at which the chase stem plays at
+/// full volume, and past which it doesn't play.
+const CHASE_NEAR: f32 = 3.0;
+const CHASE_FAR: f32 = 10.0;
+/// Share of the par time after which the danger stem starts to come in.
+const PAR_WARNING: f32 = 0.75;
+const FREQUENCY: i32 = 44100;
+const BUFFER_SAMPLES: u16 = 1024;
+
+/// A sound playing on a channel.
+struct Voice {
+    samples: Arc<[f32]>,
+    position: usize,
+    looped: bool,
+    /// Volume of the voice on the left and right speaker, the same on
+    /// both for mono output.
+    gains: [f32; 2],
+    /// Channels of the output, which `samples` are interleaved for.
+    channels: usize,
+}
+
+/// A track of music playing: its base and the stems it has.
+struct Song {
+    base: Voice,
+    stems: [Option<Voice>; STEMS.len()],
+}
+
+/// What the audio callback plays, shared with the game through the
+/// device lock.
+struct Mixer {
+    music: Option<Song>,
+    /// Music fading out under `music` and its volume before it started to.
+    fading: Option<(Song, f32)>,
+    /// Samples a crossfade lasts and those of it already played.
+    fade_length: usize,
+    fade_played: usize,
+    /// Volume of each stem as a share of the music volume, and the one it
+    /// fades toward.
+    stem_levels: [f32; STEMS.len()],
+    stem_targets: [f32; STEMS.len()],
+    /// Share of its volume a stem fades by per sample.
+    stem_step: f32,
+    effects: [Option<Voice>; SFX_CHANNELS],
+    /// Volume of the music and of the sound effects, from 0 to 1, the
+    /// master volume included.
+    music_volume: f32,
+    effect_volume: f32,
+}
+
+/// How tense the game is, as the volume of each of the [`STEMS`] from 0
+/// to 1.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Intensity(pub [f32; STEMS.len()]);
+
+impl Intensity {
+    pub fn of(game: &Game) -> Intensity {
+        // Center of every player still in the game and whether they are low on health.
+        let players: Vec<(f32, f32, bool)> = game.world.query::<(&Position, &Body, &Health)>().with::<&Player>().without::<&Defeated>().iter()
+            .map(|(position, body, health)| {
+                (position.x + body.width / 2.0, position.y + body.height / 2.0, health.max > 1 && health.hp * 3 <= health.max)
+            })
+            .collect();
+        let low_health = players.iter().any(|player| player.2);
+        let time = game.info.par.filter(|par| *par > 0).map_or(0.0, |par| {
+            let used = game.ticks as f32 / 60.0 / par as f32;
+            ((used - PAR_WARNING) / (1.0 - PAR_WARNING)).clamp(0.0, 1.0)
+        });
+        let danger = if low_health { 1.0 } else { time };
+
+        let nearest = game.world.query::<(&Position, &Body)>().with::<&Enemy>().without::<&Defeated>().iter()
+            .flat_map(|(position, body)| {
+                let enemy = (position.x + body.width / 2.0, position.y + body.height / 2.0);
+                players.iter().map(move |player| (player.0 - enemy.0).hypot(player.1 - enemy.1))
+            })
+            .fold(f32::INFINITY, f32::min);
+        let chase = ((CHASE_FAR - nearest) / (CHASE_FAR - CHASE_NEAR)).clamp(0.0, 1.0);
+        Intensity([danger, chase])
+    }
+}
+
+/// File of the stem `name` of the music in `track`.
+pub fn stem_file(track: &str, name: &str) -> String {
+    match track.rsplit_once('.').filter(|(_, extension)| !extension.contains('/')) {
+        Some((file, extension)) => { format!("{}.{}.{}", file, name, extension) }
+        None => { format!("{}.{}", track, name) }
+    }
+}
+
+/// Music a state plays, see [`crate::state::State::music`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Music {
+    #[default]
+    Off,
+    /// Loops the track at full volume.
+    Playing(String),
+    /// Loops the track quieter, under a menu.
+    Ducked(String),
+}
+
+impl Music {
+    pub fn track(&self) -> Option<&str> {
+        match self {
+            Music::Off => { None }
+            Music::Playing(track) | Music::Ducked(track) => { Some(track) }
+        }
+    }
+
+    /// The same track ducked.
+    pub fn ducked(self) -> Music {
+        match self {
+            Music::Playing(track) => { Music::Ducked(track) }
+            music => { music }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct SoundInfo {
+    pub file: String,
+    /// From 0 to 1.
+    #[serde(default = "full_volume")]
+    pub volume: f32,
+}
+
+fn full_volume() -> f32 {
+    1.0
+}
+
+/// Every sound listed in [`SOUNDS`], by id.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(transparent)]
+pub struct SoundBank(pub BTreeMap<String, SoundInfo>);
+
+impl SoundBank {
+    pub fn load(path: &str) -> Self {
+        if !Path::new(path).exists() {
+            return SoundBank::default();
+        }
+        let bank = std::fs::read_to_string(path)
+            .map_err(|error| error.to_string())
+            .and_then(|contents| toml::from_str(&contents).map_err(|error| error.to_string()));
+        match bank {
+            Ok(bank) => { bank }
+            Err(error) => {
+                warn!("Ignoring sounds in '{}': {}", path, error);
+                SoundBank::default()
+            }
+        }
+    }
+}
+
+/// Sound played for a game event, if it has one.
+pub fn cue(event: &GameEvent) -> Option<&'static str> {
+    match event {
+        GameEvent::PlayerJumped { .. } => { Some("jump") }
+        GameEvent::Footstep { material, .. } => { Some(material.footstep()) }
+        GameEvent::PlayerLanded { .. } => { Some("land") }
+        GameEvent::CoinCollected { .. } => { Some("coin") }
+        GameEvent::PlayerHurt { .. } => { Some("hurt") }
+        GameEvent::PlayerDied => { Some("death") }
+        GameEvent::LevelCompleted => { Some("level_complete") }
+        GameEvent::TurretFired { .. } => { Some("shot") }
+        _ => { None }
+    }
+}
+
+/// A sound waiting to be played and the tile it comes from, if it comes
+/// from somewhere in particular.
+type Cue = (&'static str, Option<(usize, usize)>);
+
+/// Queues the sound of every event it hears for [`Audio::play_cues`].
+pub struct SoundCues {
+    queue: Rc<RefCell<Vec<Cue>>>,
+}
+
+impl Subscriber for SoundCues {
+    fn on_event(&mut self, event: &GameEvent) {
+        if let Some(id) = cue(event) {
+            self.queue.borrow_mut().push((id, event.tile()));
+        }
+    }
+}
+
+/// Where sounds are heard from: the middle of the players still in the
+/// game. The whole level is in view, so sounds are panned by where they
+/// are across it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Listener {
+    pub x: f32,
+    pub y: f32,
+    /// Width of the level in tiles.
+    pub width: f32,
+}
+
+impl Listener {
+    pub fn of(game: &Game) -> Option<Listener> {
+        let players: Vec<(f32, f32)> = game.world.query::<(&Position, &Body)>().with::<&Player>().without::<&Defeated>().iter()
+            .map(|(position, body)| (position.x + body.width / 2.0, position.y + body.height / 2.0))
+            .collect();
+        if players.is_empty() {
+            return None;
+        }
+        let count = players.len() as f32;
+        Some(Listener {
+            x: players.iter().map(|player| player.0).sum::<f32>() / count,
+            y: players.iter().map(|player| player.1).sum::<f32>() / count,
+            width: game.playground.width as f32,
+        })
+    }
+
+    /// Volume on the left and right speaker of a sound from `tile`: panned
+    /// by how far left or right of the listener it is across the level,
+    /// and quieter the further away it is.
+    pub fn gains(&self, tile: (usize, usize)) -> [f32; 2] {
+        let (x, y) = (tile.0 as f32 + 0.5 - self.x, tile.1 as f32 + 0.5 - self.y);
+        let pan = (x / (self.width / 2.0).max(1.0)).clamp(-1.0, 1.0);
+        let volume = (1.0 - x.hypot(y) / HEARING_DISTANCE).max(FAR_VOLUME);
+        // Equal power panning, at full volume on both sides in the middle.
+        let angle = (pan + 1.0) * std::f32::consts::FRAC_PI_4;
+        [angle.cos() * std::f32::consts::SQRT_2 * volume, angle.sin() * std::f32::consts::SQRT_2 * volume]
+    }
+}
+
+impl Voice {
+    /// Adds the next samples of the voice to `out`, its volume going from
+    /// `from` to `to` across them. Returns false once a voice that doesn't
+    /// loop is over.
+    fn mix(&mut self, out: &mut [f32], from: f32, to: f32) -> bool {
+        let step = (to - from) / out.len().max(1) as f32;
+        for (index, sample) in out.iter_mut().enumerate() {
+            let volume = from + step * index as f32;
+            if self.position >= self.samples.len() {
+                if !self.looped || self.samples.is_empty() {
+                    return false;
+                }
+                self.position = 0;
+            }
+            let gain = if self.channels == 2 { self.gains[self.position % 2] } else { (self.gains[0] + self.gains[1]) / 2.0 };
+            *sample += self.samples[self.position] * volume * gain;
+            self.position += 1;
+        }
+        true
+    }
+}
+
+impl Song {
+    /// Adds the next samples of the song to `out` at a volume going from
+    /// `from` to `to`, each stem at its share of it. Returns false once
+    /// the base is over.
+    fn mix(&mut self, out: &mut [f32], from: f32, to: f32, levels: &[(f32, f32); STEMS.len()]) -> bool {
+        for (stem, (level_from, level_to)) in self.stems.iter_mut().zip(levels) {
+            if let Some(stem) = stem.as_mut().filter(|_| *level_from > 0.0 || *level_to > 0.0) {
+                stem.mix(out, from * level_from, to * level_to);
+            } else if let Some(stem) = stem {
+                // Silent stems keep their place to stay in time with the base.
+                stem.position = (stem.position + out.len()) % stem.samples.len().max(1);
+            }
+        }
+        self.base.mix(out, from, to)
+    }
+}
+
+impl AudioCallback for Mixer {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        out.fill(0.0);
+        let from = self.fade_progress();
+        self.fade_played = (self.fade_played + out.len()).min(self.fade_length);
+        let to = self.fade_progress();
+        let levels = self.step_stems(out.len());
+        if self.fading.as_mut().is_some_and(|(song, volume)| !song.mix(out, *volume * (1.0 - from), *volume * (1.0 - to), &levels) || to >= 1.0) {
+            self.fading = None;
+        }
+        if self.music.as_mut().is_some_and(|song| !song.mix(out, self.music_volume * from, self.music_volume * to, &levels)) {
+            self.music = None;
+        }
+        for channel in self.effects.iter_mut() {
+            if channel.as_mut().is_some_and(|voice| !voice.mix(out, self.effect_volume, self.effect_volume)) {
+                *channel = None;
+            }
+        }
+        for sample in out.iter_mut() {
+            *sample = sample.clamp(-1.0, 1.0);
+        }
+    }
+}
+
+impl Mixer {
+    fn new(settings: &AudioSettings, output: Output) -> Self {
+        let (music_volume, effect_volume) = volumes(settings);
+        let fade_length = fade_length(settings, output);
+        Mixer {
+            music: None,
+            fading: None,
+            fade_length,
+            fade_played: fade_length,
+            stem_levels: [0.0; STEMS.len()],
+            stem_targets: [0.0; STEMS.len()],
+            stem_step: 1.0 / (STEM_FADE * output.frequency as f32 * output.channels as f32),
+            effects: Default::default(),
+            music_volume,
+            effect_volume,
+        }
+    }
+
+    /// How far along the crossfade is, from 0 to 1.
+    fn fade_progress(&self) -> f32 {
+        if self.fade_length == 0 { 1.0 } else { self.fade_played as f32 / self.fade_length as f32 }
+    }
+
+    /// Fades every stem toward its target over `samples`, returning the
+    /// level of each before and after.
+    fn step_stems(&mut self, samples: usize) -> [(f32, f32); STEMS.len()] {
+        let step = self.stem_step * samples as f32;
+        let mut levels = [(0.0, 0.0); STEMS.len()];
+        for ((level, target), levels) in self.stem_levels.iter_mut().zip(self.stem_targets).zip(levels.iter_mut()) {
+            let from = *level;
+            *level = if *level < target { (*level + step).min(target) } else { (*level - step).max(target) };
+            *levels = (from, *level);
+        }
+        levels
+    }
+
+    /// Fades the music playing out and `music` in, at the same volume.
+    fn crossfade(&mut self, music: Option<Song>) {
+        let volume = self.music_volume * self.fade_progress();
+        self.fading = self.music.take().map(|voice| (voice, volume));
+        self.music = music;
+        self.fade_played = 0;
+    }
+}
+
+/// Format the device plays, which loaded sounds are converted to.
+#[derive(Copy, Clone, Debug)]
+struct Output {
+    frequency: i32,
+    channels: u8,
+}
+
+pub struct Audio {
+    device: AudioDevice<Mixer>,
+    output: Output,
+    /// Samples and volume of every sound loaded.
+    sounds: HashMap<String, (Arc<[f32]>, f32)>,
+    music: Music,
+    settings: AudioSettings,
+    cues: Rc<RefCell<Vec<Cue>>>,
+    listener: Option<Listener>,
+    intensity: Intensity,
+}
+
+impl Audio {
+    /// Opens the default audio device, none when there is none.
+    pub fn open(sdl: &Sdl, settings: &AudioSettings) -> Option<Self> {
+        match open_device(sdl, settings) {
+            Ok((device, output)) => {
+                Some(Audio {
+                    device,
+                    output,
+                    sounds: HashMap::new(),
+                    music: Music::Off,
+                    settings: settings.clone(),
+                    cues: Rc::default(),
+                    listener: None,
+                    intensity: Intensity::default(),
+                })
+            }
+            Err(error) => {
+                warn!("Sound is off, no audio device: {}", error);
+                None
+            }
+        }
+    }
+
+    /// Converts `sound` to the device format and keeps it as `id` to play
+    /// at `volume`, replacing any sound loaded as `id` before.
+    pub fn load(&mut self, id: &str, sound: &Sound, volume: f32) -> Result<()> {
+        let output = self.output;
+        let convert = AudioCVT::new(sound.format, sound.channels, sound.freq, AudioFormat::F32LSB, output.channels, output.frequency)
+            .map_err(|error| GameError::InvalidAsset(format!("sound {}: {}", id, error)))?;
+        let bytes = convert.convert(sound.samples.clone());
+        let samples: Arc<[f32]> = bytes.chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect();
+        self.sounds.insert(id.to_string(), (samples, volume));
+        Ok(())
+    }
+
+    /// Forgets the sound `id`; playing voices keep their samples until
+    /// they end.
+    pub fn unload(&mut self, id: &str) {
+        self.sounds.remove(id);
+    }
+
+    pub fn is_loaded(&self, id: &str) -> bool {
+        self.sounds.contains_key(id)
+    }
+
+    /// Plays the sound `id` once as a sound effect.
+    pub fn play(&mut self, id: &str) {
+        self.play_at(id, [1.0, 1.0]);
+    }
+
+    /// Plays the sound `id` once as a sound effect, at `gains` on the left
+    /// and right speaker.
+    pub fn play_at(&mut self, id: &str, gains: [f32; 2]) {
+        let Some(mut voice) = self.voice(id, false) else { return };
+        voice.gains = [voice.gains[0] * gains[0], voice.gains[1] * gains[1]];
+        let mut mixer = self.device.lock();
+        let channel = match mixer.effects.iter().position(Option::is_none) {
+            Some(free) => { free }
+            None => {
+                (0..SFX_CHANNELS)
+                    .max_by_key(|channel| mixer.effects[*channel].as_ref().map_or(0, |voice| voice.position))
+                    .unwrap_or_default()
+            }
+        };
+        mixer.effects[channel] = Some(voice);
+    }
+
+    /// Loops the sound `id` as music, crossfading from the music playing,
+    /// with the stems of `id` loaded under their [`stem_file`].
+    pub fn play_music(&mut self, id: &str) {
+        let Some(base) = self.voice(id, true) else { return };
+        let stems = STEMS.map(|name| {
+            let stem = stem_file(id, name);
+            self.is_loaded(&stem).then(|| self.voice(&stem, true)).flatten()
+        });
+        self.device.lock().crossfade(Some(Song { base, stems }));
+    }
+
+    /// Fades the music out.
+    pub fn stop_music(&mut self) {
+        self.device.lock().crossfade(None);
+    }
+
+    /// Music set last with [`Audio::set_music`].
+    pub fn music(&self) -> &Music {
+        &self.music
+    }
+
+    /// Starts, stops or ducks the music, carrying on with the track
+    /// playing when it stays the same.
+    pub fn set_music(&mut self, music: Music) {
+        if music == self.music {
+            return;
+        }
+        match music.track() {
+            Some(track) if self.music.track() != Some(track) => { self.play_music(track) }
+            Some(_) => {}
+            None => { self.stop_music() }
+        }
+        self.music = music;
+        self.apply_volume();
+    }
+
+    /// Stops every sound effect playing.
+    pub fn stop_effects(&mut self) {
+        self.device.lock().effects = Default::default();
+    }
+
+    /// Plays everything at the volumes of `settings` from now on.
+    pub fn set_volume(&mut self, settings: &AudioSettings) {
+        self.settings = settings.clone();
+        self.apply_volume();
+    }
+
+    fn apply_volume(&mut self) {
+        let (music, effects) = volumes(&self.settings);
+        let ducking = if matches!(self.music, Music::Ducked(_)) { DUCKED_VOLUME } else { 1.0 };
+        let fade_length = fade_length(&self.settings, self.output);
+        let mut mixer = self.device.lock();
+        mixer.music_volume = music * ducking;
+        mixer.effect_volume = effects;
+        mixer.fade_length = fade_length;
+    }
+
+    /// Subscriber queuing the sounds of game events for this audio.
+    pub fn cues(&self) -> SoundCues {
+        SoundCues { queue: self.cues.clone() }
+    }
+
+    /// Hears sounds from `listener` from now on, or from nowhere in
+    /// particular when there is none.
+    pub fn set_listener(&mut self, listener: Option<Listener>) {
+        self.listener = listener;
+    }
+
+    /// Fades the stems of the music toward the volumes of `intensity`.
+    pub fn set_intensity(&mut self, intensity: Intensity) {
+        if intensity == self.intensity {
+            return;
+        }
+        self.intensity = intensity;
+        self.device.lock().stem_targets = intensity.0;
+    }
+
+    /// Plays the sounds queued by [`SoundCues`] since the last call, placed
+    /// around the listener.
+    pub fn play_cues(&mut self) {
+        let cues = std::mem::take(&mut *self.cues.borrow_mut());
+        for (id, tile) in cues {
+            if !self.is_loaded(id) {
+                continue;
+            }
+            let gains = match (self.listener, tile) {
+                (Some(listener), Some(tile)) => { listener.gains(tile) }
+                _ => { [1.0, 1.0] }
+            };
+            self.play_at(id, gains);
+        }
+    }
+
+    fn voice(&self, id: &str, looped: bool) -> Option<Voice> {
+        match self.sounds.get(id) {
+            Some((samples, volume)) => {
+                Some(Voice { samples: samples.clone(), position: 0, looped, gains: [*volume; 2], channels: self.output.channels as usize })
+            }
+            None => {
+                warn!("Can't play sound '{}', it isn't loaded", id);
+                None
+            }
+        }
+    }
+}
+
+fn open_device(sdl: &Sdl, settings: &AudioSettings) -> std::result::Result<(AudioDevice<Mixer>, Output), String> {
+    let subsystem = sdl.audio()?;
+    let desired = AudioSpecDesired { freq: Some(FREQUENCY), channels: Some(2), samples: Some(BUFFER_SAMPLES) };
+    let mut output = Output { frequency: FREQUENCY, channels: 2 };
+    let device = subsystem.open_playback(None, &desired, |spec| {
+        output = Output { frequency: spec.freq, channels: spec.channels };
+        Mixer::new(settings, output)
+    })?;
+    device.resume();
+    info!("Opened audio device '{}' at {} Hz", subsystem.current_audio_driver(), output.frequency);
+    Ok((device, output))
+}
+
+/// Volume of the music and of the sound effects from 0 to 1, the master
+/// volume included.
+fn volumes(settings: &AudioSettings) -> (f32, f32) {
+    if settings.muted {
+        return (0.0, 0.0);
+    }
+    let share = |volume: u8| volume.min(100) as f32 / 100.0;
+    (share(settings.volume) * share(settings.music), share(settings.volume) * share(settings.effects))
+}
+
+/// Samples of output a crossfade lasts.
+fn fade_length(settings: &AudioSettings, output: Output) -> usize {
+    (settings.crossfade.max(0.0) * output.frequency as f32) as usize * output.channels as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One sample per second of mono output, so a crossfade of `n` seconds
+    /// lasts `n` samples.
+    const OUTPUT: Output = Output { frequency: 1, channels: 1 };
+
+    fn voice(sample: f32, looped: bool) -> Voice {
+        Voice { samples: vec![sample; 2].into(), position: 0, looped, gains: [1.0; 2], channels: 1 }
+    }
+
+    fn song(sample: f32) -> Song {
+        Song { base: voice(sample, true), stems: Default::default() }
+    }
+
+    fn mix(mixer: &mut Mixer, samples: usize) -> Vec<f32> {
+        let mut out = vec![0.0; samples];
+        mixer.callback(&mut out);
+        out
+    }
+
+    #[test]
+    fn clips_what_adds_up_past_full_scale() {
+        let mut mixer = Mixer::new(&AudioSettings::default(), OUTPUT);
+        mixer.effects[0] = Some(voice(0.8, false));
+        mixer.effects[1] = Some(voice(0.8, false));
+        mixer.effects[2] = Some(voice(-0.3, false));
+        assert_eq!(mix(&mut mixer, 2), [1.0, 1.0]);
+        mixer.effects[0] = Some(voice(-0.8, false));
+        mixer.effects[1] = Some(voice(-0.8, false));
+        assert_eq!(mix(&mut mixer, 2), [-1.0, -1.0]);
+        // Voices that ran out are freed and leave silence.
+        assert_eq!(mix(&mut mixer, 2), [0.0, 0.0]);
+        assert!(mixer.effects.iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn scales_by_the_master_and_channel_volumes() {
+        let settings = AudioSettings { volume: 50, music: 20, effects: 80, ..AudioSettings::default() };
+        assert_eq!(volumes(&settings), (0.1, 0.4));
+        assert_eq!(volumes(&AudioSettings { volume: 200, ..settings.clone() }), (0.2, 0.8));
+        assert_eq!(volumes(&AudioSettings { muted: true, ..settings.clone() }), (0.0, 0.0));
+
+        let mut mixer = Mixer::new(&settings, OUTPUT);
+        mixer.effects[0] = Some(voice(0.5, false));
+        assert_eq!(mix(&mut mixer, 1), [0.2]);
+        mixer.effects[0] = None;
+        mixer.music = Some(song(0.5));
+        assert_eq!(mix(&mut mixer, 1), [0.05]);
+    }
+
+    #[test]
+    fn crossfades_from_one_track_to_the_next() {
+        let settings = AudioSettings { crossfade: 4.0, ..AudioSettings::default() };
+        let mut mixer = Mixer::new(&settings, OUTPUT);
+        mixer.music = Some(song(1.0));
+        assert_eq!(mix(&mut mixer, 1), [1.0]);
+        mixer.crossfade(Some(song(0.2)));
+        // The old track goes from full to silent as the new one comes in.
+        assert_eq!(mix(&mut mixer, 4), [1.0, 0.8, 0.6, 0.4]);
+        assert!(mixer.fading.is_none());
+        assert_eq!(mix(&mut mixer, 2), [0.2, 0.2]);
+
+        let mut cut = Mixer::new(&AudioSettings { crossfade: 0.0, ..settings }, OUTPUT);
+        cut.music = Some(song(1.0));
+        cut.crossfade(Some(song(0.2)));
+        assert_eq!(mix(&mut cut, 2), [0.2, 0.2]);
+    }
+}
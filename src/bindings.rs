@@ -0,0 +1,357 @@
+//! Logical actions and the keys bound to them. States react to actions
+//! rather than keys, so any input device can drive them. Bindings are
+//! stored in the `[keys]` section of the settings file and can be changed
+//! from the controls screen; the menu actions use fixed keys.
+
+use sdl2::keyboard::Keycode;
+use serde::{Deserialize, Serialize};
+
+use crate::settings::KeyBindings;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    Jump,
+    Shoot,
+    SecondMoveLeft,
+    SecondMoveRight,
+    SecondJump,
+    SecondShoot,
+    Pause,
+    QuickSave,
+    QuickLoad,
+    DebugOverlay,
+    InputDisplay,
+    FrameStep,
+    Step,
+    Console,
+    Talk,
+    Fetch,
+    Mute,
+    Achievements,
+    /// Menu actions, bound to fixed keys.
+    Confirm,
+    Back,
+    Up,
+    Down,
+}
+
+impl Action {
+    /// Actions the player can rebind, in the order the controls screen lists them.
+    pub const BINDABLE: [Action; 20] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Jump,
+        Action::Shoot,
+        Action::SecondMoveLeft,
+        Action::SecondMoveRight,
+        Action::SecondJump,
+        Action::SecondShoot,
+        Action::Pause,
+        Action::QuickSave,
+        Action::QuickLoad,
+        Action::DebugOverlay,
+        Action::InputDisplay,
+        Action::FrameStep,
+        Action::Step,
+        Action::Console,
+        Action::Talk,
+        Action::Fetch,
+        Action::Mute,
+        Action::Achievements,
+    ];
+
+    /// Actions held through gameplay, the only ones the settings can make
+    /// toggles.
+    pub const TOGGLEABLE: [Action; 8] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Jump,
+        Action::Shoot,
+        Action::SecondMoveLeft,
+        Action::SecondMoveRight,
+        Action::SecondJump,
+        Action::SecondShoot,
+    ];
+
+    /// This action as done by player number `player`, for devices that
+    /// are the same for every player such as controllers.
+    pub fn for_player(self, player: usize) -> Action {
+        match (player, self) {
+            (1, Action::MoveLeft) => { Action::SecondMoveLeft }
+            (1, Action::MoveRight) => { Action::SecondMoveRight }
+            (1, Action::Jump) => { Action::SecondJump }
+            (1, Action::Shoot) => { Action::SecondShoot }
+            _ => { self }
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Action::MoveLeft => { "MOVE LEFT" }
+            Action::MoveRight => { "MOVE RIGHT" }
+            Action::Jump => { "JUMP" }
+            Action::Shoot => { "SHOOT" }
+            Action::SecondMoveLeft => { "P2 LEFT" }
+            Action::SecondMoveRight => { "P2 RIGHT" }
+            Action::SecondJump => { "P2 JUMP" }
+            Action::SecondShoot => { "P2 SHOOT" }
+            Action::Pause => { "PAUSE" }
+            Action::QuickSave => { "QUICK SAVE" }
+            Action::QuickLoad => { "QUICK LOAD" }
+            Action::DebugOverlay => { "DEBUG OVERLAY" }
+            Action::InputDisplay => { "INPUT DISPLAY" }
+            Action::FrameStep => { "FRAME STEP" }
+            Action::Step => { "STEP" }
+            Action::Console => { "CONSOLE" }
+            Action::Talk => { "TALK" }
+            Action::Fetch => { "FETCH" }
+            Action::Mute => { "MUTE" }
+            Action::Achievements => { "ACHIEVEMENTS" }
+            Action::Confirm => { "CONFIRM" }
+            Action::Back => { "BACK" }
+            Action::Up => { "UP" }
+            Action::Down => { "DOWN" }
+        }
+    }
+}
+
+/// Ready-made movement keys for common layouts, picked on the controls screen.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Preset {
+    Wasd,
+    Arrows,
+    /// WASD for AZERTY keyboards.
+    Zqsd,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 3] = [Preset::Wasd, Preset::Arrows, Preset::Zqsd];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Preset::Wasd => { "WASD + SPACE" }
+            Preset::Arrows => { "ARROWS + Z" }
+            Preset::Zqsd => { "ZQSD + SPACE" }
+        }
+    }
+
+    /// Movement keys of both players; the second player gets whichever
+    /// side of the keyboard the first one leaves free.
+    fn keys(&self) -> [(Action, Keycode); 8] {
+        let (first, second) = match self {
+            Preset::Wasd => { ([Keycode::A, Keycode::D, Keycode::Space, Keycode::F], [Keycode::Left, Keycode::Right, Keycode::Up, Keycode::RShift]) }
+            Preset::Arrows => { ([Keycode::Left, Keycode::Right, Keycode::Z, Keycode::X], [Keycode::A, Keycode::D, Keycode::W, Keycode::F]) }
+            Preset::Zqsd => { ([Keycode::Q, Keycode::D, Keycode::Space, Keycode::F], [Keycode::Left, Keycode::Right, Keycode::Up, Keycode::RShift]) }
+        };
+        [
+            (Action::MoveLeft, first[0]),
+            (Action::MoveRight, first[1]),
+            (Action::Jump, first[2]),
+            (Action::Shoot, first[3]),
+            (Action::SecondMoveLeft, second[0]),
+            (Action::SecondMoveRight, second[1]),
+            (Action::SecondJump, second[2]),
+            (Action::SecondShoot, second[3]),
+        ]
+    }
+
+    /// The preset after this one, wrapping around.
+    pub fn next(&self) -> Preset {
+        let index = Preset::ALL.iter().position(|preset| preset == self).unwrap_or_default();
+        Preset::ALL[(index + 1) % Preset::ALL.len()]
+    }
+}
+
+/// A key or button press, with the actions it triggers.
+#[derive(Clone, Debug)]
+pub struct Press {
+    /// The key pressed, for screens that work with keys themselves. `None`
+    /// for presses that come from other devices.
+    pub keycode: Option<Keycode>,
+    pub actions: Vec<Action>,
+}
+
+impl Press {
+    /// A press of `action` that did not come from a key.
+    pub fn action(action: Action) -> Self {
+        Press { keycode: None, actions: vec![action] }
+    }
+
+    pub fn is(&self, action: Action) -> bool {
+        self.actions.contains(&action)
+    }
+}
+
+/// The menu action behind `keycode`, which no binding can change.
+fn menu_action(keycode: Keycode) -> Option<Action> {
+    match keycode {
+        Keycode::Return | Keycode::KpEnter | Keycode::Space => { Some(Action::Confirm) }
+        Keycode::Backspace => { Some(Action::Back) }
+        Keycode::Up => { Some(Action::Up) }
+        Keycode::Down => { Some(Action::Down) }
+        _ => { None }
+    }
+}
+
+impl KeyBindings {
+    /// Key bound to a bindable action.
+    pub fn key(&self, action: Action) -> Keycode {
+        *self.slot(action)
+    }
+
+    /// The bindable action bound to `keycode`, if any.
+    pub fn action_for(&self, keycode: Keycode) -> Option<Action> {
+        Action::BINDABLE.into_iter().find(|action| self.key(*action) == keycode)
+    }
+
+    /// Every action `keycode` triggers, bound or fixed.
+    pub fn press(&self, keycode: Keycode) -> Press {
+        Press {
+            keycode: Some(keycode),
+            actions: self.action_for(keycode).into_iter().chain(menu_action(keycode)).collect(),
+        }
+    }
+
+    /// Binds `keycode` to `action`. An action already using the key takes
+    /// over the old key of `action`, so no action is ever left unbound.
+    pub fn rebind(&mut self, action: Action, keycode: Keycode) {
+        let previous = self.key(action);
+        if let Some(other) = self.action_for(keycode) {
+            *self.slot_mut(other) = previous;
+        }
+        *self.slot_mut(action) = keycode;
+    }
+
+    /// Binds the movement keys of `preset`. Other actions keep their keys
+    /// unless they used one of the preset's, see [`KeyBindings::rebind`].
+    pub fn apply(&mut self, preset: Preset) {
+        for (action, keycode) in preset.keys() {
+            self.rebind(action, keycode);
+        }
+    }
+
+    /// The preset the movement keys of both players match, if any.
+    pub fn preset(&self) -> Option<Preset> {
+        Preset::ALL.into_iter().find(|preset| preset.keys().iter().all(|(action, keycode)| self.key(*action) == *keycode))
+    }
+
+    fn slot(&self, action: Action) -> &Keycode {
+        match action {
+            Action::MoveLeft => { &self.left }
+            Action::MoveRight => { &self.right }
+            Action::Jump => { &self.jump }
+            Action::Shoot => { &self.shoot }
+            Action::SecondMoveLeft => { &self.second_left }
+            Action::SecondMoveRight => { &self.second_right }
+            Action::SecondJump => { &self.second_jump }
+            Action::SecondShoot => { &self.second_shoot }
+            Action::Pause => { &self.pause }
+            Action::QuickSave => { &self.quick_save }
+            Action::QuickLoad => { &self.quick_load }
+            Action::DebugOverlay => { &self.debug_overlay }
+            Action::InputDisplay => { &self.input_display }
+            Action::FrameStep => { &self.frame_step }
+            Action::Step => { &self.step }
+            Action::Console => { &self.console }
+            Action::Talk => { &self.talk }
+            Action::Fetch => { &self.fetch }
+            Action::Mute => { &self.mute }
+            Action::Achievements => { &self.achievements }
+            _ => { panic!("{:?} has a fixed key", action) }
+        }
+    }
+
+    fn slot_mut(&mut self, action: Action) -> &mut Keycode {
+        match action {
+            Action::MoveLeft => { &mut self.left }
+            Action::MoveRight => { &mut self.right }
+            Action::Jump => { &mut self.jump }
+            Action::Shoot => { &mut self.shoot }
+            Action::SecondMoveLeft => { &mut self.second_left }
+            Action::SecondMoveRight => { &mut self.second_right }
+            Action::SecondJump => { &mut self.second_jump }
+            Action::SecondShoot => { &mut self.second_shoot }
+            Action::Pause => { &mut self.pause }
+            Action::QuickSave => { &mut self.quick_save }
+            Action::QuickLoad => { &mut self.quick_load }
+            Action::DebugOverlay => { &mut self.debug_overlay }
+            Action::InputDisplay => { &mut self.input_display }
+            Action::FrameStep => { &mut self.frame_step }
+            Action::Step => { &mut self.step }
+            Action::Console => { &mut self.console }
+            Action::Talk => { &mut self.talk }
+            Action::Fetch => { &mut self.fetch }
+            Action::Mute => { &mut self.mute }
+            Action::Achievements => { &mut self.achievements }
+            _ => { panic!("{:?} has a fixed key", action) }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Whether every bindable action has a key of its own.
+    fn all_distinct(keys: &KeyBindings) -> bool {
+        Action::BINDABLE.iter().all(|action| keys.action_for(keys.key(*action)) == Some(*action))
+    }
+
+    #[test]
+    fn presses_trigger_bound_and_menu_actions() {
+        let keys = KeyBindings::default();
+        assert_eq!(keys.press(Keycode::A).actions, vec![Action::MoveLeft]);
+        assert_eq!(keys.press(Keycode::Space).actions, vec![Action::Jump, Action::Confirm]);
+        assert_eq!(keys.press(Keycode::Backspace).actions, vec![Action::Back]);
+        assert!(keys.press(Keycode::Q).actions.is_empty());
+        assert!(all_distinct(&keys));
+    }
+
+    #[test]
+    fn rebinding_a_used_key_swaps_it() {
+        let mut keys = KeyBindings::default();
+        keys.rebind(Action::Jump, Keycode::W);
+        assert_eq!(keys.key(Action::Jump), Keycode::W);
+        assert_eq!(keys.action_for(Keycode::Space), None);
+        keys.rebind(Action::Jump, Keycode::P);
+        assert_eq!(keys.key(Action::Jump), Keycode::P);
+        assert_eq!(keys.key(Action::Pause), Keycode::W);
+        assert!(all_distinct(&keys));
+    }
+
+    #[test]
+    fn second_player_actions() {
+        assert_eq!(Action::MoveLeft.for_player(0), Action::MoveLeft);
+        assert_eq!(Action::MoveLeft.for_player(1), Action::SecondMoveLeft);
+        assert_eq!(Action::Jump.for_player(1), Action::SecondJump);
+        assert_eq!(Action::Shoot.for_player(1), Action::SecondShoot);
+        assert_eq!(Action::Pause.for_player(1), Action::Pause);
+        assert_eq!(Action::SecondJump.for_player(0), Action::SecondJump);
+    }
+
+    #[test]
+    fn presets_bind_both_players() {
+        let mut keys = KeyBindings::default();
+        assert_eq!(keys.preset(), Some(Preset::Wasd));
+        keys.rebind(Action::Pause, Keycode::Z);
+        keys.apply(Preset::Arrows);
+        assert_eq!(keys.preset(), Some(Preset::Arrows));
+        assert_eq!((keys.key(Action::Jump), keys.key(Action::SecondMoveLeft)), (Keycode::Z, Keycode::A));
+        assert_ne!(keys.key(Action::Pause), Keycode::Z);
+        assert!(all_distinct(&keys));
+        keys.rebind(Action::Jump, Keycode::C);
+        assert_eq!(keys.preset(), None);
+        keys.apply(Preset::Zqsd);
+        assert_eq!(keys.preset(), Some(Preset::Zqsd));
+        assert!(all_distinct(&keys));
+    }
+
+    #[test]
+    fn presets_cycle() {
+        assert_eq!(Preset::Wasd.next(), Preset::Arrows);
+        assert_eq!(Preset::Zqsd.next(), Preset::Wasd);
+    }
+}
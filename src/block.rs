@@ -0,0 +1,191 @@
+use serde::Deserialize;
+
+use crate::collision::Layers;
+use crate::color::compose_color;
+use crate::inventory::Item;
+use crate::powerup::PowerUp;
+
+const SPIKE_COLOR: u32 = 0xC0C0C0;
+const GATE_COLOR: u32 = 0x30C0A0;
+const TRACK_COLOR: u32 = 0x8C8C8C;
+const WATER_COLOR: u32 = 0x2860D0;
+const SIGN_COLOR: u32 = 0xA0522D;
+
+#[derive(Copy, Clone, Debug)]
+pub enum Block {
+    Empty,
+    Wall { color: u32 },
+    Floor { color: u32 },
+    Exit { color: u32 },
+    /// Picked up by players walking through it.
+    Coin { color: u32 },
+    /// Gives back a hit point to a hurt player walking through it.
+    Heart { color: u32 },
+    /// Solid, and hurts players touching it from the side it points to.
+    Spikes { color: u32, facing: Side },
+    /// Takes out players entering it, whatever their health.
+    Lava { color: u32 },
+    /// Boosts the first player walking through it for a while.
+    PowerUp { color: u32, power_up: PowerUp },
+    /// Goes into the inventory of the players walking through it.
+    Item { color: u32, item: Item },
+    /// Solid until a player carrying a key walks into it.
+    Door { color: u32 },
+    /// Solid unless open, opened and closed by the buttons and plates on
+    /// its link.
+    Gate { color: u32, link: u32, open: bool },
+    /// Solid, moved a tile at a time by players pushing against it, and
+    /// falls when nothing is under it.
+    Pushable { color: u32 },
+    /// Track minecarts run along, drawn along the bottom of its tile.
+    Track { color: u32 },
+    /// Slows down bodies in it, which players swim through.
+    Water { color: u32 },
+    /// Tucked away in a corner of the level, counted toward its completion
+    /// once picked up and the level finished.
+    HiddenStar { color: u32 },
+    /// Shows what the level info has it say to a player in front of it
+    /// pressing up.
+    Sign { color: u32 },
+}
+
+/// What a block is made of, which its footsteps sound like.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Material {
+    Stone,
+    Wood,
+    Ice,
+    Water,
+}
+
+impl Material {
+    /// Id of the sound of a footstep on it.
+    pub fn footstep(&self) -> &'static str {
+        match self {
+            Material::Stone => { "step_stone" }
+            Material::Wood => { "step_wood" }
+            Material::Ice => { "step_ice" }
+            Material::Water => { "step_water" }
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Side {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Block {
+    /// Block for a map character, `None` for characters that are not blocks.
+    pub fn from_code(code: char) -> Option<Block> {
+        match code {
+            '_' => { Some(Block::Empty) }
+            '%' => { Some(Block::Floor { color: compose_color(255, 0, 0) }) }
+            '|' => { Some(Block::Wall { color: compose_color(0, 0, 255) }) }
+            '#' => { Some(Block::Exit { color: compose_color(255, 255, 0) }) }
+            'o' => { Some(Block::Coin { color: compose_color(255, 215, 0) }) }
+            '+' => { Some(Block::Heart { color: compose_color(255, 64, 128) }) }
+            '^' => { Some(Block::Spikes { color: SPIKE_COLOR, facing: Side::Up }) }
+            'v' => { Some(Block::Spikes { color: SPIKE_COLOR, facing: Side::Down }) }
+            '<' => { Some(Block::Spikes { color: SPIKE_COLOR, facing: Side::Left }) }
+            '>' => { Some(Block::Spikes { color: SPIKE_COLOR, facing: Side::Right }) }
+            '~' => { Some(Block::Lava { color: compose_color(255, 80, 0) }) }
+            's' => { Some(Block::power_up(PowerUp::Speed)) }
+            'j' => { Some(Block::power_up(PowerUp::Jump)) }
+            '*' => { Some(Block::power_up(PowerUp::Star)) }
+            'd' => { Some(Block::Door { color: compose_color(139, 69, 19) }) }
+            'b' => { Some(Block::Pushable { color: compose_color(160, 120, 60) }) }
+            't' => { Some(Block::Track { color: TRACK_COLOR }) }
+            'w' => { Some(Block::Water { color: WATER_COLOR }) }
+            'h' => { Some(Block::HiddenStar { color: compose_color(255, 250, 205) }) }
+            'i' => { Some(Block::Sign { color: SIGN_COLOR }) }
+            '1'..='9' => { Some(Block::Gate { color: GATE_COLOR, link: code.to_digit(10).unwrap(), open: false }) }
+            _ => { Item::from_code(code).map(|item| Block::Item { color: item.color(), item }) }
+        }
+    }
+
+    fn power_up(power_up: PowerUp) -> Block {
+        Block::PowerUp { color: power_up.color(), power_up }
+    }
+
+    pub fn code(&self) -> char {
+        match self {
+            Block::Empty => { '_' }
+            Block::Floor { .. } => { '%' }
+            Block::Wall { .. } => { '|' }
+            Block::Exit { .. } => { '#' }
+            Block::Coin { .. } => { 'o' }
+            Block::Heart { .. } => { '+' }
+            Block::Spikes { facing: Side::Up, .. } => { '^' }
+            Block::Spikes { facing: Side::Down, .. } => { 'v' }
+            Block::Spikes { facing: Side::Left, .. } => { '<' }
+            Block::Spikes { facing: Side::Right, .. } => { '>' }
+            Block::Lava { .. } => { '~' }
+            Block::PowerUp { power_up, .. } => { power_up.code() }
+            Block::Item { item, .. } => { item.code() }
+            Block::Door { .. } => { 'd' }
+            Block::Gate { link, .. } => { char::from_digit(*link, 10).unwrap_or('1') }
+            Block::Pushable { .. } => { 'b' }
+            Block::Track { .. } => { 't' }
+            Block::Water { .. } => { 'w' }
+            Block::HiddenStar { .. } => { 'h' }
+            Block::Sign { .. } => { 'i' }
+        }
+    }
+
+    /// Whether bodies collide with this block.
+    pub fn is_solid(&self) -> bool {
+        matches!(self, Block::Wall { .. } | Block::Floor { .. } | Block::Spikes { .. } | Block::Door { .. } | Block::Gate { open: false, .. } | Block::Pushable { .. })
+    }
+
+    /// What the block is made of, unless a level says otherwise; none for
+    /// blocks nobody walks on or in.
+    pub fn material(&self) -> Option<Material> {
+        match self {
+            Block::Wall { .. } | Block::Exit { .. } | Block::Spikes { .. } | Block::Gate { .. } => { Some(Material::Stone) }
+            Block::Floor { .. } | Block::Door { .. } | Block::Pushable { .. } | Block::Track { .. } | Block::Sign { .. } => { Some(Material::Wood) }
+            Block::Water { .. } => { Some(Material::Water) }
+            _ => { None }
+        }
+    }
+
+    /// Collision layer of the tile, for bodies to tell whether touching it
+    /// matters to them.
+    pub fn layer(&self) -> Layers {
+        match self {
+            Block::Spikes { .. } | Block::Lava { .. } => { Layers::HAZARD }
+            _ => { Layers::NONE }
+        }
+    }
+
+    /// Whether this block is a small item drawn inside its tile.
+    pub fn is_pickup(&self) -> bool {
+        matches!(self, Block::Coin { .. } | Block::Heart { .. } | Block::PowerUp { .. } | Block::Item { .. } | Block::HiddenStar { .. })
+    }
+
+    pub fn color(&self) -> Option<u32> {
+        match self {
+            Block::Wall { color } => { Some(*color) }
+            Block::Floor { color } => { Some(*color) }
+            Block::Exit { color } => { Some(*color) }
+            Block::Coin { color } => { Some(*color) }
+            Block::Heart { color } => { Some(*color) }
+            Block::Spikes { color, .. } => { Some(*color) }
+            Block::Lava { color } => { Some(*color) }
+            Block::PowerUp { color, .. } => { Some(*color) }
+            Block::Item { color, .. } => { Some(*color) }
+            Block::Door { color } => { Some(*color) }
+            Block::Gate { color, .. } => { Some(*color) }
+            Block::Pushable { color } => { Some(*color) }
+            Block::Track { color } => { Some(*color) }
+            Block::Water { color } => { Some(*color) }
+            Block::HiddenStar { color } => { Some(*color) }
+            Block::Sign { color } => { Some(*color) }
+            Block::Empty => { None }
+        }
+    }
+}
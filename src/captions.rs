@@ -0,0 +1,95 @@
+//! Captions for sounds, for players who can't hear them: a short line for
+//! each important sound, with an arrow toward where it came from as seen
+//! from the players, shown in a corner of the screen for a moment. They
+//! are on with `captions = true` under `[audio]`, with or without audio.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::audio::Listener;
+use crate::block::Side;
+use crate::events::GameEvent;
+
+/// How long a caption stays up.
+pub const CAPTION_TIME: Duration = Duration::from_secs(2);
+/// Captions shown at once; the oldest goes for a new one past that.
+const MAX_CAPTIONS: usize = 4;
+/// Tiles from the listener within which a sound is right there, without
+/// an arrow.
+const NEARBY: f32 = 2.0;
+
+/// Line shown for the sound of a game event, if it is worth one.
+pub fn caption(event: &GameEvent) -> Option<&'static str> {
+    match event {
+        GameEvent::TurretFired { .. } => { Some("TURRET FIRED") }
+        GameEvent::TileDestroyed { .. } => { Some("BLOCK CRUMBLED") }
+        GameEvent::CrateBroken { .. } => { Some("CRATE BROKEN") }
+        GameEvent::EnemyDefeated { .. } => { Some("ENEMY DEFEATED") }
+        GameEvent::DoorOpened { .. } => { Some("DOOR OPENED") }
+        GameEvent::GateOpened { .. } => { Some("GATE OPENED") }
+        GameEvent::GateCountdown { .. } => { Some("GATE TICKING") }
+        GameEvent::GateClosed { .. } => { Some("GATE CLOSED") }
+        _ => { None }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Caption {
+    pub text: &'static str,
+    /// Which way the sound came from, none when it came from nearby or
+    /// from nowhere in particular.
+    pub direction: Option<Side>,
+    since: Instant,
+}
+
+impl Caption {
+    /// The text with an arrow on the side the sound came from.
+    pub fn line(&self) -> String {
+        match self.direction {
+            Some(Side::Left) => { format!("< {}", self.text) }
+            Some(Side::Right) => { format!("{} >", self.text) }
+            Some(Side::Up) => { format!("^ {}", self.text) }
+            Some(Side::Down) => { format!("V {}", self.text) }
+            None => { self.text.to_string() }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Captions {
+    /// Captions up, oldest first.
+    lines: VecDeque<Caption>,
+    /// Where sounds are heard from, set with the audio's.
+    pub listener: Option<Listener>,
+}
+
+impl Captions {
+    /// Puts up the caption of `event`, or brings back the same one still up.
+    pub fn hear(&mut self, event: &GameEvent) {
+        let Some(text) = caption(event) else { return };
+        let direction = self.listener.zip(event.tile()).and_then(|(listener, tile)| direction(listener, tile));
+        self.lines.retain(|line| line.text != text || line.direction != direction);
+        self.lines.push_back(Caption { text, direction, since: Instant::now() });
+        while self.lines.len() > MAX_CAPTIONS {
+            self.lines.pop_front();
+        }
+    }
+
+    /// Captions still up, oldest first, once those up long enough are gone.
+    pub fn showing(&mut self) -> &VecDeque<Caption> {
+        self.lines.retain(|line| line.since.elapsed() < CAPTION_TIME);
+        &self.lines
+    }
+}
+
+/// Which way `tile` is from `listener`, mostly.
+fn direction(listener: Listener, tile: (usize, usize)) -> Option<Side> {
+    let (x, y) = (tile.0 as f32 + 0.5 - listener.x, tile.1 as f32 + 0.5 - listener.y);
+    if x.hypot(y) < NEARBY {
+        None
+    } else if x.abs() >= y.abs() {
+        Some(if x < 0.0 { Side::Left } else { Side::Right })
+    } else {
+        Some(if y < 0.0 { Side::Up } else { Side::Down })
+    }
+}
@@ -0,0 +1,47 @@
+//! Minecarts running along tracks, `t` in the map. A player landing in a
+//! cart rides it: the cart runs the way the player faced, climbing and
+//! dropping a tile at a time as the track does, until jumped out of. At a
+//! gap in the track it flies on and lands back on the track or comes to
+//! rest wherever it comes down; at a wall it stops.
+
+use hecs::{Entity, World};
+use serde::{Deserialize, Serialize};
+
+use crate::components::{Body, Position, Sprite};
+
+pub const CART_CODE: char = 'M';
+pub const CART_WIDTH: f32 = 0.9;
+pub const CART_HEIGHT: f32 = 0.5;
+pub const CART_COLOR: u32 = 0x707070;
+
+/// Marks a minecart. Carts on the track move on their own; carts off it
+/// get a velocity and move through physics until they land.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Cart {
+    /// Player riding the cart, by index.
+    pub rider: Option<usize>,
+    /// -1 running left, 1 running right and 0 standing still.
+    pub direction: i32,
+    /// Track tile the cart runs from and the one it runs to while on the
+    /// track, the same tile twice when it stands on it.
+    pub rail: Option<((usize, usize), (usize, usize))>,
+    /// Tiles covered from the first tile of `rail` toward the second.
+    pub travelled: f32,
+}
+
+/// Marks a player riding a cart.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Riding;
+
+/// Spawns a cart standing on the track in the tile at `tile`.
+pub fn spawn(world: &mut World, tile: (usize, usize)) -> Entity {
+    world.spawn((
+        Cart { rider: None, direction: 0, rail: Some((tile, tile)), travelled: 0.0 },
+        Position {
+            x: tile.0 as f32 + (1.0 - CART_WIDTH) / 2.0,
+            y: tile.1 as f32 + 1.0 - CART_HEIGHT,
+        },
+        Body::new(CART_WIDTH, CART_HEIGHT),
+        Sprite { color: CART_COLOR },
+    ))
+}
@@ -0,0 +1,71 @@
+//! Collision layers. Entities that run into one another carry a
+//! [`Collider`] naming the layer they are on and the layers they interact
+//! with, so systems looking for contacts skip pairs that have nothing to
+//! do with each other, like enemy shots and enemies. Tiles are on layers
+//! too, see [`crate::block::Block::layer`].
+
+use std::ops::BitOr;
+
+use serde::{Deserialize, Serialize};
+
+/// A set of collision layers, one bit each.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Layers(pub u8);
+
+impl Layers {
+    pub const NONE: Layers = Layers(0);
+    pub const PLAYER: Layers = Layers(1);
+    pub const ENEMY: Layers = Layers(1 << 1);
+    pub const PROJECTILE: Layers = Layers(1 << 2);
+    pub const HAZARD: Layers = Layers(1 << 3);
+    pub const TRIGGER: Layers = Layers(1 << 4);
+    pub const CRATE: Layers = Layers(1 << 5);
+
+    pub const fn union(self, other: Layers) -> Layers {
+        Layers(self.0 | other.0)
+    }
+
+    /// Whether the two sets share a layer.
+    pub fn intersects(self, other: Layers) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl BitOr for Layers {
+    type Output = Layers;
+
+    fn bitor(self, other: Layers) -> Layers {
+        self.union(other)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Collider {
+    /// Layers the entity is on.
+    pub layer: Layers,
+    /// Layers the entity interacts with.
+    pub mask: Layers,
+}
+
+impl Collider {
+    pub const PLAYER: Collider = Collider {
+        layer: Layers::PLAYER,
+        mask: Layers::ENEMY.union(Layers::PROJECTILE).union(Layers::HAZARD).union(Layers::TRIGGER),
+    };
+    pub const ENEMY: Collider = Collider { layer: Layers::ENEMY, mask: Layers::PLAYER.union(Layers::PROJECTILE) };
+    pub const CRATE: Collider = Collider { layer: Layers::CRATE, mask: Layers::PROJECTILE };
+    /// Saws and falling traps.
+    pub const HAZARD: Collider = Collider { layer: Layers::HAZARD, mask: Layers::PLAYER };
+    pub const PLAYER_SHOT: Collider = Collider { layer: Layers::PROJECTILE, mask: Layers::ENEMY.union(Layers::CRATE) };
+    pub const ENEMY_SHOT: Collider = Collider { layer: Layers::PROJECTILE, mask: Layers::PLAYER };
+
+    /// Whether the two entities touching each other matters to both.
+    pub fn interacts(&self, other: &Collider) -> bool {
+        self.mask.intersects(other.layer) && other.mask.intersects(self.layer)
+    }
+
+    /// Whether touching a tile on `layer` matters to this entity.
+    pub fn touches(&self, layer: Layers) -> bool {
+        self.mask.intersects(layer)
+    }
+}
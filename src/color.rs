@@ -0,0 +1,12 @@
+pub fn split_rgb(color: u32) -> (u8, u8, u8) {
+    (((color >> 16) & 0xFF) as u8,
+     ((color >> 8) & 0xFF) as u8,
+     (color & 0xFF) as u8)
+}
+
+pub fn compose_color(r: u32, g: u32, b: u32) -> u32 {
+    let mut rgb = r;
+    rgb = (rgb << 8) + g;
+    rgb = (rgb << 8) + b;
+    rgb
+}
@@ -0,0 +1,54 @@
+//! A small helper that floats after the first player along the path they
+//! took, a little behind. Sent to fetch, it finds its way through open
+//! tiles to the nearest coin or item in reach, picks it up for the players
+//! and comes back the way it went. Whenever it falls too far behind or
+//! takes too long on an errand it teleports back to the player.
+
+use std::collections::VecDeque;
+
+use hecs::{Entity, World};
+use serde::{Deserialize, Serialize};
+
+use crate::components::{Body, Position, Sprite};
+
+pub const COMPANION_WIDTH: f32 = 0.5;
+pub const COMPANION_HEIGHT: f32 = 0.5;
+pub const COMPANION_COLOR: u32 = 0xFFB0E0;
+/// Ticks the companion stays behind the player it follows.
+pub const FOLLOW_DELAY: usize = 20;
+/// Longest the trail gets before the companion counts as stuck, in ticks.
+pub const MAX_TRAIL: usize = 240;
+/// Farthest a collectible can be for the companion to fetch it, in steps
+/// from tile to tile.
+pub const FETCH_RANGE: usize = 16;
+/// Ticks an errand may take before the companion gives up on it.
+pub const ERRAND_TICKS: u32 = 360;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Errand {
+    /// Following the trail of the player.
+    Following,
+    /// Heading along `path` to the collectible at its end.
+    Fetching { path: VecDeque<(usize, usize)>, back: Vec<(usize, usize)> },
+    /// Heading back along `path` to where it left the trail.
+    Returning { path: VecDeque<(usize, usize)> },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Companion {
+    pub errand: Errand,
+    /// Where the companion should be, one position per tick, oldest first.
+    pub trail: VecDeque<Position>,
+    /// Ticks spent on the current errand.
+    pub busy: u32,
+}
+
+/// Spawns the companion centered on `center`.
+pub fn spawn(world: &mut World, center: (f32, f32)) -> Entity {
+    world.spawn((
+        Companion { errand: Errand::Following, trail: VecDeque::new(), busy: 0 },
+        Position { x: center.0 - COMPANION_WIDTH / 2.0, y: center.1 - COMPANION_HEIGHT / 2.0 },
+        Body::new(COMPANION_WIDTH, COMPANION_HEIGHT),
+        Sprite { color: COMPANION_COLOR },
+    ))
+}
@@ -0,0 +1,74 @@
+//! Components shared by every kind of entity in the world.
+
+use hecs::{Entity, World};
+use serde::{Deserialize, Serialize};
+
+/// Top-left corner of the entity in tile units.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Position {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Movement in tiles per tick.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Velocity {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Collision box in tile units. Entities with a body collide with solid
+/// blocks and are pulled down by gravity.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Body {
+    pub width: f32,
+    pub height: f32,
+    pub on_ground: bool,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Sprite {
+    pub color: u32,
+}
+
+impl Body {
+    pub fn new(width: f32, height: f32) -> Self {
+        Body {
+            width,
+            height,
+            on_ground: false,
+        }
+    }
+}
+
+/// Rectangle in tile units, e.g. the box of a body at its position.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Bounds {
+    /// Top left corner.
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Bounds {
+    pub fn of(position: &Position, body: &Body) -> Self {
+        Bounds { x: position.x, y: position.y, width: body.width, height: body.height }
+    }
+
+    /// Whether the two rectangles share more than an edge.
+    pub fn overlaps(&self, other: &Bounds) -> bool {
+        self.x < other.x + other.width
+            && other.x < self.x + self.width
+            && self.y < other.y + other.height
+            && other.y < self.y + self.height
+    }
+}
+
+/// Every entity whose body overlaps `bounds`.
+pub fn entities_in(world: &World, bounds: &Bounds) -> Vec<Entity> {
+    world.query::<(Entity, &Position, &Body)>().iter()
+        .filter(|(_, position, body)| Bounds::of(position, body).overlaps(bounds))
+        .map(|(entity, _, _)| entity)
+        .collect()
+}
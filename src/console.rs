@@ -0,0 +1,239 @@
+//! Drop-down developer console. A line typed into it is split on
+//! whitespace and the first word picks a command from the registry;
+//! commands return an [`Effect`] that the main loop applies, since most of
+//! them act on the running game or the current state.
+
+use crate::context::Context;
+use crate::game::GameCommand;
+use crate::inventory::Item;
+use crate::simulation::{MAX_TIME_SCALE, MIN_TIME_SCALE};
+use crate::text_entry::TextEntry;
+
+/// Lines of output kept for display.
+const MAX_LINES: usize = 64;
+const MAX_HISTORY: usize = 32;
+
+/// What a command asks the main loop to do.
+pub enum Effect {
+    Message(String),
+    /// Run against the game of the current state, then print the message.
+    Game(GameCommand, String),
+    /// Start playing the level at this path.
+    Load(String),
+}
+
+pub type CommandResult = std::result::Result<Effect, String>;
+
+pub struct Command {
+    pub name: &'static str,
+    pub usage: &'static str,
+    pub help: &'static str,
+    pub run: fn(&mut Context, &[&str]) -> CommandResult,
+}
+
+pub struct Console {
+    pub open: bool,
+    pub input: TextEntry,
+    /// Output, oldest first.
+    pub lines: Vec<String>,
+    history: Vec<String>,
+    /// Position while browsing the history with the arrow keys.
+    history_index: Option<usize>,
+    commands: Vec<Command>,
+}
+
+impl Console {
+    pub fn new() -> Self {
+        let mut console = Console {
+            open: false,
+            input: TextEntry {
+                rejected: vec!['`', '~'],
+                ..TextEntry::new()
+            },
+            lines: Vec::new(),
+            history: Vec::new(),
+            history_index: None,
+            commands: Vec::new(),
+        };
+        for command in builtin_commands() {
+            console.register(command);
+        }
+        console
+    }
+
+    /// Adds a command, replacing any with the same name.
+    pub fn register(&mut self, command: Command) {
+        self.commands.retain(|existing| existing.name != command.name);
+        self.commands.push(command);
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.history_index = None;
+    }
+
+    pub fn print(&mut self, line: impl Into<String>) {
+        self.lines.push(line.into());
+        if self.lines.len() > MAX_LINES {
+            self.lines.remove(0);
+        }
+    }
+
+    pub fn history_previous(&mut self) {
+        let index = match self.history_index {
+            Some(index) => { index.saturating_sub(1) }
+            None if !self.history.is_empty() => { self.history.len() - 1 }
+            None => { return }
+        };
+        self.history_index = Some(index);
+        self.input.set(&self.history[index]);
+    }
+
+    pub fn history_next(&mut self) {
+        match self.history_index {
+            Some(index) if index + 1 < self.history.len() => {
+                self.history_index = Some(index + 1);
+                self.input.set(&self.history[index + 1]);
+            }
+            _ => {
+                self.history_index = None;
+                self.input.set("");
+            }
+        }
+    }
+
+    /// Runs the typed line. Output is printed; effects for the main loop
+    /// are returned.
+    pub fn submit(&mut self, context: &mut Context) -> Option<Effect> {
+        let line = self.input.take();
+        self.history_index = None;
+        let line = line.trim();
+        if line.is_empty() {
+            return None;
+        }
+        self.print(format!("> {}", line));
+        if self.history.last().map(String::as_str) != Some(line) {
+            self.history.push(line.to_string());
+            if self.history.len() > MAX_HISTORY {
+                self.history.remove(0);
+            }
+        }
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if words[0] == "help" {
+            let lines: Vec<String> = self.commands.iter()
+                .map(|command| format!("{} - {}", command.usage, command.help))
+                .collect();
+            self.print("help - list commands");
+            for line in lines {
+                self.print(line);
+            }
+            return None;
+        }
+        let command = match self.commands.iter().find(|command| command.name == words[0]) {
+            Some(command) => { command }
+            None => {
+                self.print(format!("unknown command '{}', try help", words[0]));
+                return None;
+            }
+        };
+        match (command.run)(context, &words[1..]) {
+            Ok(Effect::Message(message)) => {
+                self.print(message);
+                None
+            }
+            Ok(effect) => { Some(effect) }
+            Err(error) => {
+                self.print(error);
+                None
+            }
+        }
+    }
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn parse<T: std::str::FromStr>(word: Option<&&str>, usage: &str) -> Result<T, String> {
+    word.and_then(|word| word.parse().ok()).ok_or_else(|| format!("usage: {}", usage))
+}
+
+fn builtin_commands() -> Vec<Command> {
+    vec![
+        Command {
+            name: "load",
+            usage: "load <map>",
+            help: "play another level",
+            run: |_, args| {
+                let path = args.first().ok_or("usage: load <map>")?;
+                Ok(Effect::Load(path.to_string()))
+            },
+        },
+        Command {
+            name: "tp",
+            usage: "tp <x> <y>",
+            help: "teleport the player to a tile",
+            run: |_, args| {
+                let x: usize = parse(args.first(), "tp <x> <y>")?;
+                let y: usize = parse(args.get(1), "tp <x> <y>")?;
+                Ok(Effect::Game(
+                    Box::new(move |game| { game.teleport_player(x, y); }),
+                    format!("teleported to {} {}", x, y),
+                ))
+            },
+        },
+        Command {
+            name: "set",
+            usage: "set <name> <value>",
+            help: "change gravity, max_fall_speed, move_speed, jump_speed or time_scale",
+            run: |context, args| {
+                let name = *args.first().ok_or("usage: set <name> <value>")?;
+                let value: f32 = parse(args.get(1), "set <name> <value>")?;
+                let physics = &mut context.tuning.tuning.physics;
+                let setting = match name {
+                    "gravity" => { &mut physics.gravity }
+                    "max_fall_speed" => { &mut physics.max_fall_speed }
+                    "move_speed" => { &mut physics.move_speed }
+                    "jump_speed" => { &mut physics.jump_speed }
+                    "time_scale" => { &mut context.time_scale }
+                    _ => { return Err(format!("unknown setting '{}'", name)) }
+                };
+                *setting = if name == "time_scale" { value.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE) } else { value };
+                Ok(Effect::Message(format!("{} = {}", name, setting)))
+            },
+        },
+        Command {
+            name: "give",
+            usage: "give <item>",
+            help: "give the players a key, potion or gem",
+            run: |_, args| {
+                let name = args.first().ok_or("usage: give <item>")?;
+                let item = Item::ALL.into_iter()
+                    .find(|item| item.name().eq_ignore_ascii_case(name))
+                    .ok_or_else(|| format!("no item named '{}'", name))?;
+                Ok(Effect::Game(
+                    Box::new(move |game| { game.inventory.add(item); }),
+                    format!("gave {}", name),
+                ))
+            },
+        },
+        Command {
+            name: "spawn",
+            usage: "spawn <entity>",
+            help: "spawn an entity defined by a mod next to the player",
+            run: |context, args| {
+                let name = args.first().ok_or("usage: spawn <entity>")?;
+                let definition = context.mods.definitions.find_named(name)
+                    .ok_or_else(|| format!("no entity named '{}'", name))?
+                    .clone();
+                Ok(Effect::Game(
+                    Box::new(move |game| { game.spawn_near_player(&definition); }),
+                    format!("spawned {}", name),
+                ))
+            },
+        },
+    ]
+}
@@ -0,0 +1,357 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::path::Path;
+use std::rc::Rc;
+
+use log::{info, warn};
+use sdl2::Sdl;
+
+use crate::achievement::{Achievements, ACHIEVEMENTS, UNLOCKED};
+use crate::assets::{Assets, Handle, Sound};
+use crate::audio::{stem_file, Audio, Intensity, Listener, Music, SoundBank, SOUNDS, STEMS};
+use crate::bindings::Action;
+use crate::captions::Captions;
+use crate::block::Block;
+use crate::error::{GameError, Result};
+use crate::difficulty::{Profiles, DIFFICULTY};
+use crate::events::{EventBus, GameEvent};
+use crate::game::Game;
+use crate::level;
+use crate::mods::Mods;
+use crate::paths::user_file;
+use crate::records::{Records, RECORDS};
+use crate::replay::Replay;
+use crate::rng;
+use crate::settings::{Settings, DEFAULT_SETTINGS};
+use crate::gamepad;
+use crate::systems::input::{Inputs, PlayerInput, MAX_PLAYERS};
+use crate::touch::TouchControls;
+use crate::tuning::TuningWatcher;
+
+/// Resources shared by every state for the lifetime of the application.
+pub struct Context {
+    pub settings: Settings,
+    /// Level given on the command line, used instead of the configured start level.
+    pub map: Option<String>,
+    pub seed: Option<u64>,
+    /// Where to write a replay of every level played, from `--record`.
+    pub record: Option<String>,
+    /// Players in every game started, from `--players`.
+    pub players: usize,
+    /// Actions currently held, kept up to date by the event loop through
+    /// [`Context::press_action`] and [`Context::release_action`].
+    pub held_actions: HashSet<Action>,
+    pub touch: TouchControls,
+    /// Left stick of each player's controller, from -1 to 1.
+    pub sticks: [f32; MAX_PLAYERS],
+    /// Player whose controller was unplugged during a game, until it is
+    /// plugged back in or the player carries on without it.
+    pub disconnected: Option<usize>,
+    /// Score carried into the level being played from the levels before it
+    /// in the campaign.
+    pub score: u32,
+    /// Lives carried into the level being played.
+    pub lives: u32,
+    /// Coins carried into the level being played, to spend in shops.
+    pub money: u32,
+    /// Best time of every level completed so far.
+    pub records: Records,
+    pub achievements: Achievements,
+    /// What each difficulty does.
+    pub difficulties: Profiles,
+    pub events: EventBus,
+    pub mods: Mods,
+    pub assets: Assets,
+    /// Files of the music track playing and their handles, released when
+    /// the track changes.
+    music_sounds: Vec<(String, Handle<Sound>)>,
+    /// None until the windowed game opens an audio device, and so always
+    /// absent headless, in replays run from the command line and in tests.
+    /// Sounds are only ever asked for through the event bus and the
+    /// methods here.
+    pub audio: Option<Audio>,
+    /// Captions of the sounds of game events, shared with the subscriber
+    /// putting them up.
+    pub captions: Rc<RefCell<Captions>>,
+    /// Gameplay constants, reloaded while the game runs.
+    pub tuning: TuningWatcher,
+    /// Base speed of the simulation, see [`crate::simulation::TimeScale`].
+    pub time_scale: f32,
+    /// Whether the simulation is frozen and advanced one tick per key press.
+    pub frame_step: bool,
+    /// Set by a state to close the game after this frame.
+    pub quit: bool,
+    /// Set by a state changing the window settings, for the main loop to
+    /// apply them after this frame.
+    pub window_changed: bool,
+}
+
+impl Context {
+    pub fn new(settings: Settings, mods: Mods, tuning: TuningWatcher) -> Self {
+        let achievements = Achievements::load(&mods.resolve(ACHIEVEMENTS), &user_file(UNLOCKED));
+        let difficulties = Profiles::load(&mods.resolve(DIFFICULTY));
+        Context {
+            mods,
+            tuning,
+            assets: Assets::new(),
+            music_sounds: Vec::new(),
+            audio: None,
+            captions: Rc::default(),
+            time_scale: 1.0,
+            frame_step: false,
+            quit: false,
+            window_changed: false,
+            touch: TouchControls::new(settings.input.touch_controls),
+            sticks: [0.0; MAX_PLAYERS],
+            disconnected: None,
+            score: 0,
+            lives: settings.game.lives.max(1),
+            money: 0,
+            records: Records::load(&user_file(RECORDS)),
+            achievements,
+            difficulties,
+            settings,
+            map: None,
+            seed: None,
+            record: None,
+            players: 1,
+            held_actions: HashSet::new(),
+            events: EventBus::new(),
+        }
+    }
+
+    pub fn level_path(&self) -> &str {
+        self.map.as_deref().unwrap_or(&self.settings.game.start_level)
+    }
+
+    /// Levels played in order: the start level followed by levels added by mods.
+    pub fn campaign(&self) -> Vec<String> {
+        let mut levels = vec![self.level_path().to_string()];
+        levels.extend(self.mods.levels());
+        levels
+    }
+
+    /// Level following `current` in the campaign, if there is one.
+    pub fn next_level(&self, current: &str) -> Option<String> {
+        let campaign = self.campaign();
+        let index = campaign.iter().position(|level| level == current)?;
+        campaign.get(index + 1).cloned()
+    }
+
+    /// Hidden stars in `level`, none when it can't be read.
+    pub fn hidden_star_count(&self, level: &str) -> usize {
+        level::load(&self.mods.resolve(level))
+            .map(|level| level.playground.tiles_where(|block| matches!(block, Block::HiddenStar { .. })).len())
+            .unwrap_or(0)
+    }
+
+    /// Hidden stars found across the campaign out of those in it, or
+    /// `None` when it has none.
+    pub fn campaign_stars(&self) -> Option<(usize, usize)> {
+        let (found, total) = self.campaign().iter()
+            .map(|level| {
+                let total = self.hidden_star_count(level);
+                (self.records.stars_found(level).min(total), total)
+            })
+            .fold((0, 0), |sum, level| (sum.0 + level.0, sum.1 + level.1));
+        (total > 0).then_some((found, total))
+    }
+
+    /// Opens the audio device and loads the sounds of [`SOUNDS`], played
+    /// from then on for the game events they are cues of.
+    pub fn open_audio(&mut self, sdl: &Sdl) {
+        let Some(mut audio) = Audio::open(sdl, &self.settings.audio) else { return };
+        for (id, info) in SoundBank::load(&self.mods.resolve(SOUNDS)).0 {
+            let loaded = self.assets.load_sound(&self.mods, &info.file).and_then(|handle| {
+                let loaded = self.assets.sounds.get(handle).map_or(Ok(()), |sound| audio.load(&id, sound, info.volume));
+                // The audio keeps its own converted copy.
+                self.assets.sounds.release(handle);
+                loaded
+            });
+            if let Err(error) = loaded {
+                warn!("Can't load sound '{}': {}", id, error);
+            }
+        }
+        self.assets.unload_unused();
+        self.events.subscribe(Box::new(audio.cues()));
+        self.audio = Some(audio);
+    }
+
+    /// Mutes or unmutes everything, remembered in the settings.
+    pub fn toggle_mute(&mut self) -> Result<()> {
+        self.settings.audio.muted = !self.settings.audio.muted;
+        self.apply_audio_settings();
+        info!("Sound {}", if self.settings.audio.muted { "muted" } else { "unmuted" });
+        self.settings.save(&user_file(DEFAULT_SETTINGS))
+    }
+
+    /// Switches to the music the current state plays, loading its track
+    /// and the stems it has, and unloading the track played before.
+    pub fn play_music(&mut self, music: Music) {
+        let Some(audio) = self.audio.as_mut().filter(|audio| *audio.music() != music) else { return };
+        let changed = music.track() != audio.music().track();
+        let previous = if changed { std::mem::take(&mut self.music_sounds) } else { Vec::new() };
+        if let Some(track) = music.track().filter(|track| !audio.is_loaded(track)) {
+            let mut files = vec![track.to_string()];
+            files.extend(STEMS.iter().map(|name| stem_file(track, name)).filter(|stem| Path::new(&self.mods.resolve(stem)).is_file()));
+            for file in files {
+                let loaded = self.assets.load_sound(&self.mods, &file).and_then(|handle| {
+                    self.music_sounds.push((file.clone(), handle));
+                    self.assets.sounds.get(handle).map_or(Ok(()), |sound| audio.load(&file, sound, 1.0))
+                });
+                if let Err(error) = loaded {
+                    warn!("Can't play music '{}': {}", file, error);
+                }
+            }
+        }
+        audio.set_music(music);
+        // The voices fading out hold on to their samples.
+        for (file, handle) in previous {
+            audio.unload(&file);
+            self.assets.sounds.release(handle);
+        }
+        self.assets.unload_unused();
+    }
+
+    /// Plays the sounds of the game events published since the last call.
+    pub fn play_cues(&mut self) {
+        if let Some(audio) = &mut self.audio {
+            audio.play_cues();
+        }
+    }
+
+    /// Hears sounds and places their captions from `listener`, and plays
+    /// the stems of the music at `intensity`, from now on.
+    pub fn listen(&mut self, listener: Option<Listener>, intensity: Intensity) {
+        self.captions.borrow_mut().listener = listener;
+        if let Some(audio) = &mut self.audio {
+            audio.set_listener(listener);
+            audio.set_intensity(intensity);
+        }
+    }
+
+    /// Plays everything at the volumes of the settings from now on.
+    pub fn apply_audio_settings(&mut self) {
+        if let Some(audio) = &mut self.audio {
+            audio.set_volume(&self.settings.audio);
+        }
+    }
+
+    /// Resets the score, lives and coins for a new campaign.
+    pub fn start_campaign(&mut self) {
+        self.score = 0;
+        self.money = 0;
+        self.lives = self.settings.game.lives.max(1);
+        self.achievements.start_run();
+    }
+
+    /// Publishes the events of a game being played, counting them toward
+    /// achievements. Replays publish straight to the bus instead.
+    pub fn publish_played<I: IntoIterator<Item = GameEvent>>(&mut self, events: I) {
+        let events: Vec<GameEvent> = events.into_iter().collect();
+        for event in &events {
+            self.achievements.observe(event);
+        }
+        self.events.publish(events);
+    }
+
+    /// Starts `path` with the seed from the command line, or a fresh one.
+    pub fn load_game(&self, path: &str) -> Result<Game> {
+        let mut game = Game::new(path, &self.mods, self.seed.unwrap_or_else(rng::random_seed))?;
+        game.tuning = self.tuning.tuning;
+        let difficulty = self.settings.game.difficulty;
+        game.set_difficulty(difficulty, self.difficulties.get(difficulty));
+        game.score = self.score;
+        game.lives = self.lives;
+        game.money = self.money;
+        for _ in 1..self.players {
+            game.add_player();
+        }
+        if self.settings.game.companion {
+            game.add_companion();
+        }
+        if self.record.is_some() {
+            game.start_recording();
+        }
+        Ok(game)
+    }
+
+    /// Loads the level a replay was recorded on, refusing maps that changed since.
+    pub fn load_replay(&self, replay: &Replay) -> Result<Game> {
+        let mut game = Game::new(&replay.level, &self.mods, replay.seed)?;
+        game.tuning = replay.tuning;
+        game.set_difficulty(replay.difficulty, self.difficulties.get(replay.difficulty));
+        game.lives = replay.lives;
+        game.money = replay.money;
+        for _ in 1..replay.players {
+            game.add_player();
+        }
+        if replay.companion {
+            game.add_companion();
+        }
+        if game.map_hash != replay.map_hash {
+            return Err(GameError::InvalidReplay(format!(
+                "'{}' has changed since the replay was recorded",
+                replay.level,
+            )));
+        }
+        Ok(game)
+    }
+
+    /// Writes the replay of `game` when recording was requested.
+    pub fn save_replay(&self, game: &Game) -> Result<()> {
+        match (&self.record, game.replay()) {
+            (Some(path), Some(replay)) => { replay.save(path) }
+            _ => { Ok(()) }
+        }
+    }
+
+    /// Whether the settings make `action` a toggle.
+    fn is_toggle(&self, action: Action) -> bool {
+        self.settings.input.toggled.contains(&action)
+    }
+
+    /// Holds `action`, or flips it when the settings make it a toggle.
+    pub fn press_action(&mut self, action: Action) {
+        if self.is_toggle(action) && self.held_actions.contains(&action) {
+            self.held_actions.remove(&action);
+        } else {
+            self.held_actions.insert(action);
+        }
+    }
+
+    /// Lets go of `action` unless it is a toggle.
+    pub fn release_action(&mut self, action: Action) {
+        if !self.is_toggle(action) {
+            self.held_actions.remove(&action);
+        }
+    }
+
+    /// A finger landing on the touch button of `action`. Toggles flip and
+    /// stay that way when it lifts; other actions are held by the touch
+    /// controls while the finger rests on them.
+    pub fn touch_action(&mut self, action: Action) {
+        if self.is_toggle(action) {
+            self.press_action(action);
+        }
+    }
+
+    /// Whether `action` is held by a key, a toggle or a finger on the
+    /// touch controls.
+    pub fn is_held(&self, action: Action) -> bool {
+        self.held_actions.contains(&action) || (self.touch.is_held(action) && !self.is_toggle(action))
+    }
+
+    /// Translates the held actions and controller sticks into the controls
+    /// of each player.
+    pub fn inputs(&self) -> Inputs {
+        std::array::from_fn(|player| PlayerInput {
+            left: self.is_held(Action::MoveLeft.for_player(player)),
+            right: self.is_held(Action::MoveRight.for_player(player)),
+            jump: self.is_held(Action::Jump.for_player(player)),
+            shoot: self.is_held(Action::Shoot.for_player(player)),
+            fetch: player == 0 && self.is_held(Action::Fetch),
+            stick: (gamepad::shape(self.sticks[player], &self.settings.gamepad) * i8::MAX as f32).round() as i8,
+        })
+    }
+}
@@ -0,0 +1,164 @@
+//! Controls screen: lists every action with its key and rebinds the
+//! selected one to the next key pressed. The first row switches between
+//! the movement key presets.
+
+use log::info;
+use sdl2::keyboard::Keycode;
+
+use crate::audio::Music;
+use crate::bindings::{Action, Preset, Press};
+use crate::context::Context;
+use crate::error::Result;
+use crate::mouse::Mouse;
+use crate::paths::user_file;
+use crate::renderer::{Color, Renderer};
+use crate::settings::DEFAULT_SETTINGS;
+use crate::settings_menu::{Tab, TAB_BAR};
+use crate::state::State;
+use crate::text::text_size;
+
+pub struct ControlsState {
+    /// Selected row: 0 for the preset, then one per action.
+    selected: usize,
+    /// Waiting for the key to bind to the selected action.
+    waiting: bool,
+    /// Key of every action and the matching preset, refreshed after each
+    /// change for rendering.
+    keys: Vec<Keycode>,
+    preset: Option<Preset>,
+    /// Screen it was opened from.
+    back: Box<dyn State>,
+}
+
+impl ControlsState {
+    pub fn new(context: &Context, back: Box<dyn State>) -> Self {
+        let mut state = ControlsState {
+            selected: 0,
+            waiting: false,
+            keys: Vec::new(),
+            preset: None,
+            back,
+        };
+        state.refresh(context);
+        state
+    }
+
+    fn refresh(&mut self, context: &Context) {
+        self.keys = Action::BINDABLE.iter().map(|action| context.settings.keys.key(*action)).collect();
+        self.preset = context.settings.keys.preset();
+    }
+
+    /// Switches to the next preset on the preset row, otherwise waits for
+    /// the key to bind to the selected action.
+    fn activate(&mut self, context: &mut Context) -> Result<()> {
+        if self.selected > 0 {
+            self.waiting = true;
+            return Ok(());
+        }
+        let preset = self.preset.map(|preset| preset.next()).unwrap_or(Preset::ALL[0]);
+        context.settings.keys.apply(preset);
+        context.settings.save(&user_file(DEFAULT_SETTINGS))?;
+        info!("Switched to the {} preset", preset.label());
+        self.refresh(context);
+        Ok(())
+    }
+}
+
+const PIXEL: u32 = 3;
+const ROWS: usize = Action::BINDABLE.len() + 1;
+
+/// Top of the screen's contents and the height of one line on a screen of
+/// `height`, under the bar of tabs.
+fn layout(height: u32) -> (i32, i32) {
+    let height = height as i32 - TAB_BAR;
+    let rows = ROWS as i32 + 3;
+    let line_height = (text_size("X", PIXEL).1 as i32 * 2).min(height / (rows + 1)).max(1);
+    let top = TAB_BAR + (height - line_height * rows) / 2;
+    (top, line_height)
+}
+
+impl State for ControlsState {
+    fn name(&self) -> &'static str {
+        "controls"
+    }
+
+    fn music(&self) -> Music {
+        self.back.music()
+    }
+
+    fn handle_press(mut self: Box<Self>, context: &mut Context, press: &Press) -> Result<Box<dyn State>> {
+        if let (true, Some(keycode)) = (self.waiting, press.keycode) {
+            let action = Action::BINDABLE[self.selected - 1];
+            context.settings.keys.rebind(action, keycode);
+            context.settings.save(&user_file(DEFAULT_SETTINGS))?;
+            info!("Bound {} to {}", action.label(), keycode);
+            self.refresh(context);
+            self.waiting = false;
+            return Ok(self);
+        }
+        if let Some(tab) = Tab::Controls.switch(press) {
+            return Ok(tab.open(context, self.back));
+        }
+        if press.is(Action::Up) {
+            self.selected = (self.selected + ROWS - 1) % ROWS;
+        } else if press.is(Action::Down) {
+            self.selected = (self.selected + 1) % ROWS;
+        } else if press.is(Action::Confirm) {
+            self.activate(context)?;
+        } else if press.is(Action::Back) {
+            return Ok(self.back);
+        }
+        Ok(self)
+    }
+
+    /// Clicking a row selects it, clicking the selected row activates it.
+    fn handle_mouse(mut self: Box<Self>, context: &mut Context, mouse: &Mouse) -> Result<Box<dyn State>> {
+        if !mouse.is_click() || self.waiting {
+            return Ok(self);
+        }
+        if let Some(tab) = Tab::clicked(mouse) {
+            return Ok(tab.open(context, self.back));
+        }
+        let (top, line_height) = layout(mouse.screen.1);
+        let row = (mouse.y - top).div_euclid(line_height) - 2;
+        if row >= 0 && (row as usize) < ROWS {
+            if row as usize == self.selected {
+                self.activate(context)?;
+            }
+            self.selected = row as usize;
+        }
+        Ok(self)
+    }
+
+    fn tick(self: Box<Self>, _context: &mut Context) -> Result<Box<dyn State>> {
+        Ok(self)
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer) -> Result<()> {
+        Tab::Controls.render_bar(renderer)?;
+        let (width, height) = renderer.output_size()?;
+        let (top, line_height) = layout(height);
+        let title = "CONTROLS";
+        renderer.draw_text(title, (width as i32 - text_size(title, PIXEL).0 as i32) / 2, top, PIXEL, Color::WHITE)?;
+
+        let label_x = width as i32 / 2 - text_size("DEBUG OVERLAY  ", PIXEL).0 as i32;
+        let key_x = width as i32 / 2 + PIXEL as i32 * 8;
+        let preset = self.preset.map(|preset| preset.label().to_string()).unwrap_or_else(|| "CUSTOM".to_string());
+        let mut rows = vec![("PRESET", preset)];
+        for (action, key) in Action::BINDABLE.iter().zip(&self.keys) {
+            rows.push((action.label(), key.name().to_uppercase()));
+        }
+        for (index, (label, value)) in rows.into_iter().enumerate() {
+            let y = top + line_height * (index as i32 + 2);
+            let selected = index == self.selected;
+            let color = if selected { Color::YELLOW } else { Color::GRAY };
+            renderer.draw_text(label, label_x, y, PIXEL, color)?;
+            let value = if selected && self.waiting { "PRESS A KEY".to_string() } else { value };
+            renderer.draw_text(&value, key_x, y, PIXEL, color)?;
+        }
+
+        let hint = "ENTER TO CHANGE, TAB FOR MORE, BACKSPACE TO RETURN";
+        let hint_y = top + line_height * (ROWS as i32 + 3);
+        renderer.draw_text(hint, (width as i32 - text_size(hint, 2).0 as i32) / 2, hint_y, 2, Color::GRAY)
+    }
+}
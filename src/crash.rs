@@ -0,0 +1,102 @@
+//! Crash reports. Every game keeps its last inputs, and a panic during a
+//! tick writes `crash-<time>.txt` to the data directory with the level,
+//! seed and those inputs along with the panic message and a backtrace, so
+//! a reported crash can be reproduced. A panic anywhere else gets a report
+//! with only the message and backtrace.
+
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::panic::{self, AssertUnwindSafe};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::game::Game;
+use crate::paths::user_file;
+use crate::systems::input::Inputs;
+
+/// Inputs kept for the report, five seconds of ticks.
+const RECENT_INPUTS: usize = 300;
+
+thread_local! {
+    /// Whether this thread is inside [`watch`], which then writes the report.
+    static WATCHING: Cell<bool> = const { Cell::new(false) };
+    /// Message and backtrace of a panic [`watch`] is about to catch.
+    static PANIC: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// The inputs of the last ticks of a game, with the tick each was given to.
+#[derive(Default)]
+pub struct RecentInputs {
+    inputs: VecDeque<(u64, Inputs)>,
+}
+
+impl RecentInputs {
+    pub fn push(&mut self, tick: u64, inputs: Inputs) {
+        if self.inputs.len() == RECENT_INPUTS {
+            self.inputs.pop_front();
+        }
+        self.inputs.push_back((tick, inputs));
+    }
+}
+
+/// Writes a crash report on panic, then runs the default hook.
+pub fn install() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let report = format!("{}\n{}", info, std::backtrace::Backtrace::force_capture());
+        if WATCHING.get() {
+            PANIC.set(Some(report));
+        } else {
+            write_report(None, &report);
+        }
+        default_hook(info);
+    }));
+}
+
+/// Runs `tick` on `game`. Should it panic, the report is written with
+/// what the game was doing before the panic carries on.
+pub fn watch(game: &mut Game, tick: impl FnOnce(&mut Game)) {
+    let watching = WATCHING.replace(true);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| tick(game)));
+    WATCHING.set(watching);
+    if let Err(payload) = result {
+        // Without the hook installed there is nothing to report.
+        if let Some(report) = PANIC.take() {
+            write_report(Some(game), &report);
+        }
+        panic::resume_unwind(payload);
+    }
+}
+
+fn write_report(game: Option<&Game>, report: &str) {
+    let path = user_file(&format!("crash-{}.txt", SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()));
+    let mut contents = String::new();
+    match game {
+        Some(game) => { write_game(&mut contents, game) }
+        None => { contents.push_str("# Game\nno tick was running\n") }
+    }
+    contents.push_str("\n# Panic\n");
+    contents.push_str(report);
+    match std::fs::write(&path, contents) {
+        Ok(()) => { eprintln!("platformer: crash report written to {}", path) }
+        Err(error) => { eprintln!("platformer: unable to write crash report {}: {}", path, error) }
+    }
+}
+
+fn write_game(out: &mut String, game: &Game) {
+    let _ = writeln!(out, "# Game\nlevel: {}\nseed: {}\ntick: {}", game.level_path, game.seed, game.ticks);
+    if let Some((position, velocity)) = game.player_state() {
+        let _ = writeln!(
+            out,
+            "player: position {:.3} {:.3}, velocity {:.3} {:.3}",
+            position.x, position.y, velocity.x, velocity.y,
+        );
+    }
+    let _ = writeln!(out, "\n# Inputs (tick: left right jump stick of each player)");
+    for (tick, inputs) in &game.recent_inputs.inputs {
+        let players: Vec<String> = inputs.iter()
+            .map(|input| format!("{} {} {} {}", input.left as u8, input.right as u8, input.jump as u8, input.stick))
+            .collect();
+        let _ = writeln!(out, "{}: {}", tick, players.join(" | "));
+    }
+}
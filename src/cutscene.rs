@@ -0,0 +1,171 @@
+//! Short scripted scenes defined in a level's info file: the first player
+//! walks somewhere, lines of text are shown and the screen fades out and
+//! back in, one step after the other. A scene plays when the level starts
+//! or the first time a player enters a given tile, and Backspace skips
+//! the rest of it.
+//!
+//! The game keeps ticking through walks, waits and fades, with the input
+//! recorded like any other, so replays stay in step; it waits while text
+//! is shown.
+
+use serde::Deserialize;
+
+use crate::audio::Music;
+use crate::bindings::{Action, Press};
+use crate::components::{Body, Position};
+use crate::context::Context;
+use crate::error::Result;
+use crate::game::{Game, GameCommand};
+use crate::mouse::Mouse;
+use crate::render;
+use crate::renderer::{Color, Rect, Renderer};
+use crate::state::{self, PlayingState, State};
+use crate::systems::input::{Inputs, PlayerInput};
+
+/// Ticks after which a walk that can't reach its column gives up.
+const WALK_LIMIT: u32 = 600;
+/// How close to the middle of its column a walk has to get, in tiles.
+const WALK_TOLERANCE: f32 = 0.1;
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Cutscene {
+    /// Plays when the level starts.
+    pub start: bool,
+    /// Plays the first time a player enters this tile.
+    pub at: Option<(usize, usize)>,
+    pub steps: Vec<Step>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Step {
+    /// The first player walks to the given column.
+    Walk(usize),
+    /// Shows a line of text until it is confirmed.
+    Say(String),
+    /// Lets the given number of ticks go by.
+    Wait(u32),
+    /// Fades the screen to black over the given number of ticks.
+    FadeOut(u32),
+    /// Fades the screen back in over the given number of ticks.
+    FadeIn(u32),
+}
+
+/// Plays a cutscene over the game, then carries on playing.
+pub struct CutsceneState {
+    game: Game,
+    steps: Vec<Step>,
+    step: usize,
+    /// Ticks spent in the current step.
+    elapsed: u32,
+    /// How dark the screen is, from 0 to 1.
+    darkness: f32,
+}
+
+impl CutsceneState {
+    pub fn new(game: Game, cutscene: &Cutscene) -> Self {
+        CutsceneState { game, steps: cutscene.steps.clone(), step: 0, elapsed: 0, darkness: 0.0 }
+    }
+
+    fn next_step(&mut self) {
+        self.step += 1;
+        self.elapsed = 0;
+    }
+
+    /// Input walking the first player toward the middle of `column`, or
+    /// `None` once it is there.
+    fn walk_input(&self, column: usize) -> Option<PlayerInput> {
+        let mut query = self.game.world.query_one::<(&Position, &Body)>(self.game.players[0]);
+        let (position, body) = query.get().ok()?;
+        let offset = column as f32 + 0.5 - (position.x + body.width / 2.0);
+        if offset.abs() <= WALK_TOLERANCE {
+            return None;
+        }
+        Some(PlayerInput { left: offset < 0.0, right: offset > 0.0, ..PlayerInput::default() })
+    }
+
+    fn finish(self: Box<Self>, context: &mut Context) -> Result<Box<dyn State>> {
+        Ok(Box::new(PlayingState::new(context, self.game)))
+    }
+}
+
+impl State for CutsceneState {
+    fn name(&self) -> &'static str {
+        "cutscene"
+    }
+
+    fn music(&self) -> Music {
+        state::level_music(&self.game)
+    }
+
+    fn run_on_game(&mut self, command: GameCommand) -> bool {
+        command(&mut self.game);
+        true
+    }
+
+    fn handle_press(mut self: Box<Self>, context: &mut Context, press: &Press) -> Result<Box<dyn State>> {
+        if press.is(Action::Back) {
+            return self.finish(context);
+        }
+        if press.is(Action::Confirm) && matches!(self.steps.get(self.step), Some(Step::Say(_))) {
+            self.next_step();
+        }
+        Ok(self)
+    }
+
+    /// Clicking anywhere does the same as confirming.
+    fn handle_mouse(self: Box<Self>, context: &mut Context, mouse: &Mouse) -> Result<Box<dyn State>> {
+        if mouse.is_click() {
+            return self.handle_press(context, &Press::action(Action::Confirm));
+        }
+        Ok(self)
+    }
+
+    fn tick(mut self: Box<Self>, context: &mut Context) -> Result<Box<dyn State>> {
+        let Some(step) = self.steps.get(self.step).cloned() else { return self.finish(context) };
+        let mut inputs = Inputs::default();
+        match step {
+            Step::Say(_) => { return Ok(self) }
+            Step::Walk(column) => {
+                match self.walk_input(column) {
+                    Some(input) if self.elapsed < WALK_LIMIT => { inputs[0] = input }
+                    _ => {
+                        self.next_step();
+                        return Ok(self);
+                    }
+                }
+            }
+            Step::Wait(_) => {}
+            Step::FadeOut(ticks) => { self.darkness = (self.elapsed + 1) as f32 / ticks.max(1) as f32 }
+            Step::FadeIn(ticks) => { self.darkness = 1.0 - (self.elapsed + 1) as f32 / ticks.max(1) as f32 }
+        }
+        self.game.tick(inputs);
+        context.events.publish(self.game.drain_events());
+        self.elapsed += 1;
+        let done = match step {
+            Step::Wait(ticks) | Step::FadeOut(ticks) | Step::FadeIn(ticks) => { self.elapsed >= ticks }
+            _ => { false }
+        };
+        if done {
+            self.next_step();
+        }
+        if self.game.is_player_dead() || self.game.is_level_complete() {
+            return self.finish(context);
+        }
+        Ok(self)
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer) -> Result<()> {
+        render::render_game(&self.game, renderer)?;
+        if self.darkness > 0.0 {
+            let (width, height) = renderer.output_size()?;
+            let alpha = (self.darkness.clamp(0.0, 1.0) * 255.0) as u8;
+            renderer.fill_rect(Rect::new(0, 0, width, height), Color::RGBA(0, 0, 0, alpha))?;
+        }
+        match self.steps.get(self.step) {
+            Some(Step::Say(text)) => { render::render_dialog(renderer, text, "PRESS ENTER - BACKSPACE TO SKIP") }
+            _ => { renderer.draw_text("BACKSPACE TO SKIP", 4, 4, 2, Color::GRAY) }
+        }
+    }
+}
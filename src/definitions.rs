@@ -0,0 +1,63 @@
+//! Entity types described in data files rather than code. Each definition
+//! binds a map character to the components of the entity spawned there.
+
+use hecs::{Entity, World};
+use serde::Deserialize;
+
+use crate::components::{Body, Position, Sprite, Velocity};
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct EntityDefinition {
+    pub name: String,
+    /// Map character the entity is placed with.
+    pub code: char,
+    pub color: u32,
+    #[serde(default = "full_tile")]
+    pub width: f32,
+    #[serde(default = "full_tile")]
+    pub height: f32,
+}
+
+/// Contents of an `entities.toml` file.
+#[derive(Debug, Default, Deserialize)]
+pub struct EntityDefinitions {
+    #[serde(default, rename = "entity")]
+    pub entities: Vec<EntityDefinition>,
+}
+
+fn full_tile() -> f32 {
+    1.0
+}
+
+impl EntityDefinitions {
+    pub fn find(&self, code: char) -> Option<&EntityDefinition> {
+        self.entities.iter().find(|definition| definition.code == code)
+    }
+
+    pub fn find_named(&self, name: &str) -> Option<&EntityDefinition> {
+        self.entities.iter().find(|definition| definition.name == name)
+    }
+
+    /// Adds `other`, replacing existing definitions that use the same map character.
+    pub fn merge(&mut self, other: EntityDefinitions) {
+        for definition in other.entities {
+            self.entities.retain(|existing| existing.code != definition.code);
+            self.entities.push(definition);
+        }
+    }
+}
+
+impl EntityDefinition {
+    /// Spawns the entity resting on the bottom of the tile at `tile`.
+    pub fn spawn(&self, world: &mut World, tile: (usize, usize)) -> Entity {
+        world.spawn((
+            Position {
+                x: tile.0 as f32 + (1.0 - self.width) / 2.0,
+                y: tile.1 as f32 + 1.0 - self.height,
+            },
+            Velocity::default(),
+            Body::new(self.width, self.height),
+            Sprite { color: self.color },
+        ))
+    }
+}
@@ -0,0 +1,139 @@
+//! Difficulty picked on the title screen. Each difficulty has a profile in
+//! `difficulty.toml` scaling how fast enemies move, how hard they hit and
+//! how much health players start with, and whether checkpoints count.
+
+use std::path::Path;
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::player::PLAYER_HEALTH;
+use crate::tuning::PhysicsTuning;
+
+pub const DIFFICULTY: &str = "difficulty.toml";
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => { "EASY" }
+            Difficulty::Normal => { "NORMAL" }
+            Difficulty::Hard => { "HARD" }
+        }
+    }
+
+    /// Difficulty after this one, wrapping around.
+    pub fn next(&self) -> Difficulty {
+        let index = Difficulty::ALL.iter().position(|difficulty| difficulty == self).unwrap_or(0);
+        Difficulty::ALL[(index + 1) % Difficulty::ALL.len()]
+    }
+
+    /// Number the difficulty is stored as in replays.
+    pub fn code(&self) -> u8 {
+        Difficulty::ALL.iter().position(|difficulty| difficulty == self).unwrap_or(1) as u8
+    }
+
+    pub fn from_code(code: u8) -> Option<Difficulty> {
+        Difficulty::ALL.get(code as usize).copied()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    /// Factor on the speed of walking and flying enemies.
+    pub enemy_speed: f32,
+    /// Hit points taken by each hit from an enemy or its shots.
+    pub damage: u32,
+    /// Hit points players start with.
+    pub health: u32,
+    /// Whether reaching a checkpoint moves where players come back after
+    /// losing a life; without, they always start over from the start.
+    pub checkpoints: bool,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Profile { enemy_speed: 1.0, damage: 1, health: PLAYER_HEALTH, checkpoints: true }
+    }
+}
+
+impl Profile {
+    /// `physics` with enemies moving at this profile's speed.
+    pub fn enemy_tuning(&self, physics: &PhysicsTuning) -> PhysicsTuning {
+        PhysicsTuning {
+            enemy_speed: physics.enemy_speed * self.enemy_speed,
+            flight_speed: physics.flight_speed * self.enemy_speed,
+            ..*physics
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Profiles {
+    pub easy: Profile,
+    pub normal: Profile,
+    pub hard: Profile,
+}
+
+impl Default for Profiles {
+    fn default() -> Self {
+        Profiles {
+            easy: Profile { enemy_speed: 0.75, damage: 1, health: 5, checkpoints: true },
+            normal: Profile::default(),
+            hard: Profile { enemy_speed: 1.3, damage: 2, health: 2, checkpoints: false },
+        }
+    }
+}
+
+impl Profiles {
+    /// Reads the profiles at `path`. Without the file, or with a broken
+    /// one, the built-in profiles are used.
+    pub fn load(path: &str) -> Self {
+        if !Path::new(path).exists() {
+            return Profiles::default();
+        }
+        let profiles = std::fs::read_to_string(path)
+            .map_err(|error| error.to_string())
+            .and_then(|contents| toml::from_str(&contents).map_err(|error| error.to_string()));
+        match profiles {
+            Ok(profiles) => { profiles }
+            Err(error) => {
+                warn!("Ignoring difficulties in '{}': {}", path, error);
+                Profiles::default()
+            }
+        }
+    }
+
+    pub fn get(&self, difficulty: Difficulty) -> Profile {
+        match difficulty {
+            Difficulty::Easy => { self.easy }
+            Difficulty::Normal => { self.normal }
+            Difficulty::Hard => { self.hard }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_profiles_match_the_built_in_ones() {
+        let shipped: Profiles = toml::from_str(include_str!("../assets/difficulty.toml")).unwrap();
+        for difficulty in Difficulty::ALL {
+            assert_eq!(shipped.get(difficulty), Profiles::default().get(difficulty), "{:?}", difficulty);
+        }
+    }
+}
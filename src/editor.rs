@@ -0,0 +1,201 @@
+use sdl2::keyboard::Keycode;
+
+use crate::bindings::Press;
+use crate::block::Block;
+use crate::cart;
+use crate::context::Context;
+use crate::error::Result;
+use crate::color::split_rgb;
+use crate::enemy;
+use crate::level::{self, Level, Marker};
+use crate::mouse::{Mouse, MouseAction, MouseButton};
+use crate::loot;
+use crate::npc;
+use crate::saw;
+use crate::spawner;
+use crate::swing;
+use crate::switch;
+use crate::trap;
+use crate::player;
+use crate::render;
+use crate::renderer::{Color, Rect, Renderer};
+use crate::state::State;
+use crate::tuning::AnimationTuning;
+
+/// Map characters the editor can paint, selected with the number keys
+/// (0 for the tenth) or the wheel.
+const BRUSHES: [(char, &str); 38] = [
+    ('_', "EMPTY"),
+    ('%', "FLOOR"),
+    ('|', "WALL"),
+    ('#', "EXIT"),
+    ('o', "COIN"),
+    ('+', "HEART"),
+    ('^', "SPIKES"),
+    ('~', "LAVA"),
+    (level::PLAYER_CODE, "PLAYER"),
+    (enemy::ENEMY_CODE, "ENEMY"),
+    (enemy::CHASER_CODE, "CHASER"),
+    (enemy::TURRET_CODE, "TURRET"),
+    (enemy::FLYER_CODE, "FLYER"),
+    (enemy::TURN_CODE, "TURN"),
+    (npc::NPC_CODE, "NPC"),
+    ('k', "KEY"),
+    ('d', "DOOR"),
+    ('p', "POTION"),
+    ('g', "GEM"),
+    ('s', "SPEED"),
+    ('j', "JUMP"),
+    ('*', "STAR"),
+    (loot::CRATE_CODE, "CRATE"),
+    (spawner::SPAWNER_CODE, "SPAWNER"),
+    (saw::SAW_CODE, "SAW"),
+    (saw::RAIL_CODE, "RAIL"),
+    (trap::TRAP_CODE, "TRAP"),
+    (switch::BUTTON_CODE, "BUTTON"),
+    ('1', "GATE"),
+    (switch::PLATE_CODE, "PLATE"),
+    ('b', "BLOCK"),
+    (swing::ANCHOR_CODE, "ANCHOR"),
+    ('t', "TRACK"),
+    (cart::CART_CODE, "CART"),
+    ('w', "WATER"),
+    ('h', "HIDDEN STAR"),
+    (enemy::HUNTER_CODE, "HUNTER"),
+    ('i', "SIGN"),
+];
+
+/// Level editor: arrows or the mouse move the cursor, 1-9, 0 or the wheel pick
+/// a brush, space or the left button paints, the right button erases and S
+/// writes the level back to disk.
+pub struct EditorState {
+    path: String,
+    level: Level,
+    cursor: (usize, usize),
+    brush: usize,
+    saved: bool,
+}
+
+impl EditorState {
+    pub fn new(path: &str) -> Result<Self> {
+        let level = level::load_unstamped(path)?;
+        Ok(EditorState {
+            path: path.to_string(),
+            cursor: level.player_start,
+            level,
+            brush: 1,
+            saved: true,
+        })
+    }
+
+    fn move_cursor(&mut self, dx: isize, dy: isize) {
+        let x = self.cursor.0.saturating_add_signed(dx);
+        let y = self.cursor.1.saturating_add_signed(dy);
+        if self.level.playground.contains(x, y) {
+            self.cursor = (x, y);
+        }
+    }
+
+    fn paint(&mut self) {
+        self.paint_with(BRUSHES[self.brush].0);
+    }
+
+    fn paint_with(&mut self, code: char) {
+        let (x, y) = self.cursor;
+        self.level.markers.retain(|marker| marker.x != x || marker.y != y);
+        if code == level::PLAYER_CODE {
+            self.level.player_start = (x, y.saturating_sub(1));
+            self.level.playground.set_block(x, y, Block::from_code('%').unwrap());
+        } else if let Some(block) = Block::from_code(code) {
+            self.level.playground.set_block(x, y, block);
+        } else {
+            self.level.playground.set_block(x, y, Block::Empty);
+            self.level.markers.push(Marker { code, x, y });
+        }
+        self.saved = false;
+    }
+}
+
+impl State for EditorState {
+    fn name(&self) -> &'static str {
+        "editor"
+    }
+
+    fn handle_press(mut self: Box<Self>, _context: &mut Context, press: &Press) -> Result<Box<dyn State>> {
+        // Editing works on keys directly; its shortcuts are not bindable.
+        let Some(keycode) = press.keycode else {
+            return Ok(self);
+        };
+        match keycode {
+            Keycode::Left => { self.move_cursor(-1, 0) }
+            Keycode::Right => { self.move_cursor(1, 0) }
+            Keycode::Up => { self.move_cursor(0, -1) }
+            Keycode::Down => { self.move_cursor(0, 1) }
+            Keycode::Space | Keycode::Return => { self.paint() }
+            Keycode::Num0 => { self.brush = 9 }
+            Keycode::S => {
+                level::save(&self.path, &self.level)?;
+                self.saved = true;
+            }
+            _ => {
+                let digit = (keycode as i32) - (Keycode::Num1 as i32);
+                if (0..BRUSHES.len() as i32).contains(&digit) {
+                    self.brush = digit as usize;
+                }
+            }
+        }
+        Ok(self)
+    }
+
+    fn handle_mouse(mut self: Box<Self>, _context: &mut Context, mouse: &Mouse) -> Result<Box<dyn State>> {
+        if let MouseAction::Scrolled(amount) = mouse.action {
+            self.brush = (self.brush as i32 - amount).rem_euclid(BRUSHES.len() as i32) as usize;
+            return Ok(self);
+        }
+        let Some(tile) = self.level.playground.tile_at(mouse.screen, mouse.x, mouse.y) else {
+            return Ok(self);
+        };
+        self.cursor = tile;
+        match mouse.action {
+            MouseAction::Pressed(MouseButton::Left) => { self.paint() }
+            MouseAction::Pressed(MouseButton::Right) => { self.paint_with(BRUSHES[0].0) }
+            MouseAction::Moved if mouse.left => { self.paint() }
+            MouseAction::Moved if mouse.right => { self.paint_with(BRUSHES[0].0) }
+            _ => {}
+        }
+        Ok(self)
+    }
+
+    fn tick(self: Box<Self>, _context: &mut Context) -> Result<Box<dyn State>> {
+        Ok(self)
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer) -> Result<()> {
+        let canvas_size = renderer.output_size()?;
+        let scale = self.level.playground.scale_factor(canvas_size);
+        render::render_playground(&self.level.playground, renderer, scale, 0, &AnimationTuning::default())?;
+
+        for marker in &self.level.markers {
+            renderer.draw_rect(tile_rect((marker.x, marker.y), scale), Color::MAGENTA)?;
+        }
+        renderer.fill_rect(tile_rect(self.level.player_start, scale), Color::from(split_rgb(player::PLAYER_COLORS[0])))?;
+        renderer.draw_rect(tile_rect(self.cursor, scale), Color::WHITE)?;
+
+        let status = format!(
+            "BRUSH {}: {}{}",
+            self.brush + 1,
+            BRUSHES[self.brush].1,
+            if self.saved { "" } else { " *" },
+        );
+        renderer.draw_text(&status, 4, 4, 2, Color::WHITE)
+    }
+}
+
+fn tile_rect(tile: (usize, usize), scale: (u32, u32)) -> Rect {
+    Rect::new(
+        (tile.0 as u32 * scale.0) as i32,
+        (tile.1 as u32 * scale.1) as i32,
+        scale.0,
+        scale.1,
+    )
+}
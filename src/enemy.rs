@@ -0,0 +1,191 @@
+use hecs::{Entity, World};
+use serde::{Deserialize, Serialize};
+
+use crate::collision::Collider;
+use crate::components::{Body, Position, Sprite, Velocity};
+use crate::level::Level;
+use crate::navigation::Step;
+
+/// Map character that places a patrolling enemy.
+pub const ENEMY_CODE: char = 'E';
+/// Map character that places an enemy lying in wait, see [`Behavior::Chase`].
+pub const CHASER_CODE: char = 'H';
+/// Map character that places a turret, see [`Behavior::Turret`].
+pub const TURRET_CODE: char = 'T';
+/// Map character that places a flying enemy, see [`Behavior::Fly`].
+pub const FLYER_CODE: char = 'F';
+/// Map character that places an enemy finding its way to players, see
+/// [`Behavior::Hunt`].
+pub const HUNTER_CODE: char = 'U';
+/// Map character for an empty tile where flying enemies turn around.
+pub const TURN_CODE: char = ':';
+pub const ENEMY_WIDTH: f32 = 0.8;
+pub const ENEMY_HEIGHT: f32 = 0.7;
+pub const ENEMY_COLOR: u32 = 0xFF00FF;
+pub const CHASER_COLOR: u32 = 0xFF8C00;
+pub const TURRET_COLOR: u32 = 0x808080;
+pub const FLYER_COLOR: u32 = 0x20B2AA;
+pub const HUNTER_COLOR: u32 = 0xB22222;
+/// Ticks a hunter follows its route before working it out again.
+pub const ROUTE_INTERVAL: u32 = 30;
+
+/// Tiles a flying enemy rises and sinks either side of its height.
+pub const FLIGHT_AMPLITUDE: f32 = 1.0;
+/// Ticks a flying enemy takes to rise and sink back.
+pub const FLIGHT_PERIOD: f32 = 120.0;
+pub const TURRET_SHOT_COLOR: u32 = 0xFF4040;
+/// Ticks between two shots of a turret.
+pub const TURRET_INTERVAL: u32 = 90;
+/// Color of a chaser while it pursues a player.
+pub const ALERT_COLOR: u32 = 0xFF2020;
+
+/// Marks an entity that moves on its own and takes out players it touches.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Enemy {
+    #[serde(default)]
+    pub behavior: Behavior,
+    /// -1 when walking left, 1 when walking right.
+    pub direction: f32,
+    /// Where a chaser last saw a player, while it pursues one.
+    #[serde(default)]
+    pub target: Option<(f32, f32)>,
+    /// Ticks left before a turret can shoot again.
+    #[serde(default)]
+    pub reload: u32,
+    /// Factor on how fast it moves.
+    #[serde(default = "normal_speed")]
+    pub speed: f32,
+}
+
+fn normal_speed() -> f32 {
+    1.0
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Behavior {
+    /// Walks back and forth on its platform.
+    #[default]
+    Patrol,
+    /// Stands still until a player comes into view, then runs after them
+    /// and keeps going to where they were last seen.
+    Chase,
+    /// Never moves, and shoots at the nearest player in range every so
+    /// often, walls or not.
+    Turret,
+    /// Flies back and forth along its row, bobbing up and down, without
+    /// falling. See [`Flight`].
+    Fly,
+    /// Walks, jumps and drops from platform to platform along the
+    /// shortest way to the nearest player, wherever they are. See
+    /// [`Route`].
+    Hunt,
+}
+
+impl Behavior {
+    /// Whether players landing on top of the enemy defeat it rather than
+    /// get hurt.
+    pub fn can_be_stomped(&self) -> bool {
+        *self == Behavior::Patrol
+    }
+
+    /// Behavior by the name it has in level data, e.g. "chase".
+    pub fn from_name(name: &str) -> Option<Behavior> {
+        match name {
+            "patrol" => { Some(Behavior::Patrol) }
+            "chase" => { Some(Behavior::Chase) }
+            "turret" => { Some(Behavior::Turret) }
+            "fly" => { Some(Behavior::Fly) }
+            "hunt" => { Some(Behavior::Hunt) }
+            _ => { None }
+        }
+    }
+
+    /// Map character placing an enemy with this behavior.
+    pub fn code(&self) -> char {
+        match self {
+            Behavior::Patrol => { ENEMY_CODE }
+            Behavior::Chase => { CHASER_CODE }
+            Behavior::Turret => { TURRET_CODE }
+            Behavior::Fly => { FLYER_CODE }
+            Behavior::Hunt => { HUNTER_CODE }
+        }
+    }
+
+    /// Behavior of the enemy placed with map character `code`, if any.
+    pub fn from_code(code: char) -> Option<Behavior> {
+        match code {
+            ENEMY_CODE => { Some(Behavior::Patrol) }
+            CHASER_CODE => { Some(Behavior::Chase) }
+            TURRET_CODE => { Some(Behavior::Turret) }
+            FLYER_CODE => { Some(Behavior::Fly) }
+            HUNTER_CODE => { Some(Behavior::Hunt) }
+            _ => { None }
+        }
+    }
+
+    pub fn color(&self) -> u32 {
+        match self {
+            Behavior::Patrol => { ENEMY_COLOR }
+            Behavior::Chase => { CHASER_COLOR }
+            Behavior::Turret => { TURRET_COLOR }
+            Behavior::Fly => { FLYER_COLOR }
+            Behavior::Hunt => { HUNTER_COLOR }
+        }
+    }
+}
+
+/// Path of a flying enemy, which moves on its own rather than through
+/// physics.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Flight {
+    /// Horizontal span the enemy stays within.
+    pub left: f32,
+    pub right: f32,
+    /// Height the enemy bobs around.
+    pub base_y: f32,
+    /// Ticks into the current bob.
+    pub phase: f32,
+}
+
+impl Flight {
+    /// Path of a flying enemy starting in the tile at `tile`, spanning its
+    /// row up to the nearest solid blocks or [`TURN_CODE`] markers.
+    pub fn new(level: &Level, tile: (usize, usize)) -> Self {
+        let blocks = |x: usize| {
+            level.playground.block_at(x, tile.1).is_solid()
+                || level.markers.iter().any(|marker| marker.code == TURN_CODE && marker.x == x && marker.y == tile.1)
+        };
+        let left = (0..tile.0).rev().find(|x| blocks(*x)).map_or(0, |x| x + 1);
+        let right = (tile.0 + 1..level.playground.width).find(|x| blocks(*x)).unwrap_or(level.playground.width);
+        Flight { left: left as f32, right: right as f32, base_y: tile.1 as f32 + 1.0 - ENEMY_HEIGHT, phase: 0.0 }
+    }
+}
+
+/// Way a hunter is taking to a player, worked out every so often.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Route {
+    /// Steps left to take, the next one first.
+    pub steps: Vec<Step>,
+    /// Ticks before the route is worked out again.
+    pub timer: u32,
+}
+
+/// Spawns an enemy standing in the tile at `tile`, facing left.
+pub fn spawn(world: &mut World, tile: (usize, usize), behavior: Behavior) -> Entity {
+    let enemy = world.spawn((
+        Enemy { behavior, direction: -1.0, target: None, reload: TURRET_INTERVAL, speed: 1.0 },
+        Position {
+            x: tile.0 as f32 + (1.0 - ENEMY_WIDTH) / 2.0,
+            y: tile.1 as f32 + 1.0 - ENEMY_HEIGHT,
+        },
+        Velocity::default(),
+        Body::new(ENEMY_WIDTH, ENEMY_HEIGHT),
+        Sprite { color: behavior.color() },
+        Collider::ENEMY,
+    ));
+    if behavior == Behavior::Hunt {
+        let _ = world.insert_one(enemy, Route::default());
+    }
+    enemy
+}
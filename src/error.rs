@@ -0,0 +1,74 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum GameError {
+    /// A file the game depends on could not be read or written.
+    Io { path: String, source: std::io::Error },
+    /// The map file was read but does not describe a playable level.
+    InvalidMap(String),
+    /// The settings file exists but could not be parsed or written.
+    InvalidSettings(String),
+    /// A mod's manifest or data files could not be parsed.
+    InvalidMod(String),
+    /// A replay file is damaged or does not match the level it was recorded on.
+    InvalidReplay(String),
+    /// A save file is damaged or belongs to a different map.
+    InvalidSave(String),
+    /// An image, font or sound file could not be decoded.
+    InvalidAsset(String),
+    /// A level script failed to compile or to run its top-level code.
+    Script(String),
+    /// SDL reported a failure while initializing or drawing.
+    Sdl(String),
+}
+
+pub type Result<T> = std::result::Result<T, GameError>;
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameError::Io { path, source } => { write!(f, "unable to access '{}': {}", path, source) }
+            GameError::InvalidMap(reason) => { write!(f, "invalid map: {}", reason) }
+            GameError::InvalidSettings(reason) => { write!(f, "invalid settings: {}", reason) }
+            GameError::InvalidMod(reason) => { write!(f, "invalid mod: {}", reason) }
+            GameError::InvalidReplay(reason) => { write!(f, "invalid replay: {}", reason) }
+            GameError::InvalidSave(reason) => { write!(f, "invalid save: {}", reason) }
+            GameError::InvalidAsset(reason) => { write!(f, "invalid asset: {}", reason) }
+            GameError::Script(reason) => { write!(f, "script error: {}", reason) }
+            GameError::Sdl(reason) => { write!(f, "SDL error: {}", reason) }
+        }
+    }
+}
+
+impl std::error::Error for GameError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GameError::Io { source, .. } => { Some(source) }
+            _ => { None }
+        }
+    }
+}
+
+impl From<String> for GameError {
+    fn from(reason: String) -> Self {
+        GameError::Sdl(reason)
+    }
+}
+
+impl From<sdl2::video::WindowBuildError> for GameError {
+    fn from(error: sdl2::video::WindowBuildError) -> Self {
+        match error {
+            sdl2::video::WindowBuildError::SdlError(reason) => { GameError::Sdl(reason) }
+            other => { GameError::Sdl(other.to_string()) }
+        }
+    }
+}
+
+impl From<sdl2::IntegerOrSdlError> for GameError {
+    fn from(error: sdl2::IntegerOrSdlError) -> Self {
+        match error {
+            sdl2::IntegerOrSdlError::SdlError(reason) => { GameError::Sdl(reason) }
+            other => { GameError::Sdl(other.to_string()) }
+        }
+    }
+}
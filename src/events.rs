@@ -0,0 +1,162 @@
+//! Gameplay events published by the simulation and delivered to any
+//! number of subscribers, so presentation code never reaches into systems.
+
+use log::debug;
+
+use crate::block::Material;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GameEvent {
+    PlayerDied,
+    /// Player number `player` jumped off the ground.
+    PlayerJumped { player: usize },
+    /// Player number `player` took a step on or in the given tile, made
+    /// of `material`.
+    Footstep { player: usize, x: usize, y: usize, material: Material },
+    /// Player number `player` touched the ground after falling at `speed`
+    /// tiles per tick.
+    PlayerLanded { player: usize, speed: f32 },
+    /// Player number `player` was hit and has `health` hit points left.
+    PlayerHurt { player: usize, health: u32 },
+    /// A coin at the given tile was picked up.
+    CoinCollected { x: usize, y: usize },
+    /// A heart at the given tile was picked up.
+    HeartCollected { x: usize, y: usize },
+    /// A power-up at the given tile was picked up.
+    PowerUpCollected { x: usize, y: usize },
+    /// A star at the given tile was picked up, the cue for its fanfare.
+    StarCollected { x: usize, y: usize },
+    /// A hidden star at the given tile was picked up.
+    HiddenStarFound { x: usize, y: usize },
+    /// An item at the given tile was put in the inventory.
+    ItemCollected { x: usize, y: usize },
+    /// A door at the given tile was opened with a key.
+    DoorOpened { x: usize, y: usize },
+    /// The gates of `link` opened.
+    GateOpened { link: u32 },
+    /// The gates of `link` will close in `seconds`, raised once a second.
+    GateCountdown { link: u32, seconds: u32 },
+    /// The gates of `link` closed.
+    GateClosed { link: u32 },
+    /// Player number `player` was kept in the game by drinking a potion.
+    PotionUsed { player: usize },
+    /// A turret fired a shot from the given tile.
+    TurretFired { x: usize, y: usize },
+    /// An enemy was defeated around the given tile.
+    EnemyDefeated { x: usize, y: usize },
+    /// A crate around the given tile was broken.
+    CrateBroken { x: usize, y: usize },
+    /// The block at the given tile was removed from the playground.
+    TileDestroyed { x: usize, y: usize },
+    /// The companion was sent to fetch the collectible at the given tile.
+    CompanionSent { x: usize, y: usize },
+    /// Something was bought from a shop.
+    WareBought,
+    /// A player entered trigger number `index` of the level info.
+    TriggerEntered { index: usize },
+    /// A player is still in trigger number `index`, raised every tick.
+    TriggerStayed { index: usize },
+    /// The last player in trigger number `index` left it.
+    TriggerExited { index: usize },
+    /// Objective number `index` of the level info was completed.
+    ObjectiveCompleted { index: usize },
+    LevelCompleted,
+}
+
+impl GameEvent {
+    /// Variant name without its fields, as passed to level scripts.
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameEvent::PlayerDied => { "PlayerDied" }
+            GameEvent::PlayerJumped { .. } => { "PlayerJumped" }
+            GameEvent::Footstep { .. } => { "Footstep" }
+            GameEvent::PlayerLanded { .. } => { "PlayerLanded" }
+            GameEvent::PlayerHurt { .. } => { "PlayerHurt" }
+            GameEvent::CoinCollected { .. } => { "CoinCollected" }
+            GameEvent::HeartCollected { .. } => { "HeartCollected" }
+            GameEvent::PowerUpCollected { .. } => { "PowerUpCollected" }
+            GameEvent::StarCollected { .. } => { "StarCollected" }
+            GameEvent::HiddenStarFound { .. } => { "HiddenStarFound" }
+            GameEvent::ItemCollected { .. } => { "ItemCollected" }
+            GameEvent::DoorOpened { .. } => { "DoorOpened" }
+            GameEvent::GateOpened { .. } => { "GateOpened" }
+            GameEvent::GateCountdown { .. } => { "GateCountdown" }
+            GameEvent::GateClosed { .. } => { "GateClosed" }
+            GameEvent::PotionUsed { .. } => { "PotionUsed" }
+            GameEvent::TurretFired { .. } => { "TurretFired" }
+            GameEvent::EnemyDefeated { .. } => { "EnemyDefeated" }
+            GameEvent::CrateBroken { .. } => { "CrateBroken" }
+            GameEvent::TileDestroyed { .. } => { "TileDestroyed" }
+            GameEvent::CompanionSent { .. } => { "CompanionSent" }
+            GameEvent::WareBought => { "WareBought" }
+            GameEvent::TriggerEntered { .. } => { "TriggerEntered" }
+            GameEvent::TriggerStayed { .. } => { "TriggerStayed" }
+            GameEvent::TriggerExited { .. } => { "TriggerExited" }
+            GameEvent::ObjectiveCompleted { .. } => { "ObjectiveCompleted" }
+            GameEvent::LevelCompleted => { "LevelCompleted" }
+        }
+    }
+
+    /// Tile the event happened at, for events that have one.
+    pub fn tile(&self) -> Option<(usize, usize)> {
+        match *self {
+            GameEvent::CoinCollected { x, y }
+            | GameEvent::HeartCollected { x, y }
+            | GameEvent::PowerUpCollected { x, y }
+            | GameEvent::StarCollected { x, y }
+            | GameEvent::HiddenStarFound { x, y }
+            | GameEvent::ItemCollected { x, y }
+            | GameEvent::DoorOpened { x, y }
+            | GameEvent::TurretFired { x, y }
+            | GameEvent::EnemyDefeated { x, y }
+            | GameEvent::CrateBroken { x, y }
+            | GameEvent::CompanionSent { x, y }
+            | GameEvent::TileDestroyed { x, y }
+            | GameEvent::Footstep { x, y, .. } => { Some((x, y)) }
+            _ => { None }
+        }
+    }
+}
+
+pub trait Subscriber {
+    fn on_event(&mut self, event: &GameEvent);
+}
+
+impl<F: FnMut(&GameEvent)> Subscriber for F {
+    fn on_event(&mut self, event: &GameEvent) {
+        self(event)
+    }
+}
+
+#[derive(Default)]
+pub struct EventBus {
+    subscribers: Vec<Box<dyn Subscriber>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        EventBus::default()
+    }
+
+    pub fn subscribe(&mut self, subscriber: Box<dyn Subscriber>) {
+        self.subscribers.push(subscriber);
+    }
+
+    /// Delivers every event, in order, to every subscriber.
+    pub fn publish<I: IntoIterator<Item = GameEvent>>(&mut self, events: I) {
+        for event in events {
+            for subscriber in self.subscribers.iter_mut() {
+                subscriber.on_event(&event);
+            }
+        }
+    }
+}
+
+/// Writes every event to the debug log.
+pub struct EventLogger;
+
+impl Subscriber for EventLogger {
+    fn on_event(&mut self, event: &GameEvent) {
+        debug!("Game event: {:?}", event);
+    }
+}
@@ -0,0 +1,1425 @@
+use std::collections::BTreeMap;
+
+use hecs::{Entity, World};
+use log::{info, warn};
+
+use crate::block::Block;
+use crate::cart::{self, Riding};
+use crate::companion::{self, Companion, Errand, FETCH_RANGE};
+use crate::crash::{self, RecentInputs};
+use crate::cutscene::Cutscene;
+use crate::enemy::{self, Behavior, Enemy, Flight};
+use crate::components::{Body, Bounds, Position, Sprite, Velocity};
+use crate::definitions::{EntityDefinition, EntityDefinitions};
+use crate::difficulty::{Difficulty, Profile};
+use crate::error::{GameError, Result};
+use crate::events::GameEvent;
+use crate::inventory::{Inventory, Item};
+use crate::level::{self, EntitySpawn, Level, LevelInfo, Marker};
+use crate::loot::{self, Loot, Prize};
+use crate::mods::Mods;
+use crate::navigation::{PlatformGraph, Reach};
+use crate::npc::{self, Npc, TALK_REACH};
+use crate::particles::{Particles, PARTICLE_SIZE};
+use crate::player::{self, Defeated, Health, Player, HURT_COLOR, INVULNERABLE_TICKS, KNOCKBACK, PLAYER_COLORS, PLAYER_HEALTH, STUN_TICKS};
+use crate::playground::Playground;
+use crate::powerup::{Boost, PowerUp, STAR_COLORS};
+use crate::projectile::Projectiles;
+use crate::replay::{Intervention, Replay};
+use crate::rng::Rng;
+use crate::save::{self, SaveGame};
+use crate::saw;
+use crate::score::{self, Combo, Popups};
+use crate::shop::{Offer, Ware};
+use crate::script::{Script, ScriptCommand, ScriptView};
+use crate::spawner::{self, Spawned, Spawner};
+use crate::swing::{self, Flung, Swinging};
+use crate::switch;
+use crate::systems;
+use crate::systems::input::{Inputs, MAX_PLAYERS};
+use crate::systems::projectile::Hit;
+use crate::trap::{self, TRAP_COLOR};
+use crate::trigger::{self, TriggerState, MESSAGE_TICKS};
+use crate::tuning::{PhysicsTuning, Tuning};
+use crate::waypoint::Follow;
+
+/// A change made to a running game from outside, e.g. by the console.
+pub type GameCommand = Box<dyn FnOnce(&mut Game) + Send>;
+
+const DUST_COLOR: u32 = 0xC8C8C8;
+const DEBRIS_COLOR: u32 = 0x8B5A2B;
+const SPARKLE_COLOR: u32 = 0xFFF5A0;
+const SPARK_COLOR: u32 = 0xFFD060;
+/// Ticks between two tiles a pushable block drops.
+const BLOCK_FALL_INTERVAL: u64 = 6;
+
+pub struct Game {
+    /// Level path as requested, before mod overrides are applied.
+    pub level_path: String,
+    pub world: World,
+    pub playground: Playground,
+    /// Every player in the game, in player order. The first one is the one
+    /// scripts, the console and the level's events follow.
+    pub players: Vec<Entity>,
+    /// Tile the players start in.
+    pub player_start: (usize, usize),
+    /// Tile of the last checkpoint reached, where players come back in
+    /// place of `player_start`.
+    pub checkpoint: Option<(usize, usize)>,
+    /// Lives lost in this level so far.
+    pub deaths: u32,
+    pub ticks: u64,
+    pub seed: u64,
+    pub map_hash: u64,
+    /// Shared by every system and script; see [`crate::rng`].
+    pub rng: Rng,
+    pub tuning: Tuning,
+    pub difficulty: Difficulty,
+    /// What `difficulty` does, as read when the game started.
+    pub profile: Profile,
+    /// Jumps and drops between platforms, for enemies finding their way.
+    pub platforms: PlatformGraph,
+    pub particles: Particles,
+    pub projectiles: Projectiles,
+    /// Coins picked up so far, out of the `total_coins` the level started with.
+    pub coins: u32,
+    pub total_coins: u32,
+    /// Tiles of the hidden stars picked up so far, out of the
+    /// `total_hidden_stars` the level started with.
+    pub hidden_stars: Vec<(usize, usize)>,
+    pub total_hidden_stars: u32,
+    /// Tiles of the signs the level started with, row by row, in the
+    /// order the level info lists what they say.
+    pub signs: Vec<(usize, usize)>,
+    /// Points earned in this level and the levels before it.
+    pub score: u32,
+    pub combo: Combo,
+    /// Tries left, including the current one.
+    pub lives: u32,
+    /// Coins to spend in shops, picked up in this level and the levels
+    /// before it.
+    pub money: u32,
+    pub inventory: Inventory,
+    /// Ticks left before the gates of each open link close.
+    pub links: BTreeMap<u32, u32>,
+    pub popups: Popups,
+    pub info: LevelInfo,
+    /// Progress toward every objective of the level info, in order.
+    pub objectives: Vec<u32>,
+    /// Cutscenes of the level info played so far, by index.
+    pub played_cutscenes: Vec<usize>,
+    /// Cutscene waiting to be played, which stops the simulation.
+    pending_cutscene: Option<usize>,
+    /// State of every trigger of the level info, in order.
+    pub triggers: Vec<TriggerState>,
+    /// Line shown by a trigger and the ticks it stays up.
+    pub message: Option<(String, u32)>,
+    /// Track a trigger switched the music to, in place of the level's.
+    pub music: Option<String>,
+    /// Events raised since the last call to [`Game::drain_events`].
+    events: Vec<GameEvent>,
+    script: Option<Script>,
+    /// Replay of the level so far, while recording one.
+    recording: Option<Replay>,
+    /// Whether fetch was held on the previous tick, so holding it sends
+    /// the companion only once.
+    fetching: bool,
+    /// Tile the player was in after the previous tick, to detect entering a new one.
+    last_player_tile: Option<(usize, usize)>,
+    /// Inputs of the last ticks, for crash reports.
+    pub recent_inputs: RecentInputs,
+}
+
+impl Game {
+    /// Loads the level at `path` together with its script and info, if it
+    /// has them, letting mods override any of these files.
+    pub fn new(path: &str, mods: &Mods, seed: u64) -> Result<Self> {
+        let level = level::load(&mods.resolve(path))?;
+        let mut game = Game::from_level(level, &mods.definitions, seed);
+        info!("Starting '{}' with seed {}", path, seed);
+        game.level_path = path.to_string();
+        game.script = Script::load(&mods.resolve(&Script::path_for(path)))?;
+        game.info = LevelInfo::load(&mods.resolve(&LevelInfo::path_for(path)))?;
+        game.objectives = vec![0; game.info.objectives.len()];
+        game.triggers = vec![TriggerState::default(); game.info.triggers.len()];
+        if let Some(index) = game.info.cutscenes.iter().position(|cutscene| cutscene.start) {
+            game.queue_cutscene(index);
+        }
+        game.run_script("on_start", ());
+        Ok(game)
+    }
+
+    pub fn from_level(mut level: Level, definitions: &EntityDefinitions, seed: u64) -> Self {
+        let mut world = World::new();
+        let player = player::spawn(&mut world, level.player_start, 0, PLAYER_HEALTH);
+        let mut npcs = 0;
+        let mut spawners = 0;
+        let mut saws = 0;
+        let mut buttons = 0;
+        let mut plates = 0;
+        let mut carts = Vec::new();
+        // Entities listed below the rows are placed like the map characters
+        // they stand for, then given their properties.
+        let first_listed = level.markers.len();
+        let mut listed = Vec::new();
+        for spawn in &level.entities {
+            match marker_code(spawn, definitions) {
+                Some(code) => {
+                    level.markers.push(Marker { code, x: spawn.x, y: spawn.y });
+                    listed.push(spawn);
+                }
+                None => { warn!("Ignoring unknown entity '{}' at {}:{}", spawn.kind, spawn.y + 1, spawn.x + 1) }
+            }
+        }
+        for (index, marker) in level.markers.iter().enumerate() {
+            let spawn = index.checked_sub(first_listed).map(|index| listed[index]);
+            if let Some(behavior) = Behavior::from_code(marker.code) {
+                let enemy = enemy::spawn(&mut world, (marker.x, marker.y), behavior);
+                match spawn.and_then(|spawn| follow(&level, spawn)) {
+                    Some(follow) if behavior == Behavior::Fly => { let _ = world.insert_one(enemy, follow); }
+                    Some(_) => { warn!("Only flying enemies follow paths, ignoring the path at {}:{}", marker.y + 1, marker.x + 1) }
+                    None if behavior == Behavior::Fly => { let _ = world.insert_one(enemy, Flight::new(&level, (marker.x, marker.y))); }
+                    None => {}
+                }
+                if let (Some(spawn), Ok(mut enemy)) = (spawn, world.get::<&mut Enemy>(enemy)) {
+                    enemy.speed = spawn.property("speed").unwrap_or(enemy.speed);
+                    enemy.direction = match spawn.properties.get("facing").map(String::as_str) {
+                        Some("right") => { 1.0 }
+                        Some("left") => { -1.0 }
+                        _ => { enemy.direction }
+                    };
+                }
+                continue;
+            }
+            if marker.code == saw::SAW_CODE {
+                let saw = saw::spawn(&mut world, &level, (marker.x, marker.y), saws);
+                if let Some(follow) = spawn.and_then(|spawn| follow(&level, spawn)) {
+                    let _ = world.insert_one(saw, follow);
+                }
+                saws += 1;
+                continue;
+            }
+            if marker.code == enemy::TURN_CODE || marker.code == saw::RAIL_CODE {
+                continue;
+            }
+            if marker.code == loot::CRATE_CODE {
+                loot::spawn_crate(&mut world, (marker.x, marker.y));
+                continue;
+            }
+            if marker.code == switch::BUTTON_CODE {
+                switch::spawn(&mut world, (marker.x, marker.y), buttons);
+                buttons += 1;
+                continue;
+            }
+            if marker.code == switch::PLATE_CODE {
+                switch::spawn_plate(&mut world, (marker.x, marker.y), plates);
+                plates += 1;
+                continue;
+            }
+            if marker.code == cart::CART_CODE {
+                cart::spawn(&mut world, (marker.x, marker.y));
+                carts.push((marker.x, marker.y));
+                continue;
+            }
+            if marker.code == swing::ANCHOR_CODE {
+                swing::spawn(&mut world, (marker.x, marker.y));
+                continue;
+            }
+            if marker.code == trap::TRAP_CODE {
+                trap::spawn(&mut world, (marker.x, marker.y));
+                continue;
+            }
+            if marker.code == spawner::SPAWNER_CODE {
+                spawner::spawn(&mut world, &level, (marker.x, marker.y), spawners);
+                spawners += 1;
+                continue;
+            }
+            if marker.code == npc::NPC_CODE {
+                npc::spawn(&mut world, (marker.x, marker.y), npcs);
+                npcs += 1;
+                continue;
+            }
+            match definitions.find(marker.code) {
+                Some(definition) => { definition.spawn(&mut world, (marker.x, marker.y)); }
+                None => { warn!("Ignoring unknown map character {:?} at {}:{}", marker.code, marker.y + 1, marker.x + 1) }
+            }
+        }
+        // Carts stand on a piece of track of their own.
+        for (x, y) in carts {
+            level.playground.set_block(x, y, Block::from_code('t').unwrap());
+        }
+        let total_coins = level.playground.tiles_where(|block| matches!(block, Block::Coin { .. })).len() as u32;
+        let total_hidden_stars = level.playground.tiles_where(|block| matches!(block, Block::HiddenStar { .. })).len() as u32;
+        let signs = level.playground.tiles_where(|block| matches!(block, Block::Sign { .. }));
+        let physics = Tuning::default().physics;
+        let platforms = PlatformGraph::build(&level.playground, Reach::new(physics.jump_speed, physics.gravity, physics.chase_speed));
+        Game {
+            level_path: String::new(),
+            world,
+            playground: level.playground,
+            players: vec![player],
+            player_start: level.player_start,
+            checkpoint: None,
+            deaths: 0,
+            ticks: 0,
+            seed,
+            map_hash: level.hash,
+            rng: Rng::new(seed),
+            tuning: Tuning::default(),
+            difficulty: Difficulty::default(),
+            profile: Profile::default(),
+            platforms,
+            particles: Particles::new(),
+            projectiles: Projectiles::new(),
+            coins: 0,
+            total_coins,
+            hidden_stars: Vec::new(),
+            total_hidden_stars,
+            signs,
+            score: 0,
+            combo: Combo::default(),
+            lives: 1,
+            money: 0,
+            inventory: Inventory::default(),
+            links: BTreeMap::new(),
+            popups: Popups::new(),
+            info: LevelInfo::default(),
+            objectives: Vec::new(),
+            played_cutscenes: Vec::new(),
+            pending_cutscene: None,
+            triggers: Vec::new(),
+            message: None,
+            music: None,
+            events: Vec::new(),
+            script: None,
+            recording: None,
+            fetching: false,
+            last_player_tile: Some(level.player_start),
+            recent_inputs: RecentInputs::default(),
+        }
+    }
+
+    /// Restores a saved game on top of a fresh load of its level.
+    pub fn restore(saved: &SaveGame, mods: &Mods) -> Result<Self> {
+        let mut game = Game::new(&saved.level, mods, saved.seed)?;
+        if game.map_hash != saved.map_hash {
+            return Err(GameError::InvalidSave(format!("'{}' has changed since the game was saved", saved.level)));
+        }
+        save::restore_rows(&mut game.playground, &saved.rows)?;
+        game.players = save::restore_entities(&mut game.world, &saved.entities)?;
+        let mut projectiles = saved.projectiles.clone();
+        projectiles.extend(save::legacy_projectiles(&saved.entities));
+        game.projectiles.restore(&projectiles);
+        game.ticks = saved.ticks;
+        game.rng = saved.rng;
+        game.difficulty = saved.difficulty;
+        game.profile = saved.profile;
+        game.coins = saved.coins;
+        game.hidden_stars.clone_from(&saved.hidden_stars);
+        game.score = saved.score;
+        game.combo = saved.combo;
+        game.lives = saved.lives;
+        game.money = saved.money;
+        game.inventory = saved.inventory.clone();
+        game.links.clone_from(&saved.links);
+        for link in game.links.keys().copied().collect::<Vec<_>>() {
+            game.set_gates(link, true);
+        }
+        game.played_cutscenes = saved.played_cutscenes.clone();
+        if saved.objectives.len() == game.objectives.len() {
+            game.objectives.clone_from(&saved.objectives);
+        }
+        game.pending_cutscene = None;
+        if saved.triggers.len() == game.triggers.len() {
+            game.triggers.clone_from(&saved.triggers);
+        }
+        game.music.clone_from(&saved.music);
+        game.checkpoint = saved.checkpoint;
+        game.deaths = saved.deaths;
+        game.last_player_tile = game.player_tile(game.players[0]);
+        info!("Restored '{}' at tick {}", saved.level, saved.ticks);
+        Ok(game)
+    }
+
+    pub fn snapshot(&self) -> SaveGame {
+        SaveGame {
+            level: self.level_path.clone(),
+            map_hash: self.map_hash,
+            seed: self.seed,
+            ticks: self.ticks,
+            rng: self.rng,
+            difficulty: self.difficulty,
+            profile: self.profile,
+            coins: self.coins,
+            hidden_stars: self.hidden_stars.clone(),
+            score: self.score,
+            combo: self.combo,
+            lives: self.lives,
+            money: self.money,
+            inventory: self.inventory.clone(),
+            links: self.links.clone(),
+            objectives: self.objectives.clone(),
+            played_cutscenes: self.played_cutscenes.clone(),
+            triggers: self.triggers.clone(),
+            music: self.music.clone(),
+            checkpoint: self.checkpoint,
+            deaths: self.deaths,
+            rows: save::save_rows(&self.playground),
+            entities: save::save_entities(&self.world),
+            projectiles: self.projectiles.iter().copied().collect(),
+        }
+    }
+
+    /// Plays on `difficulty`, doing what `profile` says: gives every
+    /// player the health it starts them with, and forgets the checkpoint
+    /// reached when checkpoints don't count.
+    pub fn set_difficulty(&mut self, difficulty: Difficulty, profile: Profile) {
+        self.difficulty = difficulty;
+        self.profile = profile;
+        if !profile.checkpoints {
+            self.checkpoint = None;
+        }
+        for player in &self.players {
+            if let Ok(mut health) = self.world.get::<&mut Health>(*player) {
+                *health = Health::new(profile.health.max(1));
+            }
+        }
+    }
+
+    /// Adds another player at the start of the level. Does nothing once
+    /// the game has [`MAX_PLAYERS`].
+    pub fn add_player(&mut self) {
+        if self.players.len() < MAX_PLAYERS {
+            let player = player::spawn(&mut self.world, self.player_start, self.players.len(), self.profile.health.max(1));
+            self.players.push(player);
+        }
+    }
+
+    /// Brings a companion along, next to the first player, unless there
+    /// already is one.
+    pub fn add_companion(&mut self) {
+        if self.world.query::<&Companion>().iter().next().is_some() {
+            return;
+        }
+        if let Some(center) = self.leader() {
+            companion::spawn(&mut self.world, center);
+        }
+    }
+
+    /// Sends the companion, when it isn't already on an errand, to fetch
+    /// the nearest coin or item it can get to. Returns whether it went.
+    pub fn send_companion(&mut self) -> bool {
+        let playground = &self.playground;
+        let errand = self.world.query_mut::<(&Position, &Body, &mut Companion)>().into_iter()
+            .find(|(_, _, companion)| companion.errand == Errand::Following)
+            .and_then(|(position, body, companion)| {
+                let from = ((position.x + body.width / 2.0).max(0.0) as usize, (position.y + body.height / 2.0).max(0.0) as usize);
+                let path = playground.find_path(from, FETCH_RANGE, |block| matches!(block, Block::Coin { .. } | Block::Item { .. }))?;
+                let target = *path.last()?;
+                companion.errand = Errand::Fetching { path: path.into(), back: Vec::new() };
+                Some(target)
+            });
+        let Some((x, y)) = errand else { return false };
+        self.events.push(GameEvent::CompanionSent { x, y });
+        true
+    }
+
+    /// Middle of the top edge of the first player while still in the game,
+    /// where the companion floats.
+    fn leader(&self) -> Option<(f32, f32)> {
+        if self.world.satisfies::<&Defeated>(self.players[0]) {
+            return None;
+        }
+        let mut query = self.world.query_one::<(&Position, &Body)>(self.players[0]);
+        let (position, body) = query.get().ok()?;
+        Some((position.x + body.width / 2.0, position.y))
+    }
+
+    /// Puts every player back at the last checkpoint reached or the start
+    /// of the level, unhurt, without boosts and briefly protected, leaving
+    /// the rest of the level as it is.
+    pub fn respawn(&mut self) {
+        let start = self.checkpoint.unwrap_or(self.player_start);
+        for player in self.players.clone() {
+            let _ = self.world.remove_one::<Defeated>(player);
+            let _ = self.world.remove_one::<Boost>(player);
+            let _ = self.world.remove_one::<Swinging>(player);
+            let _ = self.world.remove_one::<Flung>(player);
+            let _ = self.world.remove_one::<Riding>(player);
+            if let Ok((position, velocity, body, health)) = self.world
+                .query_one_mut::<(&mut Position, &mut Velocity, &Body, &mut Health)>(player) {
+                position.x = start.0 as f32 + (1.0 - body.width) / 2.0;
+                position.y = start.1 as f32 + 1.0 - body.height;
+                *velocity = Velocity::default();
+                *health = Health { invulnerable: INVULNERABLE_TICKS - STUN_TICKS, ..Health::new(health.max) };
+            }
+        }
+        self.last_player_tile = self.player_tile(self.players[0]);
+        self.record(Intervention::Respawn { lives: self.lives });
+        info!("Respawned with {} lives left", self.lives);
+    }
+
+    /// Starts keeping the input of every tick, and what is done between
+    /// ticks, for [`Game::replay`].
+    pub fn start_recording(&mut self) {
+        self.recording = Some(Replay {
+            level: self.level_path.clone(),
+            map_hash: self.map_hash,
+            seed: self.seed,
+            players: self.players.len(),
+            difficulty: self.difficulty,
+            lives: self.lives,
+            money: self.money,
+            companion: self.world.query::<&Companion>().iter().next().is_some(),
+            tuning: self.tuning,
+            interventions: Vec::new(),
+            inputs: Vec::new(),
+        });
+    }
+
+    /// Everything needed to replay the level up to now, if recording.
+    pub fn replay(&self) -> Option<Replay> {
+        self.recording.clone()
+    }
+
+    /// Keeps `intervention` in the replay being recorded, if any.
+    fn record(&mut self, intervention: Intervention) {
+        if let Some(recording) = &mut self.recording {
+            recording.interventions.push((self.ticks, intervention));
+        }
+    }
+
+    /// Does again what a replay says was done between ticks.
+    pub fn intervene(&mut self, intervention: &Intervention) {
+        match intervention {
+            Intervention::Respawn { lives } => {
+                self.lives = *lives;
+                self.deaths += 1;
+                self.respawn();
+            }
+            Intervention::Buy(offer) => {
+                self.buy(offer);
+            }
+            Intervention::Tune(tuning) => { self.tuning = *tuning }
+        }
+    }
+
+    /// Runs one tick, writing a crash report should it panic.
+    pub fn tick(&mut self, inputs: Inputs) {
+        self.recent_inputs.push(self.ticks, inputs);
+        crash::watch(self, |game| game.run_tick(inputs));
+    }
+
+    fn run_tick(&mut self, inputs: Inputs) {
+        if let Some(recording) = &mut self.recording {
+            if recording.latest_tuning() != self.tuning {
+                recording.interventions.push((self.ticks, Intervention::Tune(self.tuning)));
+            }
+            recording.inputs.push(inputs);
+        }
+        let first_event = self.events.len();
+        let was_over = self.is_player_dead() || self.is_level_complete();
+        self.run_script("on_tick", (self.ticks as i64,));
+        let were_on_ground: Vec<bool> = self.players.iter().map(|player| self.is_on_ground(*player)).collect();
+        let fall_speeds: Vec<f32> = self.players.iter()
+            .map(|player| self.world.get::<&Velocity>(*player).map(|velocity| velocity.y).unwrap_or_default())
+            .collect();
+        for player in systems::input::run(&mut self.world, &inputs, &self.tuning.physics) {
+            self.events.push(GameEvent::PlayerJumped { player });
+        }
+        systems::swing::run(&mut self.world, &self.playground, &inputs, &self.tuning.physics);
+        systems::projectile::fire(&mut self.world, &mut self.projectiles, &inputs, &self.tuning.physics);
+        for spawner in systems::spawner::run(&mut self.world, &self.info.spawners, self.tuning.physics.spawner_range) {
+            self.spawn_from(spawner);
+        }
+        let enemy_tuning = self.profile.enemy_tuning(&self.tuning.physics);
+        self.update_platforms(&enemy_tuning);
+        for (x, y) in systems::enemy::run(&mut self.world, &mut self.projectiles, &self.playground, &self.platforms, &enemy_tuning) {
+            self.events.push(GameEvent::TurretFired { x: x.max(0.0) as usize, y: y.max(0.0) as usize });
+        }
+        systems::trap::run(&mut self.world, &self.playground);
+        systems::saw::run(&mut self.world, &self.info.saws, self.tuning.physics.saw_speed);
+        systems::waypoint::run(&mut self.world, &self.info.saws, &enemy_tuning);
+        systems::water::run(&mut self.world, &self.playground, &self.info.currents, &inputs, &self.tuning.physics);
+        systems::physics::run(&mut self.world, &self.playground, &self.tuning.physics);
+        for (player, (x, y), material) in systems::footstep::run(&mut self.world, &self.playground, &self.info.materials) {
+            self.events.push(GameEvent::Footstep { player, x, y, material });
+        }
+        systems::cart::run(&mut self.world, &self.playground, self.tuning.physics.cart_speed);
+        for hit in systems::projectile::run(&self.world, &mut self.projectiles, &self.playground) {
+            match hit {
+                Hit::Wall { x, y } => { self.particles.burst(x, y, 4, 0.04, 8, SPARK_COLOR) }
+                Hit::Enemy(enemy) => { self.defeat_enemy(enemy) }
+                Hit::Crate(crate_) => { self.break_crate(crate_) }
+                Hit::Player(player, from) => { self.hurt_player(player, self.profile.damage, from) }
+            }
+        }
+        self.update_boosts();
+        self.update_invulnerability();
+        for contact in systems::enemy::players_hit(&self.world) {
+            let stomped = contact.from_above && self.world.get::<&Enemy>(contact.enemy)
+                .is_ok_and(|enemy| enemy.behavior.can_be_stomped());
+            if stomped {
+                self.bounce(contact.player);
+                self.defeat_enemy(contact.enemy);
+                self.combo.extend();
+            } else if self.has_star(contact.player) {
+                self.defeat_enemy(contact.enemy);
+            } else {
+                self.hurt_player(contact.player, self.profile.damage, contact.from);
+            }
+        }
+        for (player, crate_) in systems::loot::crates_landed_on(&self.world) {
+            self.bounce(player);
+            self.break_crate(crate_);
+        }
+        for (player, from) in systems::saw::players_hit(&self.world) {
+            self.hurt_player(player, 1, from);
+        }
+        for (player, trap, from) in systems::trap::players_hit(&self.world) {
+            self.hurt_player(player, 1, from);
+            self.shatter_trap(trap);
+        }
+        for trap in systems::trap::landed(&self.world) {
+            self.shatter_trap(trap);
+        }
+        for (player, from) in systems::hazard::players_on_spikes(&self.world, &self.playground) {
+            self.hurt_player(player, 1, from);
+        }
+        for player in systems::hazard::players_in_lava(&self.world, &self.playground) {
+            self.kill_player(player);
+        }
+        let fetch = inputs.iter().any(|input| input.fetch);
+        if fetch && !self.fetching {
+            self.send_companion();
+        }
+        self.fetching = fetch;
+        self.move_companion();
+        self.collect_pickups();
+        self.collect_loot();
+        self.open_doors();
+        self.push_blocks(&inputs);
+        self.update_links();
+        self.particles.update(self.tuning.animation.particle_gravity);
+        self.popups.update();
+        self.combo.update();
+        self.ticks += 1;
+        for (index, entity) in self.players.clone().into_iter().enumerate() {
+            if self.is_on_ground(entity) && !were_on_ground[index] {
+                self.combo.land();
+                self.emit_landing_dust(entity);
+                self.events.push(GameEvent::PlayerLanded { player: index, speed: fall_speeds[index] });
+            }
+        }
+
+        let player_tile = self.player_tile(self.players[0]);
+        if player_tile != self.last_player_tile {
+            self.last_player_tile = player_tile;
+            if let Some((x, y)) = player_tile {
+                self.run_script("on_enter", (x as i64, y as i64));
+                let cutscene = (0..self.info.cutscenes.len())
+                    .find(|index| self.info.cutscenes[*index].at == Some((x, y)) && !self.played_cutscenes.contains(index));
+                if let Some(index) = cutscene {
+                    self.queue_cutscene(index);
+                }
+            }
+        }
+        self.message = self.message.take()
+            .filter(|(_, left)| *left > 1)
+            .map(|(text, left)| (text, left - 1));
+        self.update_triggers();
+        if !was_over {
+            if self.is_player_dead() {
+                self.events.push(GameEvent::PlayerDied);
+            } else if self.is_level_complete() {
+                self.award_time_bonus();
+                self.events.push(GameEvent::LevelCompleted);
+            }
+        }
+
+        self.track_objectives(first_event);
+        let raised: Vec<&'static str> = self.events[first_event..].iter().map(GameEvent::name).collect();
+        for name in raised {
+            self.run_script("on_event", (name.to_string(),));
+        }
+    }
+
+    fn run_script(&mut self, hook: &str, args: impl rhai::FuncArgs) {
+        let view = match &self.script {
+            Some(script) if script.has_hook(hook) => { self.script_view() }
+            _ => { return }
+        };
+        let (commands, rng) = self.script.as_mut().unwrap().call(view, hook, args);
+        self.rng = rng;
+        for command in commands {
+            self.apply_script_command(command);
+        }
+    }
+
+    fn script_view(&self) -> ScriptView {
+        let player = self.world.get::<&Position>(self.players[0])
+            .map(|position| (position.x, position.y))
+            .unwrap_or_default();
+        let mut blocks = Vec::with_capacity(self.playground.width * self.playground.height);
+        for y in 0..self.playground.height {
+            for x in 0..self.playground.width {
+                blocks.push(self.playground.block_at(x, y).code());
+            }
+        }
+        ScriptView {
+            player,
+            rng: self.rng,
+            width: self.playground.width,
+            height: self.playground.height,
+            blocks,
+        }
+    }
+
+    fn apply_script_command(&mut self, command: ScriptCommand) {
+        match command {
+            ScriptCommand::SetBlock { x, y, code } => {
+                let block = match Block::from_code(code) {
+                    Some(block) if self.playground.contains(x, y) => { block }
+                    _ => { return }
+                };
+                let destroyed = !matches!(self.playground.block_at(x, y), Block::Empty)
+                    && matches!(block, Block::Empty);
+                self.playground.set_block(x, y, block);
+                if destroyed {
+                    self.particles.burst(x as f32 + 0.5, y as f32 + 0.5, 16, 0.12, 30, DEBRIS_COLOR);
+                    self.events.push(GameEvent::TileDestroyed { x, y });
+                }
+            }
+            ScriptCommand::Teleport { x, y } => { self.teleport_player(x, y); }
+        }
+    }
+
+    /// Puts the first player at rest on the bottom of the tile `(x, y)`.
+    pub fn teleport_player(&mut self, x: usize, y: usize) -> bool {
+        if !self.playground.contains(x, y) {
+            return false;
+        }
+        let Ok((position, velocity, body)) = self.world
+            .query_one_mut::<(&mut Position, &mut Velocity, &Body)>(self.players[0]) else { return false };
+        position.x = x as f32 + (1.0 - body.width) / 2.0;
+        position.y = y as f32 + 1.0 - body.height;
+        *velocity = Velocity::default();
+        let _ = self.world.remove_one::<Swinging>(self.players[0]);
+        let _ = self.world.remove_one::<Flung>(self.players[0]);
+        let _ = self.world.remove_one::<Riding>(self.players[0]);
+        true
+    }
+
+    /// Spawns an entity in the tile to the right of the first player.
+    pub fn spawn_near_player(&mut self, definition: &EntityDefinition) -> Option<Entity> {
+        let (x, y) = self.player_tile(self.players[0])?;
+        Some(definition.spawn(&mut self.world, ((x + 1).min(self.playground.width - 1), y)))
+    }
+
+    pub fn drain_events(&mut self) -> std::vec::Drain<'_, GameEvent> {
+        self.events.drain(..)
+    }
+
+    /// Position and velocity of the first player.
+    pub fn player_state(&self) -> Option<(Position, Velocity)> {
+        let mut query = self.world.query_one::<(&Position, &Velocity)>(self.players[0]);
+        query.get().ok().map(|(position, velocity)| (*position, *velocity))
+    }
+
+    /// Adds `points` to the score, with a popup at `(x, y)` in tile units.
+    pub fn award(&mut self, points: u32, x: f32, y: f32) {
+        if points == 0 {
+            return;
+        }
+        self.score = self.score.saturating_add(points);
+        self.popups.spawn(x, y, points);
+    }
+
+    /// Builds the platform graph again when the playground or how far
+    /// enemies jump changed since it was last built.
+    fn update_platforms(&mut self, tuning: &PhysicsTuning) {
+        let reach = Reach::new(tuning.jump_speed, tuning.gravity, tuning.chase_speed);
+        if self.platforms.reach != reach || self.platforms.revision != self.playground.revision() {
+            self.platforms = PlatformGraph::build(&self.playground, reach);
+        }
+    }
+
+    /// Awards the points of a coin at `(x, y)` picked up by `player`,
+    /// adding a link to the combo when the player is in the air.
+    fn award_coin(&mut self, player: Entity, x: usize, y: usize) {
+        self.award(score::COIN_POINTS * self.combo.multiplier(), x as f32 + 0.5, y as f32 + 0.5);
+        if !self.is_on_ground(player) {
+            self.combo.extend();
+        }
+    }
+
+    /// Awards what is left of the time bonus over the exit a player reached.
+    fn award_time_bonus(&mut self) {
+        let exit = self.players.iter()
+            .filter_map(|player| self.player_tile(*player))
+            .find(|(x, y)| matches!(self.playground.block_at(*x, *y), Block::Exit { .. }))
+            .unwrap_or(self.player_start);
+        self.award(score::time_bonus(self.ticks), exit.0 as f32 + 0.5, exit.1 as f32);
+    }
+
+    /// Takes `amount` hit points from `player` unless a recent hit or a
+    /// star protects it, throwing it away from the horizontal position
+    /// `from`. A player left without hit points drinks a potion if there
+    /// is one, and is defeated otherwise.
+    pub fn hurt_player(&mut self, player: Entity, amount: u32, from: f32) {
+        let star = self.has_star(player);
+        let Ok((health, position, velocity, body, index)) = self.world
+            .query_one_mut::<(&mut Health, &Position, &mut Velocity, &Body, &Player)>(player) else { return };
+        if star || health.invulnerable > 0 || health.hp == 0 {
+            return;
+        }
+        health.hp = health.hp.saturating_sub(amount);
+        health.invulnerable = INVULNERABLE_TICKS;
+        let potion = health.hp == 0 && self.inventory.take(Item::Potion);
+        if potion {
+            health.hp = health.max;
+        }
+        // The last hit only pops the player up, so it doesn't slide away.
+        let away = match health.hp {
+            0 => { 0.0 }
+            _ if position.x + body.width / 2.0 < from => { -1.0 }
+            _ => { 1.0 }
+        };
+        velocity.x = away * KNOCKBACK.0;
+        velocity.y = -KNOCKBACK.1;
+        let (hp, index) = (health.hp, index.index);
+        self.events.push(GameEvent::PlayerHurt { player: index, health: hp });
+        if potion {
+            self.events.push(GameEvent::PotionUsed { player: index });
+        } else if hp == 0 {
+            let _ = self.world.insert_one(player, Defeated);
+        }
+    }
+
+    /// Counts the events raised from `first_event` on toward the
+    /// objectives they are part of.
+    fn track_objectives(&mut self, first_event: usize) {
+        let raised: Vec<GameEvent> = self.events[first_event..].to_vec();
+        for event in raised {
+            for (index, objective) in self.info.objectives.iter().enumerate() {
+                let Some(progress) = self.objectives.get_mut(index) else { continue };
+                if *progress >= objective.count || !objective.counts(&event) {
+                    continue;
+                }
+                *progress += 1;
+                if *progress == objective.count {
+                    info!("Objective completed: {}", objective.text);
+                    self.events.push(GameEvent::ObjectiveCompleted { index });
+                }
+            }
+        }
+    }
+
+    /// Marks cutscene number `index` of the level info to be played next.
+    fn queue_cutscene(&mut self, index: usize) {
+        self.played_cutscenes.push(index);
+        self.pending_cutscene = Some(index);
+    }
+
+    pub fn has_pending_cutscene(&self) -> bool {
+        self.pending_cutscene.is_some()
+    }
+
+    /// The cutscene waiting to be played, no longer waiting afterwards.
+    pub fn take_cutscene(&mut self) -> Option<Cutscene> {
+        let index = self.pending_cutscene.take()?;
+        self.info.cutscenes.get(index).cloned()
+    }
+
+    /// An NPC with something to say within reach of a player still in the game.
+    pub fn npc_in_reach(&self) -> Option<Entity> {
+        let players: Vec<(f32, f32)> = self.world.query::<(&Position, &Body)>().with::<&Player>().without::<&Defeated>().iter()
+            .map(|(position, body)| (position.x + body.width / 2.0, position.y + body.height / 2.0))
+            .collect();
+        self.world.query::<(Entity, &Position, &Body, &Npc)>().iter()
+            .filter(|(_, _, _, npc)| !self.npc_pages(npc.index).is_empty())
+            .find(|(_, position, body, _)| {
+                let center = (position.x + body.width / 2.0, position.y + body.height / 2.0);
+                players.iter().any(|player| (player.0 - center.0).hypot(player.1 - center.1) <= TALK_REACH)
+            })
+            .map(|(entity, _, _, _)| entity)
+    }
+
+    /// What NPC number `index` says, nothing when the level info leaves it out.
+    pub fn npc_pages(&self, index: usize) -> &[String] {
+        self.info.npcs.get(index).map(|npc| npc.pages.as_slice()).unwrap_or_default()
+    }
+
+    /// What NPC number `index` sells, nothing when it isn't a shopkeeper.
+    pub fn npc_wares(&self, index: usize) -> &[Offer] {
+        self.info.npcs.get(index).map(|npc| npc.wares.as_slice()).unwrap_or_default()
+    }
+
+    /// Index of a sign with something to say that a player still in the
+    /// game stands in front of.
+    pub fn sign_in_reach(&self) -> Option<usize> {
+        let players: Vec<Bounds> = self.world.query::<(&Position, &Body)>().with::<&Player>().without::<&Defeated>().iter()
+            .map(|(position, body)| Bounds::of(position, body))
+            .collect();
+        self.signs.iter().enumerate()
+            .filter(|(index, (x, y))| !self.sign_text(*index).is_empty() && matches!(self.playground.block_at(*x, *y), Block::Sign { .. }))
+            .find(|(_, (x, y))| {
+                let tile = Bounds { x: *x as f32, y: *y as f32, width: 1.0, height: 1.0 };
+                players.iter().any(|player| player.overlaps(&tile))
+            })
+            .map(|(index, _)| index)
+    }
+
+    /// What sign number `index` says, nothing when the level info leaves it out.
+    pub fn sign_text(&self, index: usize) -> &str {
+        self.info.signs.get(index).map(|sign| sign.text.as_str()).unwrap_or_default()
+    }
+
+    /// Sells `offer` to the first player, returning whether it was bought.
+    /// Nothing is sold when the coins carried don't cover the price, or
+    /// when the ware would be wasted, like a heart at full health.
+    pub fn buy(&mut self, offer: &Offer) -> bool {
+        let player = self.players[0];
+        if self.money < offer.price {
+            return false;
+        }
+        match offer.ware {
+            Ware::Heart => {
+                let Ok(mut health) = self.world.get::<&mut Health>(player) else { return false };
+                if health.hp >= health.max {
+                    return false;
+                }
+                health.hp += 1;
+            }
+            Ware::Life => { self.lives += 1 }
+            ware => {
+                if let Some(power_up) = ware.power_up() {
+                    if self.world.insert_one(player, self.boost(power_up)).is_err() {
+                        return false;
+                    }
+                }
+                if let Some(item) = ware.item() {
+                    self.inventory.add(item);
+                }
+            }
+        }
+        self.money -= offer.price;
+        self.record(Intervention::Buy(*offer));
+        info!("Bought {} for {} coins", offer.ware.name(), offer.price);
+        self.events.push(GameEvent::WareBought);
+        true
+    }
+
+    /// Boost given by `power_up`, lasting as long as the tuning says.
+    fn boost(&self, power_up: PowerUp) -> Boost {
+        let ticks = match power_up {
+            PowerUp::Star => { self.tuning.physics.star_ticks }
+            _ => { self.tuning.physics.power_up_ticks }
+        };
+        Boost { power_up, ticks, duration: ticks }
+    }
+
+    /// Whether `player` is protected by a star.
+    fn has_star(&self, player: Entity) -> bool {
+        self.world.get::<&Boost>(player).is_ok_and(|boost| boost.power_up == PowerUp::Star)
+    }
+
+    /// Removes `enemy` from the level, awarding its points.
+    pub fn defeat_enemy(&mut self, enemy: Entity) {
+        let Ok((position, body, sprite)) = self.world.query_one_mut::<(&Position, &Body, &Sprite)>(enemy) else { return };
+        let (x, y) = (position.x + body.width / 2.0, position.y + body.height / 2.0);
+        let color = sprite.color;
+        let _ = self.world.despawn(enemy);
+        self.particles.burst(x, y, 12, 0.08, 20, color);
+        self.award(score::ENEMY_POINTS * self.combo.multiplier(), x, y);
+        self.events.push(GameEvent::EnemyDefeated { x: x as usize, y: y as usize });
+    }
+
+    /// Puts a new enemy into the level at `spawner`.
+    fn spawn_from(&mut self, spawner: Entity) {
+        let Ok(spawner) = self.world.get::<&Spawner>(spawner).map(|spawner| *spawner) else { return };
+        let behavior = self.info.spawners.get(spawner.index).copied().unwrap_or_default().enemy;
+        let enemy = enemy::spawn(&mut self.world, spawner.tile, behavior);
+        let _ = self.world.insert_one(enemy, Spawned { spawner: spawner.index });
+        if behavior == Behavior::Fly {
+            let _ = self.world.insert_one(enemy, spawner.flight);
+        }
+    }
+
+    /// Removes a trap that fell, in a burst of shards.
+    fn shatter_trap(&mut self, trap: Entity) {
+        let Ok((position, body)) = self.world.query_one_mut::<(&Position, &Body)>(trap) else { return };
+        let (x, y) = (position.x + body.width / 2.0, position.y + body.height);
+        let _ = self.world.despawn(trap);
+        self.particles.burst(x, y - PARTICLE_SIZE, 10, 0.08, 15, TRAP_COLOR);
+    }
+
+    /// Throws `player` up off whatever it landed on.
+    fn bounce(&mut self, player: Entity) {
+        if let Ok(mut velocity) = self.world.get::<&mut Velocity>(player) {
+            velocity.y = -self.tuning.physics.bounce_speed;
+        }
+    }
+
+    /// Breaks `crate_` open, throwing out what the level's loot table gives.
+    pub fn break_crate(&mut self, crate_: Entity) {
+        let Ok((position, body)) = self.world.query_one_mut::<(&Position, &Body)>(crate_) else { return };
+        let (x, y) = (position.x + body.width / 2.0, position.y + body.height / 2.0);
+        let _ = self.world.despawn(crate_);
+        self.particles.burst(x, y, 12, 0.08, 20, DEBRIS_COLOR);
+        let (prize, count) = loot::roll(&self.info.loot, &mut self.rng);
+        if prize != Prize::Nothing {
+            for _ in 0..count {
+                loot::spawn_drop(&mut self.world, x, y, prize, &mut self.rng);
+            }
+        }
+        self.events.push(GameEvent::CrateBroken { x: x as usize, y: y as usize });
+    }
+
+    /// Takes out `player` at once, whatever its health or protection.
+    pub fn kill_player(&mut self, player: Entity) {
+        let Ok((health, velocity, index)) = self.world.query_one_mut::<(&mut Health, &mut Velocity, &Player)>(player) else { return };
+        health.hp = 0;
+        *velocity = Velocity::default();
+        let index = index.index;
+        self.events.push(GameEvent::PlayerHurt { player: index, health: 0 });
+        let _ = self.world.insert_one(player, Defeated);
+    }
+
+    /// Counts down the boost of every player, removing those that wore off.
+    fn update_boosts(&mut self) {
+        let mut expired = Vec::new();
+        for (entity, boost) in self.world.query_mut::<(Entity, &mut Boost)>() {
+            boost.ticks = boost.ticks.saturating_sub(1);
+            if boost.ticks == 0 {
+                expired.push(entity);
+            }
+        }
+        for entity in expired {
+            let _ = self.world.remove_one::<Boost>(entity);
+        }
+    }
+
+    /// Counts down the protection of recently hurt players, making them
+    /// flash while it lasts, and tints boosted players. Players with a star
+    /// cycle through the star colors.
+    fn update_invulnerability(&mut self) {
+        for (health, sprite, player, boost) in self.world.query_mut::<(&mut Health, &mut Sprite, &Player, Option<&Boost>)>() {
+            health.invulnerable = health.invulnerable.saturating_sub(1);
+            let flashing = health.invulnerable > 0 && (health.invulnerable / 4) % 2 == 1;
+            sprite.color = match boost {
+                _ if flashing => { HURT_COLOR }
+                Some(boost) if boost.power_up == PowerUp::Star => { STAR_COLORS[(boost.ticks as usize / 4) % STAR_COLORS.len()] }
+                Some(boost) => { boost.power_up.color() }
+                None => { PLAYER_COLORS[player.index] }
+            };
+        }
+    }
+
+    /// Moves the companion along, handing whatever it fetched to the
+    /// first player.
+    fn move_companion(&mut self) {
+        let leader = self.leader();
+        for outcome in systems::companion::run(&mut self.world, leader, self.tuning.physics.companion_speed) {
+            match outcome {
+                systems::companion::Outcome::Fetched { x, y } => { self.collect_block(self.players[0], x, y) }
+                systems::companion::Outcome::Teleported { x, y } => { self.particles.burst(x, y, 8, 0.05, 15, SPARKLE_COLOR) }
+            }
+        }
+    }
+
+    /// Picks up every coin and power-up a player still in the game
+    /// overlaps, and every heart a hurt one does.
+    fn collect_pickups(&mut self) {
+        let mut collected = Vec::new();
+        for (entity, position, body) in self.world.query::<(Entity, &Position, &Body)>().with::<&Player>().without::<&Defeated>().iter() {
+            let left = position.x.floor().max(0.0) as usize;
+            let top = position.y.floor().max(0.0) as usize;
+            let right = (position.x + body.width).ceil().max(0.0) as usize;
+            let bottom = (position.y + body.height).ceil().max(0.0) as usize;
+            for y in top..bottom {
+                for x in left..right {
+                    if self.playground.contains(x, y) && self.playground.block_at(x, y).is_pickup() {
+                        collected.push((entity, x, y));
+                    }
+                }
+            }
+        }
+        for (player, x, y) in collected {
+            self.collect_block(player, x, y);
+        }
+    }
+
+    /// Gives the pickup at `(x, y)` to `player`, leaving hearts for as long
+    /// as the player is unhurt.
+    fn collect_block(&mut self, player: Entity, x: usize, y: usize) {
+        match self.playground.block_at(x, y) {
+            Block::Coin { .. } => {
+                self.coins += 1;
+                self.money += 1;
+                self.award_coin(player, x, y);
+                self.events.push(GameEvent::CoinCollected { x, y });
+            }
+            Block::Heart { .. } => {
+                let Ok(mut health) = self.world.get::<&mut Health>(player) else { return };
+                if health.hp >= health.max {
+                    return;
+                }
+                health.hp += 1;
+                self.events.push(GameEvent::HeartCollected { x, y });
+            }
+            Block::Item { item, .. } => {
+                self.inventory.add(*item);
+                self.events.push(GameEvent::ItemCollected { x, y });
+            }
+            Block::PowerUp { power_up: PowerUp::Star, .. } => {
+                if self.world.insert_one(player, self.boost(PowerUp::Star)).is_err() {
+                    return;
+                }
+                self.events.push(GameEvent::StarCollected { x, y });
+            }
+            Block::HiddenStar { .. } => {
+                self.hidden_stars.push((x, y));
+                self.award(score::HIDDEN_STAR_POINTS, x as f32 + 0.5, y as f32 + 0.5);
+                self.events.push(GameEvent::HiddenStarFound { x, y });
+            }
+            Block::PowerUp { power_up, .. } => {
+                if self.world.insert_one(player, self.boost(*power_up)).is_err() {
+                    return;
+                }
+                self.events.push(GameEvent::PowerUpCollected { x, y });
+            }
+            _ => { return }
+        }
+        self.playground.set_block(x, y, Block::Empty);
+        self.particles.burst(x as f32 + 0.5, y as f32 + 0.5, 8, 0.05, 15, SPARKLE_COLOR);
+    }
+
+    /// Hands every drop a player touches to that player. Hearts are left
+    /// lying for as long as the player is unhurt.
+    fn collect_loot(&mut self) {
+        for (player, drop) in systems::loot::loot_touched(&self.world) {
+            let Ok((position, loot)) = self.world.query_one_mut::<(&Position, &Loot)>(drop) else { continue };
+            let (x, y) = (position.x.max(0.0) as usize, position.y.max(0.0) as usize);
+            match loot.prize {
+                Prize::Coin => {
+                    self.money += 1;
+                    self.award_coin(player, x, y);
+                    self.events.push(GameEvent::CoinCollected { x, y });
+                }
+                Prize::Heart => {
+                    let Ok(mut health) = self.world.get::<&mut Health>(player) else { continue };
+                    if health.hp >= health.max {
+                        continue;
+                    }
+                    health.hp += 1;
+                    self.events.push(GameEvent::HeartCollected { x, y });
+                }
+                Prize::Nothing => {}
+            }
+            let _ = self.world.despawn(drop);
+        }
+    }
+
+    /// Opens the gates of every button and plate pressed, and counts down
+    /// those of links left alone, closing them once their time is up and
+    /// no player is in the way.
+    fn update_links(&mut self) {
+        let mut held = Vec::new();
+        for index in systems::switch::press_buttons(&mut self.world) {
+            let info = self.info.buttons.get(index).copied().unwrap_or_default();
+            held.push((info.link, (info.seconds.max(0.0) * 60.0).round() as u32 + 1));
+        }
+        for index in systems::switch::press_plates(&mut self.world, &self.playground) {
+            // Plates give the link the tick after this one, so it closes as
+            // soon as they are let go.
+            held.push((self.info.plates.get(index).copied().unwrap_or_default().link, 2));
+        }
+        for (link, ticks) in held.iter().copied() {
+            self.hold_link(link, ticks);
+        }
+        for (link, left) in self.links.clone() {
+            let left = left.saturating_sub(1);
+            if left > 0 {
+                if left % 60 == 0 && !held.iter().any(|(other, _)| *other == link) {
+                    self.events.push(GameEvent::GateCountdown { link, seconds: left / 60 });
+                }
+                self.links.insert(link, left);
+            } else if !self.gates(link).iter().any(|(x, y)| systems::switch::is_occupied(&self.world, *x, *y)) {
+                self.links.remove(&link);
+                self.set_gates(link, false);
+                self.events.push(GameEvent::GateClosed { link });
+            }
+        }
+    }
+
+    /// Moves the pushable blocks players pushed long enough, and drops
+    /// those with nothing under them every few ticks.
+    fn push_blocks(&mut self, inputs: &Inputs) {
+        let mut moves = systems::push::run(&mut self.world, &self.playground, inputs);
+        if self.ticks.is_multiple_of(BLOCK_FALL_INTERVAL) {
+            moves.extend(systems::push::falling(&self.world, &self.playground));
+        }
+        for ((x, y), to) in moves {
+            let block = *self.playground.block_at(x, y);
+            if matches!(block, Block::Pushable { .. }) && matches!(self.playground.block_at(to.0, to.1), Block::Empty) {
+                self.playground.set_block(x, y, Block::Empty);
+                self.playground.set_block(to.0, to.1, block);
+            }
+        }
+    }
+
+    /// Opens the gates of `link` if they are closed and keeps them open
+    /// for at least `ticks`.
+    fn hold_link(&mut self, link: u32, ticks: u32) {
+        if !self.links.contains_key(&link) {
+            self.set_gates(link, true);
+            self.events.push(GameEvent::GateOpened { link });
+        }
+        let left = self.links.entry(link).or_default();
+        *left = (*left).max(ticks);
+    }
+
+    /// Raises the events of every trigger players entered, stayed in or
+    /// left this tick, and does what those entered do.
+    fn update_triggers(&mut self) {
+        let occupied = systems::trigger::occupied(&self.world, &self.info.triggers);
+        for (index, inside) in occupied.into_iter().enumerate() {
+            let Some(state) = self.triggers.get(index).copied() else { continue };
+            if state.spent {
+                continue;
+            }
+            let (event, hook) = match (state.occupied, inside) {
+                (false, true) => { (GameEvent::TriggerEntered { index }, "enter") }
+                (true, true) => { (GameEvent::TriggerStayed { index }, "stay") }
+                (true, false) => { (GameEvent::TriggerExited { index }, "exit") }
+                (false, false) => { continue }
+            };
+            let trigger = self.info.triggers[index].clone();
+            self.triggers[index].occupied = inside;
+            self.events.push(event);
+            self.run_script("on_trigger", (trigger.name, hook.to_string()));
+            if state.occupied {
+                continue;
+            }
+            self.triggers[index].spent = trigger.once;
+            match trigger.action {
+                Some(trigger::Action::Say(text)) => { self.message = Some((text, MESSAGE_TICKS)) }
+                Some(trigger::Action::Cutscene(cutscene)) if cutscene < self.info.cutscenes.len() && !self.played_cutscenes.contains(&cutscene) => {
+                    self.queue_cutscene(cutscene);
+                }
+                Some(trigger::Action::OpenGates { link, seconds }) => {
+                    self.hold_link(link, (seconds.max(0.0) * 60.0).round() as u32 + 1);
+                }
+                Some(trigger::Action::Music(track)) => { self.music = Some(track) }
+                Some(trigger::Action::Checkpoint) if self.profile.checkpoints => {
+                    let tile = (trigger.x, trigger.y + trigger.height.max(1) - 1);
+                    if self.checkpoint != Some(tile) {
+                        info!("Reached the checkpoint at {},{}", tile.0, tile.1);
+                    }
+                    self.checkpoint = Some(tile);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Tiles of every gate on `link`.
+    fn gates(&self, link: u32) -> Vec<(usize, usize)> {
+        (0..self.playground.height)
+            .flat_map(|y| (0..self.playground.width).map(move |x| (x, y)))
+            .filter(|(x, y)| matches!(self.playground.block_at(*x, *y), Block::Gate { link: other, .. } if *other == link))
+            .collect()
+    }
+
+    fn set_gates(&mut self, link: u32, open: bool) {
+        for (x, y) in self.gates(link) {
+            if let Block::Gate { color, .. } = *self.playground.block_at(x, y) {
+                self.playground.set_block(x, y, Block::Gate { color, link, open });
+            }
+        }
+    }
+
+    /// Opens every door a player pushes against, for as long as the keys last.
+    fn open_doors(&mut self) {
+        for (x, y) in systems::door::doors_touched(&self.world, &self.playground) {
+            if !self.inventory.take(Item::Key) {
+                break;
+            }
+            self.playground.set_block(x, y, Block::Empty);
+            self.particles.burst(x as f32 + 0.5, y as f32 + 0.5, 12, 0.06, 20, DEBRIS_COLOR);
+            self.events.push(GameEvent::DoorOpened { x, y });
+        }
+    }
+
+    fn is_on_ground(&self, entity: Entity) -> bool {
+        self.world.get::<&Body>(entity).map(|body| body.on_ground).unwrap_or(false)
+    }
+
+    fn emit_landing_dust(&mut self, player: Entity) {
+        let feet = self.world.query_one_mut::<(&Position, &Body)>(player)
+            .map(|(position, body)| (position.x + body.width / 2.0, position.y + body.height));
+        if let Ok((x, y)) = feet {
+            self.particles.burst(x, y - PARTICLE_SIZE, 6, 0.06, 12, DUST_COLOR);
+        }
+    }
+
+    /// Tile under the center of `player`, `None` once it has left the playground.
+    fn player_tile(&self, player: Entity) -> Option<(usize, usize)> {
+        let mut query = self.world.query_one::<(&Position, &Body)>(player);
+        let (position, body) = query.get().ok()?;
+        let x = position.x + body.width / 2.0;
+        let y = position.y + body.height / 2.0;
+        if x < 0.0 || y < 0.0 || !self.playground.contains(x as usize, y as usize) {
+            return None;
+        }
+        Some((x as usize, y as usize))
+    }
+
+    /// Whether every player has left the playground or been defeated.
+    pub fn is_player_dead(&self) -> bool {
+        self.players.iter().all(|player| self.player_tile(*player).is_none() || self.world.satisfies::<&Defeated>(*player))
+    }
+
+    /// Track the level plays now, switched by a trigger or its own.
+    pub fn music_track(&self) -> Option<&str> {
+        self.music.as_deref().or(self.info.music.as_deref())
+    }
+
+    /// Whether any player has reached an exit.
+    pub fn is_level_complete(&self) -> bool {
+        self.players.iter().any(|player| match self.player_tile(*player) {
+            Some((x, y)) => { matches!(self.playground.block_at(x, y), Block::Exit { .. }) }
+            None => { false }
+        })
+    }
+}
+
+/// Map character an entity listed below the rows of a map stands for: one
+/// of the built-in kinds, or an entity definition by name.
+fn marker_code(spawn: &EntitySpawn, definitions: &EntityDefinitions) -> Option<char> {
+    match spawn.kind.as_str() {
+        "enemy" => {
+            let behavior = spawn.properties.get("type").map_or(Some(Behavior::Patrol), |name| Behavior::from_name(name))?;
+            Some(behavior.code())
+        }
+        "turn" => { Some(enemy::TURN_CODE) }
+        "crate" => { Some(loot::CRATE_CODE) }
+        "saw" => { Some(saw::SAW_CODE) }
+        "rail" => { Some(saw::RAIL_CODE) }
+        "trap" => { Some(trap::TRAP_CODE) }
+        "button" => { Some(switch::BUTTON_CODE) }
+        "plate" => { Some(switch::PLATE_CODE) }
+        "cart" => { Some(cart::CART_CODE) }
+        "anchor" => { Some(swing::ANCHOR_CODE) }
+        "spawner" => { Some(spawner::SPAWNER_CODE) }
+        "npc" => { Some(npc::NPC_CODE) }
+        name => { definitions.find_named(name).map(|definition| definition.code) }
+    }
+}
+
+/// How a listed entity follows the path named by its `path` property, if
+/// it has one and the map declares it.
+fn follow(level: &Level, spawn: &EntitySpawn) -> Option<Follow> {
+    let name = spawn.properties.get("path")?;
+    let path = level.paths.iter().find(|path| &path.name == name);
+    if path.is_none() {
+        warn!("Ignoring unknown path '{}' of the {} at {}:{}", name, spawn.kind, spawn.y + 1, spawn.x + 1);
+    }
+    Some(path?.follow())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::paths::SearchPaths;
+    use crate::replay::{self, Playback};
+    use crate::systems::input::PlayerInput;
+
+    fn mods() -> Mods {
+        Mods { search: SearchPaths { roots: vec![Path::new(env!("CARGO_MANIFEST_DIR")).join("assets")] }, ..Mods::default() }
+    }
+
+    /// Hash of everything a save keeps of `game`.
+    fn state_hash(game: &Game) -> u64 {
+        replay::map_hash(&serde_json::to_string(&game.snapshot()).unwrap())
+    }
+
+    /// Inputs of two players mashing buttons, mostly running right.
+    fn mashing(seed: u64, ticks: usize) -> Vec<Inputs> {
+        let mut rng = Rng::new(seed);
+        let mut player = || PlayerInput {
+            left: rng.chance(0.2),
+            right: rng.chance(0.6),
+            jump: rng.chance(0.3),
+            shoot: rng.chance(0.1),
+            fetch: false,
+            stick: if rng.chance(0.1) { rng.range(-127, 128) as i8 } else { 0 },
+        };
+        (0..ticks).map(|_| [player(), player()]).collect()
+    }
+
+    /// Two players in a corridor of crates, whose drops draw from the
+    /// random numbers. `name` keeps tests running at the same time apart.
+    fn two_players(mods: &Mods, name: &str, seed: u64) -> Game {
+        let path = std::env::temp_dir().join(format!("platformer-{}-{}.txt", name, std::process::id()));
+        std::fs::write(&path, concat!(
+            "||||||||||||||||||||||\n",
+            "|....................|\n",
+            "|....................|\n",
+            "|....X..X..X..X.X.X..|\n",
+            "|@%%%%%%%%%%%%%%%%%%%|",
+        )).unwrap();
+        let mut game = Game::new(&path.to_string_lossy(), mods, seed).unwrap();
+        game.add_player();
+        game
+    }
+
+    fn is_over(game: &Game) -> bool {
+        game.is_player_dead() || game.is_level_complete()
+    }
+
+    #[test]
+    fn same_seed_and_inputs_give_the_same_ticks() {
+        let mods = mods();
+        let mut first = two_players(&mods, "same", 11);
+        let mut second = two_players(&mods, "same", 11);
+        std::fs::remove_file(&first.level_path).unwrap();
+        assert_eq!(state_hash(&first), state_hash(&second));
+        for inputs in mashing(3, 1200) {
+            first.tick(inputs);
+            second.tick(inputs);
+            assert_eq!(state_hash(&first), state_hash(&second), "diverged at tick {}", first.ticks);
+            assert_eq!(first.drain_events().collect::<Vec<_>>(), second.drain_events().collect::<Vec<_>>());
+            if is_over(&first) {
+                break;
+            }
+        }
+        assert!(first.ticks > 60);
+        assert_ne!(first.rng.next_u64(), Rng::new(11).next_u64(), "nothing random happened");
+    }
+
+    #[test]
+    fn replays_end_where_the_game_did() {
+        let mods = mods();
+        let mut recorded = two_players(&mods, "recorded", 11);
+        recorded.start_recording();
+        for inputs in mashing(5, 1200) {
+            recorded.tick(inputs);
+            if is_over(&recorded) {
+                break;
+            }
+        }
+        assert!(recorded.ticks > 60);
+        let replay = Replay::decode(&recorded.replay().unwrap().encode()).unwrap();
+        let mut played = Game::new(&replay.level, &mods, replay.seed).unwrap();
+        played.add_player();
+        let mut playback = Playback::new(replay);
+        while playback.step(&mut played) {}
+        assert_eq!(played.ticks, recorded.ticks);
+        assert_eq!(state_hash(&played), state_hash(&recorded));
+        std::fs::remove_file(&played.level_path).unwrap();
+    }
+}
@@ -0,0 +1,144 @@
+//! Game controllers. Each controller is assigned to the first player
+//! without one when it is connected, at startup or later, and frees that
+//! player again when unplugged. Buttons map to fixed actions and the left
+//! stick gives analog movement, shaped by the `[gamepad]` settings.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use log::{debug, info, warn};
+use sdl2::controller::{Button, GameController};
+use sdl2::GameControllerSubsystem;
+
+use crate::bindings::Action;
+use crate::events::{GameEvent, Subscriber};
+use crate::settings::GamepadSettings;
+use crate::systems::input::MAX_PLAYERS;
+
+pub struct Gamepads {
+    subsystem: GameControllerSubsystem,
+    /// Controller of each player, if it has one.
+    controllers: [Option<GameController>; MAX_PLAYERS],
+}
+
+impl Gamepads {
+    /// Opens every controller connected at startup.
+    pub fn open(subsystem: GameControllerSubsystem) -> Self {
+        let mut gamepads = Gamepads {
+            subsystem,
+            controllers: Default::default(),
+        };
+        for index in 0..gamepads.subsystem.num_joysticks().unwrap_or(0) {
+            gamepads.connect(index);
+        }
+        gamepads
+    }
+
+    /// Opens the controller at joystick `index` for the first player
+    /// without one. Returns that player, or `None` when the controller is
+    /// already open or every player has one.
+    pub fn connect(&mut self, index: u32) -> Option<usize> {
+        if !self.subsystem.is_game_controller(index) {
+            return None;
+        }
+        let controller = match self.subsystem.open(index) {
+            Ok(controller) => { controller }
+            Err(error) => {
+                warn!("Unable to open controller {}: {}", index, error);
+                return None;
+            }
+        };
+        if self.player_for(controller.instance_id()).is_some() {
+            return None;
+        }
+        let player = self.controllers.iter().position(Option::is_none)?;
+        info!("Controller '{}' assigned to player {}", controller.name(), player + 1);
+        self.controllers[player] = Some(controller);
+        Some(player)
+    }
+
+    /// Closes the controller with joystick id `which`, returning the player
+    /// it belonged to.
+    pub fn disconnect(&mut self, which: u32) -> Option<usize> {
+        let player = self.player_for(which)?;
+        if let Some(controller) = self.controllers[player].take() {
+            info!("Controller '{}' of player {} disconnected", controller.name(), player + 1);
+        }
+        Some(player)
+    }
+
+    /// Player steered by the controller with joystick id `which`, if any.
+    pub fn player_for(&self, which: u32) -> Option<usize> {
+        self.controllers.iter().position(|controller| {
+            controller.as_ref().is_some_and(|controller| controller.instance_id() == which)
+        })
+    }
+
+    /// Shakes the controller of `player` at `strength`, from 0 to 1, for
+    /// `duration` milliseconds.
+    pub fn rumble(&mut self, player: usize, strength: f32, duration: u32) {
+        let Some(Some(controller)) = self.controllers.get_mut(player) else { return };
+        let motor = (strength.clamp(0.0, 1.0) * u16::MAX as f32) as u16;
+        if let Err(error) = controller.set_rumble(motor, motor, duration) {
+            debug!("Controller '{}' can't rumble: {}", controller.name(), error);
+        }
+    }
+}
+
+/// Rumbles controllers on landings and deaths.
+pub struct RumbleFeedback {
+    pub gamepads: Rc<RefCell<Gamepads>>,
+    pub settings: GamepadSettings,
+}
+
+impl Subscriber for RumbleFeedback {
+    fn on_event(&mut self, event: &GameEvent) {
+        if !self.settings.rumble {
+            return;
+        }
+        let intensity = self.settings.rumble_intensity;
+        let mut gamepads = self.gamepads.borrow_mut();
+        match *event {
+            GameEvent::PlayerLanded { player, speed } => {
+                // Only hard landings, scaled by how fast the player fell.
+                let strength = ((speed - 0.15) / 0.35).clamp(0.0, 1.0) * 0.6;
+                if strength > 0.0 {
+                    gamepads.rumble(player, strength * intensity, 120);
+                }
+            }
+            GameEvent::PlayerHurt { player, .. } => { gamepads.rumble(player, 0.8 * intensity, 200); }
+            GameEvent::PlayerDied => {
+                for player in 0..MAX_PLAYERS {
+                    gamepads.rumble(player, intensity, 400);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Actions of `button` for the first player, see [`Action::for_player`].
+pub fn button_actions(button: Button) -> Vec<Action> {
+    match button {
+        Button::A => { vec![Action::Jump, Action::Confirm] }
+        Button::B => { vec![Action::Back] }
+        Button::X => { vec![Action::Shoot] }
+        Button::Start => { vec![Action::Pause] }
+        Button::DPadLeft => { vec![Action::MoveLeft] }
+        Button::DPadRight => { vec![Action::MoveRight] }
+        Button::DPadUp => { vec![Action::Up] }
+        Button::DPadDown => { vec![Action::Down] }
+        _ => { Vec::new() }
+    }
+}
+
+/// Applies the deadzone and response curve to a stick position from -1 to 1.
+pub fn shape(value: f32, settings: &GamepadSettings) -> f32 {
+    let deadzone = settings.deadzone.clamp(0.0, 0.99);
+    let magnitude = value.abs();
+    if magnitude <= deadzone {
+        return 0.0;
+    }
+    let scaled = ((magnitude - deadzone) / (1.0 - deadzone)).min(1.0);
+    scaled.powf(settings.response.max(0.1)).copysign(value)
+}
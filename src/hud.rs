@@ -0,0 +1,63 @@
+//! What the heads-up display shows, captured from a game so it can be
+//! drawn from a snapshot as well as from the game itself.
+
+use crate::game::Game;
+use crate::inventory::Item;
+use crate::player::Health;
+use crate::powerup::Boost;
+use crate::score::Combo;
+
+#[derive(Clone, Debug, Default)]
+pub struct Hud {
+    /// Ticks spent in the level so far.
+    pub ticks: u64,
+    pub score: u32,
+    pub combo: Combo,
+    pub lives: u32,
+    pub coins: u32,
+    pub total_coins: u32,
+    pub hidden_stars: u32,
+    pub total_hidden_stars: u32,
+    pub money: u32,
+    /// Health of every player, in player order.
+    pub health: Vec<Health>,
+    /// Items carried, with how many of each.
+    pub items: Vec<(Item, u32)>,
+    /// Ticks left before the gates of each link counting down close.
+    pub links: Vec<(u32, u32)>,
+    /// Boost of every player, in player order.
+    pub boosts: Vec<Option<Boost>>,
+    /// Line shown by a trigger.
+    pub message: Option<String>,
+}
+
+impl Hud {
+    pub fn new(game: &Game) -> Self {
+        let mut hud = Hud::default();
+        hud.capture(game);
+        hud
+    }
+
+    /// Copies the state of `game`, reusing the buffers already allocated.
+    pub fn capture(&mut self, game: &Game) {
+        self.ticks = game.ticks;
+        self.score = game.score;
+        self.combo = game.combo;
+        self.lives = game.lives;
+        self.coins = game.coins;
+        self.total_coins = game.total_coins;
+        self.hidden_stars = game.hidden_stars.len() as u32;
+        self.total_hidden_stars = game.total_hidden_stars;
+        self.money = game.money;
+        self.health.clear();
+        self.health.extend(game.players.iter().map(|player| game.world.get::<&Health>(*player).map(|health| *health).unwrap_or_default()));
+        self.items.clear();
+        self.items.extend(game.inventory.iter());
+        self.links.clear();
+        // Links held by a plate only have the next tick left.
+        self.links.extend(game.links.iter().filter(|(_, left)| **left > 1).map(|(link, left)| (*link, *left)));
+        self.boosts.clear();
+        self.boosts.extend(game.players.iter().map(|player| game.world.get::<&Boost>(*player).map(|boost| *boost).ok()));
+        self.message = game.message.as_ref().map(|(text, _)| text.clone());
+    }
+}
@@ -0,0 +1,80 @@
+//! Items the players pick up and carry through a level, shared by every
+//! player. Keys open doors, potions are drunk on their own when a player
+//! would be defeated and gems are kept for whatever the level asks of
+//! them, such as objectives.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Item {
+    Key,
+    Potion,
+    Gem,
+}
+
+impl Item {
+    pub const ALL: [Item; 3] = [Item::Key, Item::Potion, Item::Gem];
+
+    /// Map character of the item lying in a level.
+    pub fn code(&self) -> char {
+        match self {
+            Item::Key => { 'k' }
+            Item::Potion => { 'p' }
+            Item::Gem => { 'g' }
+        }
+    }
+
+    pub fn from_code(code: char) -> Option<Item> {
+        Item::ALL.into_iter().find(|item| item.code() == code)
+    }
+
+    pub fn color(&self) -> u32 {
+        match self {
+            Item::Key => { 0x40E0FF }
+            Item::Potion => { 0xA0FF40 }
+            Item::Gem => { 0x00FFA0 }
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Item::Key => { "KEY" }
+            Item::Potion => { "POTION" }
+            Item::Gem => { "GEM" }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Inventory {
+    /// How many of each item is carried; items run out are left out.
+    items: BTreeMap<Item, u32>,
+}
+
+impl Inventory {
+    pub fn add(&mut self, item: Item) {
+        *self.items.entry(item).or_default() += 1;
+    }
+
+    pub fn count(&self, item: Item) -> u32 {
+        self.items.get(&item).copied().unwrap_or_default()
+    }
+
+    /// Uses up one `item`, returning whether there was one to use.
+    pub fn take(&mut self, item: Item) -> bool {
+        let Some(count) = self.items.get_mut(&item) else { return false };
+        *count -= 1;
+        if *count == 0 {
+            self.items.remove(&item);
+        }
+        true
+    }
+
+    /// Every item carried with how many, in the order of [`Item::ALL`].
+    pub fn iter(&self) -> impl Iterator<Item = (Item, u32)> + '_ {
+        self.items.iter().map(|(item, count)| (*item, *count))
+    }
+}
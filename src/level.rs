@@ -0,0 +1,490 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use log::info;
+use serde::Deserialize;
+
+use crate::block::{Block, Material};
+use crate::cutscene::Cutscene;
+use crate::error::{GameError, Result};
+use crate::loot::LootEntry;
+use crate::objective::Objective;
+use crate::playground::Playground;
+use crate::replay;
+use crate::saw::SawInfo;
+use crate::shop::Offer;
+use crate::spawner::SpawnerInfo;
+use crate::switch::{ButtonInfo, PlateInfo};
+use crate::trigger::Trigger;
+use crate::water::Current;
+use crate::waypoint::WaypointPath;
+
+pub const DEFAULT_MAP: &str = "map.txt";
+
+/// Map character for the floor block the player starts on.
+pub const PLAYER_CODE: char = '@';
+/// Directory next to a map holding the prefabs it includes.
+pub const PREFAB_DIR: &str = "prefabs";
+/// Prefab character leaving the tile under it as it is.
+pub const KEEP_CODE: char = ' ';
+
+/// A parsed map: the blocks, the tile the player starts in and the
+/// characters that are not blocks, left for the game to interpret.
+pub struct Level {
+    pub playground: Playground,
+    pub player_start: (usize, usize),
+    pub markers: Vec<Marker>,
+    /// Entities listed below the rows, in the order they are listed.
+    pub entities: Vec<EntitySpawn>,
+    /// Waypoint paths declared below the rows, see [`WaypointPath`].
+    pub paths: Vec<WaypointPath>,
+    /// Prefabs stamped onto the rows, see [`Include`].
+    pub includes: Vec<Include>,
+    /// Hash of the map text, see [`replay::map_hash`].
+    pub hash: u64,
+}
+
+/// A map character that is not a block. Its tile is left empty.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Marker {
+    pub code: char,
+    pub x: usize,
+    pub y: usize,
+}
+
+/// An entity listed below the rows of a map, one per line, as its kind
+/// followed by `key=value` properties, e.g. `enemy x=4 y=7 type=chase`.
+/// `x` and `y` are the tile it is placed in; what else it takes depends on
+/// the kind.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntitySpawn {
+    pub kind: String,
+    pub x: usize,
+    pub y: usize,
+    pub properties: BTreeMap<String, String>,
+}
+
+impl EntitySpawn {
+    /// Parses an entity line; `line` is its number, for errors.
+    fn parse(text: &str, line: usize) -> Result<EntitySpawn> {
+        let error = |message: String| GameError::InvalidMap(format!("line {}: {}", line, message));
+        let mut words = text.split_whitespace();
+        let kind = words.next().unwrap_or_default().to_string();
+        let mut properties = BTreeMap::new();
+        for word in words {
+            let (key, value) = word.split_once('=').ok_or_else(|| error(format!("expected key=value, found '{}'", word)))?;
+            properties.insert(key.to_string(), value.to_string());
+        }
+        let mut coordinate = |key: &str| {
+            let value = properties.remove(key).ok_or_else(|| error(format!("{} has no {}", kind, key)))?;
+            value.parse::<usize>().map_err(|_| error(format!("{} is not a tile: '{}'", key, value)))
+        };
+        let (x, y) = (coordinate("x")?, coordinate("y")?);
+        Ok(EntitySpawn { kind, x, y, properties })
+    }
+
+    /// Property `key` read as a `T`, `None` when it is left out or isn't one.
+    pub fn property<T: std::str::FromStr>(&self, key: &str) -> Option<T> {
+        self.properties.get(key)?.parse().ok()
+    }
+}
+
+impl std::fmt::Display for EntitySpawn {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "{} x={} y={}", self.kind, self.x, self.y)?;
+        for (key, value) in &self.properties {
+            write!(formatter, " {}={}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// A prefab stamped onto a map, declared below its rows as `include`,
+/// its name and the tiles its top left corner goes on, e.g.
+/// `include spike_pit 4,8 20,8`. Prefabs are snippets of map rows kept in
+/// [`PREFAB_DIR`] next to the map as `name.txt`; a space in one leaves the
+/// tile under it as it is.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Include {
+    pub name: String,
+    pub at: Vec<(usize, usize)>,
+}
+
+impl Include {
+    /// Parses the words of an include line after `include`; `line` is its
+    /// number, for errors.
+    fn parse(words: &str, line: usize) -> Result<Include> {
+        let error = |message: String| GameError::InvalidMap(format!("line {}: {}", line, message));
+        let mut words = words.split_whitespace();
+        let name = words.next().ok_or_else(|| error("include has no prefab".to_string()))?.to_string();
+        let at = words
+            .map(|word| parse_tile(word).ok_or_else(|| error(format!("expected a tile like 3,4, found '{}'", word))))
+            .collect::<Result<Vec<_>>>()?;
+        if at.is_empty() {
+            return Err(error(format!("include {} has no tile to go on", name)));
+        }
+        Ok(Include { name, at })
+    }
+}
+
+impl std::fmt::Display for Include {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "include {}", self.name)?;
+        for (x, y) in &self.at {
+            write!(formatter, " {},{}", x, y)?;
+        }
+        Ok(())
+    }
+}
+
+/// A tile written as `x,y`.
+pub fn parse_tile(word: &str) -> Option<(usize, usize)> {
+    let (x, y) = word.split_once(',')?;
+    Some((x.parse().ok()?, y.parse().ok()?))
+}
+
+/// Data about a level kept next to its map as `name.toml`, e.g.
+/// `map.toml` for `map.txt`. Every part of it is optional.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct LevelInfo {
+    /// What each NPC says and sells, in the order the NPCs appear in the map, row by row.
+    #[serde(rename = "npc")]
+    pub npcs: Vec<NpcInfo>,
+    #[serde(rename = "cutscene")]
+    pub cutscenes: Vec<Cutscene>,
+    #[serde(rename = "objective")]
+    pub objectives: Vec<Objective>,
+    /// How each spawner behaves, in the order the spawners appear in the
+    /// map, row by row.
+    #[serde(rename = "spawner")]
+    pub spawners: Vec<SpawnerInfo>,
+    /// How each saw moves, in the order the saws appear in the map, row
+    /// by row.
+    #[serde(rename = "saw")]
+    pub saws: Vec<SawInfo>,
+    /// What each button opens, in the order the buttons appear in the
+    /// map, row by row.
+    #[serde(rename = "button")]
+    pub buttons: Vec<ButtonInfo>,
+    /// What each plate holds open, in the order the plates appear in the
+    /// map, row by row.
+    #[serde(rename = "plate")]
+    pub plates: Vec<PlateInfo>,
+    /// What a broken crate drops, one line rolled per crate.
+    pub loot: Vec<LootEntry>,
+    /// Where the water flows and which way.
+    #[serde(rename = "current")]
+    pub currents: Vec<Current>,
+    /// What each sign says, in the order the signs appear in the map, row
+    /// by row.
+    #[serde(rename = "sign")]
+    pub signs: Vec<SignInfo>,
+    #[serde(rename = "trigger")]
+    pub triggers: Vec<Trigger>,
+    /// Seconds to finish the level in for the par time achievement.
+    pub par: Option<u32>,
+    /// WAV file looped while the level is played.
+    pub music: Option<String>,
+    /// Material of the blocks of each map character, in place of their own.
+    pub materials: BTreeMap<char, Material>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct NpcInfo {
+    /// Text shown one page at a time.
+    pub pages: Vec<String>,
+    /// What the NPC sells once the pages are read.
+    pub wares: Vec<Offer>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct SignInfo {
+    pub text: String,
+}
+
+impl LevelInfo {
+    /// Path of the info belonging to the level at `level_path`.
+    pub fn path_for(level_path: &str) -> String {
+        Path::new(level_path).with_extension("toml").to_string_lossy().into_owned()
+    }
+
+    /// Reads the info at `path`; a level without one gets the defaults.
+    pub fn load(path: &str) -> Result<LevelInfo> {
+        if !Path::new(path).exists() {
+            return Ok(LevelInfo::default());
+        }
+        let contents = std::fs::read_to_string(path)
+            .map_err(|source| GameError::Io { path: path.to_string(), source })?;
+        toml::from_str(&contents)
+            .map_err(|error| GameError::InvalidMap(format!("{}: {}", path, error)))
+    }
+}
+
+impl Level {
+    pub fn marker_at(&self, x: usize, y: usize) -> Option<&Marker> {
+        self.markers.iter().find(|marker| marker.x == x && marker.y == y)
+    }
+}
+
+pub fn load(path: &str) -> Result<Level> {
+    let contents = read(path)?;
+    let mut level = parse(&contents)?;
+    // Prefabs count towards the hash, so replays notice when one changes.
+    let mut hashed = contents;
+    let directory = Path::new(path).parent().unwrap_or(Path::new(""));
+    for include in level.includes.clone() {
+        let prefab_path = directory.join(PREFAB_DIR).join(format!("{}.txt", include.name));
+        let prefab = std::fs::read_to_string(&prefab_path)
+            .map_err(|source| GameError::Io { path: prefab_path.display().to_string(), source })?;
+        stamp(&mut level, &include, &prefab)?;
+        hashed.push_str(&prefab);
+    }
+    level.hash = replay::map_hash(&hashed);
+    info!("Loaded map '{}' ({}x{})", path, level.playground.width, level.playground.height);
+    Ok(level)
+}
+
+/// Reads the level at `path` without stamping its prefabs, for the editor:
+/// saving a level loaded with [`load`] would write the prefab tiles into
+/// its rows on top of the include lines.
+pub fn load_unstamped(path: &str) -> Result<Level> {
+    parse(&read(path)?)
+}
+
+fn read(path: &str) -> Result<String> {
+    std::fs::read_to_string(path)
+        .map_err(|source| GameError::Io { path: path.to_string(), source })
+}
+
+/// Stamps the rows of `prefab` onto the level wherever `include` puts
+/// it, replacing blocks and markers alike but where the prefab has a space.
+pub fn stamp(level: &mut Level, include: &Include, prefab: &str) -> Result<()> {
+    let error = |message: String| GameError::InvalidMap(format!("prefab {}: {}", include.name, message));
+    for at in &include.at {
+        for (row, line) in prefab.lines().enumerate() {
+            for (column, code) in line.chars().enumerate() {
+                let (x, y) = (at.0 + column, at.1 + row);
+                if code == KEEP_CODE {
+                    continue;
+                }
+                if code == PLAYER_CODE {
+                    return Err(error("prefabs can't place the player".to_string()));
+                }
+                if !level.playground.contains(x, y) {
+                    return Err(error(format!("{},{} is outside the map", x, y)));
+                }
+                level.markers.retain(|marker| marker.x != x || marker.y != y);
+                match Block::from_code(code) {
+                    Some(block) => { level.playground.set_block(x, y, block) }
+                    None => {
+                        level.markers.push(Marker { code, x, y });
+                        level.playground.set_block(x, y, Block::Empty);
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parses a map where every line is a row of blocks. `@` marks the floor
+/// block the player starts on. An empty line ends the rows; every line
+/// after it lists an entity, see [`EntitySpawn`], or declares a waypoint
+/// path when it starts with `path` or includes a prefab when it starts
+/// with `include`, see [`load`].
+pub fn parse(contents: &str) -> Result<Level> {
+    let mut width = 0;
+    let mut schema = Vec::new();
+    let mut player_index = 0;
+    let mut markers = Vec::new();
+    let rows = contents.lines().take_while(|line| !line.is_empty()).count();
+    let mut entities = Vec::new();
+    let mut paths = Vec::new();
+    let mut includes = Vec::new();
+    for (index, line) in contents.lines().enumerate().skip(rows).filter(|(_, line)| !line.trim().is_empty()) {
+        let line = line.trim_start();
+        if let Some(words) = line.strip_prefix("path ") {
+            paths.push(WaypointPath::parse(words, index + 1)?);
+        } else if let Some(words) = line.strip_prefix("include ") {
+            includes.push(Include::parse(words, index + 1)?);
+        } else {
+            entities.push(EntitySpawn::parse(line, index + 1)?);
+        }
+    }
+    for (row, line) in contents.lines().take(rows).enumerate() {
+        let length = line.chars().count();
+        if width == 0 {
+            width = length;
+        } else if length != width {
+            return Err(GameError::InvalidMap(format!("line {}: row is {} tiles wide, the first is {}", row + 1, length, width)));
+        }
+        for (column, code) in line.chars().enumerate() {
+            if code == PLAYER_CODE {
+                player_index = schema.len();
+                schema.push(Block::from_code('%').unwrap());
+            } else if let Some(block) = Block::from_code(code) {
+                schema.push(block);
+            } else {
+                markers.push(Marker { code, x: column, y: row });
+                schema.push(Block::Empty);
+            }
+        }
+    }
+    if width == 0 {
+        return Err(GameError::InvalidMap("map is empty".to_string()));
+    }
+    let height = schema.len() / width;
+    Ok(Level {
+        playground: Playground::new(schema, height, width),
+        player_start: (player_index % width, (player_index / width).saturating_sub(1)),
+        markers,
+        entities,
+        paths,
+        includes,
+        hash: replay::map_hash(contents),
+    })
+}
+
+/// Writes the level back in the format accepted by [`parse`].
+pub fn serialize(level: &Level) -> String {
+    let playground = &level.playground;
+    let (start_x, start_y) = level.player_start;
+    let mut contents = String::new();
+    for y in 0..playground.height {
+        if y > 0 {
+            contents.push('\n');
+        }
+        for x in 0..playground.width {
+            if x == start_x && y == start_y + 1 {
+                contents.push(PLAYER_CODE);
+            } else if let Some(marker) = level.marker_at(x, y) {
+                contents.push(marker.code);
+            } else {
+                contents.push(playground.block_at(x, y).code());
+            }
+        }
+    }
+    if !level.entities.is_empty() || !level.paths.is_empty() || !level.includes.is_empty() {
+        contents.push('\n');
+    }
+    for include in &level.includes {
+        contents.push('\n');
+        contents.push_str(&include.to_string());
+    }
+    for path in &level.paths {
+        contents.push('\n');
+        contents.push_str(&path.to_string());
+    }
+    for entity in &level.entities {
+        contents.push('\n');
+        contents.push_str(&entity.to_string());
+    }
+    contents
+}
+
+pub fn save(path: &str, level: &Level) -> Result<()> {
+    std::fs::write(path, serialize(level))
+        .map_err(|source| GameError::Io { path: path.to_string(), source })?;
+    info!("Saved map '{}'", path);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_of(contents: &str) -> String {
+        match parse(contents) {
+            Ok(_) => { panic!("parsed {:?}", contents) }
+            Err(error) => { error.to_string() }
+        }
+    }
+
+    #[test]
+    fn parses_rows_and_the_player_start() {
+        let level = parse("....\n.@..\n####").unwrap();
+        assert_eq!((level.playground.width, level.playground.height), (4, 3));
+        assert_eq!(level.player_start, (1, 0));
+        assert!(level.playground.block_at(1, 1).is_solid());
+    }
+
+    #[test]
+    fn rejects_ragged_rows() {
+        // 12 tiles in all, a multiple of the first row's 4.
+        assert_eq!(error_of("....\n...\n....."), "invalid map: line 2: row is 3 tiles wide, the first is 4");
+        assert_eq!(error_of("....\n....\n....."), "invalid map: line 3: row is 5 tiles wide, the first is 4");
+    }
+
+    #[test]
+    fn parses_entities_below_the_rows() {
+        let level = parse("....\n@###\n\nenemy x=2 y=0 type=chase\n  npc x=0 y=0").unwrap();
+        assert_eq!(level.entities.len(), 2);
+        let enemy = &level.entities[0];
+        assert_eq!((enemy.kind.as_str(), enemy.x, enemy.y), ("enemy", 2, 0));
+        assert_eq!(enemy.property::<String>("type").as_deref(), Some("chase"));
+        assert_eq!(enemy.property::<u32>("type"), None);
+        assert_eq!(enemy.to_string(), "enemy x=2 y=0 type=chase");
+        assert_eq!(level.entities[1].kind, "npc");
+        assert_eq!(serialize(&level), "....\n@###\n\nenemy x=2 y=0 type=chase\nnpc x=0 y=0");
+    }
+
+    #[test]
+    fn parses_path_lines() {
+        let level = parse("..\n@%\n\npath ridge mode=loop 0,0 1,0\nenemy x=1 y=0 path=ridge").unwrap();
+        assert_eq!(level.paths.len(), 1);
+        assert_eq!(level.paths[0].name, "ridge");
+        assert_eq!(level.entities[0].property::<String>("path").as_deref(), Some("ridge"));
+        assert_eq!(error_of("..\n\npath ridge"), "invalid map: line 3: path ridge has no waypoints");
+    }
+
+    #[test]
+    fn rejects_bad_entity_lines() {
+        assert_eq!(error_of("..\n\nenemy x=1"), "invalid map: line 3: enemy has no y");
+        assert_eq!(error_of("..\n\nenemy x=1 y=up"), "invalid map: line 3: y is not a tile: 'up'");
+        assert_eq!(error_of("..\n\n\nenemy x=1 y=0 fast"), "invalid map: line 4: expected key=value, found 'fast'");
+    }
+
+    #[test]
+    fn stamps_prefabs_but_where_they_have_a_space() {
+        let mut level = parse("....\n.o..\n@%%%\n\ninclude pit 0,0 2,0").unwrap();
+        assert_eq!(level.includes, vec![Include { name: "pit".to_string(), at: vec![(0, 0), (2, 0)] }]);
+        let include = level.includes[0].clone();
+        stamp(&mut level, &include, "^\n E").unwrap();
+        assert_eq!(serialize(&level).lines().take(3).collect::<Vec<_>>(), ["^.^.", ".E.E", "@%%%"]);
+        assert_eq!(level.marker_at(1, 1).map(|marker| marker.code), Some('E'));
+    }
+
+    #[test]
+    fn rejects_bad_prefabs() {
+        let mut level = parse("..\n@%").unwrap();
+        let include = Include { name: "pit".to_string(), at: vec![(1, 0)] };
+        assert_eq!(stamp(&mut level, &include, "^^").unwrap_err().to_string(), "invalid map: prefab pit: 2,0 is outside the map");
+        assert_eq!(stamp(&mut level, &include, "@").unwrap_err().to_string(), "invalid map: prefab pit: prefabs can't place the player");
+        assert_eq!(error_of("..\n\ninclude pit"), "invalid map: line 3: include pit has no tile to go on");
+        assert_eq!(error_of("..\n\ninclude pit 4"), "invalid map: line 3: expected a tile like 3,4, found '4'");
+    }
+
+    #[test]
+    fn saving_an_unstamped_level_keeps_its_prefabs_apart() {
+        let directory = std::env::temp_dir().join(format!("platformer-level-{}", std::process::id()));
+        std::fs::create_dir_all(directory.join(PREFAB_DIR)).unwrap();
+        std::fs::write(directory.join(PREFAB_DIR).join("pit.txt"), "^ ^").unwrap();
+        let map = directory.join("map.txt").to_string_lossy().into_owned();
+        let contents = "......\n......\n@#####\n\ninclude pit 1,1";
+        std::fs::write(&map, contents).unwrap();
+
+        let stamped = serialize(&load(&map).unwrap());
+        save(&map, &load_unstamped(&map).unwrap()).unwrap();
+        assert_eq!(std::fs::read_to_string(&map).unwrap(), contents);
+        assert_eq!(serialize(&load(&map).unwrap()), stamped);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn rejects_an_empty_map() {
+        assert_eq!(error_of(""), "invalid map: map is empty");
+    }
+}
@@ -0,0 +1,78 @@
+//! Level select screen: every level of the campaign with the hidden stars
+//! found in it, starting a campaign from the one picked.
+
+use std::path::Path;
+
+use crate::bindings::{Action, Press};
+use crate::context::Context;
+use crate::error::Result;
+use crate::menu::Menu;
+use crate::mouse::Mouse;
+use crate::renderer::Renderer;
+use crate::state::{MenuState, PlayingState, State};
+
+pub struct LevelSelectState {
+    menu: Menu,
+    /// Path of every level, in the order of the menu items before BACK.
+    levels: Vec<String>,
+}
+
+impl LevelSelectState {
+    pub fn new(context: &Context) -> Self {
+        let levels = context.campaign();
+        let mut items: Vec<String> = levels.iter()
+            .map(|level| {
+                let name = Path::new(level).file_stem().map_or(level.clone(), |stem| stem.to_string_lossy().into_owned());
+                let total = context.hidden_star_count(level);
+                if total == 0 {
+                    name.to_uppercase()
+                } else {
+                    format!("{} {}/{}", name.to_uppercase(), context.records.stars_found(level).min(total), total)
+                }
+            })
+            .collect();
+        items.push("BACK".to_string());
+        LevelSelectState { menu: Menu::new("LEVELS", items), levels }
+    }
+
+    fn pick(&self, context: &mut Context, item: usize) -> Result<Box<dyn State>> {
+        match self.levels.get(item) {
+            Some(level) => {
+                context.start_campaign();
+                Ok(Box::new(PlayingState::new(context, context.load_game(level)?)))
+            }
+            None => { Ok(Box::new(MenuState::new())) }
+        }
+    }
+}
+
+impl State for LevelSelectState {
+    fn name(&self) -> &'static str {
+        "level select"
+    }
+
+    fn handle_press(mut self: Box<Self>, context: &mut Context, press: &Press) -> Result<Box<dyn State>> {
+        if let Some(item) = self.menu.handle_press(press) {
+            return self.pick(context, item);
+        }
+        if press.is(Action::Back) {
+            return Ok(Box::new(MenuState::new()));
+        }
+        Ok(self)
+    }
+
+    fn handle_mouse(mut self: Box<Self>, context: &mut Context, mouse: &Mouse) -> Result<Box<dyn State>> {
+        match self.menu.handle_mouse(mouse) {
+            Some(item) => { self.pick(context, item) }
+            None => { Ok(self) }
+        }
+    }
+
+    fn tick(self: Box<Self>, _context: &mut Context) -> Result<Box<dyn State>> {
+        Ok(self)
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer) -> Result<()> {
+        self.menu.render(renderer)
+    }
+}
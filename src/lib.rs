@@ -0,0 +1,78 @@
+//! Simulation and SDL presentation for the platformer. The binary in
+//! `main.rs` only owns the window and the event loop.
+
+pub mod achievement;
+pub mod achievements;
+pub mod assets;
+pub mod audio;
+pub mod bindings;
+pub mod block;
+pub mod captions;
+pub mod cart;
+pub mod collision;
+pub mod color;
+pub mod companion;
+pub mod components;
+pub mod console;
+pub mod context;
+pub mod controls;
+pub mod crash;
+pub mod cutscene;
+pub mod definitions;
+pub mod difficulty;
+pub mod editor;
+pub mod enemy;
+pub mod error;
+pub mod events;
+pub mod game;
+pub mod gamepad;
+pub mod hud;
+pub mod inventory;
+pub mod level;
+pub mod level_select;
+pub mod loot;
+pub mod menu;
+pub mod menu_input;
+pub mod mods;
+pub mod mouse;
+pub mod navigation;
+pub mod npc;
+pub mod objective;
+pub mod particles;
+pub mod paths;
+pub mod player;
+pub mod playground;
+pub mod pool;
+pub mod powerup;
+pub mod profiler;
+pub mod projectile;
+pub mod records;
+pub mod render;
+pub mod renderer;
+pub mod replay;
+pub mod rng;
+pub mod save;
+pub mod saw;
+pub mod score;
+pub mod script;
+pub mod settings;
+pub mod settings_menu;
+pub mod shop;
+pub mod simulation;
+pub mod spawner;
+pub mod state;
+pub mod swing;
+pub mod switch;
+pub mod systems;
+pub mod text;
+pub mod text_entry;
+pub mod touch;
+pub mod trap;
+pub mod trigger;
+pub mod tuning;
+pub mod video;
+pub mod volume;
+pub mod water;
+pub mod waypoint;
+#[cfg(target_os = "emscripten")]
+pub mod web;
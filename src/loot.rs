@@ -0,0 +1,151 @@
+//! Crates that break when a player lands on them or shoots them, and the
+//! coins and hearts they drop. What a crate drops is rolled from the loot
+//! table in the level's info file, see [`crate::level::LevelInfo`].
+
+use hecs::{Entity, World};
+use serde::{Deserialize, Serialize};
+
+use crate::collision::Collider;
+use crate::components::{Body, Position, Sprite, Velocity};
+use crate::rng::Rng;
+
+pub const CRATE_CODE: char = 'X';
+pub const CRATE_SIZE: f32 = 0.9;
+pub const CRATE_COLOR: u32 = 0xA0522D;
+pub const DROP_SIZE: f32 = 0.4;
+/// Fastest sideways speed a drop flies off with, in tiles per tick.
+const DROP_SPREAD: f32 = 0.08;
+/// Upward speed every drop flies off with, in tiles per tick.
+const DROP_LIFT: f32 = 0.2;
+
+/// Marks an entity as a crate. Crates fall like anything with a body but
+/// players go through them rather than standing on them.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Crate;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Prize {
+    Nothing,
+    Coin,
+    Heart,
+}
+
+impl Prize {
+    pub fn color(&self) -> u32 {
+        match self {
+            Prize::Nothing => { 0 }
+            Prize::Coin => { 0xFFD700 }
+            Prize::Heart => { 0xFF4080 }
+        }
+    }
+}
+
+/// A drop lying around until a player picks it up.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Loot {
+    pub prize: Prize,
+}
+
+/// One line of a level's loot table.
+#[derive(Copy, Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct LootEntry {
+    pub prize: Prize,
+    /// How likely this line is compared to the others.
+    pub weight: u32,
+    /// How many of `prize` a crate rolling this line drops.
+    pub count: u32,
+}
+
+impl Default for LootEntry {
+    fn default() -> Self {
+        LootEntry { prize: Prize::Coin, weight: 1, count: 1 }
+    }
+}
+
+/// Picks what a crate drops from `table`, a single coin when it's empty.
+pub fn roll(table: &[LootEntry], rng: &mut Rng) -> (Prize, u32) {
+    let total: u32 = table.iter().map(|entry| entry.weight).sum();
+    let mut pick = rng.range(0, total as i64) as u32;
+    for entry in table {
+        if pick < entry.weight {
+            return (entry.prize, entry.count);
+        }
+        pick -= entry.weight;
+    }
+    (Prize::Coin, 1)
+}
+
+/// Spawns a crate sitting in the tile at `tile`.
+pub fn spawn_crate(world: &mut World, tile: (usize, usize)) -> Entity {
+    world.spawn((
+        Crate,
+        Position {
+            x: tile.0 as f32 + (1.0 - CRATE_SIZE) / 2.0,
+            y: tile.1 as f32 + 1.0 - CRATE_SIZE,
+        },
+        Velocity::default(),
+        Body::new(CRATE_SIZE, CRATE_SIZE),
+        Sprite { color: CRATE_COLOR },
+        Collider::CRATE,
+    ))
+}
+
+/// Spawns `prize` centered on `(x, y)`, thrown up and to a random side.
+pub fn spawn_drop(world: &mut World, x: f32, y: f32, prize: Prize, rng: &mut Rng) -> Entity {
+    let velocity = Velocity { x: (rng.next_f32() * 2.0 - 1.0) * DROP_SPREAD, y: -DROP_LIFT };
+    world.spawn((
+        Loot { prize },
+        Position { x: x - DROP_SIZE / 2.0, y: y - DROP_SIZE / 2.0 },
+        velocity,
+        Body::new(DROP_SIZE, DROP_SIZE),
+        Sprite { color: prize.color() },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(prize: Prize, weight: u32, count: u32) -> LootEntry {
+        LootEntry { prize, weight, count }
+    }
+
+    #[test]
+    fn rolls_follow_the_weights() {
+        let table = [entry(Prize::Coin, 3, 2), entry(Prize::Nothing, 0, 1), entry(Prize::Heart, 1, 1)];
+        let mut rng = Rng::new(5);
+        let mut coins = 0;
+        for _ in 0..4000 {
+            match roll(&table, &mut rng) {
+                (Prize::Coin, count) => {
+                    assert_eq!(count, 2);
+                    coins += 1;
+                }
+                (Prize::Heart, count) => { assert_eq!(count, 1) }
+                (Prize::Nothing, _) => { panic!("rolled a line without weight") }
+            }
+        }
+        assert!((2800..3200).contains(&coins), "{} coins in 4000 rolls", coins);
+    }
+
+    #[test]
+    fn empty_tables_drop_a_coin() {
+        let mut rng = Rng::new(5);
+        assert_eq!(roll(&[], &mut rng), (Prize::Coin, 1));
+        assert_eq!(roll(&[entry(Prize::Heart, 0, 1)], &mut rng), (Prize::Coin, 1));
+    }
+
+    #[test]
+    fn drops_fly_up_from_where_they_spawn() {
+        let mut world = World::new();
+        let mut rng = Rng::new(5);
+        for _ in 0..20 {
+            let drop = spawn_drop(&mut world, 3.0, 4.0, Prize::Heart, &mut rng);
+            let (position, velocity) = world.query_one_mut::<(&Position, &Velocity)>(drop).unwrap();
+            assert_eq!((position.x, position.y), (3.0 - DROP_SIZE / 2.0, 4.0 - DROP_SIZE / 2.0));
+            assert!(velocity.x.abs() <= DROP_SPREAD && velocity.y == -DROP_LIFT);
+        }
+    }
+}
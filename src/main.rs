@@ -1,224 +1,616 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use clap::Parser;
+use log::{debug, info, warn};
+use sdl2::controller::Axis;
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
-use sdl2::pixels::Color;
-use sdl2::rect::Rect;
-use sdl2::render::WindowCanvas;
-
-#[derive(Copy, Clone, Debug)]
-enum Block {
-    EMPTY,
-    PLAYER { color: u32 },
-    WALL { color: u32 },
-    FLOOR { color: u32 },
+use sdl2::keyboard::TextInputUtil;
+use sdl2::{EventPump, Sdl};
+
+use platformer::bindings::{Action, Press};
+use platformer::console::{Console, Effect};
+use platformer::context::Context;
+use platformer::text_entry::TextEntry;
+use platformer::editor::EditorState;
+use platformer::error::Result;
+use platformer::events::{EventLogger, GameEvent};
+use platformer::render;
+use platformer::renderer::{Color, Renderer, SdlRenderer};
+use platformer::game::Game;
+use platformer::gamepad::{self, Gamepads, RumbleFeedback};
+use platformer::profiler::{Phase, Profiler};
+use platformer::menu_input::MenuRepeat;
+use platformer::mods::{Mods, DEFAULT_MODS};
+use platformer::mouse::{Mouse, MouseAction, MouseButton};
+use platformer::paths::{user_file, SearchPaths};
+use platformer::settings::{Settings, DEFAULT_SETTINGS};
+use platformer::replay::{Playback, Replay};
+use platformer::save::SaveGame;
+use platformer::tuning::{TuningWatcher, DEFAULT_TUNING};
+use platformer::state::{MenuState, PlayingState, ReplayState, State};
+
+/// Simple platformer game in Rust and SDL2.
+#[derive(Parser, Debug)]
+#[command(version)]
+struct Args {
+    /// Level to play or edit instead of the configured start level.
+    #[arg(long)]
+    map: Option<String>,
+    /// Window width in pixels.
+    #[arg(long)]
+    width: Option<u32>,
+    /// Window height in pixels.
+    #[arg(long)]
+    height: Option<u32>,
+    /// Run in a borderless window covering the desktop.
+    #[arg(long)]
+    fullscreen: bool,
+    /// Seed for the game's random number generator.
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Open the level editor instead of the game.
+    #[arg(long, conflicts_with_all = ["headless", "replay"])]
+    editor: bool,
+    /// Write a replay of every level played to this file.
+    #[arg(long)]
+    record: Option<String>,
+    /// Play back a replay file instead of playing.
+    #[arg(long, conflicts_with = "record")]
+    replay: Option<String>,
+    /// Resume a saved game.
+    #[arg(long, conflicts_with_all = ["editor", "replay", "headless"])]
+    load: Option<String>,
+    /// Number of players sharing the keyboard, 1 or 2.
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..=2))]
+    players: u8,
+    /// Speed of the game compared to normal, from 0.25 to 4.
+    #[arg(long, default_value_t = 1.0)]
+    time_scale: f32,
+    /// Simulate the level without opening a window.
+    #[arg(long)]
+    headless: bool,
+    /// Number of ticks to simulate in headless mode.
+    #[arg(long, default_value_t = 3600, requires = "headless")]
+    ticks: u64,
+}
+
+/// Time budget of a single frame at 60 frames per second.
+const FRAME_DURATION: Duration = Duration::from_micros(1_000_000 / 60);
+
+fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let args = Args::parse();
+    platformer::crash::install();
+    if let Err(error) = run(args) {
+        eprintln!("platformer: {}", error);
+        std::process::exit(1);
+    }
+}
+
+fn run(args: Args) -> Result<()> {
+    let settings = Settings::load_or_create(&user_file(DEFAULT_SETTINGS))?;
+    let mods = Mods::load(DEFAULT_MODS, SearchPaths::discover())?;
+    let tuning = TuningWatcher::new(&mods.search.resolve(DEFAULT_TUNING));
+    let mut context = Context::new(settings, mods, tuning);
+    context.map = args.map.as_deref().map(command_line_path);
+    context.seed = args.seed;
+    context.record = args.record.clone();
+    context.time_scale = args.time_scale;
+    context.players = args.players as usize;
+    context.events.subscribe(Box::new(EventLogger));
+
+    let replay = args.replay.as_deref().map(Replay::load).transpose()?;
+
+    if args.headless {
+        return run_headless(&mut context, args.ticks, replay);
+    }
+
+    let sdl_context = sdl2::init()?;
+    let video = sdl_context.video()?;
+    context.open_audio(&sdl_context);
+    let captions = context.captions.clone();
+    context.events.subscribe(Box::new(move |event: &GameEvent| captions.borrow_mut().hear(event)));
+    let mut window_builder = video.window(
+        "Dummy platformer on Rust",
+        args.width.unwrap_or(context.settings.window.width),
+        args.height.unwrap_or(context.settings.window.height),
+    );
+    window_builder.position_centered();
+    if args.fullscreen || context.settings.window.fullscreen {
+        window_builder.fullscreen_desktop();
+    }
+    let window = window_builder.build()?;
+
+    let events = sdl_context.event_pump()?;
+    let gamepads = match sdl_context.game_controller() {
+        Ok(subsystem) => {
+            let gamepads = Rc::new(RefCell::new(Gamepads::open(subsystem)));
+            context.events.subscribe(Box::new(RumbleFeedback {
+                gamepads: gamepads.clone(),
+                settings: context.settings.gamepad.clone(),
+            }));
+            Some(gamepads)
+        }
+        Err(error) => {
+            warn!("Controllers are unavailable: {}", error);
+            None
+        }
+    };
+    let text_input = video.text_input();
+    text_input.stop();
+
+    let mut canvas_builder = window.into_canvas().accelerated();
+    if context.settings.window.vsync {
+        canvas_builder = canvas_builder.present_vsync();
+    }
+    let canvas = canvas_builder.build()?;
+
+    let state: Box<dyn State> = if args.editor {
+        Box::new(EditorState::new(&context.mods.resolve(context.level_path()))?)
+    } else if let Some(replay) = replay {
+        Box::new(ReplayState::new(&context, replay)?)
+    } else if let Some(path) = &args.load {
+        Box::new(PlayingState::new(&context, Game::restore(&SaveGame::load(path)?, &context.mods)?))
+    } else {
+        Box::new(MenuState::new())
+    };
+
+    info!("Entering {} state", state.name());
+    let show_inputs = context.settings.input.show_inputs;
+    let app = App {
+        _sdl: sdl_context,
+        context,
+        state,
+        events,
+        renderer: SdlRenderer::new(canvas),
+        profiler: Profiler::new(),
+        show_overlay: false,
+        show_inputs,
+        pointer: (0, 0),
+        console: Console::new(),
+        text_input,
+        gamepads,
+        menu_repeat: MenuRepeat::new(),
+    };
+    run_loop(app)
 }
 
-struct Game {
-    player: Player,
-    playground: Playground,
+/// Everything the windowed game keeps between frames.
+struct App {
+    _sdl: Sdl,
+    context: Context,
+    state: Box<dyn State>,
+    events: EventPump,
+    renderer: SdlRenderer,
+    profiler: Profiler,
+    show_overlay: bool,
+    show_inputs: bool,
+    /// Last known mouse position, for wheel events which carry none.
+    pointer: (i32, i32),
+    console: Console,
+    text_input: TextInputUtil,
+    gamepads: Option<Rc<RefCell<Gamepads>>>,
+    menu_repeat: MenuRepeat,
 }
 
-impl Game {
-    fn new() -> Self {
-        let definition = Game::read_definition();
-        Game {
-            player: definition.0,
-            playground: definition.1,
-        }
-    }
-
-    fn read_definition() -> (Player, Playground) {
-        let contents = std::fs::read_to_string("map.txt")
-            .expect("Unable to read map");
-        let mut width = 0;
-        let mut index = 0;
-        let mut schema = Vec::new();
-        let mut count_width = true;
-        let mut player_index = 0;
-        for code in contents.chars() {
-            let block = match code {
-                '_' => { Some(Block::EMPTY) }
-                '%' => { Some(Block::FLOOR { color: compose_color(255, 0, 0) }) }
-                '|' => { Some(Block::WALL { color: compose_color(0, 0, 255) }) }
-                '@' => {
-                    player_index = index;
-                    Some(Block::FLOOR { color: compose_color(255, 0, 0) })
-                }
-                '\n' => {
-                    if count_width {
-                        width = index;
-                        count_width = false;
-                    }
-                    None
-                }
-                _ => { None }
-            };
-            index = index + 1;
-            if block.is_some() {
-                schema.push(block.unwrap());
+impl App {
+    /// Handles input, ticks and draws once. Returns false when the game should quit.
+    fn frame(&mut self) -> Result<bool> {
+        let frame_start = Instant::now();
+        let previous = self.state.name();
+        while let Some(event) = self.events.poll_event() {
+            match event {
+                Event::Quit { .. } => { return Ok(false) }
+                Event::KeyDown { keycode: Some(keycode), repeat, .. } if self.console.open => {
+                    let press = self.context.settings.keys.press(keycode);
+                    if repeat && MenuRepeat::repeats(&press) {
+                        continue;
+                    }
+                    self.menu_repeat.press(&press, &self.context.settings.input);
+                    self.console_key(keycode);
+                }
+                Event::TextInput { text, .. } => {
+                    if let Some(entry) = self.text_entry() {
+                        entry.insert(&text);
+                    }
+                }
+                Event::TextEditing { text, .. } => {
+                    if let Some(entry) = self.text_entry() {
+                        entry.compose(&text);
+                    }
+                }
+                Event::KeyDown { keycode: Some(Keycode::Escape), .. } => { return Ok(false) }
+                Event::KeyDown { keycode: Some(keycode), repeat, .. } => {
+                    debug!("Key pressed: {}", keycode);
+                    let press = self.context.settings.keys.press(keycode);
+                    if repeat && MenuRepeat::repeats(&press) {
+                        continue;
+                    }
+                    self.menu_repeat.press(&press, &self.context.settings.input);
+                    if press.is(Action::Console) {
+                        self.toggle_console();
+                        continue;
+                    }
+                    if !repeat {
+                        for action in &press.actions {
+                            self.context.press_action(*action);
+                        }
+                    }
+                    if press.is(Action::DebugOverlay) {
+                        self.show_overlay = !self.show_overlay;
+                        continue;
+                    }
+                    if press.is(Action::InputDisplay) {
+                        self.show_inputs = !self.show_inputs;
+                        continue;
+                    }
+                    if press.is(Action::Mute) && self.text_entry().is_none() {
+                        self.context.toggle_mute()?;
+                        continue;
+                    }
+                    self.handle_press(press)?;
+                }
+                Event::MouseMotion { x, y, .. } if !self.console.open => {
+                    self.mouse(x, y, MouseAction::Moved)?;
+                }
+                Event::MouseButtonDown { mouse_btn, x, y, .. } if !self.console.open => {
+                    self.mouse(x, y, MouseAction::Pressed(mouse_btn))?;
+                }
+                Event::MouseButtonUp { mouse_btn, x, y, .. } if !self.console.open => {
+                    self.mouse(x, y, MouseAction::Released(mouse_btn))?;
+                }
+                Event::MouseWheel { y, .. } if !self.console.open => {
+                    let (x, pointer_y) = self.pointer;
+                    self.mouse(x, pointer_y, MouseAction::Scrolled(y))?;
+                }
+                Event::FingerDown { finger_id, x, y, .. } | Event::FingerMotion { finger_id, x, y, .. } => {
+                    let screen = self.renderer.output_size()?;
+                    if let Some(action) = self.context.touch.touch(finger_id, x, y, screen) {
+                        self.context.touch_action(action);
+                        self.handle_press(Press::action(action))?;
+                    }
+                }
+                Event::FingerUp { finger_id, .. } => {
+                    self.context.touch.release(finger_id);
+                }
+                Event::ControllerDeviceAdded { which, .. } => {
+                    let player = self.gamepads.as_ref().and_then(|gamepads| gamepads.borrow_mut().connect(which));
+                    if player.is_some() && player == self.context.disconnected {
+                        self.context.disconnected = None;
+                    }
+                }
+                Event::ControllerDeviceRemoved { which, .. } => {
+                    let player = self.gamepads.as_ref().and_then(|gamepads| gamepads.borrow_mut().disconnect(which));
+                    if let Some(player) = player {
+                        self.context.sticks[player] = 0.0;
+                        for action in Action::BINDABLE {
+                            self.context.held_actions.remove(&action.for_player(player));
+                        }
+                        if player < self.context.players {
+                            self.context.disconnected = Some(player);
+                        }
+                    }
+                }
+                Event::ControllerAxisMotion { which, axis: Axis::LeftX, value, .. } => {
+                    if let Some(player) = self.gamepad_player(which) {
+                        self.context.sticks[player] = value as f32 / i16::MAX as f32;
+                    }
+                }
+                Event::ControllerButtonDown { which, button, .. } if !self.console.open => {
+                    if let Some(player) = self.gamepad_player(which) {
+                        let actions = gamepad::button_actions(button).into_iter().map(|action| action.for_player(player));
+                        let press = Press { keycode: None, actions: actions.collect() };
+                        for action in &press.actions {
+                            self.context.press_action(*action);
+                        }
+                        self.menu_repeat.press(&press, &self.context.settings.input);
+                        self.handle_press(press)?;
+                    }
+                }
+                Event::ControllerButtonUp { which, button, .. } => {
+                    if let Some(player) = self.gamepad_player(which) {
+                        for action in gamepad::button_actions(button) {
+                            self.context.release_action(action.for_player(player));
+                            self.menu_repeat.release(action.for_player(player));
+                        }
+                    }
+                }
+                Event::KeyUp { keycode: Some(keycode), .. } => {
+                    for action in self.context.settings.keys.press(keycode).actions {
+                        self.context.release_action(action);
+                        self.menu_repeat.release(action);
+                    }
+                }
+                _ => {}
             }
         }
-        let playground = Playground::new(schema, index / width, width);
+        if let Some(press) = self.menu_repeat.poll(&self.context.settings.input) {
+            self.repeat_press(press)?;
+        }
+        self.profiler.record(Phase::Input, frame_start);
 
-        let player = Player {
-            position_y: player_index / playground.height,
-            position_x: player_index / width,
-        };
-        (player, playground)
+        let tick_start = Instant::now();
+        self.context.tuning.poll();
+        self.transition(|state, context| state.tick(context))?;
+        if self.context.quit {
+            return Ok(false);
+        }
+        if std::mem::take(&mut self.context.window_changed) {
+            self.renderer.apply_window(&self.context.settings.window)?;
+        }
+        if self.state.name() != previous {
+            info!("Switching from {} to {} state", previous, self.state.name());
+        }
+        self.context.play_music(self.state.music());
+        self.context.play_cues();
+        self.update_text_input();
+        self.profiler.record(Phase::Tick, tick_start);
+
+        let render_start = Instant::now();
+        self.renderer.clear(Color::BLACK);
+        self.state.render(&mut self.renderer)?;
+        if let Some(player) = self.context.disconnected {
+            let hint = format!("RECONNECT PLAYER {} OR PRESS ENTER", player + 1);
+            render::render_banner(&mut self.renderer, "CONTROLLER LOST", &hint)?;
+        }
+        if self.context.touch.visible {
+            render::render_touch_controls(&mut self.renderer, |action| self.context.is_held(action))?;
+        }
+        if self.context.settings.audio.captions {
+            render::render_captions(&mut self.renderer, self.context.captions.borrow_mut().showing())?;
+        }
+        if self.show_inputs {
+            render::render_inputs(&mut self.renderer, &self.context.inputs()[..self.context.players])?;
+        }
+        if self.show_overlay {
+            render::render_profiler(&mut self.renderer, &self.profiler.report)?;
+        }
+        if let Some(name) = self.context.achievements.toast() {
+            render::render_toast(&mut self.renderer, "ACHIEVEMENT UNLOCKED", name)?;
+        }
+        if self.console.open {
+            render::render_console(&mut self.renderer, &self.console)?;
+        }
+        self.profiler.record(Phase::Render, render_start);
+
+        let present_start = Instant::now();
+        self.renderer.present();
+        self.profiler.record(Phase::Present, present_start);
+
+        let elapsed = frame_start.elapsed();
+        if elapsed > FRAME_DURATION * 2 {
+            warn!("Slow frame: {:.1} ms", elapsed.as_secs_f64() * 1000.0);
+        }
+        if let Some(report) = self.profiler.end_frame() {
+            let phases: Vec<String> = Phase::ALL.iter()
+                .zip(report.phases)
+                .map(|(phase, time)| format!("{} {:.2} ms", phase.name(), time))
+                .collect();
+            debug!("{} frames, {:.2} ms average frame time ({})", report.frames, report.frame, phases.join(", "));
+        }
+        Ok(true)
     }
 
-    fn handle_key_press(self: &mut Self, keycode: Keycode) {
-        match keycode {
-            Keycode::A => { self.player.position_x = self.player.position_x + 1 }
-            Keycode::D => { self.player.position_x = self.player.position_x - 1 }
-            Keycode::Space => { self.player.position_y = self.player.position_y + 1 }
-            _ => {}
+    /// Passes a mouse event to the state. With the debug overlay shown,
+    /// a right click teleports the player to the tile clicked.
+    fn mouse(&mut self, x: i32, y: i32, action: MouseAction) -> Result<()> {
+        self.pointer = (x, y);
+        let buttons = self.events.mouse_state();
+        let mouse = Mouse {
+            x,
+            y,
+            left: buttons.left(),
+            right: buttons.right(),
+            screen: self.renderer.output_size()?,
+            action,
+        };
+        if self.show_overlay && action == MouseAction::Pressed(MouseButton::Right) {
+            let screen = mouse.screen;
+            self.state.run_on_game(Box::new(move |game| {
+                if let Some((tile_x, tile_y)) = game.playground.tile_at(screen, x, y) {
+                    game.teleport_player(tile_x, tile_y);
+                }
+            }));
+            return Ok(());
         }
+        self.transition(|state, context| state.handle_mouse(context, &mouse))
     }
 
-    fn tick(self: &Self) {}
-
-    fn render(self: &Self, canvas: &mut WindowCanvas) {
-        let canvas_size = canvas.output_size()
-            .expect("Unable to extract canvas size");
-        let scale = self.playground.scale_factor(canvas_size);
-        self.render_playground(&self.playground, canvas, scale);
-        self.render_player(&self.player, canvas, scale);
-    }
-
-    fn render_playground(self: &Self, playground: &Playground, canvas: &mut WindowCanvas, scale: (u32, u32)) {
-        for y in 0..playground.height {
-            for x in 0..playground.width {
-                let block = playground.block_at(x, y);
-                let color = match block {
-                    Block::WALL { color } => { Some(color) }
-                    Block::FLOOR { color } => { Some(color) }
-                    Block::PLAYER { .. } => { None }
-                    Block::EMPTY => { None }
-                };
-                if color.is_none() {
-                    continue;
-                }
-                let actual_color = color.unwrap();
-                let split = split_rgb(*actual_color);
-                let sdl_color = Color::from(split);
-                canvas.set_draw_color(sdl_color);
-                let rect = Rect::new(
-                    (x as u32 * scale.0) as i32,
-                    (y as u32 * scale.1) as i32,
-                    scale.0,
-                    scale.1,
-                );
-                canvas.fill_rect(rect).unwrap();
-                canvas.draw_rect(rect).unwrap();
+    /// Passes a press to the state, unless it dismisses the reconnect prompt.
+    fn handle_press(&mut self, press: Press) -> Result<()> {
+        if self.context.disconnected.is_some() {
+            if press.is(Action::Confirm) {
+                self.context.disconnected = None;
             }
+            return Ok(());
         }
+        self.transition(|state, context| state.handle_press(context, &press))
     }
 
-    fn render_player(self: &Self, player: &Player, canvas: &mut WindowCanvas, scale: (u32, u32)) {
-        canvas.set_draw_color(Color::GREEN);
-        let rect = Rect::new(
-            (player.position_x as u32 * scale.0) as i32,
-            (player.position_y as u32 * scale.1 + scale.1) as i32,
-            scale.0,
-            scale.1,
-        );
-        canvas.fill_rect(rect).unwrap();
-        canvas.draw_rect(rect).unwrap();
+    /// Delivers a repeated menu press to the console history or the state.
+    fn repeat_press(&mut self, press: Press) -> Result<()> {
+        if !self.console.open {
+            return self.handle_press(press);
+        }
+        if press.is(Action::Up) {
+            self.console.history_previous();
+        } else if press.is(Action::Down) {
+            self.console.history_next();
+        }
+        Ok(())
     }
-}
 
-struct Player {
-    position_x: usize,
-    position_y: usize,
-}
+    fn gamepad_player(&self, which: u32) -> Option<usize> {
+        self.gamepads.as_ref()?.borrow().player_for(which)
+    }
 
-struct Playground {
-    schema: Vec<Block>,
-    height: usize,
-    width: usize,
-}
+    fn toggle_console(&mut self) {
+        self.console.toggle();
+        if self.console.open {
+            // Keys held when the console opens would otherwise stay held.
+            self.context.held_actions.clear();
+        }
+        self.update_text_input();
+    }
 
-impl Playground {
-    fn new(schema: Vec<Block>, height: usize, width: usize) -> Self {
-        Playground {
-            schema,
-            height,
-            width,
+    /// Where typed text goes: the console while it is open, otherwise the
+    /// state's text field, if it has one.
+    fn text_entry(&mut self) -> Option<&mut TextEntry> {
+        if self.console.open {
+            return Some(&mut self.console.input);
         }
+        self.state.text_entry()
     }
 
-    fn block_at(self: &Self, x: usize, y: usize) -> &Block {
-        &self.schema[y * self.width + x]
+    /// Turns SDL text input on while something wants text and off otherwise,
+    /// so the on-screen keyboard and input methods only show when needed.
+    fn update_text_input(&mut self) {
+        let wanted = self.text_entry().is_some();
+        if wanted != self.text_input.is_active() {
+            if wanted {
+                self.text_input.start();
+            } else {
+                self.text_input.stop();
+            }
+        }
     }
 
-    fn scale_factor(self: &Self, size: (u32, u32)) -> (u32, u32) {
-        let dh = size.0 / self.width as u32;
-        let dw = size.1 / self.height as u32;
-        (dh, dw)
+    /// Editing keys while the console is open; text arrives as text input.
+    fn console_key(&mut self, keycode: Keycode) {
+        match keycode {
+            Keycode::Escape => { self.toggle_console() }
+            Keycode::Backspace => { self.console.input.backspace() }
+            Keycode::Up => { self.console.history_previous() }
+            Keycode::Down => { self.console.history_next() }
+            Keycode::Return | Keycode::KpEnter => {
+                if let Some(effect) = self.console.submit(&mut self.context) {
+                    self.apply_console_effect(effect);
+                }
+            }
+            _ if self.context.settings.keys.action_for(keycode) == Some(Action::Console) => { self.toggle_console() }
+            _ => {}
+        }
+    }
+
+    fn apply_console_effect(&mut self, effect: Effect) {
+        match effect {
+            Effect::Message(message) => { self.console.print(message) }
+            Effect::Game(command, message) => {
+                if self.state.run_on_game(command) {
+                    self.console.print(message);
+                } else {
+                    self.console.print("no level is running");
+                }
+            }
+            Effect::Load(path) => {
+                match self.context.load_game(&path) {
+                    Ok(game) => {
+                        self.state = Box::new(PlayingState::new(&self.context, game));
+                        self.console.print(format!("loaded {}", path));
+                    }
+                    Err(error) => { self.console.print(error.to_string()) }
+                }
+            }
+        }
+    }
+
+    /// Replaces the current state with the one returned by `step`.
+    fn transition(
+        &mut self,
+        step: impl FnOnce(Box<dyn State>, &mut Context) -> Result<Box<dyn State>>,
+    ) -> Result<()> {
+        let state = std::mem::replace(&mut self.state, Box::new(MenuState::new()));
+        self.state = step(state, &mut self.context)?;
+        Ok(())
+    }
+
+    fn shutdown(&self) -> Result<()> {
+        info!("Shutting down");
+        self.context.settings.save(&user_file(DEFAULT_SETTINGS))
     }
 }
 
-fn split_rgb(color: u32) -> (u8, u8, u8) {
-    (((color >> 8 * 2) & 0xFF) as u8,
-     ((color >> 8 * 1) & 0xFF) as u8,
-     ((color >> 8 * 0) & 0xFF) as u8)
+/// Runs frames until the game quits, sleeping off what is left of each
+/// frame's time budget.
+#[cfg(not(target_os = "emscripten"))]
+fn run_loop(mut app: App) -> Result<()> {
+    loop {
+        let frame_start = Instant::now();
+        if !app.frame()? {
+            break;
+        }
+        std::thread::sleep(FRAME_DURATION.saturating_sub(frame_start.elapsed()));
+    }
+    app.shutdown()
 }
 
-fn compose_color(r: u32, g: u32, b: u32) -> u32 {
-    let mut rgb = r;
-    rgb = (rgb << 8) + g;
-    rgb = (rgb << 8) + b;
-    rgb as u32
+/// Browsers cannot block in a loop, so frames are driven by the page's
+/// animation callback instead.
+#[cfg(target_os = "emscripten")]
+fn run_loop(mut app: App) -> Result<()> {
+    platformer::web::set_main_loop(move || {
+        let result = app.frame().and_then(|running| {
+            if !running {
+                app.shutdown()?;
+            }
+            Ok(running)
+        });
+        match result {
+            Ok(running) => { running }
+            Err(error) => {
+                eprintln!("platformer: {}", error);
+                false
+            }
+        }
+    });
+    Ok(())
 }
 
-fn main() {
-    const WINDOW_HEIGHT: usize = 600;
-    const WINDOW_WIDTH: usize = 800;
-
-    let sdl_context = sdl2::init()
-        .expect("Unable to init SDL");
-    let video = sdl_context.video()
-        .expect("Unable to init SDL video subsystem");
-    let window = video.window(
-        &"Dummy platformer on Rust",
-        WINDOW_WIDTH as u32,
-        WINDOW_HEIGHT as u32,
-    )
-        .position_centered()
-        .build()
-        .expect("Unable to create window for application");
-
-    let mut running = true;
-
-    let mut events = sdl_context.event_pump()
-        .expect("Unable to extract SDL event listener");
-
-    let mut canvas = window.into_canvas()
-        .present_vsync()
-        .accelerated()
-        .build()
-        .expect("Unable to create canvas");
-
-    let mut game = Game::new();
-
-    while running {
-        for event in events.poll_iter() {
-            match event {
-                Event::Quit { .. } => { running = false }
-                Event::KeyDown { keycode: Some(Keycode::Escape), .. } => { running = false }
-                Event::KeyDown { keycode, .. } => {
-                    if keycode.is_some() {
-                        game.handle_key_press(keycode.unwrap());
-                    }
-                }
-                _ => {}
+/// Makes a path given on the command line absolute when it names an
+/// existing file, so it is not looked up in the game directories instead.
+fn command_line_path(path: &str) -> String {
+    match std::path::absolute(path) {
+        Ok(absolute) if absolute.exists() => { absolute.to_string_lossy().into_owned() }
+        _ => { path.to_string() }
+    }
+}
+
+/// Runs the level without a window until it ends or `ticks` have passed.
+/// With a replay, it plays back until it is over, respawning the players
+/// where they came back after losing a life.
+fn run_headless(context: &mut Context, ticks: u64, replay: Option<Replay>) -> Result<()> {
+    let (mut game, mut playback) = match replay {
+        Some(replay) => { (context.load_replay(&replay)?, Some(Playback::new(replay))) }
+        None => { (context.load_game(context.level_path())?, None) }
+    };
+    loop {
+        let ticked = match &mut playback {
+            Some(playback) => { playback.step(&mut game) }
+            None if game.ticks < ticks && !game.is_player_dead() && !game.is_level_complete() => {
+                game.tick(Default::default());
+                true
             }
+            None => { false }
+        };
+        if !ticked {
+            break;
         }
-        game.tick();
-        canvas.set_draw_color(Color::BLACK);
-        canvas.clear();
-        game.render(&mut canvas);
-        canvas.present();
-        std::thread::sleep(std::time::Duration::from_millis(1000 / 60));
+        context.events.publish(game.drain_events());
     }
+    context.save_replay(&game)?;
+    let outcome = if game.is_player_dead() {
+        "player died"
+    } else if game.is_level_complete() {
+        "level complete"
+    } else {
+        "still running"
+    };
+    println!(
+        "{}: {} after {} ticks with {}/{} coins and {} points (seed {})",
+        game.level_path, outcome, game.ticks, game.coins, game.total_coins, game.score, game.seed,
+    );
+    Ok(())
 }
@@ -0,0 +1,101 @@
+//! Menu widget: a title, an optional line under it and a column of items,
+//! one selected at a time with up and down and picked by confirming or
+//! clicking it. Screens made of choices keep one and act on what it
+//! returns.
+
+use crate::bindings::{Action, Press};
+use crate::error::Result;
+use crate::mouse::Mouse;
+use crate::renderer::{Color, Rect, Renderer};
+use crate::text::{text_size, wrap_text};
+
+const TITLE_PIXEL: u32 = 8;
+const SUBTITLE_PIXEL: u32 = 2;
+const ITEM_PIXEL: u32 = 4;
+
+pub struct Menu {
+    pub title: String,
+    /// Line under the title, wrapped to the screen.
+    pub subtitle: String,
+    pub items: Vec<String>,
+    pub selected: usize,
+    /// Dims what is behind it, for menus over the game.
+    pub overlay: bool,
+}
+
+impl Menu {
+    pub fn new(title: &str, items: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Menu {
+            title: title.to_string(),
+            subtitle: String::new(),
+            items: items.into_iter().map(Into::into).collect(),
+            selected: 0,
+            overlay: false,
+        }
+    }
+
+    /// Moves the selection up or down, or returns the item picked.
+    pub fn handle_press(&mut self, press: &Press) -> Option<usize> {
+        let count = self.items.len().max(1);
+        if press.is(Action::Up) {
+            self.selected = (self.selected + count - 1) % count;
+        } else if press.is(Action::Down) {
+            self.selected = (self.selected + 1) % count;
+        } else if press.is(Action::Confirm) && !self.items.is_empty() {
+            return Some(self.selected);
+        }
+        None
+    }
+
+    /// Selects the item clicked and returns it.
+    pub fn handle_mouse(&mut self, mouse: &Mouse) -> Option<usize> {
+        if !mouse.is_click() {
+            return None;
+        }
+        let (top, line_height) = self.layout(mouse.screen);
+        let row = (mouse.y - top).div_euclid(line_height);
+        let item = usize::try_from(row).ok().filter(|row| *row < self.items.len())?;
+        self.selected = item;
+        Some(item)
+    }
+
+    /// Top of the items and the height of one on a screen of `size`,
+    /// with the title and subtitle above them, all centered.
+    fn layout(&self, size: (u32, u32)) -> (i32, i32) {
+        let title_height = text_size("X", TITLE_PIXEL).1 as i32;
+        let subtitle_height = text_size("X", SUBTITLE_PIXEL).1 as i32 * 2;
+        let subtitle_lines = self.subtitle_lines(size.0).len() as i32;
+        let item_height = text_size("X", ITEM_PIXEL).1 as i32 * 2;
+        let height = title_height * 2 + subtitle_height * subtitle_lines + item_height * self.items.len() as i32;
+        let top = ((size.1 as i32 - height) / 2).max(0);
+        (top + title_height * 2 + subtitle_height * subtitle_lines, item_height)
+    }
+
+    fn subtitle_lines(&self, width: u32) -> Vec<String> {
+        wrap_text(&self.subtitle, SUBTITLE_PIXEL, width.saturating_sub(16))
+    }
+
+    pub fn render(&self, renderer: &mut dyn Renderer) -> Result<()> {
+        let size = renderer.output_size()?;
+        if self.overlay {
+            renderer.fill_rect(Rect::new(0, 0, size.0, size.1), Color::RGBA(0, 0, 0, 160))?;
+        }
+        let center = |text: &str, pixel: u32| (size.0 as i32 - text_size(text, pixel).0 as i32) / 2;
+        let (items_top, item_height) = self.layout(size);
+        let lines = self.subtitle_lines(size.0);
+        let subtitle_height = text_size("X", SUBTITLE_PIXEL).1 as i32 * 2;
+        let title_height = text_size("X", TITLE_PIXEL).1 as i32;
+        let mut y = items_top - subtitle_height * lines.len() as i32 - title_height * 2;
+        renderer.draw_text(&self.title, center(&self.title, TITLE_PIXEL), y, TITLE_PIXEL, Color::WHITE)?;
+        y += title_height * 2;
+        for line in &lines {
+            renderer.draw_text(line, center(line, SUBTITLE_PIXEL), y, SUBTITLE_PIXEL, Color::GRAY)?;
+            y += subtitle_height;
+        }
+        for (index, item) in self.items.iter().enumerate() {
+            let color = if index == self.selected { Color::YELLOW } else { Color::GRAY };
+            renderer.draw_text(item, center(item, ITEM_PIXEL), items_top + item_height * index as i32, ITEM_PIXEL, color)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,54 @@
+//! Repeats menu navigation while a direction is held: the press itself,
+//! then after a delay one more press at a steady rate until it is released.
+//! The operating system's key repeat is ignored for these actions, so keys
+//! and controller buttons scroll lists at the same speed.
+
+use std::time::{Duration, Instant};
+
+use crate::bindings::{Action, Press};
+use crate::settings::InputSettings;
+
+/// Actions that repeat while held.
+const REPEATING: [Action; 2] = [Action::Up, Action::Down];
+
+#[derive(Default)]
+pub struct MenuRepeat {
+    /// The press being repeated and when it repeats next.
+    held: Option<(Press, Instant)>,
+}
+
+impl MenuRepeat {
+    pub fn new() -> Self {
+        MenuRepeat::default()
+    }
+
+    /// Whether `press` is one this layer repeats.
+    pub fn repeats(press: &Press) -> bool {
+        REPEATING.iter().any(|action| press.is(*action))
+    }
+
+    /// Starts repeating `press` if it navigates.
+    pub fn press(&mut self, press: &Press, settings: &InputSettings) {
+        if MenuRepeat::repeats(press) {
+            self.held = Some((press.clone(), Instant::now() + Duration::from_millis(settings.repeat_delay)));
+        }
+    }
+
+    /// Stops repeating once any action of the held press is released.
+    pub fn release(&mut self, action: Action) {
+        if self.held.as_ref().is_some_and(|(press, _)| press.is(action)) {
+            self.held = None;
+        }
+    }
+
+    /// The press to repeat now, if one is due.
+    pub fn poll(&mut self, settings: &InputSettings) -> Option<Press> {
+        let (press, next) = self.held.as_mut()?;
+        let now = Instant::now();
+        if now < *next {
+            return None;
+        }
+        *next = now + Duration::from_millis(settings.repeat_interval.max(1));
+        Some(press.clone())
+    }
+}
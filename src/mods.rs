@@ -0,0 +1,124 @@
+//! Mods live in `mods/<name>/` folders. A mod can:
+//!
+//! - override any game file by providing one at the same relative path,
+//!   e.g. `mods/night/map.txt` replaces `map.txt`,
+//! - add levels to the campaign as `mods/<name>/levels/*.txt`,
+//! - define entity types in `mods/<name>/entities.toml`.
+//!
+//! `mods/load_order.toml` lists the mods to load (`order = ["a", "b"]`);
+//! without it every mod folder is loaded alphabetically. Later mods win
+//! over earlier ones.
+
+use std::path::{Path, PathBuf};
+
+use log::{info, warn};
+use serde::Deserialize;
+
+use crate::definitions::EntityDefinitions;
+use crate::error::{GameError, Result};
+use crate::paths::SearchPaths;
+
+pub const DEFAULT_MODS: &str = "mods";
+
+const LOAD_ORDER: &str = "load_order.toml";
+const ENTITIES: &str = "entities.toml";
+const LEVELS: &str = "levels";
+
+#[derive(Deserialize)]
+struct LoadOrder {
+    order: Vec<String>,
+}
+
+pub struct Mod {
+    pub name: String,
+    pub root: PathBuf,
+}
+
+#[derive(Default)]
+pub struct Mods {
+    /// Loaded mods, in load order.
+    pub loaded: Vec<Mod>,
+    pub definitions: EntityDefinitions,
+    /// Where files that no mod overrides are looked up.
+    pub search: SearchPaths,
+}
+
+impl Mods {
+    /// Finds the mods under `dir`, itself looked up through `search`.
+    /// A missing directory simply means no mods.
+    pub fn load(dir: &str, search: SearchPaths) -> Result<Mods> {
+        let dir = search.resolve(dir);
+        let dir = Path::new(&dir);
+        let mut mods = Mods { search, ..Mods::default() };
+        if !dir.is_dir() {
+            return Ok(mods);
+        }
+        for name in Mods::load_order(dir)? {
+            let root = dir.join(&name);
+            if !root.is_dir() {
+                warn!("Mod '{}' is listed in the load order but {} does not exist", name, root.display());
+                continue;
+            }
+            let definitions = root.join(ENTITIES);
+            if definitions.is_file() {
+                mods.definitions.merge(read_toml(&definitions)?);
+            }
+            info!("Loaded mod '{}'", name);
+            mods.loaded.push(Mod { name, root });
+        }
+        Ok(mods)
+    }
+
+    fn load_order(dir: &Path) -> Result<Vec<String>> {
+        let manifest = dir.join(LOAD_ORDER);
+        if manifest.is_file() {
+            let load_order: LoadOrder = read_toml(&manifest)?;
+            return Ok(load_order.order);
+        }
+        let entries = std::fs::read_dir(dir)
+            .map_err(|source| GameError::Io { path: dir.display().to_string(), source })?;
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    /// Path of the file to use for `path`, taking overrides from the last
+    /// mod that provides one and searching the game directories otherwise.
+    pub fn resolve(&self, path: &str) -> String {
+        self.loaded.iter().rev()
+            .map(|loaded| loaded.root.join(path))
+            .find(|candidate| candidate.is_file())
+            .map(|candidate| candidate.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.search.resolve(path))
+    }
+
+    /// Levels added by mods, in load order and then by file name.
+    pub fn levels(&self) -> Vec<String> {
+        let mut levels = Vec::new();
+        for loaded in &self.loaded {
+            let entries = match std::fs::read_dir(loaded.root.join(LEVELS)) {
+                Ok(entries) => { entries }
+                Err(_) => { continue }
+            };
+            let mut paths: Vec<PathBuf> = entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|extension| extension == "txt"))
+                .collect();
+            paths.sort();
+            levels.extend(paths.into_iter().map(|path| path.to_string_lossy().into_owned()));
+        }
+        levels
+    }
+}
+
+fn read_toml<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|source| GameError::Io { path: path.display().to_string(), source })?;
+    toml::from_str(&contents)
+        .map_err(|error| GameError::InvalidMod(format!("{}: {}", path.display(), error)))
+}
@@ -0,0 +1,30 @@
+//! Mouse input in canvas coordinates, passed to states next to key presses.
+
+pub use sdl2::mouse::MouseButton;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MouseAction {
+    Moved,
+    Pressed(MouseButton),
+    Released(MouseButton),
+    /// Wheel movement, positive away from the user.
+    Scrolled(i32),
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Mouse {
+    pub x: i32,
+    pub y: i32,
+    /// Buttons held down while the event happened.
+    pub left: bool,
+    pub right: bool,
+    /// Size of the canvas the coordinates refer to.
+    pub screen: (u32, u32),
+    pub action: MouseAction,
+}
+
+impl Mouse {
+    pub fn is_click(&self) -> bool {
+        self.action == MouseAction::Pressed(MouseButton::Left)
+    }
+}
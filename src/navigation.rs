@@ -0,0 +1,427 @@
+//! Ways across the playground for enemies that walk, jump and fall like
+//! players do. Routes run between the tiles a body can stand in, open
+//! tiles right above a solid one, and are found with A*, either tile by
+//! tile or over a [`PlatformGraph`] of the jumps and drops between
+//! platforms worked out once per level.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::playground::Playground;
+
+/// How a route gets to a tile from the one before it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Movement {
+    /// Walks over to the tile next to it.
+    Walk,
+    /// Walks off a ledge and drops onto the tile.
+    Fall,
+    /// Jumps up and over onto the tile.
+    Jump,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Step {
+    pub tile: (usize, usize),
+    pub movement: Movement,
+}
+
+/// How far a body can jump, in whole tiles.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Reach {
+    /// Rows it can climb.
+    pub height: usize,
+    /// Columns it can cross at the top of its jump.
+    pub distance: usize,
+}
+
+impl Reach {
+    /// Reach of a body jumping at `jump_speed` under `gravity` and moving
+    /// at `run_speed`, leaving a margin so jumps at the limit still land.
+    pub fn new(jump_speed: f32, gravity: f32, run_speed: f32) -> Self {
+        if gravity <= 0.0 {
+            return Reach { height: 0, distance: 0 };
+        }
+        let height = jump_speed * jump_speed / (2.0 * gravity) - 0.25;
+        let air_time = 2.0 * jump_speed / gravity;
+        Reach { height: height.max(0.0) as usize, distance: (run_speed * air_time * 0.75).max(0.0) as usize }
+    }
+}
+
+/// Whether a body fits in the tile at signed coordinates and has ground
+/// under it.
+pub fn can_stand(playground: &Playground, x: i32, y: i32) -> bool {
+    x >= 0 && y >= 0
+        && playground.contains(x as usize, y as usize)
+        && !playground.is_solid(x, y)
+        && playground.is_solid(x, y + 1)
+}
+
+/// Tile a body whose feet are at `(x, feet)` stands in, or would land in
+/// falling straight down.
+pub fn standing_tile(playground: &Playground, x: f32, feet: f32) -> Option<(usize, usize)> {
+    let column = x.floor() as i32;
+    let row = (feet - 0.01).floor() as i32;
+    landing(playground, column, row).map(|y| (column as usize, y as usize))
+}
+
+/// First row from `y` down where a body falling in column `x` comes to
+/// stand, if it doesn't fall out of the playground or start in a wall.
+fn landing(playground: &Playground, x: i32, y: i32) -> Option<i32> {
+    if x < 0 || x as usize >= playground.width {
+        return None;
+    }
+    (y.max(0)..playground.height as i32)
+        .take_while(|row| !playground.is_solid(x, *row))
+        .find(|row| can_stand(playground, x, *row))
+}
+
+/// Steps a body standing in `tile` can take, each with what it costs: one
+/// per tile crossed, and one more for a jump.
+fn neighbors(playground: &Playground, tile: (usize, usize), reach: Reach) -> Vec<(Step, usize)> {
+    let (x, y) = (tile.0 as i32, tile.1 as i32);
+    let mut steps = Vec::new();
+    let step = |x: i32, y: i32, movement: Movement| Step { tile: (x as usize, y as usize), movement };
+    for direction in [-1, 1] {
+        let next = x + direction;
+        if !playground.is_solid(next, y) {
+            if can_stand(playground, next, y) {
+                steps.push((step(next, y, Movement::Walk), 1));
+            } else if let Some(ground) = landing(playground, next, y) {
+                steps.push((step(next, ground, Movement::Fall), 1 + (ground - y) as usize));
+            }
+        }
+        for height in 1..=reach.height as i32 {
+            if playground.is_solid(x, y - height) || y - height < 0 {
+                break;
+            }
+            for distance in 1..=reach.distance as i32 {
+                let column = x + direction * distance;
+                if playground.is_solid(column, y - height) {
+                    break;
+                }
+                let Some(ground) = landing(playground, column, y - height) else { continue };
+                if ground == y && distance == 1 {
+                    continue;
+                }
+                let cost = distance as usize + height as usize + (ground - (y - height)) as usize + 1;
+                steps.push((step(column, ground, Movement::Jump), cost));
+            }
+        }
+    }
+    steps
+}
+
+/// Cheapest route from the standing tile `from` to the standing tile
+/// `to`, leaving out `from`, giving up after looking at `limit` tiles.
+pub fn find_route(playground: &Playground, from: (usize, usize), to: (usize, usize), reach: Reach, limit: usize) -> Option<Vec<Step>> {
+    let estimate = |tile: (usize, usize)| tile.0.abs_diff(to.0) + tile.1.abs_diff(to.1);
+    // Cheapest known cost of each tile and the step that got there from
+    // the tile before it.
+    let mut costs: HashMap<(usize, usize), usize> = HashMap::from([(from, 0)]);
+    let mut previous: HashMap<(usize, usize), ((usize, usize), Movement)> = HashMap::new();
+    let mut open = BinaryHeap::from([Reverse((estimate(from), 0, from))]);
+    let mut looked = 0;
+    while let Some(Reverse((_, cost, tile))) = open.pop() {
+        if tile == to {
+            let mut route = Vec::new();
+            let mut current = tile;
+            while let Some((before, movement)) = previous.get(&current) {
+                route.push(Step { tile: current, movement: *movement });
+                current = *before;
+            }
+            route.reverse();
+            return Some(route);
+        }
+        if costs.get(&tile).is_some_and(|best| cost > *best) {
+            continue;
+        }
+        looked += 1;
+        if looked > limit {
+            return None;
+        }
+        for (step, step_cost) in neighbors(playground, tile, reach) {
+            let next_cost = cost + step_cost;
+            if costs.get(&step.tile).is_some_and(|best| next_cost >= *best) {
+                continue;
+            }
+            costs.insert(step.tile, next_cost);
+            previous.insert(step.tile, (tile, step.movement));
+            open.push(Reverse((next_cost + estimate(step.tile), next_cost, step.tile)));
+        }
+    }
+    None
+}
+
+/// A run of standing tiles along one row, walkable end to end.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Platform {
+    pub row: usize,
+    pub left: usize,
+    pub right: usize,
+}
+
+/// A jump or drop from a tile of one platform onto another platform.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Link {
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+    pub movement: Movement,
+    pub cost: usize,
+}
+
+/// Tile a walk along a platform started from and the link it then took,
+/// if it didn't end on the platform.
+type Leg = ((usize, usize), Option<Link>);
+
+/// Every platform of a playground and the jumps and drops between them,
+/// worked out once for a reach and kept until the playground changes.
+#[derive(Clone, Debug)]
+pub struct PlatformGraph {
+    pub reach: Reach,
+    /// [`Playground::revision`] the graph was built from.
+    pub revision: u64,
+    pub platforms: Vec<Platform>,
+    /// Links leaving each platform, cheapest landing on each tile only.
+    pub links: Vec<Vec<Link>>,
+    /// Platform of every standing tile.
+    platform_of: HashMap<(usize, usize), usize>,
+}
+
+impl PlatformGraph {
+    pub fn build(playground: &Playground, reach: Reach) -> Self {
+        let mut platforms = Vec::new();
+        let mut platform_of = HashMap::new();
+        for y in 0..playground.height {
+            let mut x = 0;
+            while x < playground.width {
+                if !can_stand(playground, x as i32, y as i32) {
+                    x += 1;
+                    continue;
+                }
+                let left = x;
+                while x + 1 < playground.width && can_stand(playground, x as i32 + 1, y as i32) {
+                    x += 1;
+                }
+                for column in left..=x {
+                    platform_of.insert((column, y), platforms.len());
+                }
+                platforms.push(Platform { row: y, left, right: x });
+                x += 1;
+            }
+        }
+        let links = platforms.iter().enumerate()
+            .map(|(index, platform)| {
+                let mut links: Vec<Link> = Vec::new();
+                for x in platform.left..=platform.right {
+                    for (step, cost) in neighbors(playground, (x, platform.row), reach) {
+                        if platform_of.get(&step.tile) == Some(&index) {
+                            continue;
+                        }
+                        let link = Link { from: (x, platform.row), to: step.tile, movement: step.movement, cost };
+                        match links.iter_mut().find(|other| other.to == step.tile) {
+                            Some(other) if other.cost > cost => { *other = link }
+                            Some(_) => {}
+                            None => { links.push(link) }
+                        }
+                    }
+                }
+                links
+            })
+            .collect();
+        PlatformGraph { reach, revision: playground.revision(), platforms, links, platform_of }
+    }
+
+    /// Platform the standing tile `tile` belongs to.
+    pub fn platform_at(&self, tile: (usize, usize)) -> Option<usize> {
+        self.platform_of.get(&tile).copied()
+    }
+
+    /// Cheapest route from the standing tile `from` to the standing tile
+    /// `to`: walks along platforms to where a link leaves, then the link.
+    /// Leaves out `from`.
+    pub fn find_route(&self, from: (usize, usize), to: (usize, usize)) -> Option<Vec<Step>> {
+        let goal_platform = self.platform_at(to)?;
+        self.platform_at(from)?;
+        let estimate = |tile: (usize, usize)| tile.0.abs_diff(to.0) + tile.1.abs_diff(to.1);
+        let mut costs: HashMap<(usize, usize), usize> = HashMap::from([(from, 0)]);
+        // Link taken to reach each tile, from where the walk to it started.
+        let mut previous: HashMap<(usize, usize), Leg> = HashMap::new();
+        let mut open = BinaryHeap::from([Reverse((estimate(from), 0, from))]);
+        while let Some(Reverse((_, cost, tile))) = open.pop() {
+            if tile == to {
+                return Some(self.unwind(&previous, to));
+            }
+            if costs.get(&tile).is_some_and(|best| cost > *best) {
+                continue;
+            }
+            let Some(platform) = self.platform_at(tile) else { continue };
+            let mut moves: Vec<((usize, usize), usize, Option<Link>)> = self.links[platform].iter()
+                .map(|link| (link.to, link.from.0.abs_diff(tile.0) + link.cost, Some(*link)))
+                .collect();
+            if platform == goal_platform {
+                moves.push((to, to.0.abs_diff(tile.0), None));
+            }
+            for (next, move_cost, link) in moves {
+                let next_cost = cost + move_cost;
+                if costs.get(&next).is_some_and(|best| next_cost >= *best) {
+                    continue;
+                }
+                costs.insert(next, next_cost);
+                previous.insert(next, (tile, link));
+                open.push(Reverse((next_cost + estimate(next), next_cost, next)));
+            }
+        }
+        None
+    }
+
+    /// Steps of the route ending at `end`, walking tile by tile along each
+    /// platform.
+    fn unwind(&self, previous: &HashMap<(usize, usize), Leg>, end: (usize, usize)) -> Vec<Step> {
+        let mut legs = Vec::new();
+        let mut current = end;
+        while let Some((start, link)) = previous.get(&current) {
+            legs.push((*start, *link, current));
+            current = *start;
+        }
+        legs.reverse();
+        let mut steps = Vec::new();
+        for (start, link, end) in legs {
+            let launch = link.map_or(end, |link| link.from);
+            let mut x = start.0;
+            while x != launch.0 {
+                x = if launch.0 > x { x + 1 } else { x - 1 };
+                steps.push(Step { tile: (x, start.1), movement: Movement::Walk });
+            }
+            if let Some(link) = link {
+                steps.push(Step { tile: link.to, movement: link.movement });
+            }
+        }
+        steps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Block;
+
+    /// `#` for walls and anything else for empty tiles, a row per line.
+    fn playground(rows: &[&str]) -> Playground {
+        let schema = rows.iter()
+            .flat_map(|row| row.chars())
+            .map(|tile| if tile == '#' { Block::Wall { color: 0 } } else { Block::Empty })
+            .collect();
+        Playground::new(schema, rows.len(), rows[0].len())
+    }
+
+    fn step(x: usize, y: usize, movement: Movement) -> Step {
+        Step { tile: (x, y), movement }
+    }
+
+    const REACH: Reach = Reach { height: 1, distance: 1 };
+
+    #[test]
+    fn reach_of_a_jump() {
+        assert_eq!(Reach::new(6.0, 6.0, 4.0), Reach { height: 2, distance: 6 });
+        assert_eq!(Reach::new(6.0, 0.0, 4.0), Reach { height: 0, distance: 0 });
+    }
+
+    #[test]
+    fn standing_needs_ground_underneath() {
+        let playground = playground(&[
+            "...",
+            ".#.",
+            "###",
+        ]);
+        assert!(can_stand(&playground, 0, 1));
+        assert!(can_stand(&playground, 1, 0));
+        assert!(!can_stand(&playground, 0, 0));
+        assert!(!can_stand(&playground, 1, 1));
+        assert!(!can_stand(&playground, -1, 1));
+        assert_eq!(standing_tile(&playground, 0.5, 0.5), Some((0, 1)));
+    }
+
+    #[test]
+    fn route_walks_and_jumps_onto_a_ledge() {
+        let playground = playground(&[
+            "......",
+            "...###",
+            "######",
+        ]);
+        let route = find_route(&playground, (0, 1), (4, 0), REACH, 100).unwrap();
+        assert_eq!(route, vec![
+            step(1, 1, Movement::Walk),
+            step(2, 1, Movement::Walk),
+            step(3, 0, Movement::Jump),
+            step(4, 0, Movement::Walk),
+        ]);
+    }
+
+    #[test]
+    fn route_drops_off_a_ledge() {
+        let playground = playground(&[
+            "......",
+            "##....",
+            "......",
+            "######",
+        ]);
+        let route = find_route(&playground, (1, 0), (3, 2), REACH, 100).unwrap();
+        assert_eq!(route, vec![step(2, 2, Movement::Fall), step(3, 2, Movement::Walk)]);
+    }
+
+    #[test]
+    fn no_route_over_a_wall_out_of_reach() {
+        let playground = playground(&[
+            ".....",
+            "..#..",
+            "..#..",
+            "#####",
+        ]);
+        assert_eq!(find_route(&playground, (0, 2), (4, 2), REACH, 100), None);
+        let high = Reach { height: 2, distance: 1 };
+        assert!(find_route(&playground, (0, 2), (4, 2), high, 100).is_some());
+        assert_eq!(find_route(&playground, (0, 2), (4, 2), high, 2), None);
+    }
+
+    #[test]
+    fn graph_links_platforms() {
+        let playground = playground(&[
+            "......",
+            "...###",
+            "######",
+        ]);
+        let graph = PlatformGraph::build(&playground, REACH);
+        assert_eq!(graph.platforms, vec![
+            Platform { row: 0, left: 3, right: 5 },
+            Platform { row: 1, left: 0, right: 2 },
+        ]);
+        assert_eq!(graph.platform_at((4, 0)), Some(0));
+        assert_eq!(graph.platform_at((1, 1)), Some(1));
+        assert_eq!(graph.platform_at((1, 0)), None);
+        assert_eq!(graph.links[0], vec![Link { from: (3, 0), to: (2, 1), movement: Movement::Fall, cost: 2 }]);
+        assert_eq!(graph.links[1], vec![Link { from: (2, 1), to: (3, 0), movement: Movement::Jump, cost: 3 }]);
+    }
+
+    #[test]
+    fn graph_routes_match_tile_routes() {
+        let playground = playground(&[
+            "........",
+            ".....###",
+            "...##...",
+            "########",
+        ]);
+        let graph = PlatformGraph::build(&playground, REACH);
+        for (from, to) in [((0, 2), (7, 0)), ((7, 0), (0, 2)), ((7, 0), (3, 1))] {
+            let route = graph.find_route(from, to);
+            assert!(route.is_some(), "no route from {:?} to {:?}", from, to);
+            assert_eq!(route, find_route(&playground, from, to, REACH, 1000), "from {:?} to {:?}", from, to);
+        }
+        // Walled in under the overhang.
+        assert_eq!(graph.find_route((6, 2), (0, 2)), None);
+        assert_eq!(find_route(&playground, (6, 2), (0, 2), REACH, 1000), None);
+        assert_eq!(graph.find_route((0, 0), (7, 0)), None, "(0, 0) is not a standing tile");
+    }
+}
@@ -0,0 +1,36 @@
+//! Friendly characters that say a few pages of text when a player talks
+//! to them. What each one says comes from the level's info file, see
+//! [`crate::level::LevelInfo`].
+
+use hecs::{Entity, World};
+use serde::{Deserialize, Serialize};
+
+use crate::components::{Body, Position, Sprite, Velocity};
+
+pub const NPC_CODE: char = 'N';
+pub const NPC_WIDTH: f32 = 0.8;
+pub const NPC_HEIGHT: f32 = 0.9;
+pub const NPC_COLOR: u32 = 0xF5DEB3;
+/// Farthest a player can be from an NPC, center to center, to talk to it.
+pub const TALK_REACH: f32 = 1.5;
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Npc {
+    /// Place of the NPC among the level's NPCs, in map order, which picks
+    /// its lines from the level info.
+    pub index: usize,
+}
+
+/// Spawns NPC number `index` standing in the tile at `tile`.
+pub fn spawn(world: &mut World, tile: (usize, usize), index: usize) -> Entity {
+    world.spawn((
+        Npc { index },
+        Position {
+            x: tile.0 as f32 + (1.0 - NPC_WIDTH) / 2.0,
+            y: tile.1 as f32 + 1.0 - NPC_HEIGHT,
+        },
+        Velocity::default(),
+        Body::new(NPC_WIDTH, NPC_HEIGHT),
+        Sprite { color: NPC_COLOR },
+    ))
+}
@@ -0,0 +1,34 @@
+//! Goals listed in a level's info file, each completed by a number of
+//! gameplay events of one kind, e.g. picking up three coins or defeating
+//! the enemy placed at a given tile. Objectives are only tracked and
+//! shown; the exit stays open either way.
+
+use serde::Deserialize;
+
+use crate::events::GameEvent;
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Objective {
+    /// What to do, as shown on the pause screen.
+    pub text: String,
+    /// Name of the event counted, as passed to scripts, e.g. "CoinCollected".
+    pub event: String,
+    /// Times the event has to happen.
+    pub count: u32,
+    /// Only counts events happening at this tile, for events that have one.
+    pub at: Option<(usize, usize)>,
+}
+
+impl Default for Objective {
+    fn default() -> Self {
+        Objective { text: String::new(), event: String::new(), count: 1, at: None }
+    }
+}
+
+impl Objective {
+    /// Whether `event` counts toward this objective.
+    pub fn counts(&self, event: &GameEvent) -> bool {
+        event.name() == self.event && (self.at.is_none() || event.tile() == self.at)
+    }
+}
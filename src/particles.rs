@@ -0,0 +1,66 @@
+//! Purely visual particles. They live in a fixed pool next to the world
+//! rather than in it, take no part in collisions and are not saved.
+
+use crate::components::{Position, Velocity};
+use crate::pool::Pool;
+
+/// Particles alive at once; bursts beyond this are cut short.
+pub const MAX_PARTICLES: usize = 512;
+/// Size of a particle in tiles.
+pub const PARTICLE_SIZE: f32 = 0.12;
+
+#[derive(Copy, Clone, Debug)]
+pub struct Particle {
+    pub position: Position,
+    pub velocity: Velocity,
+    /// Ticks left before the particle disappears.
+    pub life: u32,
+    pub color: u32,
+}
+
+pub struct Particles {
+    pool: Pool<Particle>,
+}
+
+impl Particles {
+    pub fn new() -> Self {
+        Particles { pool: Pool::with_capacity(MAX_PARTICLES) }
+    }
+
+    /// Throws `count` particles from `(x, y)` in a fan opening upwards.
+    pub fn burst(&mut self, x: f32, y: f32, count: usize, speed: f32, life: u32, color: u32) {
+        for i in 0..count {
+            let angle = std::f32::consts::PI * (i as f32 + 0.5) / count as f32;
+            let particle = Particle {
+                position: Position { x, y },
+                velocity: Velocity { x: -angle.cos() * speed, y: -angle.sin() * speed },
+                life,
+                color,
+            };
+            if self.pool.insert(particle).is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Moves every particle, pulled down by `gravity`.
+    pub fn update(&mut self, gravity: f32) {
+        self.pool.retain(|particle| {
+            particle.velocity.y += gravity;
+            particle.position.x += particle.velocity.x;
+            particle.position.y += particle.velocity.y;
+            particle.life = particle.life.saturating_sub(1);
+            particle.life > 0
+        });
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Particle> {
+        self.pool.iter()
+    }
+}
+
+impl Default for Particles {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,93 @@
+//! Where game files are looked up. A relative path such as `map.txt` is
+//! tried against each of these directories in turn, so the game finds its
+//! files no matter which directory it was started from:
+//!
+//! 1. the directory named by `PLATFORMER_ASSETS`,
+//! 2. `assets/` next to the executable,
+//! 3. the platform data directory: `$XDG_DATA_HOME/platformer` (or
+//!    `~/.local/share/platformer`), `%APPDATA%\platformer` on Windows and
+//!    `~/Library/Application Support/platformer` on macOS,
+//! 4. in debug builds, `assets/` in the source tree, for `cargo run`.
+//!
+//! Paths found in none of them are used as given, relative to the
+//! current directory.
+//!
+//! The player's own files, such as the settings, best times and saves,
+//! are written to the platform data directory, see [`user_file`].
+
+use std::env;
+use std::path::{Path, PathBuf};
+
+use log::{debug, warn};
+
+pub const ASSETS_ENV: &str = "PLATFORMER_ASSETS";
+const ASSETS_DIR: &str = "assets";
+const APP_NAME: &str = "platformer";
+
+#[derive(Clone, Debug, Default)]
+pub struct SearchPaths {
+    /// Directories to search, most preferred first.
+    pub roots: Vec<PathBuf>,
+}
+
+impl SearchPaths {
+    /// The standard directories for this platform and build that exist.
+    pub fn discover() -> Self {
+        let mut roots = Vec::new();
+        if let Some(dir) = env::var_os(ASSETS_ENV) {
+            roots.push(PathBuf::from(dir));
+        }
+        if let Some(dir) = env::current_exe().ok().and_then(|exe| exe.parent().map(Path::to_path_buf)) {
+            roots.push(dir.join(ASSETS_DIR));
+        }
+        if let Some(dir) = data_dir() {
+            roots.push(dir.join(APP_NAME));
+        }
+        if cfg!(debug_assertions) {
+            roots.push(Path::new(env!("CARGO_MANIFEST_DIR")).join(ASSETS_DIR));
+        }
+        roots.retain(|root| root.is_dir());
+        for root in &roots {
+            debug!("Searching for game files in {}", root.display());
+        }
+        SearchPaths { roots }
+    }
+
+    /// First existing file or directory for `path`, or `path` itself.
+    pub fn resolve(&self, path: &str) -> String {
+        if Path::new(path).is_absolute() {
+            return path.to_string();
+        }
+        self.roots.iter()
+            .map(|root| root.join(path))
+            .find(|candidate| candidate.exists())
+            .map(|candidate| candidate.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.to_string())
+    }
+}
+
+/// Where the player's file `name` is read and written: in the platform
+/// data directory, created when missing, or in the current directory on a
+/// system without one.
+pub fn user_file(name: &str) -> String {
+    let Some(dir) = data_dir().map(|dir| dir.join(APP_NAME)) else { return name.to_string() };
+    if let Err(error) = std::fs::create_dir_all(&dir) {
+        warn!("Can't create {}, keeping {} in the current directory: {}", dir.display(), name, error);
+        return name.to_string();
+    }
+    dir.join(name).to_string_lossy().into_owned()
+}
+
+fn data_dir() -> Option<PathBuf> {
+    if cfg!(windows) {
+        return env::var_os("APPDATA").map(PathBuf::from);
+    }
+    let home = env::var_os("HOME").map(PathBuf::from);
+    if cfg!(target_os = "macos") {
+        return home.map(|home| home.join("Library").join("Application Support"));
+    }
+    env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| home.map(|home| home.join(".local").join("share")))
+}
@@ -0,0 +1,88 @@
+use hecs::{Entity, World};
+use serde::{Deserialize, Serialize};
+
+use crate::collision::Collider;
+use crate::components::{Body, Position, Sprite, Velocity};
+use crate::systems::input::MAX_PLAYERS;
+
+pub const PLAYER_WIDTH: f32 = 0.8;
+pub const PLAYER_HEIGHT: f32 = 0.9;
+/// Color of each player, in player order.
+pub const PLAYER_COLORS: [u32; MAX_PLAYERS] = [0x00FF00, 0x00BFFF];
+/// Color players flash to while they can't be hurt.
+pub const HURT_COLOR: u32 = 0xFFFFFF;
+pub const PLAYER_HEALTH: u32 = 3;
+/// Ticks after a hit during which the player can't be hurt again.
+pub const INVULNERABLE_TICKS: u32 = 90;
+/// Ticks after a hit during which the player is thrown back and ignores input.
+pub const STUN_TICKS: u32 = 15;
+/// Speeds a hit throws the player back with, in tiles per tick.
+pub const KNOCKBACK: (f32, f32) = (0.12, 0.2);
+
+/// Marks an entity steered by the input of player number `index`.
+#[derive(Copy, Clone, Debug)]
+pub struct Player {
+    pub index: usize,
+    /// -1 when facing left, 1 when facing right.
+    pub facing: f32,
+    /// Ticks left before the player can shoot again.
+    pub reload: u32,
+    /// Ticks spent pushing against a block that can be pushed.
+    pub pushing: u32,
+    /// Tiles walked since the last footstep.
+    pub stride: f32,
+}
+
+impl Player {
+    pub fn new(index: usize) -> Self {
+        Player { index, facing: 1.0, reload: 0, pushing: 0, stride: 0.0 }
+    }
+}
+
+/// Hit points of a player.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Health {
+    pub hp: u32,
+    pub max: u32,
+    /// Ticks left before the player can be hurt again.
+    pub invulnerable: u32,
+}
+
+impl Health {
+    pub fn new(max: u32) -> Self {
+        Health { hp: max, max, invulnerable: 0 }
+    }
+
+    /// Whether the player is still thrown back by the last hit.
+    pub fn is_stunned(&self) -> bool {
+        self.invulnerable > INVULNERABLE_TICKS - STUN_TICKS
+    }
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Health::new(PLAYER_HEALTH)
+    }
+}
+
+/// Marks a player out of hit points. Defeated players no longer respond
+/// to input and count as dead.
+#[derive(Copy, Clone, Debug)]
+pub struct Defeated;
+
+/// Spawns player number `index` standing in the tile at `start` with
+/// `health` hit points.
+pub fn spawn(world: &mut World, start: (usize, usize), index: usize, health: u32) -> Entity {
+    world.spawn((
+        Player::new(index),
+        Health::new(health),
+        Position {
+            x: start.0 as f32 + (1.0 - PLAYER_WIDTH) / 2.0,
+            y: start.1 as f32 + 1.0 - PLAYER_HEIGHT,
+        },
+        Velocity::default(),
+        Body::new(PLAYER_WIDTH, PLAYER_HEIGHT),
+        Sprite { color: PLAYER_COLORS[index] },
+        Collider::PLAYER,
+    ))
+}
@@ -0,0 +1,301 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::block::Block;
+use crate::components::Bounds;
+
+/// Keeps rectangles that end exactly on a tile edge from counting the next
+/// tile.
+const EPSILON: f32 = 0.001;
+
+/// Where a ray cast with [`Playground::raycast`] meets a solid tile.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RayHit {
+    pub tile: (usize, usize),
+    /// Point on the edge of the tile the ray enters it at.
+    pub point: (f32, f32),
+    /// Side of the tile the ray enters through, pointing back out of it.
+    pub normal: (i32, i32),
+    /// Tiles travelled from the origin.
+    pub distance: f32,
+}
+
+#[derive(Clone)]
+pub struct Playground {
+    schema: Vec<Block>,
+    pub height: usize,
+    pub width: usize,
+    /// Bumped on every change to a block.
+    revision: u64,
+}
+
+impl Playground {
+    pub fn new(schema: Vec<Block>, height: usize, width: usize) -> Self {
+        Playground {
+            schema,
+            height,
+            width,
+            revision: 0,
+        }
+    }
+
+    pub fn block_at(&self, x: usize, y: usize) -> &Block {
+        &self.schema[y * self.width + x]
+    }
+
+    pub fn set_block(&mut self, x: usize, y: usize, block: Block) {
+        self.schema[y * self.width + x] = block;
+        self.revision += 1;
+    }
+
+    /// Number of changes made to the blocks, to tell when what was worked
+    /// out from them is out of date.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height
+    }
+
+    /// Every tile whose block matches `predicate`, row by row.
+    pub fn tiles_where(&self, predicate: impl Fn(&Block) -> bool) -> Vec<(usize, usize)> {
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .filter(|(x, y)| predicate(self.block_at(*x, *y)))
+            .collect()
+    }
+
+    /// Tiles of the playground `bounds` overlaps, row by row.
+    pub fn tiles_in(&self, bounds: &Bounds) -> Vec<(usize, usize)> {
+        let left = bounds.x.floor().max(0.0) as usize;
+        let top = bounds.y.floor().max(0.0) as usize;
+        let right = (bounds.x + bounds.width - EPSILON).floor();
+        let bottom = (bounds.y + bounds.height - EPSILON).floor();
+        if right < 0.0 || bottom < 0.0 {
+            return Vec::new();
+        }
+        let right = (right as usize).min(self.width.saturating_sub(1));
+        let bottom = (bottom as usize).min(self.height.saturating_sub(1));
+        (top..=bottom)
+            .flat_map(|y| (left..=right).map(move |x| (x, y)))
+            .collect()
+    }
+
+    /// Solid tiles `bounds` overlaps, row by row.
+    pub fn query_rect(&self, bounds: &Bounds) -> Vec<(usize, usize)> {
+        self.tiles_in(bounds).into_iter()
+            .filter(|(x, y)| self.block_at(*x, *y).is_solid())
+            .collect()
+    }
+
+    /// Whether the tile at signed coordinates is solid. Everything outside
+    /// the playground is open, so bodies can fall out of it.
+    pub fn is_solid(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0
+            && self.contains(x as usize, y as usize)
+            && self.block_at(x as usize, y as usize).is_solid()
+    }
+
+    /// Whether the segment between two points in tile units crosses no
+    /// solid tile, walking every tile it passes through.
+    pub fn line_of_sight(&self, from: (f32, f32), to: (f32, f32)) -> bool {
+        let direction = (to.0 - from.0, to.1 - from.1);
+        self.raycast(from, direction, direction.0.hypot(direction.1)).is_none()
+    }
+
+    /// First solid tile a ray from `origin` heading along `direction`
+    /// enters within `max_distance` tiles, walking every tile it passes
+    /// through. A ray starting in a solid tile hits it at once, with no
+    /// normal.
+    pub fn raycast(&self, origin: (f32, f32), direction: (f32, f32), max_distance: f32) -> Option<RayHit> {
+        let mut tile = (origin.0.floor() as i32, origin.1.floor() as i32);
+        if self.is_solid(tile.0, tile.1) {
+            return Some(RayHit { tile: (tile.0 as usize, tile.1 as usize), point: origin, normal: (0, 0), distance: 0.0 });
+        }
+        let length = direction.0.hypot(direction.1);
+        if length == 0.0 {
+            return None;
+        }
+        let direction = (direction.0 / length, direction.1 / length);
+        let step = (sign(direction.0), sign(direction.1));
+        // Distance travelled between two vertical and two horizontal tile
+        // edges, and to the next edge of each kind.
+        let delta = ((1.0 / direction.0).abs(), (1.0 / direction.1).abs());
+        let next_edge = |position: f32, tile: i32, step: i32, delta: f32| match step {
+            0 => { f32::INFINITY }
+            1 => { (tile as f32 + 1.0 - position) * delta }
+            _ => { (position - tile as f32) * delta }
+        };
+        let mut next = (next_edge(origin.0, tile.0, step.0, delta.0), next_edge(origin.1, tile.1, step.1, delta.1));
+        loop {
+            let distance = next.0.min(next.1);
+            let normal = if next.0 < next.1 {
+                tile.0 += step.0;
+                next.0 += delta.0;
+                (-step.0, 0)
+            } else {
+                tile.1 += step.1;
+                next.1 += delta.1;
+                (0, -step.1)
+            };
+            if distance > max_distance || self.is_left_behind(tile, step) {
+                return None;
+            }
+            if self.is_solid(tile.0, tile.1) {
+                let point = (origin.0 + direction.0 * distance, origin.1 + direction.1 * distance);
+                return Some(RayHit { tile: (tile.0 as usize, tile.1 as usize), point, normal, distance });
+            }
+        }
+    }
+
+    /// Whether a ray stepping `step` tiles at a time has gone past the
+    /// playground on a side it moves away from, never to come back.
+    fn is_left_behind(&self, tile: (i32, i32), step: (i32, i32)) -> bool {
+        (tile.0 < 0 && step.0 <= 0) || (tile.0 >= self.width as i32 && step.0 >= 0)
+            || (tile.1 < 0 && step.1 <= 0) || (tile.1 >= self.height as i32 && step.1 >= 0)
+    }
+
+    /// Shortest walk through open tiles, moving up, down, left or right,
+    /// from `from` to the nearest tile whose block passes `goal`, at most
+    /// `range` steps long. The walk leaves out `from` and ends on the goal.
+    pub fn find_path(&self, from: (usize, usize), range: usize, goal: impl Fn(&Block) -> bool) -> Option<Vec<(usize, usize)>> {
+        if !self.contains(from.0, from.1) {
+            return None;
+        }
+        // Tile each visited tile was reached from.
+        let mut previous: HashMap<(usize, usize), (usize, usize)> = HashMap::new();
+        let mut frontier = VecDeque::from([(from, 0)]);
+        while let Some((tile, steps)) = frontier.pop_front() {
+            if tile != from && goal(self.block_at(tile.0, tile.1)) {
+                let mut path = vec![tile];
+                while let Some(before) = previous.get(path.last().unwrap()).filter(|before| **before != from) {
+                    path.push(*before);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            if steps == range {
+                continue;
+            }
+            let (x, y) = (tile.0 as i32, tile.1 as i32);
+            for (next_x, next_y) in [(x, y - 1), (x - 1, y), (x + 1, y), (x, y + 1)] {
+                if next_x < 0 || next_y < 0 || self.is_solid(next_x, next_y) {
+                    continue;
+                }
+                let next = (next_x as usize, next_y as usize);
+                if !self.contains(next.0, next.1) || next == from || previous.contains_key(&next) {
+                    continue;
+                }
+                previous.insert(next, tile);
+                frontier.push_back((next, steps + 1));
+            }
+        }
+        None
+    }
+
+    pub fn scale_factor(&self, size: (u32, u32)) -> (u32, u32) {
+        let dw = size.0 / self.width as u32;
+        let dh = size.1 / self.height as u32;
+        (dw, dh)
+    }
+
+    /// Tile under the point `(x, y)` of a canvas of `size`, if there is one.
+    pub fn tile_at(&self, size: (u32, u32), x: i32, y: i32) -> Option<(usize, usize)> {
+        let scale = self.scale_factor(size);
+        if x < 0 || y < 0 || scale.0 == 0 || scale.1 == 0 {
+            return None;
+        }
+        let tile = ((x as u32 / scale.0) as usize, (y as u32 / scale.1) as usize);
+        self.contains(tile.0, tile.1).then_some(tile)
+    }
+}
+
+/// -1, 0 or 1 by the sign of `value`; `f32::signum` gives 1 for 0.
+fn sign(value: f32) -> i32 {
+    if value > 0.0 { 1 } else if value < 0.0 { -1 } else { 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `#` for walls, `o` for coins and anything else for empty tiles, a
+    /// row per line.
+    fn playground(rows: &[&str]) -> Playground {
+        let schema = rows.iter()
+            .flat_map(|row| row.chars())
+            .map(|tile| match tile {
+                '#' => { Block::Wall { color: 0 } }
+                'o' => { Block::Coin { color: 0 } }
+                _ => { Block::Empty }
+            })
+            .collect();
+        Playground::new(schema, rows.len(), rows[0].len())
+    }
+
+    #[test]
+    fn raycast_along_an_axis() {
+        let playground = playground(&[
+            "....",
+            "...#",
+            "....",
+            ".#..",
+        ]);
+        let hit = playground.raycast((0.5, 1.5), (1.0, 0.0), 10.0).unwrap();
+        assert_eq!(hit, RayHit { tile: (3, 1), point: (3.0, 1.5), normal: (-1, 0), distance: 2.5 });
+        let hit = playground.raycast((1.5, 0.25), (0.0, 1.0), 10.0).unwrap();
+        assert_eq!(hit, RayHit { tile: (1, 3), point: (1.5, 3.0), normal: (0, -1), distance: 2.75 });
+        let hit = playground.raycast((3.5, 3.5), (0.0, -2.0), 10.0).unwrap();
+        assert_eq!((hit.tile, hit.normal, hit.distance), ((3, 1), (0, 1), 1.5));
+    }
+
+    #[test]
+    fn raycast_from_inside_a_solid_tile() {
+        let playground = playground(&["#."]);
+        let hit = playground.raycast((0.5, 0.5), (1.0, 0.0), 10.0).unwrap();
+        assert_eq!(hit, RayHit { tile: (0, 0), point: (0.5, 0.5), normal: (0, 0), distance: 0.0 });
+        assert!(playground.raycast((0.5, 0.5), (0.0, 0.0), 10.0).is_some());
+    }
+
+    #[test]
+    fn raycast_misses() {
+        let playground = playground(&[
+            "...#",
+            "....",
+        ]);
+        assert_eq!(playground.raycast((0.5, 0.5), (1.0, 0.0), 2.0), None);
+        assert_eq!(playground.raycast((0.5, 1.5), (1.0, 0.0), 10.0), None);
+        assert_eq!(playground.raycast((0.5, 0.5), (-1.0, 0.0), 10.0), None);
+        assert_eq!(playground.raycast((0.5, 0.5), (0.0, 0.0), 10.0), None);
+        assert!(playground.line_of_sight((0.5, 1.5), (3.5, 1.5)));
+        assert!(!playground.line_of_sight((0.5, 0.5), (3.5, 0.5)));
+    }
+
+    #[test]
+    fn raycast_diagonally() {
+        let playground = playground(&[
+            "...",
+            "...",
+            "..#",
+        ]);
+        let hit = playground.raycast((0.5, 0.5), (1.0, 1.0), 10.0).unwrap();
+        assert_eq!(hit.tile, (2, 2));
+        assert!((hit.point.0 - 2.0).abs() < 1e-5 && (hit.point.1 - 2.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn find_path_walks_around_walls() {
+        let playground = playground(&[
+            ".#o",
+            ".#.",
+            "...",
+        ]);
+        let coin = |block: &Block| matches!(block, Block::Coin { .. });
+        let path = playground.find_path((0, 0), 10, coin).unwrap();
+        assert_eq!(path, [(0, 1), (0, 2), (1, 2), (2, 2), (2, 1), (2, 0)]);
+        assert_eq!(playground.find_path((0, 0), 5, coin), None);
+        assert_eq!(playground.find_path((0, 0), 10, |block| matches!(block, Block::Empty)).unwrap(), [(0, 1)]);
+        assert_eq!(playground.find_path((0, 0), 10, Block::is_solid), None);
+        assert_eq!(playground.find_path((5, 5), 10, |_| true), None);
+    }
+}
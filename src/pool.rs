@@ -0,0 +1,138 @@
+//! Fixed-capacity storage for short-lived objects. Slots are reused in
+//! place, so inserting and removing never allocates once the pool exists,
+//! and a generation counter keeps stale handles from reaching the object
+//! that took over their slot.
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Handle {
+    index: u32,
+    generation: u32,
+}
+
+struct Slot<T> {
+    generation: u32,
+    value: Option<T>,
+}
+
+pub struct Pool<T> {
+    slots: Vec<Slot<T>>,
+    /// Indices of empty slots, reused most recently freed first.
+    free: Vec<u32>,
+}
+
+impl<T> Pool<T> {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Pool {
+            slots: (0..capacity).map(|_| Slot { generation: 0, value: None }).collect(),
+            free: (0..capacity as u32).rev().collect(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.free.len() == self.slots.len()
+    }
+
+    /// Stores `value`, or hands it back when every slot is taken.
+    pub fn insert(&mut self, value: T) -> Result<Handle, T> {
+        let index = match self.free.pop() {
+            Some(index) => { index }
+            None => { return Err(value) }
+        };
+        let slot = &mut self.slots[index as usize];
+        slot.value = Some(value);
+        Ok(Handle { index, generation: slot.generation })
+    }
+
+    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+        let slot = self.slots.get_mut(handle.index as usize)?;
+        if slot.generation != handle.generation {
+            return None;
+        }
+        let value = slot.value.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(handle.index);
+        Some(value)
+    }
+
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        let slot = self.slots.get(handle.index as usize)?;
+        if slot.generation != handle.generation {
+            return None;
+        }
+        slot.value.as_ref()
+    }
+
+    pub fn get_mut(&mut self, handle: Handle) -> Option<&mut T> {
+        let slot = self.slots.get_mut(handle.index as usize)?;
+        if slot.generation != handle.generation {
+            return None;
+        }
+        slot.value.as_mut()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.slots.iter().filter_map(|slot| slot.value.as_ref())
+    }
+
+    /// Removes every object for which `keep` returns false.
+    pub fn retain(&mut self, mut keep: impl FnMut(&mut T) -> bool) {
+        for (index, slot) in self.slots.iter_mut().enumerate() {
+            let Some(value) = &mut slot.value else { continue };
+            if !keep(value) {
+                slot.value = None;
+                slot.generation = slot.generation.wrapping_add(1);
+                self.free.push(index as u32);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hands_back_values_once_full() {
+        let mut pool = Pool::with_capacity(2);
+        let a = pool.insert('a').unwrap();
+        let b = pool.insert('b').unwrap();
+        assert_eq!(pool.insert('c'), Err('c'));
+        assert_eq!((pool.len(), pool.capacity()), (2, 2));
+        assert_eq!((pool.get(a), pool.get(b)), (Some(&'a'), Some(&'b')));
+    }
+
+    #[test]
+    fn stale_handles_miss_the_new_value() {
+        let mut pool = Pool::with_capacity(1);
+        let old = pool.insert(1).unwrap();
+        assert_eq!(pool.remove(old), Some(1));
+        assert!(pool.is_empty());
+        let new = pool.insert(2).unwrap();
+        assert_ne!(old, new);
+        assert_eq!(pool.get(old), None);
+        assert_eq!(pool.get_mut(old), None);
+        assert_eq!(pool.remove(old), None);
+        assert_eq!(pool.get(new), Some(&2));
+    }
+
+    #[test]
+    fn retain_frees_slots() {
+        let mut pool = Pool::with_capacity(4);
+        let handles: Vec<Handle> = (0..4).map(|value| pool.insert(value).unwrap()).collect();
+        pool.retain(|value| *value % 2 == 0);
+        assert_eq!(pool.iter().copied().collect::<Vec<_>>(), [0, 2]);
+        assert_eq!(pool.get(handles[1]), None);
+        *pool.get_mut(handles[2]).unwrap() = 20;
+        assert!(pool.insert(5).is_ok() && pool.insert(7).is_ok());
+        assert_eq!(pool.insert(9), Err(9));
+        assert_eq!(pool.get(handles[2]), Some(&20));
+    }
+}
@@ -0,0 +1,58 @@
+//! Pickups that change how a player moves, or what hurts them, for a
+//! while. A player carries at most one boost; picking up another
+//! replaces it.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PowerUp {
+    /// Runs faster, by `speed_boost` in the tuning.
+    Speed,
+    /// Jumps higher, by `jump_boost` in the tuning.
+    Jump,
+    /// Can't be hurt and defeats enemies by touching them, for
+    /// `star_ticks` in the tuning. Lava and falling off the map still
+    /// take the player out.
+    Star,
+}
+
+/// Colors a player with a star cycles through.
+pub const STAR_COLORS: [u32; 4] = [0xFFFF60, 0xFF6060, 0x60FFFF, 0xFFFFFF];
+
+impl PowerUp {
+    pub fn code(&self) -> char {
+        match self {
+            PowerUp::Speed => { 's' }
+            PowerUp::Jump => { 'j' }
+            PowerUp::Star => { '*' }
+        }
+    }
+
+    /// Color of the pickup, and of players while the boost lasts.
+    pub fn color(&self) -> u32 {
+        match self {
+            PowerUp::Speed => { 0xFF9F1C }
+            PowerUp::Jump => { 0xB070FF }
+            PowerUp::Star => { STAR_COLORS[0] }
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PowerUp::Speed => { "SPEED" }
+            PowerUp::Jump => { "JUMP" }
+            PowerUp::Star => { "STAR" }
+        }
+    }
+}
+
+/// A power-up a player picked up, until it runs out.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Boost {
+    pub power_up: PowerUp,
+    /// Ticks left before the boost wears off.
+    pub ticks: u32,
+    /// Ticks the boost lasts in all.
+    #[serde(default)]
+    pub duration: u32,
+}
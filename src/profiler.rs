@@ -0,0 +1,83 @@
+//! Frame profiler. The main loop times each phase of a frame and the
+//! totals are averaged once per second, for the log and the debug overlay.
+
+use std::time::{Duration, Instant};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Phase {
+    Input,
+    Tick,
+    Render,
+    Present,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 4] = [Phase::Input, Phase::Tick, Phase::Render, Phase::Present];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Phase::Input => { "input" }
+            Phase::Tick => { "tick" }
+            Phase::Render => { "render" }
+            Phase::Present => { "present" }
+        }
+    }
+}
+
+/// Averages over the last complete second.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FrameReport {
+    pub frames: u32,
+    /// Milliseconds per frame spent in each phase, in the order of `Phase::ALL`.
+    pub phases: [f64; 4],
+    /// Milliseconds per frame spent working, sleep excluded.
+    pub frame: f64,
+}
+
+pub struct Profiler {
+    totals: [Duration; 4],
+    frames: u32,
+    report_at: Instant,
+    pub report: FrameReport,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Profiler {
+            totals: [Duration::ZERO; 4],
+            frames: 0,
+            report_at: Instant::now() + Duration::from_secs(1),
+            report: FrameReport::default(),
+        }
+    }
+
+    /// Adds the time elapsed since `start` to `phase`.
+    pub fn record(&mut self, phase: Phase, start: Instant) {
+        self.totals[phase as usize] += start.elapsed();
+    }
+
+    /// Counts a frame and returns a fresh report once a second has passed.
+    pub fn end_frame(&mut self) -> Option<FrameReport> {
+        self.frames += 1;
+        if Instant::now() < self.report_at {
+            return None;
+        }
+        let frames = self.frames as f64;
+        let mut report = FrameReport { frames: self.frames, ..FrameReport::default() };
+        for (average, total) in report.phases.iter_mut().zip(self.totals) {
+            *average = total.as_secs_f64() * 1000.0 / frames;
+        }
+        report.frame = report.phases.iter().sum();
+        self.report = report;
+        self.totals = [Duration::ZERO; 4];
+        self.frames = 0;
+        self.report_at += Duration::from_secs(1);
+        Some(report)
+    }
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,84 @@
+//! Projectiles fly in a straight line until they hit a solid tile or
+//! something they can hurt. They live in a fixed pool next to the world,
+//! like particles, so steady fire never spawns or despawns entities;
+//! their own system moves them, so gravity doesn't pull them down.
+
+use serde::{Deserialize, Serialize};
+
+use crate::collision::Collider;
+use crate::components::{Body, Bounds, Position, Sprite, Velocity};
+use crate::pool::Pool;
+
+pub const PROJECTILE_SIZE: f32 = 0.25;
+pub const PLAYER_SHOT_COLOR: u32 = 0xFFFF80;
+/// Ticks between two shots of the same player.
+pub const FIRE_INTERVAL: u32 = 20;
+/// Projectiles in flight at once; shots beyond this are not fired.
+pub const MAX_PROJECTILES: usize = 128;
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Projectile {
+    /// Top left corner, in tile units.
+    pub position: Position,
+    pub velocity: Velocity,
+    /// Whether the projectile hurts players rather than enemies.
+    pub hostile: bool,
+    pub color: u32,
+}
+
+impl Projectile {
+    pub fn body(&self) -> Body {
+        Body::new(PROJECTILE_SIZE, PROJECTILE_SIZE)
+    }
+
+    pub fn bounds(&self) -> Bounds {
+        Bounds::of(&self.position, &self.body())
+    }
+
+    pub fn collider(&self) -> Collider {
+        if self.hostile { Collider::ENEMY_SHOT } else { Collider::PLAYER_SHOT }
+    }
+
+    pub fn sprite(&self) -> (Position, Body, Sprite) {
+        (self.position, self.body(), Sprite { color: self.color })
+    }
+}
+
+pub struct Projectiles {
+    pool: Pool<Projectile>,
+}
+
+impl Projectiles {
+    pub fn new() -> Self {
+        Projectiles { pool: Pool::with_capacity(MAX_PROJECTILES) }
+    }
+
+    /// Fires a projectile centered on `(x, y)` in tile units.
+    pub fn spawn(&mut self, x: f32, y: f32, velocity: Velocity, hostile: bool, color: u32) {
+        let position = Position { x: x - PROJECTILE_SIZE / 2.0, y: y - PROJECTILE_SIZE / 2.0 };
+        let _ = self.pool.insert(Projectile { position, velocity, hostile, color });
+    }
+
+    /// Puts back projectiles from a save.
+    pub fn restore(&mut self, projectiles: &[Projectile]) {
+        self.pool.retain(|_| false);
+        for projectile in projectiles {
+            let _ = self.pool.insert(*projectile);
+        }
+    }
+
+    /// Removes every projectile for which `keep` returns false.
+    pub fn retain(&mut self, keep: impl FnMut(&mut Projectile) -> bool) {
+        self.pool.retain(keep);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Projectile> {
+        self.pool.iter()
+    }
+}
+
+impl Default for Projectiles {
+    fn default() -> Self {
+        Self::new()
+    }
+}
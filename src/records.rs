@@ -0,0 +1,105 @@
+//! Best completion time and points of every level and the hidden stars
+//! found in it, kept across runs. Times are in ticks of the simulation, so time spent
+//! paused doesn't count.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{GameError, Result};
+
+pub const RECORDS: &str = "records.json";
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Records {
+    /// Fewest ticks each level was completed in, by level path.
+    pub best_ticks: BTreeMap<String, u64>,
+    /// Tiles of the hidden stars found in each level it was completed
+    /// with, by level path.
+    #[serde(default)]
+    pub hidden_stars: BTreeMap<String, BTreeSet<(usize, usize)>>,
+    /// Most points earned in each level, by level path.
+    #[serde(default)]
+    pub best_points: BTreeMap<String, u32>,
+}
+
+impl Records {
+    /// Reads the records at `path`. A missing or unreadable file starts
+    /// from no records rather than keeping the game from starting.
+    pub fn load(path: &str) -> Self {
+        if !Path::new(path).exists() {
+            return Records::default();
+        }
+        let records = std::fs::read_to_string(path)
+            .map_err(|error| error.to_string())
+            .and_then(|contents| serde_json::from_str(&contents).map_err(|error| error.to_string()));
+        match records {
+            Ok(records) => { records }
+            Err(error) => {
+                warn!("Ignoring best times in '{}': {}", path, error);
+                Records::default()
+            }
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<()> {
+        let contents = serde_json::to_string_pretty(self)
+            .map_err(|error| GameError::InvalidSave(error.to_string()))?;
+        std::fs::write(path, contents)
+            .map_err(|source| GameError::Io { path: path.to_string(), source })
+    }
+
+    /// Records completing `level` in `ticks` if that beats its best time,
+    /// returning the best time from before.
+    pub fn submit(&mut self, level: &str, ticks: u64) -> Option<u64> {
+        let previous = self.best_ticks.get(level).copied();
+        if previous.is_none_or(|best| ticks < best) {
+            info!("New best time for '{}': {}", level, format_time(ticks));
+            self.best_ticks.insert(level.to_string(), ticks);
+        }
+        previous
+    }
+
+    /// Records earning `points` in `level` if that beats its best,
+    /// returning the best from before.
+    pub fn submit_points(&mut self, level: &str, points: u32) -> Option<u32> {
+        let previous = self.best_points.get(level).copied();
+        if previous.is_none_or(|best| points > best) {
+            self.best_points.insert(level.to_string(), points);
+        }
+        previous
+    }
+
+    /// Adds the hidden stars at `stars` to those found in `level`.
+    pub fn submit_stars(&mut self, level: &str, stars: &[(usize, usize)]) {
+        self.hidden_stars.entry(level.to_string()).or_default().extend(stars.iter().copied());
+    }
+
+    /// Hidden stars found in `level` across runs.
+    pub fn stars_found(&self, level: &str) -> usize {
+        self.hidden_stars.get(level).map_or(0, BTreeSet::len)
+    }
+}
+
+/// Share of `total` that `found` is, in whole percents. Nothing to find
+/// counts as complete.
+pub fn percent(found: usize, total: usize) -> u32 {
+    if total == 0 {
+        return 100;
+    }
+    (found.min(total) * 100 / total) as u32
+}
+
+/// `ticks` as minutes, seconds and hundredths, e.g. "1:05.20".
+pub fn format_time(ticks: u64) -> String {
+    let hundredths = ticks * 100 / 60;
+    format!("{}:{:02}.{:02}", hundredths / 6000, hundredths / 100 % 60, hundredths % 100)
+}
+
+/// Difference between `ticks` and `best`, e.g. "-0:01.50" when faster.
+pub fn format_delta(ticks: u64, best: u64) -> String {
+    let sign = if ticks < best { '-' } else { '+' };
+    format!("{}{}", sign, format_time(ticks.abs_diff(best)))
+}
@@ -0,0 +1,684 @@
+use hecs::World;
+
+use crate::bindings::Action;
+use crate::block::{Block, Side};
+use crate::captions::Caption;
+use crate::color::split_rgb;
+use crate::components::{Body, Position, Sprite};
+use crate::console::Console;
+use crate::error::Result;
+use crate::game::Game;
+use crate::hud::Hud;
+use crate::objective::Objective;
+use crate::particles::{Particle, PARTICLE_SIZE};
+use crate::player::PLAYER_COLORS;
+use crate::playground::Playground;
+use crate::profiler::{FrameReport, Phase};
+use crate::projectile::Projectile;
+use crate::records;
+use crate::renderer::{Color, Point, Rect, Renderer};
+use crate::score::{Combo, Popup, COMBO_HOLD};
+use crate::simulation::GameSnapshot;
+use crate::swing::{self, ROPE_COLOR};
+use crate::systems::input::PlayerInput;
+use crate::text::{text_size, wrap_text};
+use crate::touch::TouchControls;
+use crate::tuning::AnimationTuning;
+use crate::water::Current;
+
+pub fn render_game(game: &Game, renderer: &mut dyn Renderer) -> Result<()> {
+    let canvas_size = renderer.output_size()?;
+    let scale = game.playground.scale_factor(canvas_size);
+    render_playground(&game.playground, renderer, scale, game.ticks, &game.tuning.animation)?;
+    render_currents(&game.info.currents, renderer, scale, game.ticks)?;
+    for (anchor, player) in swing::ropes(&game.world) {
+        render_rope(renderer, anchor, player, scale)?;
+    }
+    render_entities(&game.world, renderer, scale)?;
+    for (position, body, sprite) in game.projectiles.iter().map(Projectile::sprite) {
+        render_sprite(renderer, &position, &body, &sprite, scale)?;
+    }
+    render_particles(game.particles.iter(), renderer, scale)?;
+    render_popups(game.popups.iter(), renderer, scale)?;
+    render_hud(renderer, &Hud::new(game))
+}
+
+pub fn render_snapshot(snapshot: &GameSnapshot, renderer: &mut dyn Renderer) -> Result<()> {
+    let canvas_size = renderer.output_size()?;
+    let scale = snapshot.playground.scale_factor(canvas_size);
+    render_playground(&snapshot.playground, renderer, scale, snapshot.ticks, &snapshot.animation)?;
+    render_currents(&snapshot.currents, renderer, scale, snapshot.ticks)?;
+    for (anchor, player) in &snapshot.ropes {
+        render_rope(renderer, *anchor, *player, scale)?;
+    }
+    for (position, body, sprite) in &snapshot.sprites {
+        render_sprite(renderer, position, body, sprite, scale)?;
+    }
+    render_particles(snapshot.particles.iter(), renderer, scale)?;
+    render_popups(snapshot.popups.iter(), renderer, scale)?;
+    if let Some(prompt) = snapshot.talk_prompt {
+        render_talk_prompt(renderer, prompt, scale)?;
+    }
+    render_hud(renderer, &snapshot.hud)
+}
+
+/// Draws a rope as a row of dots from `from` to `to`, in tile units.
+fn render_rope(renderer: &mut dyn Renderer, from: (f32, f32), to: (f32, f32), scale: (u32, u32)) -> Result<()> {
+    let color = Color::from(split_rgb(ROPE_COLOR));
+    let length = ((to.0 - from.0).powi(2) + (to.1 - from.1).powi(2)).sqrt();
+    let dots = (length * 8.0).ceil() as u32;
+    for dot in 0..=dots {
+        let along = dot as f32 / dots.max(1) as f32;
+        let x = ((from.0 + (to.0 - from.0) * along) * scale.0 as f32) as i32;
+        let y = ((from.1 + (to.1 - from.1) * along) * scale.1 as f32) as i32;
+        renderer.fill_rect(Rect::new(x - 1, y - 1, 2, 2), color)?;
+    }
+    Ok(())
+}
+
+/// Draws a "?" above an NPC that can be talked to, `at` being the middle
+/// of its top edge.
+fn render_talk_prompt(renderer: &mut dyn Renderer, at: Position, scale: (u32, u32)) -> Result<()> {
+    let pixel = 2;
+    let (width, height) = text_size("?", pixel);
+    let x = (at.x * scale.0 as f32) as i32 - width as i32 / 2;
+    let y = (at.y * scale.1 as f32) as i32 - height as i32 - 4;
+    renderer.draw_text("?", x, y, pixel, Color::WHITE)
+}
+
+/// Draws a page of dialog in a box along the bottom of the screen, with
+/// `hint` in its bottom right corner.
+pub fn render_dialog(renderer: &mut dyn Renderer, text: &str, hint: &str) -> Result<()> {
+    let pixel = 3;
+    let margin = 16;
+    let (canvas_width, canvas_height) = renderer.output_size()?;
+    let inner_width = canvas_width.saturating_sub(4 * margin);
+    let lines = wrap_text(text, pixel, inner_width);
+    let line_height = text_size("X", pixel).1 as i32 + 6;
+    let height = (lines.len() as i32 + 2) * line_height + margin as i32;
+    let frame = Rect::new(margin as i32, canvas_height as i32 - height - margin as i32, canvas_width - 2 * margin, height as u32);
+    renderer.fill_rect(frame, Color::RGBA(0, 0, 0, 200))?;
+    renderer.draw_rect(frame, Color::WHITE)?;
+    let left = frame.left() + margin as i32;
+    let mut y = frame.top() + margin as i32;
+    for line in &lines {
+        renderer.draw_text(line, left, y, pixel, Color::WHITE)?;
+        y += line_height;
+    }
+    let (hint_width, _) = text_size(hint, 2);
+    renderer.draw_text(hint, frame.right() - margin as i32 - hint_width as i32, y + line_height / 2, 2, Color::GRAY)
+}
+
+/// Draws the heads-up display over the level, each part anchored to a
+/// corner of the screen rather than to the level: health and items in
+/// the top left, the score and counters in the top right and the level
+/// timer with what counts down in the bottom right.
+pub fn render_hud(renderer: &mut dyn Renderer, hud: &Hud) -> Result<()> {
+    render_vitals(renderer, hud)?;
+    render_counters(renderer, hud)?;
+    render_timers(renderer, hud)?;
+    if let Some(message) = &hud.message {
+        render_dialog(renderer, message, "")?;
+    }
+    Ok(())
+}
+
+/// Draws a row of hearts for each player, then the items carried with how
+/// many of each, from the top left corner.
+fn render_vitals(renderer: &mut dyn Renderer, hud: &Hud) -> Result<()> {
+    let pixel = 2;
+    let left = 4;
+    let heart = 10;
+    let mut y = 4;
+    for (index, health) in hud.health.iter().enumerate() {
+        let color = Color::from(split_rgb(PLAYER_COLORS[index]));
+        for slot in 0..health.max {
+            let rect = Rect::new(left + (slot * (heart + 4)) as i32, y, heart, heart);
+            if slot < health.hp {
+                renderer.fill_rect(rect, color)?;
+            }
+            renderer.draw_rect(rect, color)?;
+        }
+        y += heart as i32 + 4;
+    }
+    for (item, count) in &hud.items {
+        let text = format!("{} {}", item.name(), count);
+        let (_, height) = text_size(&text, pixel);
+        renderer.fill_rect(Rect::new(left, y, height, height), Color::from(split_rgb(item.color())))?;
+        renderer.draw_text(&text, left + height as i32 + 4, y, pixel, Color::WHITE)?;
+        y += height as i32 + 4;
+    }
+    Ok(())
+}
+
+/// Draws the score, lives and the counters the level has, with the combo
+/// under them, from the top right corner.
+fn render_counters(renderer: &mut dyn Renderer, hud: &Hud) -> Result<()> {
+    let pixel = 2;
+    let (canvas_width, _) = renderer.output_size()?;
+    let right = canvas_width as i32 - 4;
+    let mut lines = vec![
+        (format!("SCORE {}", hud.score), Color::WHITE),
+        (format!("LIVES {}", hud.lives), Color::WHITE),
+    ];
+    if hud.total_coins > 0 {
+        lines.push((format!("COINS {}/{}", hud.coins, hud.total_coins), Color::RGB(255, 215, 0)));
+    }
+    if hud.total_hidden_stars > 0 {
+        lines.push((format!("STARS {}/{}", hud.hidden_stars, hud.total_hidden_stars), Color::RGB(255, 250, 205)));
+    }
+    if hud.money > 0 {
+        lines.push((format!("WALLET {}", hud.money), Color::RGB(255, 215, 0)));
+    }
+    let mut y = 4;
+    for (text, color) in lines {
+        let (width, height) = text_size(&text, pixel);
+        renderer.draw_text(&text, right - width as i32, y, pixel, color)?;
+        y += height as i32 + 4;
+    }
+    if hud.combo.chain > 0 {
+        render_combo(renderer, &hud.combo, right, y)?;
+    }
+    Ok(())
+}
+
+/// Draws the level timer in the bottom right corner, with the power-ups
+/// wearing off and the gates about to close stacked above it.
+fn render_timers(renderer: &mut dyn Renderer, hud: &Hud) -> Result<()> {
+    let pixel = 2;
+    let (canvas_width, canvas_height) = renderer.output_size()?;
+    let right = canvas_width as i32 - 4;
+    let line_height = text_size("X", pixel).1 as i32;
+    let mut y = canvas_height as i32 - 4 - line_height;
+    let time = format!("TIME {}", records::format_time(hud.ticks));
+    renderer.draw_text(&time, right - text_size(&time, pixel).0 as i32, y, pixel, Color::WHITE)?;
+    for (link, left) in &hud.links {
+        y -= line_height + 4;
+        let text = format!("GATE {} {}", link, left.div_ceil(60));
+        let color = if *left < 60 && left / 8 % 2 == 0 { Color::RED } else { Color::YELLOW };
+        renderer.draw_text(&text, right - text_size(&text, pixel).0 as i32, y, pixel, color)?;
+    }
+    for boost in hud.boosts.iter().flatten() {
+        y -= line_height + 10;
+        let color = Color::from(split_rgb(boost.power_up.color()));
+        let text = format!("{} {}", boost.power_up.name(), boost.ticks.div_ceil(60));
+        let (width, _) = text_size(&text, pixel);
+        renderer.draw_text(&text, right - width as i32, y, pixel, color)?;
+        let meter = Rect::new(right - width as i32, y + line_height + 2, width, 4);
+        let filled = width * boost.ticks / boost.duration.max(boost.ticks).max(1);
+        if filled > 0 {
+            renderer.fill_rect(Rect::new(meter.x(), meter.y(), filled, 4), color)?;
+        }
+        renderer.draw_rect(meter, color)?;
+    }
+    Ok(())
+}
+
+/// Draws the combo multiplier with a meter of the time left before it
+/// drops, right aligned to `right` from `y`. Returns where the next line goes.
+fn render_combo(renderer: &mut dyn Renderer, combo: &Combo, right: i32, y: i32) -> Result<i32> {
+    let pixel = 2;
+    let color = Color::RGB(255, 140, 0);
+    let text = format!("COMBO X{}", combo.multiplier());
+    let (width, height) = text_size(&text, pixel);
+    renderer.draw_text(&text, right - width as i32, y, pixel, color)?;
+    let y = y + height as i32 + 2;
+    let meter = Rect::new(right - width as i32, y, width, 4);
+    let filled = width * combo.timer.min(COMBO_HOLD) / COMBO_HOLD;
+    if filled > 0 {
+        renderer.fill_rect(Rect::new(meter.x(), y, filled, 4), color)?;
+    }
+    renderer.draw_rect(meter, color)?;
+    Ok(y + 8)
+}
+
+/// Draws the points of every popup centered on its position, fading out.
+pub fn render_popups<'a>(popups: impl Iterator<Item = &'a Popup>, renderer: &mut dyn Renderer, scale: (u32, u32)) -> Result<()> {
+    let pixel = 2;
+    for popup in popups {
+        let text = format!("+{}", popup.points);
+        let (width, height) = text_size(&text, pixel);
+        let alpha = (popup.life * 255 / 30).min(255) as u8;
+        let x = (popup.position.x * scale.0 as f32) as i32 - width as i32 / 2;
+        let y = (popup.position.y * scale.1 as f32) as i32 - height as i32 / 2;
+        renderer.draw_text(&text, x, y, pixel, Color::RGBA(255, 255, 255, alpha))?;
+    }
+    Ok(())
+}
+
+/// Draws every block. `ticks` drives animated blocks.
+pub fn render_playground(playground: &Playground, renderer: &mut dyn Renderer, scale: (u32, u32), ticks: u64, animation: &AnimationTuning) -> Result<()> {
+    for y in 0..playground.height {
+        for x in 0..playground.width {
+            let block = playground.block_at(x, y);
+            let color = match block.color() {
+                Some(color) => { color }
+                None => { continue }
+            };
+            let mut rect = Rect::new(
+                (x as u32 * scale.0) as i32,
+                (y as u32 * scale.1) as i32,
+                scale.0,
+                scale.1,
+            );
+            if block.is_pickup() {
+                rect = Rect::from_center(rect.center(), (scale.0 / 2).max(1), (scale.1 / 2).max(1));
+            }
+            if let Block::Spikes { facing, .. } = block {
+                render_spikes(renderer, rect, *facing, Color::from(split_rgb(color)))?;
+                continue;
+            }
+            if let Block::Gate { open: true, .. } = block {
+                renderer.draw_rect(rect, Color::from(split_rgb(color)))?;
+                continue;
+            }
+            if let Block::Sign { .. } = block {
+                render_sign(renderer, rect, Color::from(split_rgb(color)))?;
+                continue;
+            }
+            if let Block::Track { .. } = block {
+                let height = (scale.1 / 8).max(1);
+                renderer.fill_rect(Rect::new(rect.left(), rect.bottom() - height as i32, scale.0, height), Color::from(split_rgb(color)))?;
+                continue;
+            }
+            if let Block::Water { .. } = block {
+                let (r, g, b) = split_rgb(color);
+                renderer.fill_rect(rect, Color::RGBA(r, g, b, 140))?;
+                continue;
+            }
+            if let Block::Lava { .. } = block {
+                let surface = y == 0 || !matches!(playground.block_at(x, y - 1), Block::Lava { .. });
+                render_lava(renderer, rect, surface, x, ticks as f32 * animation.lava_wave_speed, Color::from(split_rgb(color)))?;
+                continue;
+            }
+            renderer.fill_rect(rect, Color::from(split_rgb(color)))?;
+            renderer.draw_rect(rect, Color::from(split_rgb(color)))?;
+        }
+    }
+    render_lava_glow(playground, renderer, scale, ticks as f32 * animation.lava_glow_speed)
+}
+
+/// Draws a board on a post, standing on the bottom of `rect`.
+fn render_sign(renderer: &mut dyn Renderer, rect: Rect, color: Color) -> Result<()> {
+    let (width, height) = (rect.width(), rect.height());
+    let post = (width / 8).max(1);
+    renderer.fill_rect(Rect::new(rect.center().x() - post as i32 / 2, rect.top() + height as i32 / 2, post, height / 2), color)?;
+    let board = Rect::new(rect.left() + width as i32 / 8, rect.top() + height as i32 / 8, width * 3 / 4, (height * 3 / 8).max(1));
+    renderer.fill_rect(board, color)?;
+    renderer.draw_rect(board, Color::RGB(60, 30, 10))
+}
+
+/// Draws a dot drifting across every tile of each current, the way it
+/// flows. `ticks` moves the dots along.
+fn render_currents(currents: &[Current], renderer: &mut dyn Renderer, scale: (u32, u32), ticks: u64) -> Result<()> {
+    let color = Color::RGBA(200, 230, 255, 160);
+    for current in currents {
+        let length = (current.push.0 * current.push.0 + current.push.1 * current.push.1).sqrt();
+        if length == 0.0 {
+            continue;
+        }
+        let direction = (current.push.0 / length, current.push.1 / length);
+        for y in current.y..current.y + current.height {
+            for x in current.x..current.x + current.width {
+                let along = (ticks as f32 * length + (x + y) as f32 * 0.37).fract() - 0.5;
+                let dot = (x as f32 + 0.5 + direction.0 * along, y as f32 + 0.5 + direction.1 * along);
+                renderer.fill_rect(Rect::new((dot.0 * scale.0 as f32) as i32 - 1, (dot.1 * scale.1 as f32) as i32 - 1, 3, 3), color)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Draws a tile of lava. Tiles at the surface get a rolling wave on top,
+/// continuing from one tile to the next along the row and `rolled` radians
+/// along.
+fn render_lava(renderer: &mut dyn Renderer, rect: Rect, surface: bool, column: usize, rolled: f32, color: Color) -> Result<()> {
+    if !surface {
+        return renderer.fill_rect(rect, color);
+    }
+    let crest = Color::RGB(255, 200, 40);
+    let amplitude = rect.height() as f32 / 8.0;
+    for offset in 0..rect.width() as i32 {
+        let phase = (column as f32 + offset as f32 / rect.width() as f32) * std::f32::consts::TAU / 2.0 - rolled;
+        let top = rect.top() + (amplitude * (1.0 + phase.sin())) as i32;
+        let height = (rect.bottom() - top).max(0) as u32;
+        renderer.fill_rect(Rect::new(rect.left() + offset, top, 1, height), color)?;
+        renderer.fill_rect(Rect::new(rect.left() + offset, top, 1, 2.min(height)), crest)?;
+    }
+    Ok(())
+}
+
+/// Lights up the tile above every stretch of lava surface, brightest
+/// right over it and throbbing slowly, over whatever is drawn there.
+fn render_lava_glow(playground: &Playground, renderer: &mut dyn Renderer, scale: (u32, u32), phase: f32) -> Result<()> {
+    const BANDS: u32 = 4;
+    for y in 1..playground.height {
+        for x in 0..playground.width {
+            if !matches!(playground.block_at(x, y), Block::Lava { .. }) || matches!(playground.block_at(x, y - 1), Block::Lava { .. }) {
+                continue;
+            }
+            let pulse = 0.8 + 0.2 * (phase + x as f32 * 0.7).sin();
+            let band = (scale.1 / BANDS).max(1);
+            let surface = (y as u32 * scale.1) as i32;
+            for index in 0..BANDS {
+                let alpha = (70.0 * pulse * (BANDS - index) as f32 / BANDS as f32) as u8;
+                let rect = Rect::new((x as u32 * scale.0) as i32, surface - (band * (index + 1)) as i32, scale.0, band);
+                renderer.fill_rect(rect, Color::RGBA(255, 110, 20, alpha))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Draws two spikes filling `rect`, pointing to `facing`.
+fn render_spikes(renderer: &mut dyn Renderer, rect: Rect, facing: Side, color: Color) -> Result<()> {
+    let (left, top, right, bottom) = (rect.left(), rect.top(), rect.right() - 1, rect.bottom() - 1);
+    let (middle_x, middle_y) = ((left + right) / 2, (top + bottom) / 2);
+    let spikes = match facing {
+        Side::Up => { [[(left, bottom), (middle_x, bottom), ((left + middle_x) / 2, top)], [(middle_x, bottom), (right, bottom), ((middle_x + right) / 2, top)]] }
+        Side::Down => { [[(left, top), (middle_x, top), ((left + middle_x) / 2, bottom)], [(middle_x, top), (right, top), ((middle_x + right) / 2, bottom)]] }
+        Side::Left => { [[(right, top), (right, middle_y), (left, (top + middle_y) / 2)], [(right, middle_y), (right, bottom), (left, (middle_y + bottom) / 2)]] }
+        Side::Right => { [[(left, top), (left, middle_y), (right, (top + middle_y) / 2)], [(left, middle_y), (left, bottom), (right, (middle_y + bottom) / 2)]] }
+    };
+    for spike in spikes {
+        renderer.fill_triangle(spike.map(Point::from), color)?;
+    }
+    Ok(())
+}
+
+pub fn render_entities(world: &World, renderer: &mut dyn Renderer, scale: (u32, u32)) -> Result<()> {
+    for (position, body, sprite) in world.query::<(&Position, &Body, &Sprite)>().iter() {
+        render_sprite(renderer, position, body, sprite, scale)?;
+    }
+    Ok(())
+}
+
+fn render_sprite(renderer: &mut dyn Renderer, position: &Position, body: &Body, sprite: &Sprite, scale: (u32, u32)) -> Result<()> {
+    let color = Color::from(split_rgb(sprite.color));
+    let rect = Rect::new(
+        (position.x * scale.0 as f32) as i32,
+        (position.y * scale.1 as f32) as i32,
+        (body.width * scale.0 as f32) as u32,
+        (body.height * scale.1 as f32) as u32,
+    );
+    renderer.fill_rect(rect, color)?;
+    renderer.draw_rect(rect, color)?;
+    Ok(())
+}
+
+pub fn render_particles<'a>(particles: impl Iterator<Item = &'a Particle>, renderer: &mut dyn Renderer, scale: (u32, u32)) -> Result<()> {
+    let size = ((PARTICLE_SIZE * scale.0 as f32) as u32).max(1);
+    for particle in particles {
+        let rect = Rect::new(
+            (particle.position.x * scale.0 as f32) as i32,
+            (particle.position.y * scale.1 as f32) as i32,
+            size,
+            size,
+        );
+        renderer.fill_rect(rect, Color::from(split_rgb(particle.color)))?;
+    }
+    Ok(())
+}
+
+/// Dims whatever is already on the canvas and centers a title with a hint below it.
+const CHOICE_TITLE_PIXEL: u32 = 8;
+const CHOICE_PIXEL: u32 = 3;
+
+/// Top of the title of a choice screen with `options` on a canvas of
+/// `height`, and the height of one option's line.
+fn choice_layout(height: u32, options: usize) -> (i32, i32) {
+    let title_height = text_size("X", CHOICE_TITLE_PIXEL).1 as i32;
+    let line_height = text_size("X", CHOICE_PIXEL).1 as i32 * 2;
+    let total = title_height + line_height * (options as i32 + 1);
+    ((height as i32 - total) / 2, line_height)
+}
+
+/// The option of a choice screen under the height `y` of a canvas of `size`.
+pub fn choice_at(size: (u32, u32), options: usize, y: i32) -> Option<usize> {
+    let (top, line_height) = choice_layout(size.1, options);
+    let first = top + text_size("X", CHOICE_TITLE_PIXEL).1 as i32 + line_height;
+    let option = (y - first).div_euclid(line_height);
+    (0..options as i32).contains(&option).then_some(option as usize)
+}
+
+/// Darkens the screen under a title and a list of options, highlighting
+/// the selected one.
+pub fn render_choice(renderer: &mut dyn Renderer, title: &str, options: &[&str], selected: usize) -> Result<()> {
+    let canvas_size = renderer.output_size()?;
+    renderer.fill_rect(Rect::new(0, 0, canvas_size.0, canvas_size.1), Color::RGBA(0, 0, 0, 160))?;
+    let (top, line_height) = choice_layout(canvas_size.1, options.len());
+    let (title_width, title_height) = text_size(title, CHOICE_TITLE_PIXEL);
+    renderer.draw_text(title, (canvas_size.0 as i32 - title_width as i32) / 2, top, CHOICE_TITLE_PIXEL, Color::WHITE)?;
+    let first = top + title_height as i32 + line_height;
+    for (index, option) in options.iter().enumerate() {
+        let color = if index == selected { Color::YELLOW } else { Color::GRAY };
+        let width = text_size(option, CHOICE_PIXEL).0 as i32;
+        renderer.draw_text(option, (canvas_size.0 as i32 - width) / 2, first + line_height * index as i32, CHOICE_PIXEL, color)?;
+    }
+    Ok(())
+}
+
+const CARD_PIXEL: u32 = 3;
+const CARD_TITLE_PIXEL: u32 = 5;
+const CARD_MARGIN: i32 = 24;
+
+/// Frame of a card with `rows` centered on a screen of `size`, and of the
+/// button along its bottom.
+pub fn card_layout(size: (u32, u32), rows: usize) -> (Rect, Rect) {
+    let row_height = text_size("X", CARD_PIXEL).1 as i32 * 2;
+    let title_height = text_size("X", CARD_TITLE_PIXEL).1 as i32 * 2;
+    let button_height = row_height + CARD_PIXEL as i32 * 4;
+    let width = (size.0 as i32 - 32).clamp(1, 640);
+    let height = CARD_MARGIN * 3 + title_height + row_height * rows as i32 + button_height;
+    let frame = Rect::new((size.0 as i32 - width) / 2, (size.1 as i32 - height) / 2, width as u32, height as u32);
+    let button_width = width / 2;
+    let button = Rect::new(
+        frame.x() + (width - button_width) / 2,
+        frame.bottom() - CARD_MARGIN - button_height,
+        button_width as u32,
+        button_height as u32,
+    );
+    (frame, button)
+}
+
+/// Darkens the screen under a card with a title, a row for each label and
+/// value, and a button along its bottom.
+pub fn render_card(renderer: &mut dyn Renderer, title: &str, rows: &[(&str, String, Color)], button: &str) -> Result<()> {
+    let canvas_size = renderer.output_size()?;
+    renderer.fill_rect(Rect::new(0, 0, canvas_size.0, canvas_size.1), Color::RGBA(0, 0, 0, 160))?;
+    let (frame, button_rect) = card_layout(canvas_size, rows.len());
+    renderer.fill_rect(frame, Color::RGBA(0, 0, 0, 200))?;
+    renderer.draw_rect(frame, Color::WHITE)?;
+
+    let title_width = text_size(title, CARD_TITLE_PIXEL).0 as i32;
+    let mut y = frame.top() + CARD_MARGIN;
+    renderer.draw_text(title, frame.x() + (frame.width() as i32 - title_width) / 2, y, CARD_TITLE_PIXEL, Color::WHITE)?;
+    y += text_size("X", CARD_TITLE_PIXEL).1 as i32 * 2;
+    for (label, value, color) in rows {
+        renderer.draw_text(label, frame.left() + CARD_MARGIN, y, CARD_PIXEL, Color::GRAY)?;
+        let value_width = text_size(value, CARD_PIXEL).0 as i32;
+        renderer.draw_text(value, frame.right() - CARD_MARGIN - value_width, y, CARD_PIXEL, *color)?;
+        y += text_size("X", CARD_PIXEL).1 as i32 * 2;
+    }
+
+    renderer.draw_rect(button_rect, Color::YELLOW)?;
+    let (button_width, button_height) = text_size(button, CARD_PIXEL);
+    let x = button_rect.x() + (button_rect.width() as i32 - button_width as i32) / 2;
+    let y = button_rect.y() + (button_rect.height() as i32 - button_height as i32) / 2;
+    renderer.draw_text(button, x, y, CARD_PIXEL, Color::YELLOW)
+}
+
+pub fn render_banner(renderer: &mut dyn Renderer, title: &str, hint: &str) -> Result<()> {
+    let canvas_size = renderer.output_size()?;
+    renderer.fill_rect(Rect::new(0, 0, canvas_size.0, canvas_size.1), Color::RGBA(0, 0, 0, 160))?;
+
+    let title_pixel = 8;
+    let hint_pixel = 3;
+    let (title_width, title_height) = text_size(title, title_pixel);
+    // Long hints go on as many lines as they need.
+    let lines = wrap_text(hint, hint_pixel, canvas_size.0.saturating_sub(16));
+    let hint_height = text_size("X", hint_pixel).1 as i32;
+    let gap = hint_height;
+    let top = (canvas_size.1 as i32 - title_height as i32 - gap - hint_height * (lines.len() as i32 * 2 - 1).max(1)) / 2;
+    renderer.draw_text(
+        title,
+        (canvas_size.0 as i32 - title_width as i32) / 2,
+        top,
+        title_pixel,
+        Color::WHITE,
+    )?;
+    for (index, line) in lines.iter().enumerate() {
+        renderer.draw_text(
+            line,
+            (canvas_size.0 as i32 - text_size(line, hint_pixel).0 as i32) / 2,
+            top + title_height as i32 + gap + hint_height * 2 * index as i32,
+            hint_pixel,
+            Color::GRAY,
+        )?;
+    }
+    Ok(())
+}
+
+/// Draws a box at the top of the screen announcing `text` under `title`.
+pub fn render_toast(renderer: &mut dyn Renderer, title: &str, text: &str) -> Result<()> {
+    let (canvas_width, _) = renderer.output_size()?;
+    let text = text.to_uppercase();
+    let (title_width, title_height) = text_size(title, 2);
+    let (text_width, text_height) = text_size(&text, 3);
+    let width = title_width.max(text_width) + 16;
+    let height = title_height + text_height + 20;
+    let left = (canvas_width as i32 - width as i32) / 2;
+    let rect = Rect::new(left, 8, width, height);
+    renderer.fill_rect(rect, Color::RGBA(0, 0, 0, 200))?;
+    renderer.draw_rect(rect, Color::YELLOW)?;
+    renderer.draw_text(title, (canvas_width as i32 - title_width as i32) / 2, 16, 2, Color::GRAY)?;
+    renderer.draw_text(&text, (canvas_width as i32 - text_width as i32) / 2, 16 + title_height as i32 + 4, 3, Color::YELLOW)
+}
+
+/// Stacks the captions of sounds in the bottom left corner, the newest
+/// at the bottom.
+pub fn render_captions<'a>(renderer: &mut dyn Renderer, captions: impl IntoIterator<Item = &'a Caption>) -> Result<()> {
+    let pixel = 2;
+    let (_, canvas_height) = renderer.output_size()?;
+    let line_height = text_size("X", pixel).1 as i32 + 8;
+    let lines: Vec<String> = captions.into_iter().map(Caption::line).collect();
+    let mut y = canvas_height as i32 - 16 - line_height * lines.len() as i32;
+    for line in lines {
+        let (width, height) = text_size(&line, pixel);
+        renderer.fill_rect(Rect::new(12, y - 4, width + 8, height + 8), Color::RGBA(0, 0, 0, 200))?;
+        renderer.draw_text(&line, 16, y, pixel, Color::WHITE)?;
+        y += line_height;
+    }
+    Ok(())
+}
+
+/// Lists the objectives of a level along the bottom of the screen, each
+/// with how far along it is, completed ones in green.
+pub fn render_objectives(renderer: &mut dyn Renderer, objectives: &[Objective], progress: &[u32]) -> Result<()> {
+    if objectives.is_empty() {
+        return Ok(());
+    }
+    let pixel = 2;
+    let (canvas_width, canvas_height) = renderer.output_size()?;
+    let line_height = text_size("X", pixel).1 as i32 + 6;
+    let margin = 16;
+    let mut y = canvas_height as i32 - margin - line_height * (objectives.len() as i32 + 1);
+    renderer.draw_text("OBJECTIVES", margin, y, pixel, Color::WHITE)?;
+    for (objective, done) in objectives.iter().zip(progress) {
+        y += line_height;
+        let color = if *done >= objective.count { Color::GREEN } else { Color::GRAY };
+        let count = format!("{}/{}", done, objective.count);
+        renderer.draw_text(&objective.text, margin, y, pixel, color)?;
+        let (width, _) = text_size(&count, pixel);
+        renderer.draw_text(&count, canvas_width as i32 - margin - width as i32, y, pixel, color)?;
+    }
+    Ok(())
+}
+
+/// Per-phase frame timings in the top-left corner.
+pub fn render_profiler(renderer: &mut dyn Renderer, report: &FrameReport) -> Result<()> {
+    let pixel = 2;
+    let mut lines = vec![format!("FPS {}  FRAME {:.2} MS", report.frames, report.frame)];
+    for (phase, time) in Phase::ALL.iter().zip(report.phases) {
+        lines.push(format!("{} {:.2} MS", phase.name().to_uppercase(), time));
+    }
+    let line_height = text_size("X", pixel).1 as i32 + pixel as i32 * 2;
+    let width = lines.iter().map(|line| text_size(line, pixel).0).max().unwrap_or(0);
+
+    let panel = Rect::new(0, 0, width + pixel * 8, (line_height * lines.len() as i32) as u32 + pixel * 6);
+    renderer.fill_rect(panel, Color::RGBA(0, 0, 0, 160))?;
+    for (index, line) in lines.iter().enumerate() {
+        let y = pixel as i32 * 4 + line_height * index as i32;
+        renderer.draw_text(line, pixel as i32 * 4, y, pixel, Color::WHITE)?;
+    }
+    Ok(())
+}
+
+/// The console over the top half of the screen, latest output just above the prompt.
+pub fn render_console(renderer: &mut dyn Renderer, console: &Console) -> Result<()> {
+    let canvas_size = renderer.output_size()?;
+    let pixel = 2;
+    let margin = pixel as i32 * 4;
+    let line_height = text_size("X", pixel).1 as i32 + pixel as i32 * 2;
+    let height = canvas_size.1 / 2;
+    renderer.fill_rect(Rect::new(0, 0, canvas_size.0, height), Color::RGBA(0, 0, 0, 200))?;
+
+    let mut y = height as i32 - margin - line_height;
+    renderer.draw_text(&format!("> {}_", console.input.display()), margin, y, pixel, Color::YELLOW)?;
+    for line in console.lines.iter().rev() {
+        y -= line_height;
+        if y < margin {
+            break;
+        }
+        renderer.draw_text(line, margin, y, pixel, Color::WHITE)?;
+    }
+    Ok(())
+}
+
+/// Touch buttons, filled while a finger holds them.
+/// Draws the held controls of each player in the bottom left corner, one
+/// row per player.
+pub fn render_inputs(renderer: &mut dyn Renderer, inputs: &[PlayerInput]) -> Result<()> {
+    let pixel = 2;
+    let (_, height) = renderer.output_size()?;
+    let margin = 8;
+    let row_height = 28;
+    for (index, input) in inputs.iter().enumerate() {
+        let y = height as i32 - margin - row_height * (inputs.len() - index) as i32;
+        let keys = [
+            ("<", input.left || input.stick < 0),
+            (">", input.right || input.stick > 0),
+            ("JUMP", input.jump),
+            ("FIRE", input.shoot),
+        ];
+        let mut x = margin;
+        for (label, held) in keys {
+            let (width, text_height) = text_size(label, pixel);
+            let rect = Rect::new(x, y, width + 12, row_height as u32 - 4);
+            let color = Color::from(split_rgb(PLAYER_COLORS[index]));
+            if held {
+                renderer.fill_rect(rect, color)?;
+            }
+            renderer.draw_rect(rect, color)?;
+            let text = if held { Color::BLACK } else { color };
+            renderer.draw_text(label, x + 6, y + (rect.height() as i32 - text_height as i32) / 2, pixel, text)?;
+            x += rect.width() as i32 + 4;
+        }
+    }
+    Ok(())
+}
+
+/// Draws the touch buttons, lit while `is_held` says their action is.
+pub fn render_touch_controls(renderer: &mut dyn Renderer, is_held: impl Fn(Action) -> bool) -> Result<()> {
+    let pixel = 2;
+    for (action, label, rect) in TouchControls::buttons(renderer.output_size()?) {
+        let fill = if is_held(action) { Color::RGBA(255, 255, 255, 120) } else { Color::RGBA(255, 255, 255, 40) };
+        renderer.fill_rect(rect, fill)?;
+        renderer.draw_rect(rect, Color::WHITE)?;
+        let (width, height) = text_size(label, pixel);
+        let x = rect.x() + (rect.width() as i32 - width as i32) / 2;
+        let y = rect.y() + (rect.height() as i32 - height as i32) / 2;
+        renderer.draw_text(label, x, y, pixel, Color::WHITE)?;
+    }
+    Ok(())
+}
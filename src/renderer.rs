@@ -0,0 +1,228 @@
+//! Drawing backends. Everything that draws goes through [`Renderer`], so
+//! only [`SdlRenderer`] knows about the SDL canvas and other backends can
+//! be added without touching the game.
+
+use sdl2::pixels;
+use sdl2::rect;
+use sdl2::render::{BlendMode, WindowCanvas};
+use sdl2::video::{FullscreenType, WindowPos};
+
+use crate::error::Result;
+use crate::settings::WindowSettings;
+use crate::text;
+
+/// An RGBA color; an alpha below 255 blends with what is already drawn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+#[allow(non_snake_case)]
+impl Color {
+    pub const WHITE: Color = Color::RGB(255, 255, 255);
+    pub const BLACK: Color = Color::RGB(0, 0, 0);
+    pub const GRAY: Color = Color::RGB(128, 128, 128);
+    pub const RED: Color = Color::RGB(255, 0, 0);
+    pub const GREEN: Color = Color::RGB(0, 255, 0);
+    pub const YELLOW: Color = Color::RGB(255, 255, 0);
+    pub const MAGENTA: Color = Color::RGB(255, 0, 255);
+
+    pub const fn RGB(r: u8, g: u8, b: u8) -> Color {
+        Color { r, g, b, a: 255 }
+    }
+
+    pub const fn RGBA(r: u8, g: u8, b: u8, a: u8) -> Color {
+        Color { r, g, b, a }
+    }
+}
+
+impl From<(u8, u8, u8)> for Color {
+    fn from((r, g, b): (u8, u8, u8)) -> Color {
+        Color::RGB(r, g, b)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Point {
+    x: i32,
+    y: i32,
+}
+
+impl Point {
+    pub fn new(x: i32, y: i32) -> Point {
+        Point { x, y }
+    }
+
+    pub fn x(&self) -> i32 {
+        self.x
+    }
+
+    pub fn y(&self) -> i32 {
+        self.y
+    }
+}
+
+impl From<(i32, i32)> for Point {
+    fn from((x, y): (i32, i32)) -> Point {
+        Point::new(x, y)
+    }
+}
+
+/// A screen rectangle, `x` and `y` being its top left corner.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rect {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+impl Rect {
+    pub fn new(x: i32, y: i32, width: u32, height: u32) -> Rect {
+        Rect { x, y, width, height }
+    }
+
+    pub fn from_center<P: Into<Point>>(center: P, width: u32, height: u32) -> Rect {
+        let center = center.into();
+        Rect::new(center.x - width as i32 / 2, center.y - height as i32 / 2, width, height)
+    }
+
+    pub fn x(&self) -> i32 {
+        self.x
+    }
+
+    pub fn y(&self) -> i32 {
+        self.y
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn left(&self) -> i32 {
+        self.x
+    }
+
+    pub fn right(&self) -> i32 {
+        self.x + self.width as i32
+    }
+
+    pub fn top(&self) -> i32 {
+        self.y
+    }
+
+    pub fn bottom(&self) -> i32 {
+        self.y + self.height as i32
+    }
+
+    pub fn center(&self) -> Point {
+        Point::new(self.x + self.width as i32 / 2, self.y + self.height as i32 / 2)
+    }
+
+    pub fn contains_point<P: Into<Point>>(&self, point: P) -> bool {
+        let point = point.into();
+        point.x >= self.left() && point.x < self.right() && point.y >= self.top() && point.y < self.bottom()
+    }
+}
+
+pub trait Renderer {
+    /// Size of the drawing area in pixels.
+    fn output_size(&self) -> Result<(u32, u32)>;
+    fn clear(&mut self, color: Color);
+    /// Fills `rect`, blending with what is below when `color` is translucent.
+    fn fill_rect(&mut self, rect: Rect, color: Color) -> Result<()>;
+    /// Draws the outline of `rect`.
+    fn draw_rect(&mut self, rect: Rect, color: Color) -> Result<()>;
+    /// Fills the triangle with corners `points`.
+    fn fill_triangle(&mut self, points: [Point; 3], color: Color) -> Result<()>;
+    /// Draws `text` in the built-in bitmap font, `pixel` screen pixels per font pixel.
+    fn draw_text(&mut self, text: &str, x: i32, y: i32, pixel: u32, color: Color) -> Result<()> {
+        text::draw_text(self, text, x, y, pixel, color)
+    }
+    /// Shows everything drawn since the last call.
+    fn present(&mut self);
+}
+
+pub struct SdlRenderer {
+    canvas: WindowCanvas,
+}
+
+impl SdlRenderer {
+    pub fn new(canvas: WindowCanvas) -> Self {
+        SdlRenderer { canvas }
+    }
+
+    /// Resizes the window and switches it in or out of fullscreen. Vsync
+    /// is only set as the window opens.
+    pub fn apply_window(&mut self, settings: &WindowSettings) -> Result<()> {
+        let window = self.canvas.window_mut();
+        window.set_fullscreen(if settings.fullscreen { FullscreenType::Desktop } else { FullscreenType::Off })?;
+        if !settings.fullscreen {
+            window.set_size(settings.width, settings.height).map_err(|error| error.to_string())?;
+            window.set_position(WindowPos::Centered, WindowPos::Centered);
+        }
+        Ok(())
+    }
+
+    fn set_color(&mut self, color: Color) {
+        self.canvas.set_blend_mode(if color.a < 255 { BlendMode::Blend } else { BlendMode::None });
+        self.canvas.set_draw_color(pixels::Color::RGBA(color.r, color.g, color.b, color.a));
+    }
+}
+
+impl Renderer for SdlRenderer {
+    fn output_size(&self) -> Result<(u32, u32)> {
+        Ok(self.canvas.output_size()?)
+    }
+
+    fn clear(&mut self, color: Color) {
+        self.set_color(color);
+        self.canvas.clear();
+    }
+
+    fn fill_rect(&mut self, rect: Rect, color: Color) -> Result<()> {
+        self.set_color(color);
+        Ok(self.canvas.fill_rect(sdl_rect(rect))?)
+    }
+
+    fn draw_rect(&mut self, rect: Rect, color: Color) -> Result<()> {
+        self.set_color(color);
+        Ok(self.canvas.draw_rect(sdl_rect(rect))?)
+    }
+
+    fn fill_triangle(&mut self, points: [Point; 3], color: Color) -> Result<()> {
+        self.set_color(color);
+        let top = points.iter().map(|point| point.y()).min().unwrap_or_default();
+        let bottom = points.iter().map(|point| point.y()).max().unwrap_or_default();
+        for y in top..=bottom {
+            // Where the row crosses each edge spanning it.
+            let crossings = (0..3).filter_map(|index| {
+                let (a, b) = (points[index], points[(index + 1) % 3]);
+                if a.y() == b.y() || y < a.y().min(b.y()) || y > a.y().max(b.y()) {
+                    return None;
+                }
+                Some(a.x() + (b.x() - a.x()) * (y - a.y()) / (b.y() - a.y()))
+            });
+            let (left, right) = crossings.fold((i32::MAX, i32::MIN), |(left, right), x| (left.min(x), right.max(x)));
+            if left <= right {
+                self.canvas.draw_line(rect::Point::new(left, y), rect::Point::new(right, y))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn present(&mut self) {
+        self.canvas.present();
+    }
+}
+
+fn sdl_rect(rect: Rect) -> rect::Rect {
+    rect::Rect::new(rect.x, rect.y, rect.width, rect.height)
+}
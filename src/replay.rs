@@ -0,0 +1,470 @@
+//! Replays store the seed, the map and the input of every tick, which is
+//! all a deterministic simulation needs to play a level again exactly.
+//!
+//! The file starts with `PLRP`, a format version, the seed, a hash of the
+//! map, the level path, the number of players, the difficulty and the
+//! lives and coins to spend the level started with, whether a companion
+//! came along and the tuning in effect, as TOML. Then come what was done
+//! to the game between ticks, see [`Intervention`], as a count followed
+//! by `(tick, kind, data)` entries, the number of ticks and the inputs
+//! run-length encoded as `(buttons, sticks, count)` runs; held buttons
+//! rarely change between ticks. Each player takes five bits of the two
+//! bytes of `buttons` and one byte of `sticks`.
+//!
+//! The runs have to add up to the number of ticks, which can't be more
+//! than [`MAX_TICKS`], so a damaged file can't make decoding run out of
+//! memory.
+
+use log::info;
+
+use crate::difficulty::Difficulty;
+use crate::error::{GameError, Result};
+use crate::game::Game;
+use crate::shop::{Offer, Ware};
+use crate::systems::input::{Inputs, PlayerInput, MAX_PLAYERS};
+use crate::tuning::Tuning;
+
+/// Replay the title screen plays when left idle.
+pub const DEMO_REPLAY: &str = "demo.plrp";
+
+const MAGIC: &[u8; 4] = b"PLRP";
+const VERSION: u8 = 1;
+/// Longest replay kept, a day of play.
+pub const MAX_TICKS: u32 = 60 * 60 * 60 * 24;
+
+const LEFT: u16 = 1;
+const RIGHT: u16 = 2;
+const JUMP: u16 = 4;
+const SHOOT: u16 = 8;
+const FETCH: u16 = 16;
+/// Bits of `buttons` used by each player.
+const PLAYER_BITS: usize = 5;
+
+/// Kinds of intervention in the file.
+const RESPAWN: u8 = 0;
+const BUY: u8 = 1;
+const TUNE: u8 = 2;
+
+/// Something done to a game between two ticks, outside of the players'
+/// inputs, which a replay has to do again at the same tick.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Intervention {
+    /// The players came back after losing a life, with `lives` left.
+    Respawn { lives: u32 },
+    /// The first player bought `offer` from a shop.
+    Buy(Offer),
+    /// The tuning was edited while playing.
+    Tune(Tuning),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    pub level: String,
+    /// Hash of the map the replay was recorded on, see [`map_hash`].
+    pub map_hash: u64,
+    pub seed: u64,
+    pub players: usize,
+    pub difficulty: Difficulty,
+    /// Lives the level started with.
+    pub lives: u32,
+    /// Coins to spend in shops the level started with.
+    pub money: u32,
+    /// Whether a companion came along.
+    pub companion: bool,
+    /// Tuning the level started with.
+    pub tuning: Tuning,
+    /// What was done between ticks, with the tick it was done before, in
+    /// order.
+    pub interventions: Vec<(u64, Intervention)>,
+    /// Input of every tick, in order.
+    pub inputs: Vec<Inputs>,
+}
+
+/// FNV-1a hash of a map file, used to detect replays of a different map.
+pub fn map_hash(contents: &str) -> u64 {
+    contents.bytes().fold(0xCBF2_9CE4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01B3)
+    })
+}
+
+fn pack(inputs: &Inputs) -> u16 {
+    inputs.iter()
+        .enumerate()
+        .fold(0, |buttons, (player, input)| buttons | pack_player(input) << (player * PLAYER_BITS))
+}
+
+fn unpack(buttons: u16) -> Inputs {
+    std::array::from_fn(|player| unpack_player((buttons >> (player * PLAYER_BITS)) & ((1 << PLAYER_BITS) - 1)))
+}
+
+fn pack_player(input: &PlayerInput) -> u16 {
+    let mut buttons = 0;
+    if input.left {
+        buttons |= LEFT;
+    }
+    if input.right {
+        buttons |= RIGHT;
+    }
+    if input.jump {
+        buttons |= JUMP;
+    }
+    if input.shoot {
+        buttons |= SHOOT;
+    }
+    if input.fetch {
+        buttons |= FETCH;
+    }
+    buttons
+}
+
+fn unpack_player(buttons: u16) -> PlayerInput {
+    PlayerInput {
+        left: buttons & LEFT != 0,
+        right: buttons & RIGHT != 0,
+        jump: buttons & JUMP != 0,
+        shoot: buttons & SHOOT != 0,
+        fetch: buttons & FETCH != 0,
+        stick: 0,
+    }
+}
+
+impl Replay {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.extend_from_slice(&self.map_hash.to_le_bytes());
+        bytes.extend_from_slice(&(self.level.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(self.level.as_bytes());
+        bytes.push(self.players as u8);
+        bytes.push(self.difficulty.code());
+        bytes.extend_from_slice(&self.lives.to_le_bytes());
+        bytes.extend_from_slice(&self.money.to_le_bytes());
+        bytes.push(self.companion as u8);
+        write_tuning(&mut bytes, &self.tuning);
+
+        bytes.extend_from_slice(&(self.interventions.len() as u32).to_le_bytes());
+        for (tick, intervention) in &self.interventions {
+            bytes.extend_from_slice(&tick.to_le_bytes());
+            match intervention {
+                Intervention::Respawn { lives } => {
+                    bytes.push(RESPAWN);
+                    bytes.extend_from_slice(&lives.to_le_bytes());
+                }
+                Intervention::Buy(offer) => {
+                    bytes.push(BUY);
+                    bytes.push(offer.ware.code());
+                    bytes.extend_from_slice(&offer.price.to_le_bytes());
+                }
+                Intervention::Tune(tuning) => {
+                    bytes.push(TUNE);
+                    write_tuning(&mut bytes, tuning);
+                }
+            }
+        }
+
+        bytes.extend_from_slice(&(self.inputs.len() as u32).to_le_bytes());
+        let mut runs: Vec<(Inputs, u32)> = Vec::new();
+        for inputs in &self.inputs {
+            match runs.last_mut() {
+                Some((last, count)) if last == inputs && *count < u32::MAX => { *count += 1 }
+                _ => { runs.push((*inputs, 1)) }
+            }
+        }
+        for (inputs, count) in runs {
+            bytes.extend_from_slice(&pack(&inputs).to_le_bytes());
+            for input in &inputs[..self.players] {
+                bytes.push(input.stick as u8);
+            }
+            bytes.extend_from_slice(&count.to_le_bytes());
+        }
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Replay> {
+        let mut reader = Reader { bytes, offset: 0 };
+        if reader.take(4)? != MAGIC {
+            return Err(GameError::InvalidReplay("not a replay file".to_string()));
+        }
+        let version = reader.take(1)?[0];
+        if version != VERSION {
+            return Err(GameError::InvalidReplay(format!("unsupported version {}", version)));
+        }
+        let seed = u64::from_le_bytes(reader.take(8)?.try_into().unwrap());
+        let map_hash = u64::from_le_bytes(reader.take(8)?.try_into().unwrap());
+        let length = u16::from_le_bytes(reader.take(2)?.try_into().unwrap()) as usize;
+        let level = String::from_utf8(reader.take(length)?.to_vec())
+            .map_err(|_| GameError::InvalidReplay("level path is not UTF-8".to_string()))?;
+        let players = reader.take(1)?[0] as usize;
+        if !(1..=MAX_PLAYERS).contains(&players) {
+            return Err(GameError::InvalidReplay(format!("unsupported number of players {}", players)));
+        }
+        let code = reader.take(1)?[0];
+        let difficulty = Difficulty::from_code(code)
+            .ok_or_else(|| GameError::InvalidReplay(format!("unknown difficulty {}", code)))?;
+        let lives = u32::from_le_bytes(reader.take(4)?.try_into().unwrap());
+        let money = u32::from_le_bytes(reader.take(4)?.try_into().unwrap());
+        let companion = reader.take(1)?[0] != 0;
+        let tuning = read_tuning(&mut reader)?;
+
+        let count = u32::from_le_bytes(reader.take(4)?.try_into().unwrap());
+        let mut interventions = Vec::new();
+        for _ in 0..count {
+            let tick = u64::from_le_bytes(reader.take(8)?.try_into().unwrap());
+            let intervention = match reader.take(1)?[0] {
+                RESPAWN => { Intervention::Respawn { lives: u32::from_le_bytes(reader.take(4)?.try_into().unwrap()) } }
+                BUY => {
+                    let code = reader.take(1)?[0];
+                    let ware = Ware::from_code(code)
+                        .ok_or_else(|| GameError::InvalidReplay(format!("unknown ware {}", code)))?;
+                    Intervention::Buy(Offer { ware, price: u32::from_le_bytes(reader.take(4)?.try_into().unwrap()) })
+                }
+                TUNE => { Intervention::Tune(read_tuning(&mut reader)?) }
+                kind => { return Err(GameError::InvalidReplay(format!("unknown intervention {}", kind))) }
+            };
+            interventions.push((tick, intervention));
+        }
+
+        let ticks = u32::from_le_bytes(reader.take(4)?.try_into().unwrap());
+        if ticks > MAX_TICKS {
+            return Err(GameError::InvalidReplay(format!("{} ticks is longer than a replay can be", ticks)));
+        }
+        let mut inputs = Vec::with_capacity(ticks as usize);
+        while !reader.is_empty() {
+            let mut input = unpack(u16::from_le_bytes(reader.take(2)?.try_into().unwrap()));
+            for player in &mut input[..players] {
+                player.stick = reader.take(1)?[0] as i8;
+            }
+            let count = u32::from_le_bytes(reader.take(4)?.try_into().unwrap()) as usize;
+            if count > ticks as usize - inputs.len() {
+                return Err(GameError::InvalidReplay(format!("inputs run past the {} ticks", ticks)));
+            }
+            inputs.extend(std::iter::repeat_n(input, count));
+        }
+        if inputs.len() != ticks as usize {
+            return Err(GameError::InvalidReplay(format!("inputs stop after {} of {} ticks", inputs.len(), ticks)));
+        }
+        Ok(Replay {
+            level,
+            map_hash,
+            seed,
+            players,
+            difficulty,
+            lives,
+            money,
+            companion,
+            tuning,
+            interventions,
+            inputs,
+        })
+    }
+
+    /// Tuning in effect at the end of the replay so far.
+    pub fn latest_tuning(&self) -> Tuning {
+        self.interventions.iter().rev()
+            .find_map(|(_, intervention)| match intervention {
+                Intervention::Tune(tuning) => { Some(*tuning) }
+                _ => { None }
+            })
+            .unwrap_or(self.tuning)
+    }
+
+    pub fn load(path: &str) -> Result<Replay> {
+        let bytes = std::fs::read(path)
+            .map_err(|source| GameError::Io { path: path.to_string(), source })?;
+        Replay::decode(&bytes)
+    }
+
+    pub fn save(&self, path: &str) -> Result<()> {
+        std::fs::write(path, self.encode())
+            .map_err(|source| GameError::Io { path: path.to_string(), source })?;
+        info!("Saved replay of {} ticks to '{}'", self.inputs.len(), path);
+        Ok(())
+    }
+}
+
+fn write_tuning(bytes: &mut Vec<u8>, tuning: &Tuning) {
+    let text = toml::to_string(tuning).unwrap_or_default();
+    bytes.extend_from_slice(&(text.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(text.as_bytes());
+}
+
+fn read_tuning(reader: &mut Reader) -> Result<Tuning> {
+    let length = u32::from_le_bytes(reader.take(4)?.try_into().unwrap()) as usize;
+    let text = std::str::from_utf8(reader.take(length)?)
+        .map_err(|_| GameError::InvalidReplay("tuning is not UTF-8".to_string()))?;
+    toml::from_str(text).map_err(|error| GameError::InvalidReplay(format!("bad tuning: {}", error)))
+}
+
+/// Plays a replay back on a game loaded for it, doing again what was done
+/// between ticks as their ticks come.
+pub struct Playback {
+    pub replay: Replay,
+    /// Index of the next intervention to do.
+    next: usize,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        Playback { replay, next: 0 }
+    }
+
+    /// Plays the next tick on `game`. Returns false, without playing it,
+    /// once the replay is over.
+    pub fn step(&mut self, game: &mut Game) -> bool {
+        self.catch_up(game);
+        if self.is_over(game) {
+            return false;
+        }
+        game.tick(self.replay.inputs[game.ticks as usize]);
+        self.catch_up(game);
+        true
+    }
+
+    /// Does what is due by the tick `game` is at.
+    fn catch_up(&mut self, game: &mut Game) {
+        while let Some((tick, intervention)) = self.replay.interventions.get(self.next) {
+            if *tick > game.ticks {
+                break;
+            }
+            game.intervene(intervention);
+            self.next += 1;
+        }
+    }
+
+    /// Whether the inputs ran out, the level was completed or the players
+    /// died without coming back. Players who come back keep playing
+    /// until then, as the death plays out.
+    pub fn is_over(&self, game: &Game) -> bool {
+        let respawns = matches!(self.replay.interventions.get(self.next), Some((_, Intervention::Respawn { .. })));
+        game.ticks as usize >= self.replay.inputs.len()
+            || game.is_level_complete()
+            || (game.is_player_dead() && !respawns)
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8]> {
+        let end = self.offset + count;
+        if end > self.bytes.len() {
+            return Err(GameError::InvalidReplay("file is truncated".to_string()));
+        }
+        let slice = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(slice)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.offset >= self.bytes.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(left: bool, jump: bool, stick: i8) -> PlayerInput {
+        PlayerInput { left, jump, stick, ..PlayerInput::default() }
+    }
+
+    fn replay(inputs: Vec<Inputs>) -> Replay {
+        let mut tuning = Tuning::default();
+        tuning.physics.gravity *= 2.0;
+        Replay {
+            level: "levels/1.txt".to_string(),
+            map_hash: map_hash("#.@"),
+            seed: 42,
+            players: 2,
+            difficulty: Difficulty::Hard,
+            lives: 3,
+            money: 250,
+            companion: true,
+            tuning: Tuning::default(),
+            interventions: vec![
+                (10, Intervention::Buy(Offer { ware: Ware::Potion, price: 40 })),
+                (12, Intervention::Tune(tuning)),
+                (30, Intervention::Respawn { lives: 2 }),
+            ],
+            inputs,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let both = [input(true, false, 0), PlayerInput { fetch: true, shoot: true, right: true, ..PlayerInput::default() }];
+        let inputs = vec![
+            both,
+            both,
+            [input(false, true, -127), input(false, false, 127)],
+            [PlayerInput::default(); MAX_PLAYERS],
+        ];
+        let replay = replay(inputs);
+        assert_eq!(Replay::decode(&replay.encode()).unwrap(), replay);
+    }
+
+    #[test]
+    fn repeated_inputs_take_one_run() {
+        let held = [input(true, true, 5); MAX_PLAYERS];
+        let once = replay(vec![held]).encode();
+        let many = replay(vec![held; 1000]);
+        let encoded = many.encode();
+        assert_eq!(encoded.len(), once.len());
+        assert_eq!(Replay::decode(&encoded).unwrap().inputs.len(), 1000);
+    }
+
+    #[test]
+    fn latest_tuning_follows_the_edits() {
+        let replay = replay(Vec::new());
+        let Intervention::Tune(edited) = replay.interventions[1].1 else { unreachable!() };
+        assert_eq!(replay.latest_tuning(), edited);
+        assert_eq!(Replay { interventions: Vec::new(), ..replay }.latest_tuning(), Tuning::default());
+    }
+
+    #[test]
+    fn decodes_the_demo() {
+        let demo = Replay::decode(include_bytes!("../assets/demo.plrp")).unwrap();
+        assert_eq!((demo.seed, demo.players), (7, 1));
+        let again = Replay::decode(&demo.encode()).unwrap();
+        assert_eq!(again, demo);
+        assert_eq!(demo.inputs.len(), 693);
+    }
+
+    #[test]
+    fn rejects_bad_files() {
+        assert!(Replay::decode(b"PLRQ\x01").is_err());
+        assert!(Replay::decode(&[MAGIC.as_slice(), &[VERSION + 1]].concat()).is_err());
+        let encoded = replay(vec![[PlayerInput::default(); MAX_PLAYERS]]).encode();
+        assert!(Replay::decode(&encoded[..encoded.len() - 1]).is_err());
+    }
+
+    /// Offset of the tick count in `encoded`, right before the single run
+    /// of a replay with one distinct input.
+    fn tick_count_offset(encoded: &[u8]) -> usize {
+        encoded.len() - 4 - 2 - 2 - 4
+    }
+
+    #[test]
+    fn rejects_runs_longer_than_the_replay() {
+        let mut encoded = replay(vec![[PlayerInput::default(); MAX_PLAYERS]; 3]).encode();
+        let count = encoded.len() - 4;
+        encoded[count..].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(
+            Replay::decode(&encoded).unwrap_err().to_string(),
+            "invalid replay: inputs run past the 3 ticks",
+        );
+        encoded[count..].copy_from_slice(&2u32.to_le_bytes());
+        assert_eq!(
+            Replay::decode(&encoded).unwrap_err().to_string(),
+            "invalid replay: inputs stop after 2 of 3 ticks",
+        );
+        let ticks = tick_count_offset(&encoded);
+        encoded[ticks..ticks + 4].copy_from_slice(&(MAX_TICKS + 1).to_le_bytes());
+        assert!(Replay::decode(&encoded).is_err());
+    }
+}
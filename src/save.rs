@@ -0,0 +1,440 @@
+//! Snapshots of a running level written as JSON, so a game can be
+//! resumed exactly where it was left: blocks, entities, tick count and
+//! the state of the random number generator. Variables kept by the level
+//! script are not part of the snapshot.
+
+use std::collections::BTreeMap;
+
+use hecs::{Entity, World};
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::block::Block;
+use crate::cart::{Cart, Riding};
+use crate::collision::Collider;
+use crate::companion::Companion;
+use crate::components::{Body, Position, Sprite, Velocity};
+use crate::difficulty::{Difficulty, Profile};
+use crate::error::{GameError, Result};
+use crate::enemy::{Enemy, Flight, Route};
+use crate::inventory::Inventory;
+use crate::loot::{Crate, Loot};
+use crate::npc::Npc;
+use crate::player::{Defeated, Health, Player};
+use crate::playground::Playground;
+use crate::powerup::Boost;
+use crate::projectile::Projectile;
+use crate::rng::Rng;
+use crate::saw::Saw;
+use crate::score::Combo;
+use crate::spawner::{Spawned, Spawner};
+use crate::swing::{Anchor, Flung, Swinging};
+use crate::switch::{Button, Plate};
+use crate::systems::input::MAX_PLAYERS;
+use crate::trap::Trap;
+use crate::trigger::TriggerState;
+use crate::waypoint::Follow;
+
+pub const QUICK_SAVE: &str = "quicksave.json";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SaveGame {
+    pub level: String,
+    pub map_hash: u64,
+    pub seed: u64,
+    pub ticks: u64,
+    pub rng: Rng,
+    #[serde(default)]
+    pub difficulty: Difficulty,
+    #[serde(default)]
+    pub profile: Profile,
+    #[serde(default)]
+    pub coins: u32,
+    /// Tiles of the hidden stars picked up.
+    #[serde(default)]
+    pub hidden_stars: Vec<(usize, usize)>,
+    #[serde(default)]
+    pub score: u32,
+    #[serde(default)]
+    pub combo: Combo,
+    /// Saves from before lives existed had a single try.
+    #[serde(default = "single_life")]
+    pub lives: u32,
+    #[serde(default)]
+    pub money: u32,
+    #[serde(default)]
+    pub inventory: Inventory,
+    /// Ticks left before the gates of each open link close.
+    #[serde(default)]
+    pub links: BTreeMap<u32, u32>,
+    /// Progress toward each objective of the level.
+    #[serde(default)]
+    pub objectives: Vec<u32>,
+    #[serde(default)]
+    pub played_cutscenes: Vec<usize>,
+    /// State of every trigger of the level info, in order.
+    #[serde(default)]
+    pub triggers: Vec<TriggerState>,
+    /// Track a trigger switched the music to.
+    #[serde(default)]
+    pub music: Option<String>,
+    /// Tile of the last checkpoint reached.
+    #[serde(default)]
+    pub checkpoint: Option<(usize, usize)>,
+    #[serde(default)]
+    pub deaths: u32,
+    /// Every row of the playground in map characters.
+    pub rows: Vec<String>,
+    pub entities: Vec<SavedEntity>,
+    #[serde(default)]
+    pub projectiles: Vec<Projectile>,
+}
+
+/// The components of one entity; absent components are left out.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedEntity {
+    #[serde(default)]
+    pub player: bool,
+    #[serde(default)]
+    pub player_index: usize,
+    #[serde(default)]
+    pub defeated: bool,
+    pub health: Option<Health>,
+    pub boost: Option<Boost>,
+    pub enemy: Option<Enemy>,
+    pub flight: Option<Flight>,
+    pub route: Option<Route>,
+    pub npc: Option<Npc>,
+    #[serde(default, rename = "crate")]
+    pub crate_: bool,
+    pub loot: Option<Loot>,
+    pub spawner: Option<Spawner>,
+    pub saw: Option<Saw>,
+    pub follow: Option<Follow>,
+    pub trap: Option<Trap>,
+    pub button: Option<Button>,
+    pub plate: Option<Plate>,
+    pub companion: Option<Companion>,
+    #[serde(default)]
+    pub anchor: bool,
+    pub swinging: Option<Swinging>,
+    #[serde(default)]
+    pub flung: bool,
+    pub cart: Option<Cart>,
+    #[serde(default)]
+    pub riding: bool,
+    pub spawned: Option<Spawned>,
+    /// Set only by saves from before projectiles left the world.
+    #[serde(default, skip_serializing)]
+    pub projectile: Option<LegacyProjectile>,
+    pub collider: Option<Collider>,
+    pub position: Option<Position>,
+    pub velocity: Option<Velocity>,
+    pub body: Option<Body>,
+    pub sprite: Option<Sprite>,
+}
+
+/// The marker projectile entities had in saves from before they left the world.
+#[derive(Copy, Clone, Debug, Deserialize)]
+pub struct LegacyProjectile {
+    pub hostile: bool,
+}
+
+impl SaveGame {
+    pub fn load(path: &str) -> Result<SaveGame> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|source| GameError::Io { path: path.to_string(), source })?;
+        serde_json::from_str(&contents)
+            .map_err(|error| GameError::InvalidSave(format!("{}: {}", path, error)))
+    }
+
+    pub fn save(&self, path: &str) -> Result<()> {
+        let contents = serde_json::to_string_pretty(self)
+            .map_err(|error| GameError::InvalidSave(error.to_string()))?;
+        std::fs::write(path, contents)
+            .map_err(|source| GameError::Io { path: path.to_string(), source })?;
+        info!("Saved game to '{}' at tick {}", path, self.ticks);
+        Ok(())
+    }
+}
+
+fn single_life() -> u32 {
+    1
+}
+
+pub fn save_rows(playground: &Playground) -> Vec<String> {
+    (0..playground.height)
+        .map(|y| (0..playground.width).map(|x| playground.block_at(x, y).code()).collect())
+        .collect()
+}
+
+/// Overwrites every block of `playground` with the saved rows.
+pub fn restore_rows(playground: &mut Playground, rows: &[String]) -> Result<()> {
+    if rows.len() != playground.height {
+        return Err(GameError::InvalidSave("the saved map has a different size".to_string()));
+    }
+    for (y, row) in rows.iter().enumerate() {
+        let blocks: Vec<Block> = row.chars().filter_map(Block::from_code).collect();
+        if blocks.len() != playground.width {
+            return Err(GameError::InvalidSave("the saved map has a different size".to_string()));
+        }
+        for (x, block) in blocks.into_iter().enumerate() {
+            playground.set_block(x, y, block);
+        }
+    }
+    Ok(())
+}
+
+/// Collider of an entity from a save from before colliders existed, the
+/// one its kind spawns with.
+fn legacy_collider(saved: &SavedEntity) -> Option<Collider> {
+    if saved.player {
+        Some(Collider::PLAYER)
+    } else if saved.enemy.is_some() {
+        Some(Collider::ENEMY)
+    } else if saved.crate_ {
+        Some(Collider::CRATE)
+    } else if saved.saw.is_some() || saved.trap.is_some() {
+        Some(Collider::HAZARD)
+    } else {
+        None
+    }
+}
+
+/// Projectiles that older saves kept as entities.
+pub fn legacy_projectiles(entities: &[SavedEntity]) -> Vec<Projectile> {
+    entities.iter()
+        .filter_map(|saved| Some(Projectile {
+            position: saved.position?,
+            velocity: saved.velocity?,
+            hostile: saved.projectile?.hostile,
+            color: saved.sprite?.color,
+        }))
+        .collect()
+}
+
+pub fn save_entities(world: &World) -> Vec<SavedEntity> {
+    world.iter()
+        .map(|entity| SavedEntity {
+            player: entity.has::<Player>(),
+            player_index: entity.get::<&Player>().map(|player| player.index).unwrap_or_default(),
+            defeated: entity.has::<Defeated>(),
+            health: entity.get::<&Health>().map(|component| *component),
+            boost: entity.get::<&Boost>().map(|component| *component),
+            enemy: entity.get::<&Enemy>().map(|component| *component),
+            flight: entity.get::<&Flight>().map(|component| *component),
+            route: entity.get::<&Route>().map(|component| (*component).clone()),
+            npc: entity.get::<&Npc>().map(|component| *component),
+            crate_: entity.has::<Crate>(),
+            loot: entity.get::<&Loot>().map(|component| *component),
+            spawner: entity.get::<&Spawner>().map(|component| *component),
+            spawned: entity.get::<&Spawned>().map(|component| *component),
+            saw: entity.get::<&Saw>().map(|component| (*component).clone()),
+            follow: entity.get::<&Follow>().map(|component| (*component).clone()),
+            trap: entity.get::<&Trap>().map(|component| *component),
+            button: entity.get::<&Button>().map(|component| *component),
+            plate: entity.get::<&Plate>().map(|component| *component),
+            companion: entity.get::<&Companion>().map(|component| (*component).clone()),
+            anchor: entity.has::<Anchor>(),
+            swinging: entity.get::<&Swinging>().map(|component| *component),
+            flung: entity.has::<Flung>(),
+            cart: entity.get::<&Cart>().map(|component| *component),
+            riding: entity.has::<Riding>(),
+            projectile: None,
+            collider: entity.get::<&Collider>().map(|component| *component),
+            position: entity.get::<&Position>().map(|component| *component),
+            velocity: entity.get::<&Velocity>().map(|component| *component),
+            body: entity.get::<&Body>().map(|component| *component),
+            sprite: entity.get::<&Sprite>().map(|component| *component),
+        })
+        .collect()
+}
+
+/// Replaces every entity in `world` with the saved ones and returns the
+/// players in order.
+pub fn restore_entities(world: &mut World, entities: &[SavedEntity]) -> Result<Vec<Entity>> {
+    world.clear();
+    let mut players = Vec::new();
+    for saved in entities.iter().filter(|saved| saved.projectile.is_none()) {
+        let mut builder = hecs::EntityBuilder::new();
+        if saved.player {
+            if saved.player_index >= MAX_PLAYERS {
+                return Err(GameError::InvalidSave(format!("the save has player {}", saved.player_index + 1)));
+            }
+            builder.add(Player::new(saved.player_index));
+            // Saves from before players had health restore them unhurt.
+            builder.add(saved.health.unwrap_or_default());
+        }
+        if let Some(boost) = saved.boost {
+            builder.add(boost);
+        }
+        if saved.defeated {
+            builder.add(Defeated);
+        }
+        if let Some(enemy) = saved.enemy {
+            builder.add(enemy);
+        }
+        if let Some(flight) = saved.flight {
+            builder.add(flight);
+        }
+        if let Some(route) = &saved.route {
+            builder.add(route.clone());
+        }
+        if let Some(npc) = saved.npc {
+            builder.add(npc);
+        }
+        if saved.crate_ {
+            builder.add(Crate);
+        }
+        if let Some(loot) = saved.loot {
+            builder.add(loot);
+        }
+        if let Some(spawner) = saved.spawner {
+            builder.add(spawner);
+        }
+        if let Some(spawned) = saved.spawned {
+            builder.add(spawned);
+        }
+        if let Some(saw) = &saved.saw {
+            builder.add(saw.clone());
+        }
+        if let Some(follow) = &saved.follow {
+            builder.add(follow.clone());
+        }
+        if let Some(trap) = saved.trap {
+            builder.add(trap);
+        }
+        if let Some(button) = saved.button {
+            builder.add(button);
+        }
+        if let Some(plate) = saved.plate {
+            builder.add(plate);
+        }
+        if let Some(companion) = &saved.companion {
+            builder.add(companion.clone());
+        }
+        if saved.anchor {
+            builder.add(Anchor);
+        }
+        if let Some(swinging) = saved.swinging {
+            builder.add(swinging);
+        }
+        if saved.flung {
+            builder.add(Flung);
+        }
+        if let Some(cart) = saved.cart {
+            builder.add(cart);
+        }
+        if saved.riding {
+            builder.add(Riding);
+        }
+        if let Some(collider) = saved.collider.or_else(|| legacy_collider(saved)) {
+            builder.add(collider);
+        }
+        if let Some(position) = saved.position {
+            builder.add(position);
+        }
+        if let Some(velocity) = saved.velocity {
+            builder.add(velocity);
+        }
+        if let Some(body) = saved.body {
+            builder.add(body);
+        }
+        if let Some(sprite) = saved.sprite {
+            builder.add(sprite);
+        }
+        let entity = world.spawn(builder.build());
+        if saved.player {
+            players.push((saved.player_index, entity));
+        }
+    }
+    players.sort_by_key(|(index, _)| *index);
+    if players.is_empty() || players.iter().enumerate().any(|(expected, (index, _))| *index != expected) {
+        return Err(GameError::InvalidSave("the save has no player or a player is missing".to_string()));
+    }
+    Ok(players.into_iter().map(|(_, entity)| entity).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::game::Game;
+    use crate::mods::Mods;
+    use crate::paths::SearchPaths;
+    use crate::projectile::PROJECTILE_SIZE;
+    use crate::replay::Replay;
+    use crate::systems::input::Inputs;
+
+    fn mods() -> Mods {
+        Mods { search: SearchPaths { roots: vec![Path::new(env!("CARGO_MANIFEST_DIR")).join("assets")] }, ..Mods::default() }
+    }
+
+    fn json(game: &Game) -> String {
+        serde_json::to_string(&game.snapshot()).unwrap()
+    }
+
+    #[test]
+    fn restored_games_carry_on_the_same() {
+        let mods = mods();
+        let demo = Replay::decode(include_bytes!("../assets/demo.plrp")).unwrap();
+        let mut game = Game::new(&demo.level, &mods, demo.seed).unwrap();
+        for inputs in &demo.inputs[..300] {
+            game.tick(*inputs);
+        }
+        let saved: SaveGame = serde_json::from_str(&json(&game)).unwrap();
+        let mut restored = Game::restore(&saved, &mods).unwrap();
+        assert_eq!(json(&restored), json(&game));
+        for inputs in &demo.inputs[300..] {
+            game.tick(*inputs);
+            restored.tick(*inputs);
+        }
+        assert_eq!(json(&restored), json(&game));
+        assert!(restored.is_level_complete());
+    }
+
+    #[test]
+    fn rejects_saves_of_another_map() {
+        let mods = mods();
+        let game = Game::new("map.txt", &mods, 1).unwrap();
+        let mut saved = game.snapshot();
+        saved.rows.pop();
+        assert!(Game::restore(&saved, &mods).is_err());
+        saved = game.snapshot();
+        saved.rows[0].pop();
+        assert!(Game::restore(&saved, &mods).is_err());
+        saved = game.snapshot();
+        saved.map_hash ^= 1;
+        assert!(Game::restore(&saved, &mods).is_err());
+        saved = game.snapshot();
+        saved.entities.retain(|entity| !entity.player);
+        assert!(Game::restore(&saved, &mods).is_err());
+    }
+
+    #[test]
+    fn keeps_projectiles_in_flight() {
+        let mods = mods();
+        let mut game = Game::new("map.txt", &mods, 1).unwrap();
+        let mut inputs = Inputs::default();
+        inputs[0].shoot = true;
+        game.tick(inputs);
+        let saved = game.snapshot();
+        assert_eq!(saved.projectiles.len(), 1);
+        assert_eq!(json(&Game::restore(&saved, &mods).unwrap()), json(&game));
+
+        // Older saves kept projectiles as entities.
+        let mut value = serde_json::to_value(&saved).unwrap();
+        let projectile = value["projectiles"].as_array_mut().unwrap().remove(0);
+        value["entities"].as_array_mut().unwrap().push(serde_json::json!({
+            "projectile": { "hostile": projectile["hostile"] },
+            "collider": Collider::PLAYER_SHOT,
+            "position": projectile["position"],
+            "velocity": projectile["velocity"],
+            "body": Body::new(PROJECTILE_SIZE, PROJECTILE_SIZE),
+            "sprite": { "color": projectile["color"] },
+        }));
+        let legacy: SaveGame = serde_json::from_value(value).unwrap();
+        assert_eq!(json(&Game::restore(&legacy, &mods).unwrap()), json(&game));
+    }
+}
@@ -0,0 +1,115 @@
+//! Saw blades that run along rails laid out in the map and hurt players
+//! they touch. A saw follows the rail tiles connected to its own, back and
+//! forth, or round and round when the rail closes on itself. How fast each
+//! saw goes and where along its rail it starts comes from the level's
+//! info file, see [`crate::level::LevelInfo`].
+
+use hecs::{Entity, World};
+use serde::{Deserialize, Serialize};
+
+use crate::collision::Collider;
+use crate::components::{Body, Position, Sprite};
+use crate::level::Level;
+
+pub const SAW_CODE: char = 'W';
+/// Map character for an empty tile that saws run along.
+pub const RAIL_CODE: char = '=';
+pub const SAW_SIZE: f32 = 0.8;
+pub const SAW_COLOR: u32 = 0xD0D0E0;
+
+/// How a saw moves, set per saw in the level info.
+#[derive(Copy, Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct SawInfo {
+    /// Tiles per tick, `saw_speed` in the tuning when left out.
+    pub speed: Option<f32>,
+    /// How far along a whole trip of its rail the saw starts ahead of its
+    /// tile, from 0 to 1.
+    pub phase: f32,
+}
+
+/// Marks a saw blade. Saws move on their own rather than through physics.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Saw {
+    /// Place of the saw among the level's saws, in map order, which picks
+    /// its info.
+    pub index: usize,
+    /// Tiles of its rail in order, starting from one end.
+    pub path: Vec<(usize, usize)>,
+    /// Whether the last tile of the path joins the first.
+    pub looped: bool,
+    /// Place of the saw's own tile in the path.
+    pub start: usize,
+    /// Tiles travelled since the level started.
+    pub travelled: f32,
+}
+
+impl Saw {
+    /// Tiles from the start of the path back to where a trip begins again.
+    pub fn trip(&self) -> f32 {
+        if self.looped {
+            self.path.len() as f32
+        } else {
+            2.0 * self.path.len().saturating_sub(1) as f32
+        }
+    }
+
+    /// Center of the saw once it is `distance` tiles into a trip.
+    pub fn point_at(&self, distance: f32) -> (f32, f32) {
+        let trip = self.trip();
+        if trip <= 0.0 {
+            return center(self.path[0]);
+        }
+        let mut distance = distance.rem_euclid(trip);
+        if !self.looped && distance > trip / 2.0 {
+            distance = trip - distance;
+        }
+        let index = (distance.floor() as usize).min(self.path.len() - 1);
+        let (from, to) = (center(self.path[index]), center(self.path[(index + 1) % self.path.len()]));
+        let along = distance - index as f32;
+        (from.0 + (to.0 - from.0) * along, from.1 + (to.1 - from.1) * along)
+    }
+}
+
+fn center(tile: (usize, usize)) -> (f32, f32) {
+    (tile.0 as f32 + 0.5, tile.1 as f32 + 0.5)
+}
+
+/// Tiles of the rail through `tile`, from one end, and whether it loops.
+fn trace(level: &Level, tile: (usize, usize)) -> (Vec<(usize, usize)>, bool) {
+    let is_rail = |x: usize, y: usize| {
+        (x, y) == tile || level.marker_at(x, y).is_some_and(|marker| marker.code == RAIL_CODE)
+    };
+    let walk = |from: (usize, usize)| {
+        let mut path = vec![from];
+        loop {
+            let (x, y) = *path.last().unwrap();
+            let next = [(x + 1, y), (x, y + 1), (x.wrapping_sub(1), y), (x, y.wrapping_sub(1))].into_iter()
+                .find(|(x, y)| is_rail(*x, *y) && !path.contains(&(*x, *y)));
+            match next {
+                Some(next) => { path.push(next) }
+                None => { return path }
+            }
+        }
+    };
+    let path = walk(*walk(tile).last().unwrap());
+    let (first, last) = (path[0], path[path.len() - 1]);
+    let looped = path.len() > 2 && first.0.abs_diff(last.0) + first.1.abs_diff(last.1) == 1;
+    (path, looped)
+}
+
+/// Spawns saw number `index` in the tile at `tile`, on the rail through it.
+pub fn spawn(world: &mut World, level: &Level, tile: (usize, usize), index: usize) -> Entity {
+    let (path, looped) = trace(level, tile);
+    let start = path.iter().position(|step| *step == tile).unwrap_or_default();
+    world.spawn((
+        Saw { index, path, looped, start, travelled: 0.0 },
+        Position {
+            x: tile.0 as f32 + (1.0 - SAW_SIZE) / 2.0,
+            y: tile.1 as f32 + (1.0 - SAW_SIZE) / 2.0,
+        },
+        Body::new(SAW_SIZE, SAW_SIZE),
+        Sprite { color: SAW_COLOR },
+        Collider::HAZARD,
+    ))
+}
@@ -0,0 +1,180 @@
+//! Points awarded during a level, the combo multiplying them and the
+//! "+100" popups shown where they were earned. Like particles, popups are
+//! purely visual and not saved.
+
+use serde::{Deserialize, Serialize};
+
+use crate::components::Position;
+use crate::pool::Pool;
+
+pub const COIN_POINTS: u32 = 100;
+pub const HIDDEN_STAR_POINTS: u32 = 1000;
+/// Points for each enemy defeated.
+pub const ENEMY_POINTS: u32 = 200;
+/// Bonus for finishing a level on the first tick, shrinking by
+/// `TIME_BONUS_DECAY` every second until it runs out.
+pub const TIME_BONUS: u32 = 5000;
+pub const TIME_BONUS_DECAY: u32 = 50;
+
+/// Ticks a combo holds after each link before it starts decaying.
+pub const COMBO_HOLD: u32 = 120;
+/// Ticks between the multiplier dropping a step once the hold runs out.
+const COMBO_DECAY: u32 = 30;
+pub const MAX_MULTIPLIER: u32 = 8;
+
+/// Popups shown at once; points beyond this are still counted.
+pub const MAX_POPUPS: usize = 32;
+/// Ticks a popup stays on screen.
+const POPUP_LIFE: u32 = 45;
+/// Tiles a popup rises every tick.
+const POPUP_RISE: f32 = 0.02;
+
+/// Time bonus for completing a level after `ticks`.
+pub fn time_bonus(ticks: u64) -> u32 {
+    let seconds = (ticks / 60).min(u32::MAX as u64) as u32;
+    TIME_BONUS.saturating_sub(seconds.saturating_mul(TIME_BONUS_DECAY))
+}
+
+/// Multiplier on the points of enemies and coins, built by stomping
+/// enemies and picking up coins in the air one after the other.
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Combo {
+    /// Links in the chain, each adding a step to the multiplier.
+    pub chain: u32,
+    /// Ticks left before the chain loses a link.
+    pub timer: u32,
+}
+
+impl Combo {
+    pub fn multiplier(&self) -> u32 {
+        (1 + self.chain).min(MAX_MULTIPLIER)
+    }
+
+    /// Adds a link to the chain and holds it for a while.
+    pub fn extend(&mut self) {
+        self.chain += 1;
+        self.timer = COMBO_HOLD;
+    }
+
+    /// Cuts the hold short, for when the chain touches the ground.
+    pub fn land(&mut self) {
+        self.timer = self.timer.min(COMBO_DECAY);
+    }
+
+    /// Counts down, dropping a link every time the timer runs out.
+    pub fn update(&mut self) {
+        if self.chain == 0 {
+            return;
+        }
+        self.timer = self.timer.saturating_sub(1);
+        if self.timer == 0 {
+            self.chain -= 1;
+            self.timer = if self.chain > 0 { COMBO_DECAY } else { 0 };
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Popup {
+    /// Center of the text in tile units.
+    pub position: Position,
+    pub points: u32,
+    /// Ticks left before the popup disappears.
+    pub life: u32,
+}
+
+pub struct Popups {
+    pool: Pool<Popup>,
+}
+
+impl Popups {
+    pub fn new() -> Self {
+        Popups { pool: Pool::with_capacity(MAX_POPUPS) }
+    }
+
+    /// Shows `points` rising from `(x, y)`.
+    pub fn spawn(&mut self, x: f32, y: f32, points: u32) {
+        let _ = self.pool.insert(Popup { position: Position { x, y }, points, life: POPUP_LIFE });
+    }
+
+    pub fn update(&mut self) {
+        self.pool.retain(|popup| {
+            popup.position.y -= POPUP_RISE;
+            popup.life = popup.life.saturating_sub(1);
+            popup.life > 0
+        });
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Popup> {
+        self.pool.iter()
+    }
+}
+
+impl Default for Popups {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_bonus_runs_out() {
+        assert_eq!(time_bonus(0), TIME_BONUS);
+        assert_eq!(time_bonus(59), TIME_BONUS);
+        assert_eq!(time_bonus(600), TIME_BONUS - 10 * TIME_BONUS_DECAY);
+        assert_eq!(time_bonus(60 * 3600), 0);
+        assert_eq!(time_bonus(u64::MAX), 0);
+    }
+
+    #[test]
+    fn popups_rise_and_expire() {
+        let mut popups = Popups::new();
+        for _ in 0..MAX_POPUPS + 5 {
+            popups.spawn(1.0, 2.0, 100);
+        }
+        assert_eq!(popups.iter().count(), MAX_POPUPS);
+        popups.update();
+        assert!(popups.iter().all(|popup| popup.position.y < 2.0 && popup.life == POPUP_LIFE - 1));
+        for _ in 1..POPUP_LIFE {
+            popups.update();
+        }
+        assert_eq!(popups.iter().count(), 0);
+    }
+
+    #[test]
+    fn combos_hold_then_decay() {
+        let mut combo = Combo::default();
+        assert_eq!(combo.multiplier(), 1);
+        combo.update();
+        assert_eq!((combo.chain, combo.timer), (0, 0));
+        for _ in 0..3 {
+            combo.extend();
+        }
+        assert_eq!(combo.multiplier(), 4);
+        for _ in 1..COMBO_HOLD {
+            combo.update();
+        }
+        assert_eq!(combo.chain, 3);
+        combo.update();
+        assert_eq!((combo.chain, combo.timer), (2, COMBO_DECAY));
+        for _ in 0..2 * COMBO_DECAY {
+            combo.update();
+        }
+        assert_eq!((combo.chain, combo.timer), (0, 0));
+    }
+
+    #[test]
+    fn landing_cuts_the_hold_short() {
+        let mut combo = Combo::default();
+        combo.extend();
+        combo.land();
+        assert_eq!(combo.timer, COMBO_DECAY);
+        for _ in 0..20 {
+            combo.extend();
+        }
+        assert_eq!(combo.multiplier(), MAX_MULTIPLIER);
+    }
+}
@@ -0,0 +1,165 @@
+//! Level scripts written in Rhai. A level `name.txt` may have a
+//! `name.rhai` next to it defining any of these hooks:
+//!
+//! - `on_start()` once when the level begins,
+//! - `on_tick(tick)` every simulation tick,
+//! - `on_enter(x, y)` whenever the player moves into another tile,
+//! - `on_event(name)` for every gameplay event, e.g. `"LevelCompleted"`,
+//! - `on_trigger(name, what)` when a player enters, stays in or leaves a
+//!   trigger zone, with `what` being `"enter"`, `"stay"` or `"exit"`.
+//!
+//! `random(min, max)` draws from the game's seeded generator, so scripted
+//! randomness replays like everything else.
+//!
+//! Scripts can only reach the game through the functions registered in
+//! [`Script::load`]; they have no file or process access and are limited
+//! in how much work a single hook may do.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use log::{info, warn};
+use rhai::{CallFnOptions, Dynamic, Engine, FuncArgs, Scope, AST};
+
+use crate::error::{GameError, Result};
+use crate::rng::Rng;
+
+/// Changes requested by a script, applied by the game once the hook returns.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScriptCommand {
+    SetBlock { x: usize, y: usize, code: char },
+    Teleport { x: usize, y: usize },
+}
+
+/// What scripts can read about the game, refreshed before every hook.
+/// The generator is lent to the script and handed back afterwards so
+/// script randomness stays reproducible.
+pub struct ScriptView {
+    pub player: (f32, f32),
+    pub rng: Rng,
+    pub width: usize,
+    pub height: usize,
+    pub blocks: Vec<char>,
+}
+
+struct Shared {
+    view: ScriptView,
+    commands: Vec<ScriptCommand>,
+}
+
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    hooks: HashSet<String>,
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl Script {
+    /// Path of the script belonging to the level at `level_path`.
+    pub fn path_for(level_path: &str) -> String {
+        Path::new(level_path).with_extension("rhai").to_string_lossy().into_owned()
+    }
+
+    /// Loads the script at `path`, or `None` when the level has no script.
+    pub fn load(path: &str) -> Result<Option<Script>> {
+        if !Path::new(path).exists() {
+            return Ok(None);
+        }
+        let source = std::fs::read_to_string(path)
+            .map_err(|source| GameError::Io { path: path.to_string(), source })?;
+
+        let shared = Arc::new(Mutex::new(Shared {
+            view: ScriptView {
+                player: (0.0, 0.0),
+                rng: Rng::new(0),
+                width: 0,
+                height: 0,
+                blocks: Vec::new(),
+            },
+            commands: Vec::new(),
+        }));
+        let mut engine = Engine::new();
+        engine.set_max_operations(100_000)
+            .set_max_call_levels(32)
+            .set_max_expr_depths(64, 32)
+            .set_max_string_size(4096)
+            .set_max_array_size(1024)
+            .set_max_map_size(256);
+        engine.on_print(|text| info!("Script: {}", text));
+        engine.on_debug(|text, _, _| info!("Script: {}", text));
+        Script::register_api(&mut engine, &shared);
+
+        let ast = engine.compile(&source)
+            .map_err(|error| GameError::Script(format!("{}: {}", path, error)))?;
+        let hooks = ast.iter_functions().map(|function| function.name.to_string()).collect();
+        let mut scope = Scope::new();
+        engine.run_ast_with_scope(&mut scope, &ast)
+            .map_err(|error| GameError::Script(format!("{}: {}", path, error)))?;
+        info!("Loaded script '{}'", path);
+
+        Ok(Some(Script {
+            engine,
+            ast,
+            scope,
+            hooks,
+            shared,
+        }))
+    }
+
+    fn register_api(engine: &mut Engine, shared: &Arc<Mutex<Shared>>) {
+        let state = shared.clone();
+        engine.register_fn("player_x", move || state.lock().unwrap().view.player.0 as f64);
+        let state = shared.clone();
+        engine.register_fn("player_y", move || state.lock().unwrap().view.player.1 as f64);
+        let state = shared.clone();
+        engine.register_fn("map_width", move || state.lock().unwrap().view.width as i64);
+        let state = shared.clone();
+        engine.register_fn("map_height", move || state.lock().unwrap().view.height as i64);
+        let state = shared.clone();
+        engine.register_fn("block_at", move |x: i64, y: i64| {
+            let shared = state.lock().unwrap();
+            let view = &shared.view;
+            if x < 0 || y < 0 || x as usize >= view.width || y as usize >= view.height {
+                return String::new();
+            }
+            view.blocks[y as usize * view.width + x as usize].to_string()
+        });
+        let state = shared.clone();
+        engine.register_fn("random", move |min: i64, max: i64| state.lock().unwrap().view.rng.range(min, max));
+        let state = shared.clone();
+        engine.register_fn("set_block", move |x: i64, y: i64, code: &str| {
+            if let (Ok(x), Ok(y), Some(code)) = (usize::try_from(x), usize::try_from(y), code.chars().next()) {
+                state.lock().unwrap().commands.push(ScriptCommand::SetBlock { x, y, code });
+            }
+        });
+        let state = shared.clone();
+        engine.register_fn("teleport", move |x: i64, y: i64| {
+            if let (Ok(x), Ok(y)) = (usize::try_from(x), usize::try_from(y)) {
+                state.lock().unwrap().commands.push(ScriptCommand::Teleport { x, y });
+            }
+        });
+    }
+
+    pub fn has_hook(&self, name: &str) -> bool {
+        self.hooks.contains(name)
+    }
+
+    /// Runs the hook `name` if the script defines it and returns the
+    /// commands it issued along with the generator after the hook used it.
+    /// Script errors are logged and otherwise ignored so a broken level
+    /// script cannot take the game down.
+    pub fn call(&mut self, view: ScriptView, name: &str, args: impl FuncArgs) -> (Vec<ScriptCommand>, Rng) {
+        if !self.has_hook(name) {
+            return (Vec::new(), view.rng);
+        }
+        self.shared.lock().unwrap().view = view;
+        let options = CallFnOptions::new().eval_ast(false);
+        if let Err(error) = self.engine.call_fn_with_options::<Dynamic>(options, &mut self.scope, &self.ast, name, args) {
+            warn!("Script hook {} failed: {}", name, error);
+        }
+        let mut shared = self.shared.lock().unwrap();
+        (std::mem::take(&mut shared.commands), shared.view.rng)
+    }
+}
@@ -0,0 +1,300 @@
+use std::path::Path;
+
+use log::{debug, info};
+use sdl2::keyboard::Keycode;
+use serde::{Deserialize, Serialize};
+
+use crate::bindings::Action;
+use crate::difficulty::Difficulty;
+use crate::error::{GameError, Result};
+use crate::level;
+
+pub const DEFAULT_SETTINGS: &str = "settings.toml";
+
+/// User preferences persisted between runs.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub game: GameSettings,
+    pub window: WindowSettings,
+    pub audio: AudioSettings,
+    pub input: InputSettings,
+    pub gamepad: GamepadSettings,
+    pub keys: KeyBindings,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameSettings {
+    pub start_level: String,
+    /// Seconds the title screen waits before playing a demo, 0 for never.
+    pub attract_delay: u32,
+    /// Tries a campaign starts with.
+    pub lives: u32,
+    /// Brings a companion along in every level.
+    pub companion: bool,
+    pub difficulty: Difficulty,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowSettings {
+    pub width: u32,
+    pub height: u32,
+    /// Covers the whole screen, at the desktop's resolution.
+    pub fullscreen: bool,
+    /// Only applied when the game starts.
+    pub vsync: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    /// Master volume from 0 to 100.
+    pub volume: u8,
+    /// Music and sound effect volumes from 0 to 100, as a share of the
+    /// master volume.
+    pub music: u8,
+    pub effects: u8,
+    /// Silences everything without touching the volumes.
+    pub muted: bool,
+    /// Seconds one track of music takes to fade into the next, 0 to cut.
+    pub crossfade: f32,
+    /// Shows captions for important sounds, see [`crate::captions`].
+    pub captions: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputSettings {
+    pub touch_controls: TouchMode,
+    /// Milliseconds a menu direction is held before it starts repeating.
+    pub repeat_delay: u64,
+    /// Milliseconds between repeats while it stays held.
+    pub repeat_interval: u64,
+    /// Actions that a press turns on and the next press turns off, for
+    /// players who can't keep a button held. Only [`Action::TOGGLEABLE`]
+    /// ones are accepted.
+    pub toggled: Vec<Action>,
+    /// Shows the held movement controls on screen, for streaming.
+    pub show_inputs: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GamepadSettings {
+    /// Stick deflection, from 0 to 1, below which the stick counts as centered.
+    pub deadzone: f32,
+    /// Exponent of the stick's response curve; above 1 gives finer control
+    /// near the center.
+    pub response: f32,
+    pub rumble: bool,
+    /// Strength of rumble effects, from 0 to 1.
+    pub rumble_intensity: f32,
+}
+
+/// When the on-screen touch controls are shown.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TouchMode {
+    /// Once the screen is first touched.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    #[serde(with = "keycode_name")]
+    pub left: Keycode,
+    #[serde(with = "keycode_name")]
+    pub right: Keycode,
+    #[serde(with = "keycode_name")]
+    pub jump: Keycode,
+    #[serde(with = "keycode_name")]
+    pub shoot: Keycode,
+    /// Controls of the second player.
+    #[serde(with = "keycode_name")]
+    pub second_left: Keycode,
+    #[serde(with = "keycode_name")]
+    pub second_right: Keycode,
+    #[serde(with = "keycode_name")]
+    pub second_jump: Keycode,
+    #[serde(with = "keycode_name")]
+    pub second_shoot: Keycode,
+    #[serde(with = "keycode_name")]
+    pub pause: Keycode,
+    #[serde(with = "keycode_name")]
+    pub quick_save: Keycode,
+    #[serde(with = "keycode_name")]
+    pub quick_load: Keycode,
+    #[serde(with = "keycode_name")]
+    pub debug_overlay: Keycode,
+    #[serde(with = "keycode_name")]
+    pub input_display: Keycode,
+    #[serde(with = "keycode_name")]
+    pub frame_step: Keycode,
+    #[serde(with = "keycode_name")]
+    pub step: Keycode,
+    #[serde(with = "keycode_name")]
+    pub console: Keycode,
+    #[serde(with = "keycode_name")]
+    pub talk: Keycode,
+    #[serde(with = "keycode_name")]
+    pub fetch: Keycode,
+    #[serde(with = "keycode_name")]
+    pub mute: Keycode,
+    /// Opens the achievements from the title screen.
+    #[serde(with = "keycode_name")]
+    pub achievements: Keycode,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        GameSettings {
+            start_level: level::DEFAULT_MAP.to_string(),
+            attract_delay: 20,
+            lives: 3,
+            companion: false,
+            difficulty: Difficulty::default(),
+        }
+    }
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        WindowSettings {
+            width: 800,
+            height: 600,
+            fullscreen: false,
+            vsync: true,
+        }
+    }
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        AudioSettings { volume: 100, music: 100, effects: 100, muted: false, crossfade: 1.0, captions: false }
+    }
+}
+
+impl Default for InputSettings {
+    fn default() -> Self {
+        InputSettings {
+            touch_controls: TouchMode::default(),
+            repeat_delay: 400,
+            repeat_interval: 80,
+            toggled: Vec::new(),
+            show_inputs: false,
+        }
+    }
+}
+
+impl Default for GamepadSettings {
+    fn default() -> Self {
+        GamepadSettings {
+            deadzone: 0.2,
+            response: 1.5,
+            rumble: true,
+            rumble_intensity: 1.0,
+        }
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            left: Keycode::A,
+            right: Keycode::D,
+            jump: Keycode::Space,
+            shoot: Keycode::F,
+            second_left: Keycode::Left,
+            second_right: Keycode::Right,
+            second_jump: Keycode::Up,
+            second_shoot: Keycode::RShift,
+            pause: Keycode::P,
+            quick_save: Keycode::F5,
+            quick_load: Keycode::F9,
+            debug_overlay: Keycode::F3,
+            input_display: Keycode::F4,
+            frame_step: Keycode::F6,
+            step: Keycode::Period,
+            console: Keycode::Backquote,
+            talk: Keycode::E,
+            fetch: Keycode::R,
+            mute: Keycode::M,
+            achievements: Keycode::T,
+        }
+    }
+}
+
+impl Settings {
+    /// Reads settings from `path`, writing the defaults there first if the
+    /// file does not exist yet.
+    pub fn load_or_create(path: &str) -> Result<Self> {
+        if !Path::new(path).exists() {
+            info!("No settings at '{}', writing defaults", path);
+            let settings = Settings::default();
+            settings.save(path)?;
+            return Ok(settings);
+        }
+        let contents = std::fs::read_to_string(path)
+            .map_err(|source| GameError::Io { path: path.to_string(), source })?;
+        debug!("Loading settings from '{}'", path);
+        let settings: Settings = toml::from_str(&contents)
+            .map_err(|error| GameError::InvalidSettings(format!("{}: {}", path, error)))?;
+        if let Some(action) = settings.input.toggled.iter().find(|action| !Action::TOGGLEABLE.contains(action)) {
+            return Err(GameError::InvalidSettings(format!(
+                "{}: {} can't be toggled, only moving, jumping and shooting can",
+                path,
+                action.label(),
+            )));
+        }
+        Ok(settings)
+    }
+
+    pub fn save(&self, path: &str) -> Result<()> {
+        let contents = toml::to_string_pretty(self)
+            .map_err(|error| GameError::InvalidSettings(error.to_string()))?;
+        std::fs::write(path, contents)
+            .map_err(|source| GameError::Io { path: path.to_string(), source })
+    }
+}
+
+/// Stores keys by their SDL name ("A", "Space", "Left") so the file stays editable by hand.
+mod keycode_name {
+    use sdl2::keyboard::Keycode;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(keycode: &Keycode, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&keycode.name())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Keycode, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Keycode::from_name(&name)
+            .ok_or_else(|| serde::de::Error::custom(format!("unknown key '{}'", name)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_held_actions_can_be_toggled() {
+        let path = std::env::temp_dir().join(format!("platformer-settings-{}.toml", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut settings = Settings::default();
+        settings.input.toggled = vec![Action::Jump, Action::SecondMoveRight];
+        settings.save(path).unwrap();
+        assert_eq!(Settings::load_or_create(path).unwrap().input.toggled, settings.input.toggled);
+        settings.input.toggled.push(Action::Pause);
+        settings.save(path).unwrap();
+        let loaded = Settings::load_or_create(path);
+        std::fs::remove_file(path).unwrap();
+        assert!(matches!(loaded, Err(GameError::InvalidSettings(_))));
+    }
+}
@@ -0,0 +1,169 @@
+//! Settings screen opened from the title screen or the pause menu: leads
+//! to the video, sound and controls screens and switches the difficulty
+//! in place. Going back returns to the screen it was opened from.
+//!
+//! The video, sound and controls screens are the tabs of the settings:
+//! each shows a bar of all three along the top, and Tab or a click on the
+//! bar switches to another.
+
+use log::info;
+use sdl2::keyboard::Keycode;
+
+use crate::audio::Music;
+use crate::bindings::{Action, Press};
+use crate::context::Context;
+use crate::controls::ControlsState;
+use crate::error::Result;
+use crate::menu::Menu;
+use crate::mouse::Mouse;
+use crate::paths::user_file;
+use crate::renderer::{Color, Rect, Renderer};
+use crate::settings::DEFAULT_SETTINGS;
+use crate::state::State;
+use crate::text::text_size;
+use crate::video::VideoState;
+use crate::volume::VolumeState;
+
+const ITEMS: [&str; 5] = ["VIDEO", "SOUND", "CONTROLS", "DIFFICULTY", "BACK"];
+/// Height of the bar of tabs, which the tabs lay out their rows under.
+pub const TAB_BAR: i32 = 48;
+const TAB_PIXEL: u32 = 3;
+/// Space between two tabs in the bar.
+const TAB_GAP: &str = "   ";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Tab {
+    Video,
+    Sound,
+    Controls,
+}
+
+impl Tab {
+    pub const ALL: [Tab; 3] = [Tab::Video, Tab::Sound, Tab::Controls];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Tab::Video => { "VIDEO" }
+            Tab::Sound => { "SOUND" }
+            Tab::Controls => { "CONTROLS" }
+        }
+    }
+
+    /// Opens the screen of the tab, going back to `back` from it.
+    pub fn open(self, context: &Context, back: Box<dyn State>) -> Box<dyn State> {
+        match self {
+            Tab::Video => { Box::new(VideoState::new(context, back)) }
+            Tab::Sound => { Box::new(VolumeState::new(context, back)) }
+            Tab::Controls => { Box::new(ControlsState::new(context, back)) }
+        }
+    }
+
+    /// Tab to switch to from this one for `press`, Tab going to the next.
+    pub fn switch(self, press: &Press) -> Option<Tab> {
+        let index = Tab::ALL.iter().position(|tab| *tab == self).unwrap_or(0);
+        (press.keycode == Some(Keycode::Tab)).then(|| Tab::ALL[(index + 1) % Tab::ALL.len()])
+    }
+
+    /// Tab whose label in the bar was clicked, if any.
+    pub fn clicked(mouse: &Mouse) -> Option<Tab> {
+        if !mouse.is_click() || mouse.y >= TAB_BAR {
+            return None;
+        }
+        let mut x = (mouse.screen.0 as i32 - bar_width()) / 2;
+        Tab::ALL.into_iter().find(|tab| {
+            let width = text_size(tab.label(), TAB_PIXEL).0 as i32;
+            let hit = mouse.x >= x && mouse.x < x + width;
+            x += width + text_size(TAB_GAP, TAB_PIXEL).0 as i32;
+            hit
+        })
+    }
+
+    /// Draws the bar of tabs with this one lit up.
+    pub fn render_bar(self, renderer: &mut dyn Renderer) -> Result<()> {
+        let (width, _) = renderer.output_size()?;
+        let mut x = (width as i32 - bar_width()) / 2;
+        let y = (TAB_BAR - text_size("X", TAB_PIXEL).1 as i32) / 2;
+        for tab in Tab::ALL {
+            let color = if tab == self { Color::YELLOW } else { Color::GRAY };
+            renderer.draw_text(tab.label(), x, y, TAB_PIXEL, color)?;
+            x += text_size(tab.label(), TAB_PIXEL).0 as i32 + text_size(TAB_GAP, TAB_PIXEL).0 as i32;
+        }
+        renderer.fill_rect(Rect::new(0, TAB_BAR - 2, width, 2), Color::GRAY)
+    }
+}
+
+/// Width of every tab label in the bar with the gaps between them.
+fn bar_width() -> i32 {
+    let labels: Vec<&str> = Tab::ALL.iter().map(Tab::label).collect();
+    text_size(&labels.join(TAB_GAP), TAB_PIXEL).0 as i32
+}
+
+pub struct SettingsState {
+    menu: Menu,
+    /// Screen it was opened from.
+    back: Box<dyn State>,
+}
+
+impl SettingsState {
+    pub fn new(context: &Context, back: Box<dyn State>) -> Self {
+        let mut state = SettingsState { menu: Menu::new("SETTINGS", ITEMS), back };
+        state.refresh(context);
+        state
+    }
+
+    /// Shows the difficulty in its item.
+    fn refresh(&mut self, context: &Context) {
+        self.menu.items[3] = format!("DIFFICULTY: {}", context.settings.game.difficulty.label());
+    }
+
+    fn pick(mut self: Box<Self>, context: &mut Context, item: usize) -> Result<Box<dyn State>> {
+        match item {
+            0 => { Ok(Tab::Video.open(context, self)) }
+            1 => { Ok(Tab::Sound.open(context, self)) }
+            2 => { Ok(Tab::Controls.open(context, self)) }
+            3 => {
+                context.settings.game.difficulty = context.settings.game.difficulty.next();
+                context.settings.save(&user_file(DEFAULT_SETTINGS))?;
+                info!("Switched to {} difficulty", context.settings.game.difficulty.label());
+                self.refresh(context);
+                Ok(self)
+            }
+            _ => { Ok(self.back) }
+        }
+    }
+}
+
+impl State for SettingsState {
+    fn name(&self) -> &'static str {
+        "settings"
+    }
+
+    fn music(&self) -> Music {
+        self.back.music()
+    }
+
+    fn handle_press(mut self: Box<Self>, context: &mut Context, press: &Press) -> Result<Box<dyn State>> {
+        if let Some(item) = self.menu.handle_press(press) {
+            return self.pick(context, item);
+        }
+        if press.is(Action::Back) {
+            return Ok(self.back);
+        }
+        Ok(self)
+    }
+
+    fn handle_mouse(mut self: Box<Self>, context: &mut Context, mouse: &Mouse) -> Result<Box<dyn State>> {
+        match self.menu.handle_mouse(mouse) {
+            Some(item) => { self.pick(context, item) }
+            None => { Ok(self) }
+        }
+    }
+
+    fn tick(self: Box<Self>, _context: &mut Context) -> Result<Box<dyn State>> {
+        Ok(self)
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer) -> Result<()> {
+        self.menu.render(renderer)
+    }
+}
@@ -0,0 +1,74 @@
+//! Things NPCs sell for the coins the players carry. What an NPC sells,
+//! and for how much, comes from the level's info file, see
+//! [`crate::level::LevelInfo`].
+
+use serde::Deserialize;
+
+use crate::inventory::Item;
+use crate::powerup::PowerUp;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Ware {
+    /// Gives the first player back a hit point.
+    Heart,
+    /// One more try.
+    Life,
+    /// Gives the first player a boost, as if the power-up was picked up.
+    Speed,
+    Jump,
+    Star,
+    /// Puts an item in the inventory.
+    Key,
+    Potion,
+    Gem,
+}
+
+impl Ware {
+    pub const ALL: [Ware; 8] = [Ware::Heart, Ware::Life, Ware::Speed, Ware::Jump, Ware::Star, Ware::Key, Ware::Potion, Ware::Gem];
+
+    pub fn code(&self) -> u8 {
+        Ware::ALL.iter().position(|ware| ware == self).unwrap_or(0) as u8
+    }
+
+    pub fn from_code(code: u8) -> Option<Ware> {
+        Ware::ALL.get(code as usize).copied()
+    }
+
+    pub fn power_up(&self) -> Option<PowerUp> {
+        match self {
+            Ware::Speed => { Some(PowerUp::Speed) }
+            Ware::Jump => { Some(PowerUp::Jump) }
+            Ware::Star => { Some(PowerUp::Star) }
+            _ => { None }
+        }
+    }
+
+    pub fn item(&self) -> Option<Item> {
+        match self {
+            Ware::Key => { Some(Item::Key) }
+            Ware::Potion => { Some(Item::Potion) }
+            Ware::Gem => { Some(Item::Gem) }
+            _ => { None }
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Ware::Heart => { "HEART" }
+            Ware::Life => { "EXTRA LIFE" }
+            _ => {
+                self.power_up().map(|power_up| power_up.name())
+                    .or(self.item().map(|item| item.name()))
+                    .unwrap_or_default()
+            }
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct Offer {
+    pub ware: Ware,
+    /// Coins it costs.
+    pub price: u32,
+}
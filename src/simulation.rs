@@ -0,0 +1,488 @@
+//! Runs a [`Game`] on its own thread at a fixed rate. The render thread
+//! only ever sees finished [`GameSnapshot`]s handed over through a triple
+//! buffer, so a slow frame never holds up the simulation and a slow tick
+//! never leaves the renderer drawing half-updated state.
+
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryIter};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::audio::{Intensity, Listener};
+use crate::components::{Body, Position, Sprite};
+use crate::events::GameEvent;
+use crate::game::{Game, GameCommand};
+use crate::hud::Hud;
+use crate::particles::Particle;
+use crate::projectile::Projectile;
+use crate::score::Popup;
+use crate::swing;
+use crate::playground::Playground;
+use crate::systems::input::Inputs;
+use crate::tuning::{AnimationTuning, Tuning};
+use crate::water::Current;
+
+/// Length of one simulation tick, 60 ticks per second.
+pub const TICK_DURATION: Duration = Duration::from_micros(1_000_000 / 60);
+
+pub const MIN_TIME_SCALE: f32 = 0.25;
+pub const MAX_TIME_SCALE: f32 = 4.0;
+
+/// Most ticks run to catch up after a stall, so a long hitch is skipped
+/// rather than replayed at full speed.
+const MAX_CATCH_UP_TICKS: u32 = 8;
+
+
+/// How fast game time runs compared to real time: a base speed chosen by
+/// the player, times the factor of any short effect in progress.
+#[derive(Copy, Clone, Debug)]
+pub struct TimeScale {
+    base: f32,
+    /// Factor and real time left of the current effect.
+    effect: Option<(f32, Duration)>,
+}
+
+impl TimeScale {
+    pub fn new(base: f32) -> Self {
+        TimeScale { base: base.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE), effect: None }
+    }
+
+    pub fn set_base(&mut self, base: f32) {
+        self.base = base.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE);
+    }
+
+    pub fn factor(&self) -> f32 {
+        self.base * self.effect.map_or(1.0, |(factor, _)| factor)
+    }
+
+    /// Runs time at `factor` of the base speed for `duration` of real time,
+    /// replacing any effect in progress.
+    pub fn apply_effect(&mut self, factor: f32, duration: Duration) {
+        self.effect = Some((factor, duration));
+    }
+
+    pub fn has_effect(&self) -> bool {
+        self.effect.is_some()
+    }
+
+    fn advance(&mut self, elapsed: Duration) {
+        if let Some((factor, remaining)) = self.effect {
+            self.effect = remaining.checked_sub(elapsed)
+                .filter(|remaining| !remaining.is_zero())
+                .map(|remaining| (factor, remaining));
+        }
+    }
+}
+
+/// Hands values from one writer to one reader. Each side keeps a buffer of
+/// its own and only swaps it with the shared middle one, so neither waits
+/// on the other for longer than a swap and the reader always gets the most
+/// recent complete value.
+pub struct TripleBuffer<T> {
+    middle: Mutex<(T, bool)>,
+}
+
+impl<T> TripleBuffer<T> {
+    pub fn new(value: T) -> Self {
+        TripleBuffer { middle: Mutex::new((value, false)) }
+    }
+
+    /// Swaps `back` with the middle buffer and marks it fresh.
+    pub fn publish(&self, back: &mut T) {
+        let mut middle = self.middle.lock().unwrap();
+        std::mem::swap(&mut middle.0, back);
+        middle.1 = true;
+    }
+
+    /// Swaps `front` with the middle buffer if something new was published.
+    pub fn fetch(&self, front: &mut T) -> bool {
+        let mut middle = self.middle.lock().unwrap();
+        if !middle.1 {
+            return false;
+        }
+        std::mem::swap(&mut middle.0, front);
+        middle.1 = false;
+        true
+    }
+}
+
+/// Everything the renderer needs from a game after a tick.
+#[derive(Clone)]
+pub struct GameSnapshot {
+    pub ticks: u64,
+    pub playground: Playground,
+    pub sprites: Vec<(Position, Body, Sprite)>,
+    /// Currents of the level info, drawn over the water.
+    pub currents: Vec<Current>,
+    pub animation: AnimationTuning,
+    /// Ropes players swing on, from anchor to player.
+    pub ropes: Vec<((f32, f32), (f32, f32))>,
+    pub particles: Vec<Particle>,
+    pub popups: Vec<Popup>,
+    pub hud: Hud,
+    /// Where to show that an NPC can be talked to or a sign read: the
+    /// middle of its top edge.
+    pub talk_prompt: Option<Position>,
+    /// Where sounds are heard from.
+    pub listener: Option<Listener>,
+    /// Track the level plays and how loud its stems are.
+    pub music: Option<String>,
+    pub intensity: Intensity,
+}
+
+impl GameSnapshot {
+    pub fn new(game: &Game) -> Self {
+        let mut snapshot = GameSnapshot {
+            ticks: 0,
+            playground: game.playground.clone(),
+            sprites: Vec::new(),
+            currents: Vec::new(),
+            animation: AnimationTuning::default(),
+            ropes: Vec::new(),
+            particles: Vec::new(),
+            popups: Vec::new(),
+            hud: Hud::default(),
+            talk_prompt: None,
+            listener: None,
+            music: None,
+            intensity: Intensity::default(),
+        };
+        snapshot.capture(game);
+        snapshot
+    }
+
+    /// Copies the state of `game`, reusing the buffers already allocated.
+    pub fn capture(&mut self, game: &Game) {
+        self.ticks = game.ticks;
+        self.playground.clone_from(&game.playground);
+        self.sprites.clear();
+        self.sprites.extend(
+            game.world.query::<(&Position, &Body, &Sprite)>()
+                .iter()
+                .map(|(position, body, sprite)| (*position, *body, *sprite))
+                .chain(game.projectiles.iter().map(Projectile::sprite)),
+        );
+        self.currents.clone_from(&game.info.currents);
+        self.animation = game.tuning.animation;
+        self.ropes.clear();
+        self.ropes.extend(swing::ropes(&game.world));
+        self.particles.clear();
+        self.particles.extend(game.particles.iter().copied());
+        self.popups.clear();
+        self.popups.extend(game.popups.iter().copied());
+        self.hud.capture(game);
+        self.listener = Listener::of(game);
+        if self.music.as_deref() != game.music_track() {
+            self.music = game.music_track().map(str::to_string);
+        }
+        self.intensity = Intensity::of(game);
+        self.talk_prompt = game.npc_in_reach()
+            .and_then(|npc| {
+                let mut query = game.world.query_one::<(&Position, &Body)>(npc);
+                let (position, body) = query.get().ok()?;
+                Some(Position { x: position.x + body.width / 2.0, y: position.y })
+            })
+            .or_else(|| {
+                let (x, y) = game.signs[game.sign_in_reach()?];
+                Some(Position { x: x as f32 + 0.5, y: y as f32 })
+            });
+    }
+}
+
+/// State shared between the simulation thread and its owner.
+struct Shared {
+    inputs: Mutex<Inputs>,
+    tuning: Mutex<Tuning>,
+    time_scale: Mutex<f32>,
+    /// While set, time stands still and only requested steps are run.
+    frozen: AtomicBool,
+    pending_steps: AtomicU32,
+    /// Changes to make before the next tick.
+    commands: Mutex<Vec<GameCommand>>,
+    stop: AtomicBool,
+    snapshots: TripleBuffer<GameSnapshot>,
+}
+
+/// The game, its clock and what it needs to publish its ticks.
+struct Ticker {
+    game: Game,
+    events: Sender<GameEvent>,
+    back: GameSnapshot,
+    time_scale: TimeScale,
+    last_advance: Instant,
+    /// Scaled time not yet consumed by a tick.
+    accumulator: Duration,
+}
+
+impl Ticker {
+    fn new(game: Game, events: Sender<GameEvent>, back: GameSnapshot, time_scale: f32) -> Self {
+        Ticker {
+            game,
+            events,
+            back,
+            time_scale: TimeScale::new(time_scale),
+            last_advance: Instant::now(),
+            accumulator: Duration::ZERO,
+        }
+    }
+
+    /// Runs as many ticks as the scaled time since the last call allows.
+    /// Returns false once the game is over.
+    fn advance(&mut self, shared: &Shared) -> bool {
+        let now = Instant::now();
+        let elapsed = now - self.last_advance;
+        self.last_advance = now;
+        self.time_scale.set_base(*shared.time_scale.lock().unwrap());
+        let commands = std::mem::take(&mut *shared.commands.lock().unwrap());
+        if !commands.is_empty() {
+            for command in commands {
+                command(&mut self.game);
+            }
+            self.back.capture(&self.game);
+            shared.snapshots.publish(&mut self.back);
+        }
+        if shared.frozen.load(Ordering::Relaxed) {
+            self.accumulator = Duration::ZERO;
+            for _ in 0..shared.pending_steps.swap(0, Ordering::Relaxed) {
+                self.time_scale.advance(TICK_DURATION);
+                if !self.step(shared) {
+                    return false;
+                }
+            }
+            return true;
+        }
+        self.accumulator += elapsed.mul_f32(self.time_scale.factor());
+        self.time_scale.advance(elapsed);
+
+        let mut ticks = 0;
+        while self.accumulator >= TICK_DURATION {
+            self.accumulator -= TICK_DURATION;
+            if !self.step(shared) {
+                return false;
+            }
+            ticks += 1;
+            if ticks == MAX_CATCH_UP_TICKS {
+                self.accumulator = Duration::ZERO;
+            }
+        }
+        true
+    }
+
+    /// How long until the next tick is due at the current speed.
+    fn until_next_tick(&self) -> Duration {
+        let factor = self.time_scale.factor();
+        if factor <= 0.0 {
+            return TICK_DURATION;
+        }
+        TICK_DURATION.saturating_sub(self.accumulator).div_f32(factor).min(TICK_DURATION)
+    }
+
+    /// Advances one tick and publishes the result. Returns false once the
+    /// level is completed, or the player died and the death has played out.
+    fn step(&mut self, shared: &Shared) -> bool {
+        if self.game.has_pending_cutscene() {
+            return false;
+        }
+        self.game.tuning = *shared.tuning.lock().unwrap();
+        let inputs = *shared.inputs.lock().unwrap();
+        self.game.tick(inputs);
+        let animation = self.game.tuning.animation;
+        for event in self.game.drain_events() {
+            match event {
+                // The slow motion the player dies in before the game ends.
+                GameEvent::PlayerDied => {
+                    self.time_scale.apply_effect(animation.death_slow_motion, Duration::from_millis(animation.death_slow_motion_ms))
+                }
+                // The freeze when a tile is destroyed, to make the hit land.
+                GameEvent::TileDestroyed { .. } => { self.time_scale.apply_effect(0.0, Duration::from_millis(animation.hit_stop_ms)) }
+                _ => {}
+            }
+            let _ = self.events.send(event);
+        }
+        self.back.capture(&self.game);
+        shared.snapshots.publish(&mut self.back);
+        if self.game.is_level_complete() || self.game.has_pending_cutscene() {
+            return false;
+        }
+        !self.game.is_player_dead() || self.time_scale.has_effect()
+    }
+}
+
+enum Runner {
+    Thread(JoinHandle<Game>),
+    /// Without threads (in the browser) the game is ticked from [`Simulation::update`].
+    Inline { ticker: Box<Ticker>, running: bool },
+}
+
+pub struct Simulation {
+    shared: Arc<Shared>,
+    events: Receiver<GameEvent>,
+    front: GameSnapshot,
+    runner: Option<Runner>,
+}
+
+impl Simulation {
+    /// Moves `game` onto a new thread that ticks it until the player dies,
+    /// the level is completed or [`Simulation::stop`] is called.
+    /// `time_scale` is clamped to [`MIN_TIME_SCALE`]..=[`MAX_TIME_SCALE`].
+    pub fn start(game: Game, time_scale: f32) -> Self {
+        let snapshot = GameSnapshot::new(&game);
+        let shared = Arc::new(Shared {
+            inputs: Mutex::new(Inputs::default()),
+            tuning: Mutex::new(game.tuning),
+            time_scale: Mutex::new(time_scale),
+            frozen: AtomicBool::new(false),
+            pending_steps: AtomicU32::new(0),
+            commands: Mutex::new(Vec::new()),
+            stop: AtomicBool::new(false),
+            snapshots: TripleBuffer::new(snapshot.clone()),
+        });
+        let (sender, events) = mpsc::channel();
+        let ticker = Ticker::new(game, sender, snapshot.clone(), time_scale);
+        let runner = if cfg!(target_os = "emscripten") {
+            Runner::Inline { ticker: Box::new(ticker), running: true }
+        } else {
+            Runner::Thread(Simulation::spawn(ticker, shared.clone()))
+        };
+        Simulation {
+            shared,
+            events,
+            front: snapshot,
+            runner: Some(runner),
+        }
+    }
+
+    fn spawn(mut ticker: Ticker, shared: Arc<Shared>) -> JoinHandle<Game> {
+        std::thread::Builder::new()
+            .name("simulation".to_string())
+            .spawn(move || {
+                while !shared.stop.load(Ordering::Relaxed) && ticker.advance(&shared) {
+                    std::thread::sleep(ticker.until_next_tick());
+                }
+                ticker.game
+            })
+            .expect("failed to spawn the simulation thread")
+    }
+
+    /// Controls applied from the next tick on, until replaced.
+    pub fn set_inputs(&self, inputs: Inputs) {
+        *self.shared.inputs.lock().unwrap() = inputs;
+    }
+
+    pub fn set_tuning(&self, tuning: Tuning) {
+        *self.shared.tuning.lock().unwrap() = tuning;
+    }
+
+    /// Base speed of game time; gameplay effects scale it further.
+    pub fn set_time_scale(&self, time_scale: f32) {
+        *self.shared.time_scale.lock().unwrap() = time_scale;
+    }
+
+    /// Queues a change to the game, made before its next tick.
+    pub fn run_on_game(&self, command: GameCommand) {
+        self.shared.commands.lock().unwrap().push(command);
+    }
+
+    /// Freezes or resumes time. While frozen the game only moves on
+    /// [`Simulation::step_once`].
+    pub fn set_frozen(&self, frozen: bool) {
+        self.shared.frozen.store(frozen, Ordering::Relaxed);
+        if !frozen {
+            self.shared.pending_steps.store(0, Ordering::Relaxed);
+        }
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.shared.frozen.load(Ordering::Relaxed)
+    }
+
+    /// Runs exactly one tick while frozen.
+    pub fn step_once(&self) {
+        if self.is_frozen() {
+            self.shared.pending_steps.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Picks up the latest snapshot, if a tick finished since the last call.
+    pub fn update(&mut self) -> bool {
+        if let Some(Runner::Inline { ticker, running }) = &mut self.runner {
+            if *running {
+                *running = ticker.advance(&self.shared);
+            }
+        }
+        self.shared.snapshots.fetch(&mut self.front)
+    }
+
+    pub fn snapshot(&self) -> &GameSnapshot {
+        &self.front
+    }
+
+    /// Events raised by the game since they were last taken.
+    pub fn events(&self) -> TryIter<'_, GameEvent> {
+        self.events.try_iter()
+    }
+
+    /// Whether the game ended on its own and is waiting to be collected.
+    pub fn is_finished(&self) -> bool {
+        match &self.runner {
+            Some(Runner::Thread(thread)) => { thread.is_finished() }
+            Some(Runner::Inline { running, .. }) => { !running }
+            None => { true }
+        }
+    }
+
+    /// Stops ticking and hands the game back.
+    pub fn stop(mut self) -> Game {
+        self.shared.stop.store(true, Ordering::Relaxed);
+        match self.runner.take().expect("simulation already stopped") {
+            Runner::Thread(thread) => { thread.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic)) }
+            Runner::Inline { ticker, .. } => { ticker.game }
+        }
+    }
+}
+
+impl Drop for Simulation {
+    fn drop(&mut self) {
+        self.shared.stop.store(true, Ordering::Relaxed);
+        if let Some(Runner::Thread(thread)) = self.runner.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_scale_clamps_and_expires() {
+        let mut scale = TimeScale::new(10.0);
+        assert_eq!(scale.factor(), MAX_TIME_SCALE);
+        scale.set_base(1.0);
+        scale.apply_effect(0.5, Duration::from_millis(100));
+        assert_eq!(scale.factor(), 0.5);
+        scale.advance(Duration::from_millis(60));
+        assert!(scale.has_effect());
+        scale.advance(Duration::from_millis(40));
+        assert!(!scale.has_effect());
+        assert_eq!(scale.factor(), 1.0);
+        scale.set_base(0.0);
+        assert_eq!(scale.factor(), MIN_TIME_SCALE);
+    }
+
+    #[test]
+    fn triple_buffer_hands_over_the_latest() {
+        let buffer = TripleBuffer::new(0);
+        let mut front = 0;
+        assert!(!buffer.fetch(&mut front));
+        let mut back = 1;
+        buffer.publish(&mut back);
+        back = 2;
+        buffer.publish(&mut back);
+        assert!(buffer.fetch(&mut front));
+        assert_eq!(front, 2);
+        assert!(!buffer.fetch(&mut front));
+        assert_eq!(front, 2);
+    }
+}
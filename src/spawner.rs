@@ -0,0 +1,65 @@
+//! Objects that keep putting enemies into the level, one every so often,
+//! until a number of them are about. How each spawner behaves comes from
+//! the level's info file, see [`crate::level::LevelInfo`].
+
+use hecs::{Entity, World};
+use serde::{Deserialize, Serialize};
+
+use crate::components::{Body, Position, Sprite};
+use crate::enemy::{Behavior, Flight};
+use crate::level::Level;
+
+pub const SPAWNER_CODE: char = 'S';
+pub const SPAWNER_SIZE: f32 = 0.9;
+pub const SPAWNER_COLOR: u32 = 0x4B0082;
+
+/// How a spawner behaves, set per spawner in the level info.
+#[derive(Copy, Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct SpawnerInfo {
+    /// Kind of enemy spawned.
+    pub enemy: Behavior,
+    /// Ticks between two enemies.
+    pub interval: u32,
+    /// Most enemies from this spawner about at once.
+    pub cap: u32,
+}
+
+impl Default for SpawnerInfo {
+    fn default() -> Self {
+        SpawnerInfo { enemy: Behavior::Patrol, interval: 180, cap: 3 }
+    }
+}
+
+/// Marks an entity that spawns enemies. It has no velocity, so physics
+/// leaves it where it was placed.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Spawner {
+    /// Place of the spawner among the level's spawners, in map order,
+    /// which picks its info.
+    pub index: usize,
+    pub tile: (usize, usize),
+    /// Ticks counted toward the next enemy.
+    pub elapsed: u32,
+    /// Path given to flying enemies it spawns.
+    pub flight: Flight,
+}
+
+/// Marks an enemy put into the level by spawner number `spawner`.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Spawned {
+    pub spawner: usize,
+}
+
+/// Spawns spawner number `index` in the tile at `tile`.
+pub fn spawn(world: &mut World, level: &Level, tile: (usize, usize), index: usize) -> Entity {
+    world.spawn((
+        Spawner { index, tile, elapsed: 0, flight: Flight::new(level, tile) },
+        Position {
+            x: tile.0 as f32 + (1.0 - SPAWNER_SIZE) / 2.0,
+            y: tile.1 as f32 + 1.0 - SPAWNER_SIZE,
+        },
+        Body::new(SPAWNER_SIZE, SPAWNER_SIZE),
+        Sprite { color: SPAWNER_COLOR },
+    ))
+}
@@ -0,0 +1,837 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use log::{info, warn};
+
+use crate::achievement::UNLOCKED;
+use crate::achievements::AchievementsState;
+use crate::audio::{Intensity, Listener, Music};
+use crate::bindings::{Action, Press};
+use crate::context::Context;
+use crate::cutscene::CutsceneState;
+use crate::error::Result;
+use crate::game::{Game, GameCommand};
+use crate::level_select::LevelSelectState;
+use crate::menu::Menu;
+use crate::mouse::{Mouse, MouseAction};
+use crate::npc::Npc;
+use crate::paths::user_file;
+use crate::records::{self, RECORDS};
+use crate::render;
+use crate::renderer::{Color, Renderer};
+use crate::replay::{Playback, Replay, DEMO_REPLAY};
+use crate::save::{SaveGame, QUICK_SAVE};
+use crate::settings_menu::SettingsState;
+use crate::shop::Offer;
+use crate::simulation::Simulation;
+use crate::text_entry::TextEntry;
+
+/// One screen of the game. Input and ticks consume the current state and
+/// return the state to continue with, which may be the same one.
+pub trait State {
+    /// Short name used when logging transitions.
+    fn name(&self) -> &'static str;
+    fn handle_press(self: Box<Self>, context: &mut Context, press: &Press) -> Result<Box<dyn State>>;
+    fn handle_mouse(self: Box<Self>, context: &mut Context, mouse: &Mouse) -> Result<Box<dyn State>>;
+    fn tick(self: Box<Self>, context: &mut Context) -> Result<Box<dyn State>>;
+    fn render(&self, renderer: &mut dyn Renderer) -> Result<()>;
+
+    /// Music played while this state is on.
+    fn music(&self) -> Music {
+        Music::Off
+    }
+
+    /// Applies `command` to the game shown by this state, if there is one.
+    fn run_on_game(&mut self, _command: GameCommand) -> bool {
+        false
+    }
+
+    /// Field receiving typed text while this state asks for some. Text
+    /// input is on whenever a state returns one.
+    fn text_entry(&mut self) -> Option<&mut TextEntry> {
+        None
+    }
+}
+
+/// The music of the level `game` plays.
+pub fn level_music(game: &Game) -> Music {
+    game.music_track().map_or(Music::Off, |track| Music::Playing(track.to_string()))
+}
+
+/// Items of the title screen, in order.
+const MENU_ITEMS: [&str; 4] = ["PLAY", "LEVEL SELECT", "SETTINGS", "QUIT"];
+
+/// Title screen, shown first: starts the campaign, picks a level, opens
+/// the settings or quits. A for the achievements.
+pub struct MenuState {
+    menu: Menu,
+    /// Last time anything was pressed, to start the demo after a while.
+    idle_since: Instant,
+    /// Whether the line under the title was worked out, on the first tick
+    /// since it reads every level of the campaign.
+    described: bool,
+}
+
+impl MenuState {
+    pub fn new() -> Self {
+        MenuState { menu: Menu::new("PLATFORMER", MENU_ITEMS), idle_since: Instant::now(), described: false }
+    }
+
+    /// Switches to the demo replay, or keeps waiting if it can't be played.
+    fn start_demo(mut self: Box<Self>, context: &Context) -> Result<Box<dyn State>> {
+        let demo = Replay::load(&context.mods.resolve(DEMO_REPLAY))
+            .and_then(|replay| ReplayState::demo(context, replay));
+        match demo {
+            Ok(demo) => { Ok(Box::new(demo)) }
+            Err(error) => {
+                warn!("Unable to play the demo: {}", error);
+                self.idle_since = Instant::now();
+                Ok(self)
+            }
+        }
+    }
+
+    fn pick(self: Box<Self>, context: &mut Context, item: usize) -> Result<Box<dyn State>> {
+        match item {
+            0 => {
+                context.start_campaign();
+                Ok(Box::new(PlayingState::new(context, context.load_game(context.level_path())?)))
+            }
+            1 => { Ok(Box::new(LevelSelectState::new(context))) }
+            2 => { Ok(Box::new(SettingsState::new(context, self))) }
+            _ => {
+                context.quit = true;
+                Ok(self)
+            }
+        }
+    }
+}
+
+impl Default for MenuState {
+    fn default() -> Self {
+        MenuState::new()
+    }
+}
+
+impl State for MenuState {
+    fn name(&self) -> &'static str {
+        "menu"
+    }
+
+    fn handle_press(mut self: Box<Self>, context: &mut Context, press: &Press) -> Result<Box<dyn State>> {
+        self.idle_since = Instant::now();
+        if let Some(item) = self.menu.handle_press(press) {
+            return self.pick(context, item);
+        }
+        if press.is(Action::Achievements) {
+            return Ok(Box::new(AchievementsState::new(context)));
+        }
+        Ok(self)
+    }
+
+    fn handle_mouse(mut self: Box<Self>, context: &mut Context, mouse: &Mouse) -> Result<Box<dyn State>> {
+        self.idle_since = Instant::now();
+        if let Some(item) = self.menu.handle_mouse(mouse) {
+            return self.pick(context, item);
+        }
+        Ok(self)
+    }
+
+    fn tick(mut self: Box<Self>, context: &mut Context) -> Result<Box<dyn State>> {
+        if !self.described {
+            let key = context.settings.keys.key(Action::Achievements).name().to_uppercase();
+            let mut subtitle = format!("{} - {} FOR ACHIEVEMENTS", context.settings.game.difficulty.label(), key);
+            if let Some((found, total)) = context.campaign_stars() {
+                subtitle.push_str(&format!(" - {}% COMPLETE", records::percent(found, total)));
+            }
+            self.menu.subtitle = subtitle;
+            self.described = true;
+        }
+        let delay = context.settings.game.attract_delay;
+        if delay > 0 && self.idle_since.elapsed() >= Duration::from_secs(delay as u64) {
+            return self.start_demo(context);
+        }
+        Ok(self)
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer) -> Result<()> {
+        self.menu.render(renderer)
+    }
+}
+
+/// Gameplay runs on a [`Simulation`] thread while this state is active;
+/// the other states hold the game directly.
+pub struct PlayingState {
+    simulation: Simulation,
+}
+
+impl PlayingState {
+    pub fn new(context: &Context, game: Game) -> Self {
+        let simulation = Simulation::start(game, context.time_scale);
+        simulation.set_frozen(context.frame_step);
+        PlayingState { simulation }
+    }
+}
+
+impl State for PlayingState {
+    fn name(&self) -> &'static str {
+        "playing"
+    }
+
+    fn music(&self) -> Music {
+        self.simulation.snapshot().music.clone().map_or(Music::Off, Music::Playing)
+    }
+
+    fn run_on_game(&mut self, command: GameCommand) -> bool {
+        self.simulation.run_on_game(command);
+        true
+    }
+
+    fn handle_press(mut self: Box<Self>, context: &mut Context, press: &Press) -> Result<Box<dyn State>> {
+        if press.is(Action::Pause) {
+            return Ok(Box::new(PausedState::new(self.simulation.stop())));
+        }
+        if press.is(Action::FrameStep) {
+            context.frame_step = !context.frame_step;
+            self.simulation.set_frozen(context.frame_step);
+        } else if press.is(Action::Step) {
+            self.simulation.step_once();
+        } else if press.is(Action::QuickSave) {
+            let game = self.simulation.stop();
+            let saved = game.snapshot().save(&user_file(QUICK_SAVE));
+            *self = PlayingState::new(context, game);
+            saved?;
+        } else if press.is(Action::Talk) || (press.is(Action::Up) && press.actions.len() == 1) {
+            // Up only talks when it isn't also bound to something, such as
+            // the second player's jump.
+            if self.simulation.snapshot().talk_prompt.is_some() {
+                let game = self.simulation.stop();
+                let index = game.npc_in_reach().and_then(|npc| game.world.get::<&Npc>(npc).map(|npc| npc.index).ok());
+                if let Some(index) = index {
+                    let pages = game.npc_pages(index).to_vec();
+                    let wares = game.npc_wares(index).to_vec();
+                    return Ok(DialogState::open(context, game, pages, wares));
+                }
+                let Some(sign) = game.sign_in_reach() else { return Ok(Box::new(PlayingState::new(context, game))) };
+                let pages = vec![game.sign_text(sign).to_string()];
+                return Ok(DialogState::open(context, game, pages, Vec::new()));
+            }
+        } else if press.is(Action::QuickLoad) && Path::new(&user_file(QUICK_SAVE)).exists() {
+            let game = Game::restore(&SaveGame::load(&user_file(QUICK_SAVE))?, &context.mods)?;
+            *self = PlayingState::new(context, game);
+        }
+        Ok(self)
+    }
+
+    fn handle_mouse(self: Box<Self>, _context: &mut Context, _mouse: &Mouse) -> Result<Box<dyn State>> {
+        Ok(self)
+    }
+
+    fn tick(mut self: Box<Self>, context: &mut Context) -> Result<Box<dyn State>> {
+        if context.disconnected.is_some() {
+            return Ok(Box::new(PausedState::new(self.simulation.stop())));
+        }
+        self.simulation.set_inputs(context.inputs());
+        self.simulation.set_tuning(context.tuning.tuning);
+        self.simulation.set_time_scale(context.time_scale);
+        self.simulation.set_frozen(context.frame_step);
+        self.simulation.update();
+        let snapshot = self.simulation.snapshot();
+        context.listen(snapshot.listener, snapshot.intensity);
+        context.publish_played(self.simulation.events());
+        if !self.simulation.is_finished() {
+            return Ok(self);
+        }
+        let mut game = self.simulation.stop();
+        if let Some(cutscene) = game.take_cutscene() {
+            return Ok(Box::new(CutsceneState::new(game, &cutscene)));
+        }
+        context.save_replay(&game)?;
+        if game.is_level_complete() {
+            context.achievements.complete_level(&game);
+        }
+        if let Err(error) = context.achievements.save(&user_file(UNLOCKED)) {
+            warn!("Could not save achievements: {}", error);
+        }
+        if game.is_level_complete() {
+            return Ok(Box::new(LevelCompleteState::new(context, game)));
+        }
+        game.lives = game.lives.saturating_sub(1);
+        game.deaths += 1;
+        if game.lives == 0 {
+            return Ok(Box::new(GameOverState::new(game)));
+        }
+        Ok(Box::new(DeadState { game }))
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer) -> Result<()> {
+        let snapshot = self.simulation.snapshot();
+        render::render_snapshot(snapshot, renderer)?;
+        if self.simulation.is_frozen() {
+            renderer.draw_text(&format!("FRAME STEP - TICK {}", snapshot.ticks), 4, 4, 2, Color::YELLOW)?;
+        }
+        Ok(())
+    }
+}
+
+/// Items of the pause menu, in order.
+const PAUSE_ITEMS: [&str; 4] = ["RESUME", "RESTART LEVEL", "SETTINGS", "QUIT TO MENU"];
+
+/// The game stands still under a menu that resumes it, starts the level
+/// over, opens the settings or leaves for the title screen.
+pub struct PausedState {
+    game: Game,
+    menu: Menu,
+}
+
+impl PausedState {
+    pub fn new(game: Game) -> Self {
+        let mut menu = Menu::new("PAUSED", PAUSE_ITEMS);
+        menu.subtitle = "P TO RESUME".to_string();
+        menu.overlay = true;
+        PausedState { game, menu }
+    }
+
+    fn pick(self: Box<Self>, context: &mut Context, item: usize) -> Result<Box<dyn State>> {
+        match item {
+            0 => { Ok(Box::new(PlayingState::new(context, self.game))) }
+            1 => {
+                info!("Restarting '{}'", self.game.level_path);
+                Ok(Box::new(PlayingState::new(context, context.load_game(&self.game.level_path)?)))
+            }
+            2 => { Ok(Box::new(SettingsState::new(context, self))) }
+            _ => { Ok(Box::new(MenuState::new())) }
+        }
+    }
+}
+
+impl State for PausedState {
+    fn name(&self) -> &'static str {
+        "paused"
+    }
+
+    fn music(&self) -> Music {
+        level_music(&self.game).ducked()
+    }
+
+    fn run_on_game(&mut self, command: GameCommand) -> bool {
+        command(&mut self.game);
+        true
+    }
+
+    fn handle_press(mut self: Box<Self>, context: &mut Context, press: &Press) -> Result<Box<dyn State>> {
+        if press.is(Action::Pause) || press.is(Action::Back) {
+            return Ok(Box::new(PlayingState::new(context, self.game)));
+        }
+        match self.menu.handle_press(press) {
+            Some(item) => { self.pick(context, item) }
+            None => { Ok(self) }
+        }
+    }
+
+    fn handle_mouse(mut self: Box<Self>, context: &mut Context, mouse: &Mouse) -> Result<Box<dyn State>> {
+        match self.menu.handle_mouse(mouse) {
+            Some(item) => { self.pick(context, item) }
+            None => { Ok(self) }
+        }
+    }
+
+    fn tick(self: Box<Self>, _context: &mut Context) -> Result<Box<dyn State>> {
+        Ok(self)
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer) -> Result<()> {
+        render::render_game(&self.game, renderer)?;
+        self.menu.render(renderer)?;
+        render::render_objectives(renderer, &self.game.info.objectives, &self.game.objectives)
+    }
+}
+
+/// Reading what an NPC says, a page at a time. The game waits until the
+/// last page is dismissed, or until the NPC's shop is left if it has one.
+pub struct DialogState {
+    game: Game,
+    pages: Vec<String>,
+    page: usize,
+    wares: Vec<Offer>,
+}
+
+impl DialogState {
+    /// Starts talking to an NPC, going straight to its shop when it has
+    /// nothing to say.
+    pub fn open(context: &Context, game: Game, pages: Vec<String>, wares: Vec<Offer>) -> Box<dyn State> {
+        if !pages.is_empty() {
+            return Box::new(DialogState { game, pages, page: 0, wares });
+        }
+        if !wares.is_empty() {
+            return Box::new(ShopState { game, wares, selected: 0 });
+        }
+        Box::new(PlayingState::new(context, game))
+    }
+}
+
+impl State for DialogState {
+    fn name(&self) -> &'static str {
+        "dialog"
+    }
+
+    fn music(&self) -> Music {
+        level_music(&self.game)
+    }
+
+    fn run_on_game(&mut self, command: GameCommand) -> bool {
+        command(&mut self.game);
+        true
+    }
+
+    fn handle_press(mut self: Box<Self>, context: &mut Context, press: &Press) -> Result<Box<dyn State>> {
+        if press.is(Action::Confirm) || press.is(Action::Talk) {
+            self.page += 1;
+            if self.page >= self.pages.len() {
+                return Ok(DialogState::open(context, self.game, Vec::new(), self.wares));
+            }
+        } else if press.is(Action::Back) {
+            return Ok(Box::new(PlayingState::new(context, self.game)));
+        }
+        Ok(self)
+    }
+
+    /// Clicking anywhere does the same as confirming.
+    fn handle_mouse(self: Box<Self>, context: &mut Context, mouse: &Mouse) -> Result<Box<dyn State>> {
+        if mouse.is_click() {
+            return self.handle_press(context, &Press::action(Action::Confirm));
+        }
+        Ok(self)
+    }
+
+    fn tick(self: Box<Self>, _context: &mut Context) -> Result<Box<dyn State>> {
+        Ok(self)
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer) -> Result<()> {
+        render::render_game(&self.game, renderer)?;
+        let hint = format!("{}/{} - PRESS ENTER", self.page + 1, self.pages.len());
+        render::render_dialog(renderer, &self.pages[self.page], &hint)
+    }
+}
+
+/// Buying from an NPC with the coins carried. The game waits until the
+/// shop is left.
+pub struct ShopState {
+    game: Game,
+    wares: Vec<Offer>,
+    selected: usize,
+}
+
+impl ShopState {
+    /// Every ware with its price, then the way out.
+    fn options(&self) -> Vec<String> {
+        let mut options: Vec<String> = self.wares.iter()
+            .map(|offer| format!("{} - {}", offer.ware.name(), offer.price))
+            .collect();
+        options.push("LEAVE".to_string());
+        options
+    }
+
+    fn choose(mut self: Box<Self>, context: &mut Context) -> Result<Box<dyn State>> {
+        match self.wares.get(self.selected).copied() {
+            Some(offer) => {
+                if !self.game.buy(&offer) {
+                    info!("Could not buy {} with {} coins", offer.ware.name(), self.game.money);
+                }
+                context.publish_played(self.game.drain_events());
+                Ok(self)
+            }
+            None => { Ok(Box::new(PlayingState::new(context, self.game))) }
+        }
+    }
+}
+
+impl State for ShopState {
+    fn name(&self) -> &'static str {
+        "shop"
+    }
+
+    fn music(&self) -> Music {
+        level_music(&self.game)
+    }
+
+    fn run_on_game(&mut self, command: GameCommand) -> bool {
+        command(&mut self.game);
+        true
+    }
+
+    fn handle_press(mut self: Box<Self>, context: &mut Context, press: &Press) -> Result<Box<dyn State>> {
+        let options = self.wares.len() + 1;
+        if press.is(Action::Up) {
+            self.selected = (self.selected + options - 1) % options;
+        } else if press.is(Action::Down) {
+            self.selected = (self.selected + 1) % options;
+        } else if press.is(Action::Confirm) {
+            return self.choose(context);
+        } else if press.is(Action::Back) {
+            return Ok(Box::new(PlayingState::new(context, self.game)));
+        }
+        Ok(self)
+    }
+
+    /// Clicking an option picks it.
+    fn handle_mouse(mut self: Box<Self>, context: &mut Context, mouse: &Mouse) -> Result<Box<dyn State>> {
+        if !mouse.is_click() {
+            return Ok(self);
+        }
+        if let Some(option) = render::choice_at(mouse.screen, self.wares.len() + 1, mouse.y) {
+            self.selected = option;
+            return self.choose(context);
+        }
+        Ok(self)
+    }
+
+    fn tick(self: Box<Self>, _context: &mut Context) -> Result<Box<dyn State>> {
+        Ok(self)
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer) -> Result<()> {
+        render::render_game(&self.game, renderer)?;
+        let options = self.options();
+        let options: Vec<&str> = options.iter().map(String::as_str).collect();
+        let title = format!("SHOP - {} COINS", self.game.money);
+        render::render_choice(renderer, &title, &options, self.selected)
+    }
+}
+
+/// A life was lost; the players start over from the last checkpoint
+/// reached, or the start of the level.
+pub struct DeadState {
+    game: Game,
+}
+
+impl State for DeadState {
+    fn name(&self) -> &'static str {
+        "dead"
+    }
+
+    fn music(&self) -> Music {
+        level_music(&self.game)
+    }
+
+    fn run_on_game(&mut self, command: GameCommand) -> bool {
+        command(&mut self.game);
+        true
+    }
+
+    fn handle_press(mut self: Box<Self>, context: &mut Context, press: &Press) -> Result<Box<dyn State>> {
+        if press.is(Action::Confirm) {
+            self.game.respawn();
+            return Ok(Box::new(PlayingState::new(context, self.game)));
+        }
+        Ok(self)
+    }
+
+    /// Clicking anywhere does the same as confirming.
+    fn handle_mouse(self: Box<Self>, context: &mut Context, mouse: &Mouse) -> Result<Box<dyn State>> {
+        if mouse.is_click() {
+            return self.handle_press(context, &Press::action(Action::Confirm));
+        }
+        Ok(self)
+    }
+
+    fn tick(self: Box<Self>, _context: &mut Context) -> Result<Box<dyn State>> {
+        Ok(self)
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer) -> Result<()> {
+        render::render_game(&self.game, renderer)?;
+        let hint = match self.game.lives {
+            1 => { "LAST LIFE - PRESS ENTER TO RETRY".to_string() }
+            lives => { format!("{} LIVES LEFT - PRESS ENTER TO RETRY", lives) }
+        };
+        render::render_banner(renderer, "LIFE LOST", &hint)
+    }
+}
+
+/// Every life is lost: the game stands still under how the level went
+/// and a menu that tries again with fresh lives from the last checkpoint,
+/// starts the level over or leaves for the title screen.
+pub struct GameOverState {
+    game: Game,
+    menu: Menu,
+}
+
+impl GameOverState {
+    pub fn new(game: Game) -> Self {
+        let retry = if game.checkpoint.is_some() { "RETRY FROM CHECKPOINT" } else { "RETRY FROM START" };
+        let mut menu = Menu::new("GAME OVER", [retry, "RESTART LEVEL", "MAIN MENU"]);
+        let mut stats = vec![
+            format!("DEATHS {}", game.deaths),
+            format!("TIME {}", records::format_time(game.ticks)),
+            format!("{} POINTS", game.score),
+        ];
+        if game.total_coins > 0 {
+            stats.push(format!("{}/{} COINS", game.coins, game.total_coins));
+        }
+        menu.subtitle = stats.join(" - ");
+        menu.overlay = true;
+        GameOverState { game, menu }
+    }
+
+    fn pick(mut self: Box<Self>, context: &mut Context, item: usize) -> Result<Box<dyn State>> {
+        match item {
+            0 => {
+                self.game.lives = context.settings.game.lives.max(1);
+                self.game.respawn();
+                Ok(Box::new(PlayingState::new(context, self.game)))
+            }
+            1 => {
+                info!("Restarting '{}'", self.game.level_path);
+                context.lives = context.settings.game.lives.max(1);
+                Ok(Box::new(PlayingState::new(context, context.load_game(&self.game.level_path)?)))
+            }
+            _ => { Ok(Box::new(MenuState::new())) }
+        }
+    }
+}
+
+impl State for GameOverState {
+    fn name(&self) -> &'static str {
+        "game over"
+    }
+
+    fn run_on_game(&mut self, command: GameCommand) -> bool {
+        command(&mut self.game);
+        true
+    }
+
+    fn handle_press(mut self: Box<Self>, context: &mut Context, press: &Press) -> Result<Box<dyn State>> {
+        match self.menu.handle_press(press) {
+            Some(item) => { self.pick(context, item) }
+            None => { Ok(self) }
+        }
+    }
+
+    fn handle_mouse(mut self: Box<Self>, context: &mut Context, mouse: &Mouse) -> Result<Box<dyn State>> {
+        match self.menu.handle_mouse(mouse) {
+            Some(item) => { self.pick(context, item) }
+            None => { Ok(self) }
+        }
+    }
+
+    fn tick(self: Box<Self>, _context: &mut Context) -> Result<Box<dyn State>> {
+        Ok(self)
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer) -> Result<()> {
+        render::render_game(&self.game, renderer)?;
+        self.menu.render(renderer)
+    }
+}
+
+/// The level is done: a card sums up the run against the par time and
+/// the records, which it is submitted to, with a button going on to the
+/// next level of the campaign, or back to the title screen after the last.
+pub struct LevelCompleteState {
+    game: Game,
+    /// Points earned in this level alone.
+    points: u32,
+    /// Best time for the level before this run, if it was completed before.
+    previous_best: Option<u64>,
+    /// Most points earned in the level before this run.
+    previous_points: Option<u32>,
+    /// Hidden stars of the level found in this run or one before.
+    stars_found: usize,
+    /// Hidden stars found across the campaign out of those in it.
+    campaign_stars: Option<(usize, usize)>,
+    /// Level the button goes on to.
+    next: Option<String>,
+}
+
+impl LevelCompleteState {
+    pub fn new(context: &mut Context, game: Game) -> Self {
+        let points = game.score.saturating_sub(context.score);
+        let previous_best = context.records.submit(&game.level_path, game.ticks);
+        let previous_points = context.records.submit_points(&game.level_path, points);
+        context.records.submit_stars(&game.level_path, &game.hidden_stars);
+        if let Err(error) = context.records.save(&user_file(RECORDS)) {
+            warn!("Could not save best times: {}", error);
+        }
+        LevelCompleteState {
+            points,
+            previous_best,
+            previous_points,
+            stars_found: context.records.stars_found(&game.level_path),
+            campaign_stars: context.campaign_stars(),
+            next: context.next_level(&game.level_path),
+            game,
+        }
+    }
+
+    fn button(&self) -> &'static str {
+        if self.next.is_some() { "CONTINUE" } else { "FINISH" }
+    }
+
+    /// Label, value and color of every line of the card.
+    fn rows(&self) -> Vec<(&'static str, String, Color)> {
+        let ticks = self.game.ticks;
+        let time = records::format_time(ticks);
+        let mut rows = vec![match self.previous_best {
+            Some(best) if ticks < best => { ("TIME", format!("{} NEW BEST", time), Color::YELLOW) }
+            Some(best) => { ("TIME", format!("{} {}", time, records::format_delta(ticks, best)), Color::WHITE) }
+            None => { ("TIME", time, Color::WHITE) }
+        }];
+        if let Some(par) = self.game.info.par.filter(|par| *par > 0) {
+            let par = par as u64 * 60;
+            let color = if ticks <= par { Color::GREEN } else { Color::RED };
+            rows.push(("PAR", format!("{} {}", records::format_time(par), records::format_delta(ticks, par)), color));
+        }
+        if self.game.total_coins > 0 {
+            rows.push(("COINS", format!("{}/{}", self.game.coins, self.game.total_coins), Color::RGB(255, 215, 0)));
+        }
+        let total_stars = self.game.total_hidden_stars as usize;
+        if total_stars > 0 {
+            let found = self.stars_found.min(total_stars);
+            let stars = format!("{}/{} {}%", found, total_stars, records::percent(found, total_stars));
+            rows.push(("STARS", stars, Color::RGB(255, 250, 205)));
+        }
+        if let Some((found, total)) = self.campaign_stars {
+            rows.push(("CAMPAIGN", format!("{}%", records::percent(found, total)), Color::RGB(255, 250, 205)));
+        }
+        rows.push(("DEATHS", self.game.deaths.to_string(), Color::WHITE));
+        rows.push(match self.previous_points {
+            Some(best) if self.points <= best => { ("POINTS", format!("{} BEST {}", self.points, best), Color::WHITE) }
+            _ => { ("POINTS", format!("{} NEW BEST", self.points), Color::YELLOW) }
+        });
+        rows.push(("SCORE", self.game.score.to_string(), Color::WHITE));
+        rows
+    }
+
+    fn go_on(self: Box<Self>, context: &mut Context) -> Result<Box<dyn State>> {
+        context.assets.unload_unused();
+        context.score = self.game.score;
+        context.lives = self.game.lives;
+        context.money = self.game.money;
+        match &self.next {
+            Some(next) => { Ok(Box::new(PlayingState::new(context, context.load_game(next)?))) }
+            None => { Ok(Box::new(MenuState::new())) }
+        }
+    }
+}
+
+impl State for LevelCompleteState {
+    fn name(&self) -> &'static str {
+        "level complete"
+    }
+
+    fn run_on_game(&mut self, command: GameCommand) -> bool {
+        command(&mut self.game);
+        true
+    }
+
+    fn handle_press(self: Box<Self>, context: &mut Context, press: &Press) -> Result<Box<dyn State>> {
+        if press.is(Action::Confirm) {
+            return self.go_on(context);
+        }
+        Ok(self)
+    }
+
+    /// Clicking the button does the same as confirming.
+    fn handle_mouse(self: Box<Self>, context: &mut Context, mouse: &Mouse) -> Result<Box<dyn State>> {
+        let (_, button) = render::card_layout(mouse.screen, self.rows().len());
+        if mouse.is_click() && button.contains_point((mouse.x, mouse.y)) {
+            return self.go_on(context);
+        }
+        Ok(self)
+    }
+
+    fn tick(self: Box<Self>, _context: &mut Context) -> Result<Box<dyn State>> {
+        Ok(self)
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer) -> Result<()> {
+        render::render_game(&self.game, renderer)?;
+        render::render_card(renderer, "LEVEL COMPLETE", &self.rows(), self.button())
+    }
+}
+
+/// Plays a recorded replay back tick by tick, ignoring live input until it
+/// ends. A demo started from the title screen goes back to it on any input
+/// or once it ends.
+pub struct ReplayState {
+    game: Game,
+    playback: Playback,
+    demo: bool,
+}
+
+impl ReplayState {
+    pub fn new(context: &Context, replay: Replay) -> Result<Self> {
+        Ok(ReplayState {
+            game: context.load_replay(&replay)?,
+            playback: Playback::new(replay),
+            demo: false,
+        })
+    }
+
+    pub fn demo(context: &Context, replay: Replay) -> Result<Self> {
+        Ok(ReplayState {
+            demo: true,
+            ..ReplayState::new(context, replay)?
+        })
+    }
+
+    fn is_finished(&self) -> bool {
+        self.playback.is_over(&self.game)
+    }
+}
+
+impl State for ReplayState {
+    fn name(&self) -> &'static str {
+        "replay"
+    }
+
+    fn music(&self) -> Music {
+        level_music(&self.game)
+    }
+
+    fn handle_press(self: Box<Self>, _context: &mut Context, press: &Press) -> Result<Box<dyn State>> {
+        if self.demo || (self.is_finished() && press.is(Action::Confirm)) {
+            return Ok(Box::new(MenuState::new()));
+        }
+        Ok(self)
+    }
+
+    /// Clicking anywhere does the same as confirming.
+    fn handle_mouse(self: Box<Self>, context: &mut Context, mouse: &Mouse) -> Result<Box<dyn State>> {
+        if mouse.is_click() || (self.demo && matches!(mouse.action, MouseAction::Pressed(_))) {
+            return self.handle_press(context, &Press::action(Action::Confirm));
+        }
+        Ok(self)
+    }
+
+    fn tick(mut self: Box<Self>, context: &mut Context) -> Result<Box<dyn State>> {
+        if self.demo && self.is_finished() {
+            return Ok(Box::new(MenuState::new()));
+        }
+        if self.playback.step(&mut self.game) {
+            context.listen(Listener::of(&self.game), Intensity::of(&self.game));
+            context.events.publish(self.game.drain_events());
+        }
+        Ok(self)
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer) -> Result<()> {
+        render::render_game(&self.game, renderer)?;
+        if self.demo {
+            return renderer.draw_text("DEMO - PRESS ANY KEY", 4, 4, 2, Color::YELLOW);
+        }
+        if !self.is_finished() {
+            return Ok(());
+        }
+        let title = if self.game.is_level_complete() {
+            "LEVEL COMPLETE"
+        } else if self.game.is_player_dead() {
+            "GAME OVER"
+        } else {
+            "REPLAY FINISHED"
+        };
+        render::render_banner(renderer, title, "PRESS ENTER TO CONTINUE")
+    }
+}
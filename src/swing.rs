@@ -0,0 +1,57 @@
+//! Anchors players can swing from on a rope. Holding jump in the air near
+//! one grabs a rope tied to it, left and right push the swing along and
+//! letting go of jump lets go of the rope, keeping the speed of the swing
+//! until the player lands.
+
+use hecs::{Entity, World};
+use serde::{Deserialize, Serialize};
+
+use crate::components::{Body, Position, Sprite};
+
+pub const ANCHOR_CODE: char = 'A';
+pub const ANCHOR_SIZE: f32 = 0.3;
+pub const ANCHOR_COLOR: u32 = 0xC0A060;
+pub const ROPE_COLOR: u32 = 0xA08050;
+/// Farthest a player's center can be from an anchor to grab its rope, in
+/// tiles.
+pub const ROPE_REACH: f32 = 3.0;
+/// Shortest rope, in tiles.
+pub const MIN_ROPE: f32 = 1.0;
+
+/// Marks an anchor hanging in the middle of its tile. It has no velocity,
+/// so physics leaves it where it was placed.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Anchor;
+
+/// Marks a player hanging from the anchor at `anchor` on a rope `length`
+/// tiles long.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Swinging {
+    pub anchor: (f32, f32),
+    pub length: f32,
+}
+
+/// Marks a player who let go of a rope or jumped out of a cart and keeps
+/// the speed they had until they land or hit a wall.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Flung;
+
+/// Spawns an anchor in the middle of the tile at `tile`.
+pub fn spawn(world: &mut World, tile: (usize, usize)) -> Entity {
+    world.spawn((
+        Anchor,
+        Position {
+            x: tile.0 as f32 + (1.0 - ANCHOR_SIZE) / 2.0,
+            y: tile.1 as f32 + (1.0 - ANCHOR_SIZE) / 2.0,
+        },
+        Body::new(ANCHOR_SIZE, ANCHOR_SIZE),
+        Sprite { color: ANCHOR_COLOR },
+    ))
+}
+
+/// Every rope in use, from its anchor to the center of the player on it.
+pub fn ropes(world: &World) -> Vec<((f32, f32), (f32, f32))> {
+    world.query::<(&Position, &Body, &Swinging)>().iter()
+        .map(|(position, body, swinging)| (swinging.anchor, (position.x + body.width / 2.0, position.y + body.height / 2.0)))
+        .collect()
+}
@@ -0,0 +1,98 @@
+//! Buttons that hold the gates of a link open for a while, and plates that
+//! hold them open while something stands on them. Gates are the digits `1`
+//! to `9` in the map, the digit being their link; which link each button
+//! and plate works, and for how long buttons do, come from the level's
+//! info file, see [`crate::level::LevelInfo`].
+
+use hecs::{Entity, World};
+use serde::{Deserialize, Serialize};
+
+use crate::components::{Body, Position, Sprite};
+
+pub const BUTTON_CODE: char = 'B';
+pub const BUTTON_WIDTH: f32 = 0.8;
+pub const BUTTON_HEIGHT: f32 = 0.2;
+pub const BUTTON_COLOR: u32 = 0xE04040;
+pub const BUTTON_PRESSED_COLOR: u32 = 0x40E040;
+pub const PLATE_CODE: char = 'P';
+pub const PLATE_WIDTH: f32 = 0.9;
+pub const PLATE_HEIGHT: f32 = 0.1;
+pub const PLATE_COLOR: u32 = 0x909090;
+pub const PLATE_PRESSED_COLOR: u32 = 0xE0E040;
+
+/// What a button opens, set per button in the level info.
+#[derive(Copy, Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ButtonInfo {
+    /// Link of the gates it opens.
+    pub link: u32,
+    /// How long the gates stay open once no one is on the button.
+    pub seconds: f32,
+}
+
+impl Default for ButtonInfo {
+    fn default() -> Self {
+        ButtonInfo { link: 1, seconds: 5.0 }
+    }
+}
+
+/// Marks a button lying on the floor of its tile. It has no velocity, so
+/// physics leaves it where it was placed.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Button {
+    /// Place of the button among the level's buttons, in map order,
+    /// which picks its info.
+    pub index: usize,
+    pub pressed: bool,
+}
+
+/// Spawns button number `index` on the floor of the tile at `tile`.
+pub fn spawn(world: &mut World, tile: (usize, usize), index: usize) -> Entity {
+    world.spawn((
+        Button { index, pressed: false },
+        Position {
+            x: tile.0 as f32 + (1.0 - BUTTON_WIDTH) / 2.0,
+            y: tile.1 as f32 + 1.0 - BUTTON_HEIGHT,
+        },
+        Body::new(BUTTON_WIDTH, BUTTON_HEIGHT),
+        Sprite { color: BUTTON_COLOR },
+    ))
+}
+
+/// What a plate opens, set per plate in the level info.
+#[derive(Copy, Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct PlateInfo {
+    /// Link of the gates it holds open.
+    pub link: u32,
+}
+
+impl Default for PlateInfo {
+    fn default() -> Self {
+        PlateInfo { link: 1 }
+    }
+}
+
+/// Marks a pressure plate lying on the floor of its tile, pressed by
+/// players and by pushable blocks moved onto it.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Plate {
+    /// Place of the plate among the level's plates, in map order, which
+    /// picks its info.
+    pub index: usize,
+    pub tile: (usize, usize),
+    pub pressed: bool,
+}
+
+/// Spawns plate number `index` on the floor of the tile at `tile`.
+pub fn spawn_plate(world: &mut World, tile: (usize, usize), index: usize) -> Entity {
+    world.spawn((
+        Plate { index, tile, pressed: false },
+        Position {
+            x: tile.0 as f32 + (1.0 - PLATE_WIDTH) / 2.0,
+            y: tile.1 as f32 + 1.0 - PLATE_HEIGHT,
+        },
+        Body::new(PLATE_WIDTH, PLATE_HEIGHT),
+        Sprite { color: PLATE_COLOR },
+    ))
+}
@@ -0,0 +1,202 @@
+use hecs::{Entity, Or, World};
+
+use crate::block::Block;
+use crate::cart::{Cart, Riding, CART_HEIGHT, CART_WIDTH};
+use crate::components::{Body, Position, Velocity};
+use crate::player::{Defeated, Player};
+use crate::playground::Playground;
+use crate::swing::Flung;
+
+/// How deep riders sit in their cart, in tiles.
+const RIDE_DEPTH: f32 = 0.2;
+
+/// Puts players falling into an empty cart in it, lets riders who jumped
+/// out go, moves every cart `speed` tiles along the track or lands those
+/// flying, and carries the riders along. Must run after physics, since it
+/// overrides where physics put riders.
+pub fn run(world: &mut World, playground: &Playground, speed: f32) {
+    board(world);
+    leave(world);
+    let mut launched = Vec::new();
+    let mut landed = Vec::new();
+    let mut riders = Vec::new();
+    for (entity, position, body, cart, velocity) in world.query_mut::<(Entity, &mut Position, &Body, &mut Cart, Option<&mut Velocity>)>() {
+        let mut carried = Velocity::default();
+        match velocity {
+            None if cart.rail.is_some() => {
+                let launch = advance(playground, cart, speed);
+                let Some((from, to)) = cart.rail else { continue };
+                let length = distance(from, to);
+                let along = if length > 0.0 { cart.travelled / length } else { 0.0 };
+                let (x, y) = (from.0 as f32 + (to.0 as f32 - from.0 as f32) * along, from.1 as f32 + (to.1 as f32 - from.1 as f32) * along);
+                position.x = x + (1.0 - CART_WIDTH) / 2.0;
+                position.y = y + 1.0 - CART_HEIGHT;
+                if length > 0.0 && cart.direction != 0 {
+                    carried = Velocity { x: (to.0 as f32 - from.0 as f32) / length * speed, y: (to.1 as f32 - from.1 as f32) / length * speed };
+                }
+                if launch {
+                    cart.rail = None;
+                    launched.push((entity, carried));
+                }
+            }
+            None => {}
+            Some(velocity) => {
+                let bottom = (position.x + body.width / 2.0, position.y + body.height - 0.01);
+                let tile = (bottom.0.floor() as i32, bottom.1.floor() as i32);
+                let on_track = velocity.y >= 0.0 && tile.0 >= 0 && tile.1 >= 0
+                    && playground.contains(tile.0 as usize, tile.1 as usize)
+                    && matches!(playground.block_at(tile.0 as usize, tile.1 as usize), Block::Track { .. });
+                if on_track {
+                    let tile = (tile.0 as usize, tile.1 as usize);
+                    cart.rail = Some((tile, tile));
+                    cart.travelled = 0.0;
+                    position.x = tile.0 as f32 + (1.0 - CART_WIDTH) / 2.0;
+                    position.y = tile.1 as f32 + 1.0 - CART_HEIGHT;
+                    landed.push(entity);
+                } else if body.on_ground {
+                    velocity.x = 0.0;
+                    cart.direction = 0;
+                }
+                carried = Velocity { x: velocity.x, y: 0.0 };
+            }
+        }
+        if let Some(rider) = cart.rider {
+            riders.push((rider, *position, carried));
+        }
+    }
+    for (entity, velocity) in launched {
+        let _ = world.insert_one(entity, velocity);
+    }
+    for entity in landed {
+        let _ = world.remove_one::<Velocity>(entity);
+    }
+    for (position, velocity, body, player) in world.query_mut::<(&mut Position, &mut Velocity, &mut Body, &Player)>().with::<&Riding>() {
+        let Some((_, cart, carried)) = riders.iter().find(|(rider, _, _)| *rider == player.index) else { continue };
+        position.x = cart.x + (CART_WIDTH - body.width) / 2.0;
+        position.y = cart.y + RIDE_DEPTH - body.height;
+        *velocity = Velocity { x: carried.x, y: 0.0 };
+        body.on_ground = true;
+    }
+}
+
+/// Puts every player falling into an empty cart in it, starting carts
+/// standing on the track the way the player faces.
+fn board(world: &mut World) {
+    let players: Vec<(Entity, usize, f32, Position, Body)> = world
+        .query::<(Entity, &Player, &Position, &Velocity, &Body)>().without::<Or<&Riding, &Defeated>>().iter()
+        .filter(|(_, _, _, velocity, _)| velocity.y > 0.0)
+        .map(|(entity, player, position, _, body)| (entity, player.index, player.facing, *position, *body))
+        .collect();
+    let mut boarded = Vec::new();
+    for (position, cart) in world.query_mut::<(&Position, &mut Cart)>() {
+        if cart.rider.is_some() {
+            continue;
+        }
+        let rider = players.iter().find(|(entity, _, _, player, body)| {
+            let center = player.x + body.width / 2.0;
+            let bottom = player.y + body.height;
+            !boarded.contains(entity)
+                && center > position.x && center < position.x + CART_WIDTH
+                && bottom > position.y && bottom < position.y + CART_HEIGHT
+        });
+        if let Some((entity, index, facing, _, _)) = rider {
+            cart.rider = Some(*index);
+            if cart.rail.is_some() && cart.direction == 0 {
+                cart.direction = if *facing < 0.0 { -1 } else { 1 };
+            }
+            boarded.push(*entity);
+        }
+    }
+    for entity in boarded {
+        let _ = world.remove_one::<Flung>(entity);
+        let _ = world.insert_one(entity, Riding);
+    }
+}
+
+/// Empties carts whose rider jumped out, was defeated or was taken out of
+/// the cart, such as by respawning. Riders who jumped keep the speed of
+/// the cart until they land.
+fn leave(world: &mut World) {
+    let players: Vec<(Entity, usize, f32)> = world
+        .query::<(Entity, &Player, &Velocity)>().with::<&Riding>().without::<&Defeated>().iter()
+        .map(|(entity, player, velocity)| (entity, player.index, velocity.y))
+        .collect();
+    let mut jumped = Vec::new();
+    for cart in world.query_mut::<&mut Cart>() {
+        let Some(rider) = cart.rider else { continue };
+        match players.iter().find(|(_, index, _)| *index == rider) {
+            Some((entity, _, velocity)) if *velocity < 0.0 => {
+                cart.rider = None;
+                jumped.push(*entity);
+            }
+            Some(_) => {}
+            None => { cart.rider = None }
+        }
+    }
+    for entity in jumped {
+        let _ = world.remove_one::<Riding>(entity);
+        let _ = world.insert_one(entity, Flung);
+    }
+}
+
+/// Moves a cart on the track `speed` tiles the way it runs, stopping it at
+/// walls. Past the end of the track it heads on as the track last went
+/// and, once over the edge of the last track tile, returns true to be
+/// launched.
+fn advance(playground: &Playground, cart: &mut Cart, speed: f32) -> bool {
+    let Some((mut from, mut to)) = cart.rail else { return false };
+    if cart.direction == 0 {
+        return false;
+    }
+    let mut slope = 0;
+    let mut left = speed;
+    loop {
+        if from == to {
+            let ahead = to.0 as i32 + cart.direction;
+            to = match next_track(playground, to, cart.direction) {
+                Some(next) => { next }
+                None if ahead < 0 || ahead as usize >= playground.width || playground.is_solid(ahead, to.1 as i32) => {
+                    cart.direction = 0;
+                    cart.travelled = 0.0;
+                    cart.rail = Some((from, from));
+                    return false;
+                }
+                None => { (ahead as usize, (to.1 as i32 + slope).max(0) as usize) }
+            };
+        }
+        let length = distance(from, to);
+        if !is_track(playground, to) && cart.travelled + left >= length / 2.0 {
+            cart.travelled = length / 2.0;
+            cart.rail = Some((from, to));
+            return true;
+        }
+        if cart.travelled + left < length {
+            cart.travelled += left;
+            cart.rail = Some((from, to));
+            return false;
+        }
+        left -= length - cart.travelled;
+        cart.travelled = 0.0;
+        slope = to.1 as i32 - from.1 as i32;
+        from = to;
+    }
+}
+
+/// Track tile the track at `tile` leads to going `direction`: the next
+/// tile of the row, else the one above it, else the one below.
+fn next_track(playground: &Playground, tile: (usize, usize), direction: i32) -> Option<(usize, usize)> {
+    let x = tile.0 as i32 + direction;
+    [0, -1, 1].into_iter()
+        .map(|dy| (x, tile.1 as i32 + dy))
+        .filter(|(x, y)| *x >= 0 && *y >= 0)
+        .map(|(x, y)| (x as usize, y as usize))
+        .find(|next| is_track(playground, *next))
+}
+
+fn is_track(playground: &Playground, tile: (usize, usize)) -> bool {
+    playground.contains(tile.0, tile.1) && matches!(playground.block_at(tile.0, tile.1), Block::Track { .. })
+}
+
+fn distance(from: (usize, usize), to: (usize, usize)) -> f32 {
+    ((to.0 as f32 - from.0 as f32).powi(2) + (to.1 as f32 - from.1 as f32).powi(2)).sqrt()
+}
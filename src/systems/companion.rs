@@ -0,0 +1,106 @@
+use hecs::World;
+
+use crate::companion::{Companion, Errand, COMPANION_HEIGHT, COMPANION_WIDTH, ERRAND_TICKS, FOLLOW_DELAY, MAX_TRAIL};
+use crate::components::Position;
+
+/// Farthest the player may move in a tick before the companion stops
+/// following the trail and teleports, in tiles; more is a respawn or a
+/// teleport.
+const MAX_STEP: f32 = 1.0;
+
+pub enum Outcome {
+    /// The companion reached the collectible at the given tile.
+    Fetched { x: usize, y: usize },
+    /// The companion was stuck and teleported to the leader at `(x, y)`.
+    Teleported { x: f32, y: f32 },
+}
+
+/// Moves companions `speed` tiles along their trail or errand, adding
+/// `leader`, where the companion floats around the player it follows, to
+/// their trail. Companions stuck for too long teleport to the leader.
+pub fn run(world: &mut World, leader: Option<(f32, f32)>, speed: f32) -> Vec<Outcome> {
+    let mut outcomes = Vec::new();
+    for (position, companion) in world.query_mut::<(&mut Position, &mut Companion)>() {
+        if companion.errand != Errand::Following {
+            companion.busy += 1;
+        }
+        if let Some((x, y)) = leader {
+            let point = Position { x: x - COMPANION_WIDTH / 2.0, y: y - COMPANION_HEIGHT / 2.0 };
+            let jumped = companion.trail.back().is_some_and(|last| distance(last, &point) > MAX_STEP);
+            if jumped || companion.trail.len() > MAX_TRAIL || companion.busy > ERRAND_TICKS {
+                *position = point;
+                companion.trail.clear();
+                companion.errand = Errand::Following;
+                companion.busy = 0;
+                outcomes.push(Outcome::Teleported { x, y });
+                continue;
+            }
+            companion.trail.push_back(point);
+        }
+        let mut step = speed;
+        match std::mem::replace(&mut companion.errand, Errand::Following) {
+            Errand::Following => {
+                while companion.trail.len() > FOLLOW_DELAY && approach(position, companion.trail[0], &mut step) {
+                    companion.trail.pop_front();
+                }
+            }
+            Errand::Fetching { mut path, mut back } => {
+                while let Some(tile) = path.front().copied() {
+                    if !approach(position, tile_point(tile), &mut step) {
+                        break;
+                    }
+                    path.pop_front();
+                    back.push(tile);
+                }
+                companion.errand = match back.last() {
+                    Some((x, y)) if path.is_empty() => {
+                        outcomes.push(Outcome::Fetched { x: *x, y: *y });
+                        Errand::Returning { path: back.iter().rev().skip(1).copied().collect() }
+                    }
+                    _ => { Errand::Fetching { path, back } }
+                };
+            }
+            Errand::Returning { mut path } => {
+                while let Some(tile) = path.front().copied() {
+                    if !approach(position, tile_point(tile), &mut step) {
+                        break;
+                    }
+                    path.pop_front();
+                }
+                if path.is_empty() {
+                    companion.busy = 0;
+                } else {
+                    companion.errand = Errand::Returning { path };
+                }
+            }
+        }
+    }
+    outcomes
+}
+
+/// Position that centers the companion on the tile at `tile`.
+fn tile_point(tile: (usize, usize)) -> Position {
+    Position {
+        x: tile.0 as f32 + (1.0 - COMPANION_WIDTH) / 2.0,
+        y: tile.1 as f32 + (1.0 - COMPANION_HEIGHT) / 2.0,
+    }
+}
+
+/// Moves `position` toward `target` by at most `step` tiles, taking the
+/// distance covered off `step`. Returns whether it got there.
+fn approach(position: &mut Position, target: Position, step: &mut f32) -> bool {
+    let left = distance(position, &target);
+    if left <= *step {
+        *position = target;
+        *step -= left;
+        return true;
+    }
+    position.x += (target.x - position.x) / left * *step;
+    position.y += (target.y - position.y) / left * *step;
+    *step = 0.0;
+    false
+}
+
+fn distance(from: &Position, to: &Position) -> f32 {
+    ((to.x - from.x).powi(2) + (to.y - from.y).powi(2)).sqrt()
+}
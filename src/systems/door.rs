@@ -0,0 +1,28 @@
+use hecs::World;
+
+use crate::block::Block;
+use crate::components::{Body, Position};
+use crate::player::{Defeated, Player};
+use crate::playground::Playground;
+
+/// Distance from a body's side at which doors are looked for.
+const REACH: f32 = 0.01;
+
+/// Doors a player still in the game is pushing against from the side.
+pub fn doors_touched(world: &World, playground: &Playground) -> Vec<(usize, usize)> {
+    let mut doors = Vec::new();
+    for (position, body) in world.query::<(&Position, &Body)>().with::<&Player>().without::<&Defeated>().iter() {
+        let rows = position.y.floor() as i32..=(position.y + body.height - REACH).floor() as i32;
+        let sides = [(position.x - REACH).floor() as i32, (position.x + body.width + REACH).floor() as i32];
+        for y in rows {
+            for x in sides {
+                if x >= 0 && y >= 0 && playground.contains(x as usize, y as usize)
+                    && matches!(playground.block_at(x as usize, y as usize), Block::Door { .. })
+                    && !doors.contains(&(x as usize, y as usize)) {
+                    doors.push((x as usize, y as usize));
+                }
+            }
+        }
+    }
+    doors
+}
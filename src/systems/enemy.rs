@@ -0,0 +1,248 @@
+use hecs::{Entity, World};
+
+use crate::collision::Collider;
+use crate::components::{Body, Bounds, Position, Sprite, Velocity};
+use crate::enemy::{Behavior, Enemy, Flight, Route, ALERT_COLOR, FLIGHT_AMPLITUDE, FLIGHT_PERIOD, ROUTE_INTERVAL, TURRET_INTERVAL, TURRET_SHOT_COLOR};
+use crate::navigation::{self, Movement, PlatformGraph};
+use crate::player::{Defeated, Player};
+use crate::playground::Playground;
+use crate::projectile::Projectiles;
+use crate::tuning::PhysicsTuning;
+
+/// Keeps boxes that end exactly on a tile edge from counting the next tile.
+const EPSILON: f32 = 0.001;
+/// How far below an enemy's top a player's feet may have been on the tick
+/// before and still land on it, for enemies that moved up meanwhile.
+const STOMP_REACH: f32 = 0.2;
+
+/// A player touching an enemy.
+pub struct Contact {
+    pub player: Entity,
+    pub enemy: Entity,
+    /// Horizontal center of the enemy.
+    pub from: f32,
+    /// Whether the player came down onto the enemy's top this tick.
+    pub from_above: bool,
+}
+
+/// Moves every enemy standing on the ground according to its behavior,
+/// flies flying ones and lets turrets shoot. Returns where every shot
+/// fired starts.
+pub fn run(world: &mut World, projectiles: &mut Projectiles, playground: &Playground, platforms: &PlatformGraph, tuning: &PhysicsTuning) -> Vec<(f32, f32)> {
+    for (position, velocity, body, enemy, flight) in world.query_mut::<(&mut Position, &mut Velocity, &Body, &mut Enemy, &mut Flight)>() {
+        let tuning = &paced(tuning, enemy.speed);
+        fly(position, velocity, body, enemy, flight, tuning);
+    }
+    let players: Vec<(f32, f32)> = world.query::<(&Position, &Body)>().with::<&Player>().without::<&Defeated>().iter()
+        .map(|(position, body)| center(position, body))
+        .collect();
+    let mut shots = Vec::new();
+    for (position, velocity, body, enemy, sprite, route) in world.query_mut::<(&Position, &mut Velocity, &Body, &mut Enemy, &mut Sprite, Option<&mut Route>)>() {
+        let tuning = &paced(tuning, enemy.speed);
+        match (enemy.behavior, route) {
+            (Behavior::Turret, _) => {
+                velocity.x = 0.0;
+                shots.extend(aim(&players, position, body, enemy, tuning));
+                continue;
+            }
+            (Behavior::Fly, _) => { continue }
+            (Behavior::Hunt, Some(route)) => {
+                let eye = center(position, body);
+                let target = players.iter().copied()
+                    .min_by(|a, b| (a.0 - eye.0).hypot(a.1 - eye.1).total_cmp(&(b.0 - eye.0).hypot(b.1 - eye.1)));
+                plan(playground, platforms, target, position, body, route);
+                steer(target, position, velocity, body, route, tuning);
+                if velocity.x != 0.0 {
+                    enemy.direction = velocity.x.signum();
+                }
+                continue;
+            }
+            _ => {}
+        }
+        if !body.on_ground {
+            velocity.x = 0.0;
+            continue;
+        }
+        match enemy.behavior {
+            Behavior::Patrol => { patrol(playground, position, velocity, body, enemy, tuning) }
+            Behavior::Chase => {
+                chase(playground, &players, position, velocity, body, enemy, tuning);
+                sprite.color = if enemy.target.is_some() { ALERT_COLOR } else { enemy.behavior.color() };
+            }
+            Behavior::Turret | Behavior::Fly | Behavior::Hunt => {}
+        }
+    }
+    for (origin, velocity) in &shots {
+        projectiles.spawn(origin.0, origin.1, *velocity, true, TURRET_SHOT_COLOR);
+    }
+    shots.into_iter().map(|(origin, _)| origin).collect()
+}
+
+/// `tuning` with enemies moving `factor` times as fast.
+fn paced(tuning: &PhysicsTuning, factor: f32) -> PhysicsTuning {
+    PhysicsTuning {
+        enemy_speed: tuning.enemy_speed * factor,
+        flight_speed: tuning.flight_speed * factor,
+        chase_speed: tuning.chase_speed * factor,
+        ..*tuning
+    }
+}
+
+/// Moves a flying enemy along its row, turning at the ends of its span,
+/// and up or down along a sine wave around its height.
+fn fly(position: &mut Position, velocity: &mut Velocity, body: &Body, enemy: &mut Enemy, flight: &mut Flight, tuning: &PhysicsTuning) {
+    let x = position.x + enemy.direction * tuning.flight_speed;
+    if x < flight.left {
+        enemy.direction = 1.0;
+    } else if x + body.width > flight.right {
+        enemy.direction = -1.0;
+    }
+    flight.phase = (flight.phase + 1.0) % FLIGHT_PERIOD;
+    let y = flight.base_y - FLIGHT_AMPLITUDE * (flight.phase / FLIGHT_PERIOD * std::f32::consts::TAU).sin();
+    let x = x.clamp(flight.left, (flight.right - body.width).max(flight.left));
+    *velocity = Velocity { x: x - position.x, y: y - position.y };
+    position.x = x;
+    position.y = y;
+}
+
+/// Where a turret's shot starts and how it flies, once it has reloaded and
+/// a player is in range.
+fn aim(players: &[(f32, f32)], position: &Position, body: &Body, enemy: &mut Enemy, tuning: &PhysicsTuning) -> Option<((f32, f32), Velocity)> {
+    enemy.reload = enemy.reload.saturating_sub(1);
+    if enemy.reload > 0 {
+        return None;
+    }
+    let muzzle = center(position, body);
+    let distance = |point: &(f32, f32)| (point.0 - muzzle.0).hypot(point.1 - muzzle.1);
+    let target = players.iter()
+        .filter(|player| distance(player) <= tuning.turret_range)
+        .min_by(|a, b| distance(a).total_cmp(&distance(b)))?;
+    enemy.reload = TURRET_INTERVAL;
+    let length = distance(target).max(f32::EPSILON);
+    let speed = tuning.turret_shot_speed / length;
+    Some((muzzle, Velocity { x: (target.0 - muzzle.0) * speed, y: (target.1 - muzzle.1) * speed }))
+}
+
+/// Walks on, turning around when a wall or the end of the platform is
+/// ahead. Enemies blocked both ways stand still.
+fn patrol(playground: &Playground, position: &Position, velocity: &mut Velocity, body: &Body, enemy: &mut Enemy, tuning: &PhysicsTuning) {
+    let blocked = |direction: f32| {
+        let (wall, ledge) = ahead(playground, position, body, direction * tuning.enemy_speed);
+        wall || ledge
+    };
+    if blocked(enemy.direction) {
+        enemy.direction = -enemy.direction;
+    }
+    velocity.x = if blocked(enemy.direction) { 0.0 } else { enemy.direction * tuning.enemy_speed };
+}
+
+/// Heads for the nearest player in sight, or for where one was last seen.
+/// Chasers stop at walls but follow players down from ledges.
+fn chase(playground: &Playground, players: &[(f32, f32)], position: &Position, velocity: &mut Velocity, body: &Body, enemy: &mut Enemy, tuning: &PhysicsTuning) {
+    let eye = center(position, body);
+    let distance = |point: &(f32, f32)| (point.0 - eye.0).hypot(point.1 - eye.1);
+    let seen = players.iter()
+        .filter(|player| distance(player) <= tuning.sight_range && playground.line_of_sight(eye, **player))
+        .min_by(|a, b| distance(a).total_cmp(&distance(b)));
+    if let Some(player) = seen {
+        enemy.target = Some(*player);
+    }
+    let Some(target) = enemy.target else {
+        velocity.x = 0.0;
+        return;
+    };
+    let offset = target.0 - eye.0;
+    if offset.abs() <= tuning.chase_speed {
+        if seen.is_none() {
+            enemy.target = None;
+        }
+        velocity.x = 0.0;
+        return;
+    }
+    enemy.direction = offset.signum();
+    let step = enemy.direction * tuning.chase_speed;
+    let (wall, _) = ahead(playground, position, body, step);
+    if wall && seen.is_none() {
+        enemy.target = None;
+    }
+    velocity.x = if wall { 0.0 } else { step };
+}
+
+/// Works out the route of a hunter to the player at `target` again every
+/// so often while it stands, over the jumps and drops between `platforms`.
+fn plan(playground: &Playground, platforms: &PlatformGraph, target: Option<(f32, f32)>, position: &Position, body: &Body, route: &mut Route) {
+    route.timer = route.timer.saturating_sub(1);
+    if route.timer > 0 || !body.on_ground {
+        return;
+    }
+    route.timer = ROUTE_INTERVAL;
+    let from = navigation::standing_tile(playground, position.x + body.width / 2.0, position.y + body.height);
+    let to = target.and_then(|target| navigation::standing_tile(playground, target.0, target.1));
+    route.steps = match (from, to) {
+        (Some(from), Some(to)) => { platforms.find_route(from, to).unwrap_or_default() }
+        _ => { Vec::new() }
+    };
+}
+
+/// Takes the next step of a hunter's route, or heads straight for the
+/// player at `target` at the end of it. Hunters keep steering in the air,
+/// to make their jumps and drops.
+fn steer(target: Option<(f32, f32)>, position: &Position, velocity: &mut Velocity, body: &Body, route: &mut Route, tuning: &PhysicsTuning) {
+    let x = position.x + body.width / 2.0;
+    let feet = position.y + body.height;
+    let tile = (x.floor() as usize, (feet - EPSILON).floor().max(0.0) as usize);
+    let reached = route.steps.first()
+        .is_some_and(|step| step.tile == tile && (step.movement == Movement::Walk || body.on_ground));
+    if reached {
+        route.steps.remove(0);
+    }
+    let goal = match route.steps.first() {
+        Some(step) => { Some(step.tile.0 as f32 + 0.5) }
+        None => { target.map(|target| target.0) }
+    };
+    let Some(goal) = goal else {
+        velocity.x = 0.0;
+        return;
+    };
+    velocity.x = (goal - x).clamp(-tuning.chase_speed, tuning.chase_speed);
+    if body.on_ground && route.steps.first().is_some_and(|step| step.movement == Movement::Jump) {
+        velocity.y = -tuning.jump_speed;
+    }
+}
+
+/// Whether a body moving by `step` would walk into a wall, and whether it
+/// would walk off its platform.
+fn ahead(playground: &Playground, position: &Position, body: &Body, step: f32) -> (bool, bool) {
+    let edge = if step > 0.0 { position.x + body.width - EPSILON + step } else { position.x + step };
+    let column = edge.floor() as i32;
+    let top = position.y.floor() as i32;
+    let bottom = (position.y + body.height - EPSILON).floor() as i32;
+    let wall = (top..=bottom).any(|row| playground.is_solid(column, row));
+    let ledge = !playground.is_solid(column, bottom + 1);
+    (wall, ledge)
+}
+
+fn center(position: &Position, body: &Body) -> (f32, f32) {
+    (position.x + body.width / 2.0, position.y + body.height / 2.0)
+}
+
+/// Players still in the game whose body overlaps an enemy, with the side
+/// they touched it from.
+pub fn players_hit(world: &World) -> Vec<Contact> {
+    let enemies: Vec<(Entity, Position, Body, Collider)> = world.query::<(Entity, &Position, &Body, &Collider)>().with::<&Enemy>().iter()
+        .map(|(entity, position, body, collider)| (entity, *position, *body, *collider))
+        .collect();
+    world.query::<(Entity, &Position, &Velocity, &Body, &Collider)>().with::<&Player>().without::<&Defeated>().iter()
+        .filter_map(|(entity, position, velocity, body, collider)| {
+            let (enemy, other, other_body, _) = enemies.iter()
+                .find(|(_, other, other_body, other_collider)| collider.interacts(other_collider) && Bounds::of(position, body).overlaps(&Bounds::of(other, other_body)))?;
+            let feet = position.y + body.height - velocity.y;
+            Some(Contact {
+                player: entity,
+                enemy: *enemy,
+                from: other.x + other_body.width / 2.0,
+                from_above: velocity.y > 0.0 && feet <= other.y + STOMP_REACH,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,44 @@
+use std::collections::BTreeMap;
+
+use hecs::World;
+
+use crate::block::{Block, Material};
+use crate::components::{Body, Position, Velocity};
+use crate::player::{Defeated, Player};
+use crate::playground::Playground;
+
+/// Tiles walked between two footsteps.
+const STRIDE: f32 = 0.9;
+/// Speed under which a player is standing rather than walking.
+const STANDING: f32 = 0.01;
+
+/// Counts the tiles every player still in the game walks on the ground
+/// and returns those who took a step this tick, with the tile under their
+/// feet and what it is made of: water when they wade through it, the
+/// block they stand on otherwise, as `materials` has it by map character.
+pub fn run(world: &mut World, playground: &Playground, materials: &BTreeMap<char, Material>) -> Vec<(usize, (usize, usize), Material)> {
+    let mut steps = Vec::new();
+    for (player, position, velocity, body) in world.query_mut::<(&mut Player, &Position, &Velocity, &Body)>().without::<&Defeated>() {
+        if !body.on_ground || velocity.x.abs() < STANDING {
+            player.stride = 0.0;
+            continue;
+        }
+        player.stride += velocity.x.abs();
+        if player.stride < STRIDE {
+            continue;
+        }
+        player.stride -= STRIDE;
+        let x = (position.x + body.width / 2.0).max(0.0) as usize;
+        let feet = (position.y + body.height - 0.5).max(0.0) as usize;
+        let below = (position.y + body.height + 0.5).max(0.0) as usize;
+        let step = [feet, below].into_iter()
+            .filter(|y| playground.contains(x, *y))
+            .map(|y| ((x, y), playground.block_at(x, y)))
+            .find(|(_, block)| matches!(block, Block::Water { .. }) || block.is_solid())
+            .and_then(|(tile, block)| materials.get(&block.code()).copied().or(block.material()).map(|material| (tile, material)));
+        if let Some((tile, material)) = step {
+            steps.push((player.index, tile, material));
+        }
+    }
+    steps
+}
@@ -0,0 +1,55 @@
+use hecs::{Entity, World};
+
+use crate::block::{Block, Side};
+use crate::collision::{Collider, Layers};
+use crate::components::{Body, Bounds, Position};
+use crate::player::{Defeated, Player};
+use crate::playground::Playground;
+
+/// Distance from a body's edge at which touching tiles are looked for.
+const REACH: f32 = 0.01;
+
+/// Players still in the game touching spikes from the side they point
+/// to, with the horizontal center of the spikes.
+pub fn players_on_spikes(world: &World, playground: &Playground) -> Vec<(Entity, f32)> {
+    world.query::<(Entity, &Position, &Body, &Collider)>().with::<&Player>().without::<&Defeated>().iter()
+        .filter(|(_, _, _, collider)| collider.touches(Layers::HAZARD))
+        .filter_map(|(entity, position, body, _)| {
+            let columns = position.x.floor() as i32..=(position.x + body.width - REACH).floor() as i32;
+            let rows = position.y.floor() as i32..=(position.y + body.height - REACH).floor() as i32;
+            let below = (position.y + body.height + REACH).floor() as i32;
+            let above = (position.y - REACH).floor() as i32;
+            let left = (position.x - REACH).floor() as i32;
+            let right = (position.x + body.width + REACH).floor() as i32;
+            let mut touching = columns.clone().map(|x| (x, below, Side::Up))
+                .chain(columns.map(|x| (x, above, Side::Down)))
+                .chain(rows.clone().map(|y| (left, y, Side::Right)))
+                .chain(rows.map(|y| (right, y, Side::Left)));
+            touching
+                .find(|(x, y, side)| spikes_facing(playground, *x, *y) == Some(*side))
+                .map(|(x, _, _)| (entity, x as f32 + 0.5))
+        })
+        .collect()
+}
+
+/// Players still in the game overlapping a lava tile.
+pub fn players_in_lava(world: &World, playground: &Playground) -> Vec<Entity> {
+    world.query::<(Entity, &Position, &Body, &Collider)>().with::<&Player>().without::<&Defeated>().iter()
+        .filter(|(_, position, body, collider)| {
+            playground.tiles_in(&Bounds::of(position, body)).into_iter()
+                .map(|(x, y)| playground.block_at(x, y))
+                .any(|block| matches!(block, Block::Lava { .. }) && collider.touches(block.layer()))
+        })
+        .map(|(entity, _, _, _)| entity)
+        .collect()
+}
+
+fn spikes_facing(playground: &Playground, x: i32, y: i32) -> Option<Side> {
+    if x < 0 || y < 0 || !playground.contains(x as usize, y as usize) {
+        return None;
+    }
+    match playground.block_at(x as usize, y as usize) {
+        Block::Spikes { facing, .. } => { Some(*facing) }
+        _ => { None }
+    }
+}
@@ -0,0 +1,61 @@
+use hecs::World;
+
+use crate::cart::Riding;
+use crate::components::{Body, Velocity};
+use crate::player::{Defeated, Health, Player};
+use crate::powerup::{Boost, PowerUp};
+use crate::swing::{Flung, Swinging};
+use crate::tuning::PhysicsTuning;
+
+/// Most players that can share a game.
+pub const MAX_PLAYERS: usize = 2;
+
+/// Controls held by one player during a tick.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PlayerInput {
+    pub left: bool,
+    pub right: bool,
+    pub jump: bool,
+    pub shoot: bool,
+    /// Sends the companion for something to fetch, once per press.
+    pub fetch: bool,
+    /// Analog movement from -127 (full left) to 127, overriding left and
+    /// right when not 0.
+    pub stick: i8,
+}
+
+/// Controls of every player during a tick, indexed by player number.
+pub type Inputs = [PlayerInput; MAX_PLAYERS];
+
+/// Moves players as their controls say. Returns the numbers of the
+/// players who jumped.
+pub fn run(world: &mut World, inputs: &Inputs, tuning: &PhysicsTuning) -> Vec<usize> {
+    let mut jumped = Vec::new();
+    let query = world.query_mut::<(&mut Velocity, &Body, &mut Player, Option<&Health>, Option<&Boost>, Option<&Swinging>, Option<&Flung>, Option<&Riding>)>();
+    for (velocity, body, player, health, boost, swinging, flung, riding) in query.without::<&Defeated>() {
+        if health.is_some_and(Health::is_stunned) {
+            continue;
+        }
+        let power_up = boost.map(|boost| boost.power_up);
+        let move_speed = if power_up == Some(PowerUp::Speed) { tuning.move_speed * tuning.speed_boost } else { tuning.move_speed };
+        let jump_speed = if power_up == Some(PowerUp::Jump) { tuning.jump_speed * tuning.jump_boost } else { tuning.jump_speed };
+        let input = inputs[player.index];
+        // Ropes and carts keep their own speed instead.
+        if swinging.is_none() && flung.is_none() && riding.is_none() {
+            velocity.x = match (input.stick, input.left, input.right) {
+                (0, true, false) => { -move_speed }
+                (0, false, true) => { move_speed }
+                (0, _, _) => { 0.0 }
+                (stick, _, _) => { move_speed * stick as f32 / i8::MAX as f32 }
+            };
+        }
+        if velocity.x != 0.0 {
+            player.facing = velocity.x.signum();
+        }
+        if input.jump && body.on_ground {
+            velocity.y = -jump_speed;
+            jumped.push(player.index);
+        }
+    }
+    jumped
+}
@@ -0,0 +1,45 @@
+use hecs::{Entity, World};
+
+use crate::components::{Body, Position, Velocity};
+use crate::loot::{Crate, Loot};
+use crate::player::{Defeated, Player};
+
+/// Players still in the game that came down on top of a crate this tick,
+/// with the crate.
+pub fn crates_landed_on(world: &World) -> Vec<(Entity, Entity)> {
+    let crates: Vec<(Entity, Position, Body)> = world.query::<(Entity, &Position, &Body)>().with::<&Crate>().iter()
+        .map(|(entity, position, body)| (entity, *position, *body))
+        .collect();
+    let mut landed = Vec::new();
+    for (player, position, velocity, body) in world.query::<(Entity, &Position, &Velocity, &Body)>().with::<&Player>().without::<&Defeated>().iter() {
+        if velocity.y <= 0.0 {
+            continue;
+        }
+        let bottom = position.y + body.height;
+        let crate_ = crates.iter().find(|(_, other, other_body)| {
+            position.x < other.x + other_body.width && other.x < position.x + body.width
+                && bottom >= other.y && bottom - velocity.y <= other.y
+        });
+        if let Some((crate_, _, _)) = crate_ {
+            landed.push((player, *crate_));
+        }
+    }
+    landed
+}
+
+/// Players still in the game touching a drop, with the drop.
+pub fn loot_touched(world: &World) -> Vec<(Entity, Entity)> {
+    let drops: Vec<(Entity, Position, Body)> = world.query::<(Entity, &Position, &Body)>().with::<&Loot>().iter()
+        .map(|(entity, position, body)| (entity, *position, *body))
+        .collect();
+    let mut touched = Vec::new();
+    for (player, position, body) in world.query::<(Entity, &Position, &Body)>().with::<&Player>().without::<&Defeated>().iter() {
+        for (drop, other, other_body) in &drops {
+            if position.x < other.x + other_body.width && other.x < position.x + body.width
+                && position.y < other.y + other_body.height && other.y < position.y + body.height {
+                touched.push((player, *drop));
+            }
+        }
+    }
+    touched
+}
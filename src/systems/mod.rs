@@ -0,0 +1,21 @@
+//! Systems run once per tick over the entities in the world.
+
+pub mod cart;
+pub mod companion;
+pub mod door;
+pub mod enemy;
+pub mod footstep;
+pub mod hazard;
+pub mod input;
+pub mod loot;
+pub mod physics;
+pub mod projectile;
+pub mod push;
+pub mod saw;
+pub mod spawner;
+pub mod swing;
+pub mod switch;
+pub mod trap;
+pub mod trigger;
+pub mod water;
+pub mod waypoint;
@@ -0,0 +1,42 @@
+use hecs::{Or, World};
+
+use crate::components::{Body, Bounds, Position, Velocity};
+use crate::enemy::Flight;
+use crate::playground::Playground;
+use crate::systems::water;
+use crate::tuning::PhysicsTuning;
+use crate::waypoint::Follow;
+
+/// Applies gravity, weaker in water, and moves every body but flying
+/// enemies and bodies following a path, resolving collisions with solid
+/// blocks one axis at a time. Speeds stay below one tile per tick, so a
+/// body can only ever overlap the row or column it is moving into.
+pub fn run(world: &mut World, playground: &Playground, tuning: &PhysicsTuning) {
+    for (position, velocity, body) in world.query_mut::<(&mut Position, &mut Velocity, &mut Body)>().without::<Or<&Flight, &Follow>>() {
+        let gravity = if water::is_in_water(playground, position, body) { tuning.water_gravity } else { tuning.gravity };
+        velocity.y = (velocity.y + gravity).min(tuning.max_fall_speed);
+
+        position.x += velocity.x;
+        if let Some((tile_x, _)) = solid_overlap(playground, position, body) {
+            position.x = if velocity.x > 0.0 { tile_x as f32 - body.width } else { tile_x as f32 + 1.0 };
+            velocity.x = 0.0;
+        }
+
+        position.y += velocity.y;
+        body.on_ground = false;
+        if let Some((_, tile_y)) = solid_overlap(playground, position, body) {
+            if velocity.y > 0.0 {
+                position.y = tile_y as f32 - body.height;
+                body.on_ground = true;
+            } else {
+                position.y = tile_y as f32 + 1.0;
+            }
+            velocity.y = 0.0;
+        }
+    }
+}
+
+/// First solid tile overlapped by the body at `position`.
+fn solid_overlap(playground: &Playground, position: &Position, body: &Body) -> Option<(usize, usize)> {
+    playground.query_rect(&Bounds::of(position, body)).first().copied()
+}
@@ -0,0 +1,79 @@
+use hecs::{Entity, World};
+
+use crate::collision::Collider;
+use crate::components::{Body, Bounds, Position, Velocity};
+use crate::enemy::Enemy;
+use crate::loot::Crate;
+use crate::player::{Defeated, Player};
+use crate::playground::Playground;
+use crate::projectile::{Projectiles, FIRE_INTERVAL, PLAYER_SHOT_COLOR};
+use crate::systems::input::Inputs;
+use crate::tuning::PhysicsTuning;
+
+/// What a projectile ran into this tick. The projectile is gone by then.
+pub enum Hit {
+    /// A solid tile, or the edge of the playground, at the given point.
+    Wall { x: f32, y: f32 },
+    Enemy(Entity),
+    Crate(Entity),
+    /// A player, with the horizontal center of the projectile.
+    Player(Entity, f32),
+}
+
+/// Something projectiles can run into besides walls.
+#[derive(Copy, Clone, PartialEq)]
+enum Target {
+    Enemy,
+    Crate,
+    Player,
+}
+
+/// Fires a projectile from every player holding shoot whose last shot
+/// was long enough ago, in the direction the player faces.
+pub fn fire(world: &mut World, projectiles: &mut Projectiles, inputs: &Inputs, tuning: &PhysicsTuning) {
+    for (position, body, player) in world.query_mut::<(&Position, &Body, &mut Player)>().without::<&Defeated>() {
+        player.reload = player.reload.saturating_sub(1);
+        if !inputs[player.index].shoot || player.reload > 0 {
+            continue;
+        }
+        player.reload = FIRE_INTERVAL;
+        let x = position.x + body.width / 2.0 + player.facing * body.width / 2.0;
+        let velocity = Velocity { x: player.facing * tuning.projectile_speed, y: 0.0 };
+        projectiles.spawn(x, position.y + body.height / 2.0, velocity, false, PLAYER_SHOT_COLOR);
+    }
+}
+
+/// Moves every projectile, removing those that hit something its
+/// collider interacts with.
+pub fn run(world: &World, projectiles: &mut Projectiles, playground: &Playground) -> Vec<Hit> {
+    let targets: Vec<(Entity, Bounds, Collider, Target)> = world.query::<(Entity, &Position, &Body, &Collider)>().with::<&Enemy>().iter()
+        .map(|(entity, position, body, collider)| (entity, Bounds::of(position, body), *collider, Target::Enemy))
+        .chain(world.query::<(Entity, &Position, &Body, &Collider)>().with::<&Crate>().iter()
+            .map(|(entity, position, body, collider)| (entity, Bounds::of(position, body), *collider, Target::Crate)))
+        .chain(world.query::<(Entity, &Position, &Body, &Collider)>().with::<&Player>().without::<&Defeated>().iter()
+            .map(|(entity, position, body, collider)| (entity, Bounds::of(position, body), *collider, Target::Player)))
+        .collect();
+    let mut hits = Vec::new();
+    projectiles.retain(|projectile| {
+        projectile.position.x += projectile.velocity.x;
+        projectile.position.y += projectile.velocity.y;
+        let body = projectile.body();
+        let (x, y) = (projectile.position.x + body.width / 2.0, projectile.position.y + body.height / 2.0);
+        let outside = x < 0.0 || y < 0.0 || !playground.contains(x as usize, y as usize);
+        if outside || playground.is_solid(x.floor() as i32, y.floor() as i32) {
+            hits.push(Hit::Wall { x, y });
+            return false;
+        }
+        let (bounds, collider) = (projectile.bounds(), projectile.collider());
+        let target = targets.iter()
+            .find(|(_, other, other_collider, _)| collider.interacts(other_collider) && bounds.overlaps(other));
+        let Some((target, _, _, kind)) = target else { return true };
+        hits.push(match kind {
+            Target::Enemy => { Hit::Enemy(*target) }
+            Target::Crate => { Hit::Crate(*target) }
+            Target::Player => { Hit::Player(*target, x) }
+        });
+        false
+    });
+    hits
+}
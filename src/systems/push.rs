@@ -0,0 +1,60 @@
+use hecs::World;
+
+use crate::block::Block;
+use crate::components::{Body, Position};
+use crate::player::{Defeated, Player};
+use crate::playground::Playground;
+use crate::systems::input::Inputs;
+use crate::systems::switch;
+
+/// Distance from a body's side at which blocks are looked for.
+const REACH: f32 = 0.01;
+/// Ticks a player has to push against a block before it moves.
+pub const PUSH_TICKS: u32 = 12;
+
+/// Counts how long every player on the ground has been pushing against a
+/// pushable block with an empty tile behind it, returning the blocks that
+/// were pushed long enough, with the tile they move to.
+pub fn run(world: &mut World, playground: &Playground, inputs: &Inputs) -> Vec<((usize, usize), (usize, usize))> {
+    let mut pushed = Vec::new();
+    for (position, body, player) in world.query_mut::<(&Position, &Body, &mut Player)>().without::<&Defeated>() {
+        let input = inputs[player.index];
+        let direction = match (input.stick.signum(), input.left, input.right) {
+            (0, true, false) | (-1, _, _) => { -1 }
+            (0, false, true) | (1, _, _) => { 1 }
+            _ => { 0 }
+        };
+        let side = if direction < 0 { position.x - REACH } else { position.x + body.width + REACH };
+        let row = (position.y + body.height - REACH).floor() as i32;
+        let (x, y) = (side.floor() as i32, row);
+        let target = (x + direction, y);
+        let pushable = direction != 0 && body.on_ground
+            && x >= 0 && y >= 0 && playground.contains(x as usize, y as usize)
+            && matches!(playground.block_at(x as usize, y as usize), Block::Pushable { .. })
+            && target.0 >= 0 && playground.contains(target.0 as usize, y as usize)
+            && matches!(playground.block_at(target.0 as usize, y as usize), Block::Empty);
+        if !pushable {
+            player.pushing = 0;
+            continue;
+        }
+        player.pushing += 1;
+        if player.pushing >= PUSH_TICKS {
+            player.pushing = 0;
+            pushed.push(((x as usize, y as usize), (target.0 as usize, y as usize)));
+        }
+    }
+    pushed.retain(|(_, (x, y))| !switch::is_occupied(world, *x, *y));
+    pushed
+}
+
+/// Pushable blocks with an empty tile under them and no player in it,
+/// with the tile they drop to.
+pub fn falling(world: &World, playground: &Playground) -> Vec<((usize, usize), (usize, usize))> {
+    (0..playground.height.saturating_sub(1))
+        .flat_map(|y| (0..playground.width).map(move |x| (x, y)))
+        .filter(|(x, y)| matches!(playground.block_at(*x, *y), Block::Pushable { .. })
+            && matches!(playground.block_at(*x, y + 1), Block::Empty)
+            && !switch::is_occupied(world, *x, y + 1))
+        .map(|(x, y)| ((x, y), (x, y + 1)))
+        .collect()
+}
@@ -0,0 +1,34 @@
+use hecs::{Entity, World};
+
+use crate::collision::Collider;
+use crate::components::{Body, Bounds, Position};
+use crate::player::{Defeated, Player};
+use crate::saw::{Saw, SawInfo};
+use crate::waypoint::Follow;
+
+/// Moves every saw along its rail, but those following a path. `infos` are the saws of the level info,
+/// in order, and `speed` is the speed of saws they leave it out for.
+pub fn run(world: &mut World, infos: &[SawInfo], speed: f32) {
+    for (position, body, saw) in world.query_mut::<(&mut Position, &Body, &mut Saw)>().without::<&Follow>() {
+        let info = infos.get(saw.index).copied().unwrap_or_default();
+        saw.travelled += info.speed.unwrap_or(speed);
+        let (x, y) = saw.point_at(saw.start as f32 + saw.travelled + info.phase * saw.trip());
+        position.x = x - body.width / 2.0;
+        position.y = y - body.height / 2.0;
+    }
+}
+
+/// Players still in the game touching a saw, with the horizontal center
+/// of the saw.
+pub fn players_hit(world: &World) -> Vec<(Entity, f32)> {
+    let saws: Vec<(Bounds, Collider)> = world.query::<(&Position, &Body, &Collider)>().with::<&Saw>().iter()
+        .map(|(position, body, collider)| (Bounds::of(position, body), *collider))
+        .collect();
+    world.query::<(Entity, &Position, &Body, &Collider)>().with::<&Player>().without::<&Defeated>().iter()
+        .filter_map(|(entity, position, body, collider)| {
+            let bounds = Bounds::of(position, body);
+            let (saw, _) = saws.iter().find(|(saw, saw_collider)| collider.interacts(saw_collider) && bounds.overlaps(saw))?;
+            Some((entity, saw.x + saw.width / 2.0))
+        })
+        .collect()
+}
@@ -0,0 +1,30 @@
+use hecs::{Entity, World};
+
+use crate::components::{Body, Position};
+use crate::player::{Defeated, Player};
+use crate::spawner::{Spawned, Spawner, SpawnerInfo};
+
+/// Counts down every spawner with a player within `range` tiles, returning
+/// those due to spawn an enemy that are below their cap. `infos` are the
+/// spawners of the level info, in order.
+pub fn run(world: &mut World, infos: &[SpawnerInfo], range: f32) -> Vec<Entity> {
+    let players: Vec<(f32, f32)> = world.query::<(&Position, &Body)>().with::<&Player>().without::<&Defeated>().iter()
+        .map(|(position, body)| (position.x + body.width / 2.0, position.y + body.height / 2.0))
+        .collect();
+    let spawned: Vec<usize> = world.query::<&Spawned>().iter().map(|spawned| spawned.spawner).collect();
+    let mut due = Vec::new();
+    for (entity, position, body, spawner) in world.query_mut::<(Entity, &Position, &Body, &mut Spawner)>() {
+        let (x, y) = (position.x + body.width / 2.0, position.y + body.height / 2.0);
+        if !players.iter().any(|(player_x, player_y)| (player_x - x).hypot(player_y - y) <= range) {
+            continue;
+        }
+        let info = infos.get(spawner.index).copied().unwrap_or_default();
+        spawner.elapsed = (spawner.elapsed + 1).min(info.interval);
+        let about = spawned.iter().filter(|index| **index == spawner.index).count() as u32;
+        if spawner.elapsed >= info.interval && about < info.cap {
+            spawner.elapsed = 0;
+            due.push(entity);
+        }
+    }
+    due
+}
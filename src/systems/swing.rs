@@ -0,0 +1,78 @@
+use hecs::{Entity, World};
+
+use crate::components::{Body, Position, Velocity};
+use crate::player::{Defeated, Player};
+use crate::playground::Playground;
+use crate::swing::{Anchor, Flung, Swinging, MIN_ROPE, ROPE_REACH};
+use crate::systems::input::Inputs;
+use crate::tuning::PhysicsTuning;
+
+/// Hangs players holding jump in the air on the rope of the nearest anchor
+/// above them in reach, swings those on a rope and lets go of the rope
+/// for those no longer holding jump. Must run between input and physics,
+/// since it bends the velocity physics is about to apply.
+pub fn run(world: &mut World, playground: &Playground, inputs: &Inputs, tuning: &PhysicsTuning) {
+    let anchors: Vec<(f32, f32)> = world.query::<(&Position, &Body)>().with::<&Anchor>().iter()
+        .map(|(position, body)| (position.x + body.width / 2.0, position.y + body.height / 2.0))
+        .collect();
+    let mut grabbed = Vec::new();
+    let mut released = Vec::new();
+    let mut landed = Vec::new();
+    let query = world.query_mut::<(Entity, &Position, &mut Velocity, &Body, &Player, Option<&Swinging>, Option<&Flung>)>();
+    for (entity, position, velocity, body, player, swinging, flung) in query.without::<&Defeated>() {
+        let input = inputs[player.index];
+        let center = (position.x + body.width / 2.0, position.y + body.height / 2.0);
+        if flung.is_some() && (body.on_ground || velocity.x == 0.0) {
+            landed.push(entity);
+        }
+        match swinging {
+            Some(swinging) if input.jump && !body.on_ground => {
+                velocity.x += direction(input.stick, input.left, input.right) * tuning.swing_push;
+                // Bends the movement of the coming tick, gravity included,
+                // back onto the circle the rope allows.
+                let (anchor, length) = (swinging.anchor, swinging.length);
+                let next = (center.0 + velocity.x, center.1 + velocity.y + tuning.gravity);
+                let (dx, dy) = (next.0 - anchor.0, next.1 - anchor.1);
+                let distance = (dx * dx + dy * dy).sqrt();
+                if distance > length {
+                    velocity.x = anchor.0 + dx / distance * length - center.0;
+                    velocity.y = anchor.1 + dy / distance * length - center.1 - tuning.gravity;
+                }
+            }
+            Some(_) => { released.push((entity, body.on_ground)) }
+            None if input.jump && !body.on_ground => {
+                let anchor = anchors.iter()
+                    .map(|anchor| (*anchor, ((anchor.0 - center.0).powi(2) + (anchor.1 - center.1).powi(2)).sqrt()))
+                    .filter(|(anchor, distance)| anchor.1 < center.1 && *distance <= ROPE_REACH && playground.line_of_sight(*anchor, center))
+                    .min_by(|a, b| a.1.total_cmp(&b.1));
+                if let Some((anchor, distance)) = anchor {
+                    grabbed.push((entity, Swinging { anchor, length: distance.max(MIN_ROPE) }));
+                }
+            }
+            None => {}
+        }
+    }
+    for entity in landed {
+        let _ = world.remove_one::<Flung>(entity);
+    }
+    for (entity, on_ground) in released {
+        let _ = world.remove_one::<Swinging>(entity);
+        if !on_ground {
+            let _ = world.insert_one(entity, Flung);
+        }
+    }
+    for (entity, swinging) in grabbed {
+        let _ = world.remove_one::<Flung>(entity);
+        let _ = world.insert_one(entity, swinging);
+    }
+}
+
+/// Horizontal push from -1 (full left) to 1.
+fn direction(stick: i8, left: bool, right: bool) -> f32 {
+    match (stick, left, right) {
+        (0, true, false) => { -1.0 }
+        (0, false, true) => { 1.0 }
+        (0, _, _) => { 0.0 }
+        (stick, _, _) => { stick as f32 / i8::MAX as f32 }
+    }
+}
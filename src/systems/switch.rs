@@ -0,0 +1,50 @@
+use hecs::World;
+
+use crate::block::Block;
+use crate::components::{Body, Bounds, Position, Sprite};
+use crate::player::{Defeated, Player};
+use crate::playground::Playground;
+use crate::switch::{Button, Plate, BUTTON_COLOR, BUTTON_PRESSED_COLOR, PLATE_COLOR, PLATE_PRESSED_COLOR};
+
+/// Presses every button a player still in the game stands on and lets go
+/// of the others, returning the buttons pressed by index.
+pub fn press_buttons(world: &mut World) -> Vec<usize> {
+    let players: Vec<(Position, Body)> = world.query::<(&Position, &Body)>().with::<&Player>().without::<&Defeated>().iter()
+        .map(|(position, body)| (*position, *body))
+        .collect();
+    let mut pressed = Vec::new();
+    for (position, body, sprite, button) in world.query_mut::<(&Position, &Body, &mut Sprite, &mut Button)>() {
+        button.pressed = players.iter().any(|(player, player_body)| Bounds::of(player, player_body).overlaps(&Bounds::of(position, body)));
+        sprite.color = if button.pressed { BUTTON_PRESSED_COLOR } else { BUTTON_COLOR };
+        if button.pressed {
+            pressed.push(button.index);
+        }
+    }
+    pressed
+}
+
+/// Presses every plate a player still in the game stands on or a
+/// pushable block rests on and lets go of the others, returning the plates
+/// pressed by index.
+pub fn press_plates(world: &mut World, playground: &Playground) -> Vec<usize> {
+    let players: Vec<(Position, Body)> = world.query::<(&Position, &Body)>().with::<&Player>().without::<&Defeated>().iter()
+        .map(|(position, body)| (*position, *body))
+        .collect();
+    let mut pressed = Vec::new();
+    for (position, body, sprite, plate) in world.query_mut::<(&Position, &Body, &mut Sprite, &mut Plate)>() {
+        plate.pressed = matches!(playground.block_at(plate.tile.0, plate.tile.1), Block::Pushable { .. })
+            || players.iter().any(|(player, player_body)| Bounds::of(player, player_body).overlaps(&Bounds::of(position, body)));
+        sprite.color = if plate.pressed { PLATE_PRESSED_COLOR } else { PLATE_COLOR };
+        if plate.pressed {
+            pressed.push(plate.index);
+        }
+    }
+    pressed
+}
+
+/// Whether a player still in the game overlaps the tile at `(x, y)`.
+pub fn is_occupied(world: &World, x: usize, y: usize) -> bool {
+    let tile = Bounds { x: x as f32, y: y as f32, width: 1.0, height: 1.0 };
+    world.query::<(&Position, &Body)>().with::<&Player>().without::<&Defeated>().iter()
+        .any(|(position, body)| Bounds::of(position, body).overlaps(&tile))
+}
@@ -0,0 +1,74 @@
+use hecs::{Entity, World};
+
+use crate::collision::Collider;
+use crate::components::{Body, Bounds, Position, Velocity};
+use crate::player::{Defeated, Player};
+use crate::playground::Playground;
+use crate::trap::{Trap, TrapState, SHAKE_OFFSET, SHAKE_TICKS};
+
+/// Sets off armed traps with a player below them and nothing solid in
+/// between, shakes those set off and lets go of those done shaking.
+pub fn run(world: &mut World, playground: &Playground) {
+    let players: Vec<(Position, Body)> = world.query::<(&Position, &Body)>().with::<&Player>().without::<&Defeated>().iter()
+        .map(|(position, body)| (*position, *body))
+        .collect();
+    let mut released = Vec::new();
+    for (entity, position, body, trap) in world.query_mut::<(Entity, &mut Position, &Body, &mut Trap)>() {
+        match trap.state {
+            TrapState::Armed => {
+                if players.iter().any(|(player, player_body)| is_below(playground, position, body, player, player_body)) {
+                    trap.state = TrapState::Shaking(SHAKE_TICKS);
+                }
+            }
+            TrapState::Shaking(0) => {
+                position.x = trap.x;
+                trap.state = TrapState::Falling;
+                released.push(entity);
+            }
+            TrapState::Shaking(ticks) => {
+                position.x = trap.x + if ticks % 4 < 2 { SHAKE_OFFSET } else { -SHAKE_OFFSET };
+                trap.state = TrapState::Shaking(ticks - 1);
+            }
+            TrapState::Falling => {}
+        }
+    }
+    for entity in released {
+        let _ = world.insert_one(entity, Velocity::default());
+    }
+}
+
+/// Whether the player is below the trap, overlapping its column, with no
+/// solid tile in between.
+fn is_below(playground: &Playground, position: &Position, body: &Body, player: &Position, player_body: &Body) -> bool {
+    let bottom = position.y + body.height;
+    if player.y < bottom || player.x >= position.x + body.width || position.x >= player.x + player_body.width {
+        return false;
+    }
+    let column = (position.x + body.width / 2.0).floor() as i32;
+    (bottom.floor() as i32..player.y.floor() as i32).all(|y| !playground.is_solid(column, y))
+}
+
+/// Falling traps that came to rest on the ground.
+pub fn landed(world: &World) -> Vec<Entity> {
+    world.query::<(Entity, &Body, &Trap)>().iter()
+        .filter(|(_, body, trap)| trap.state == TrapState::Falling && body.on_ground)
+        .map(|(entity, _, _)| entity)
+        .collect()
+}
+
+/// Players still in the game hit by a falling trap, with the trap and its
+/// horizontal center.
+pub fn players_hit(world: &World) -> Vec<(Entity, Entity, f32)> {
+    let traps: Vec<(Entity, Bounds, Collider)> = world.query::<(Entity, &Position, &Body, &Collider, &Trap)>().iter()
+        .filter(|(_, _, _, _, trap)| trap.state == TrapState::Falling)
+        .map(|(entity, position, body, collider, _)| (entity, Bounds::of(position, body), *collider))
+        .collect();
+    world.query::<(Entity, &Position, &Body, &Collider)>().with::<&Player>().without::<&Defeated>().iter()
+        .filter_map(|(entity, position, body, collider)| {
+            let bounds = Bounds::of(position, body);
+            let (trap, other, _) = traps.iter()
+                .find(|(_, other, other_collider)| collider.interacts(other_collider) && bounds.overlaps(other))?;
+            Some((entity, *trap, other.x + other.width / 2.0))
+        })
+        .collect()
+}
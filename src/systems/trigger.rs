@@ -0,0 +1,21 @@
+use hecs::World;
+
+use crate::collision::{Collider, Layers};
+use crate::components::{Body, Bounds, Position};
+use crate::player::{Defeated, Player};
+use crate::trigger::Trigger;
+
+/// Whether a player still in the game is inside each trigger, in order.
+/// Players whose collider ignores triggers are never inside one.
+pub fn occupied(world: &World, triggers: &[Trigger]) -> Vec<bool> {
+    let players: Vec<Bounds> = world.query::<(&Position, &Body, &Collider)>().with::<&Player>().without::<&Defeated>().iter()
+        .filter(|(_, _, collider)| collider.touches(Layers::TRIGGER))
+        .map(|(position, body, _)| Bounds::of(position, body))
+        .collect();
+    triggers.iter()
+        .map(|trigger| {
+            let bounds = trigger.bounds();
+            players.iter().any(|player| player.overlaps(&bounds))
+        })
+        .collect()
+}
@@ -0,0 +1,48 @@
+use hecs::{Or, World};
+
+use crate::block::Block;
+use crate::components::{Body, Position, Velocity};
+use crate::enemy::Flight;
+use crate::player::{Defeated, Player};
+use crate::playground::Playground;
+use crate::systems::input::Inputs;
+use crate::tuning::PhysicsTuning;
+use crate::water::Current;
+use crate::waypoint::Follow;
+
+/// Share of their speed bodies in water keep from one tick to the next.
+const DRAG: f32 = 0.9;
+
+/// Slows down every body in water and lets the currents it is in push it.
+/// Players holding jump swim up, or jump out once their head is above the
+/// surface. Runs before physics, which lowers gravity in water.
+pub fn run(world: &mut World, playground: &Playground, currents: &[Current], inputs: &Inputs, tuning: &PhysicsTuning) {
+    let query = world.query_mut::<(&Position, &mut Velocity, &Body, Option<&Player>, Option<&Defeated>)>();
+    for (position, velocity, body, player, defeated) in query.without::<Or<&Flight, &Follow>>() {
+        if !is_in_water(playground, position, body) {
+            continue;
+        }
+        let center = (position.x + body.width / 2.0, position.y + body.height / 2.0);
+        let push = currents.iter()
+            .filter(|current| current.contains(center.0, center.1))
+            .fold((0.0, 0.0), |push, current| (push.0 + current.push.0, push.1 + current.push.1));
+        velocity.x = velocity.x * DRAG + push.0;
+        velocity.y = velocity.y * DRAG + push.1;
+        let swimming = player.is_some_and(|player| inputs[player.index].jump) && defeated.is_none();
+        if swimming {
+            let surfaced = !is_water(playground, center.0, position.y);
+            velocity.y = if surfaced { -tuning.jump_speed } else { -tuning.swim_speed };
+        }
+    }
+}
+
+/// Whether the center of the body at `position` is in water.
+pub fn is_in_water(playground: &Playground, position: &Position, body: &Body) -> bool {
+    is_water(playground, position.x + body.width / 2.0, position.y + body.height / 2.0)
+}
+
+fn is_water(playground: &Playground, x: f32, y: f32) -> bool {
+    x >= 0.0 && y >= 0.0
+        && playground.contains(x as usize, y as usize)
+        && matches!(playground.block_at(x as usize, y as usize), Block::Water { .. })
+}
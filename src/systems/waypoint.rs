@@ -0,0 +1,31 @@
+use hecs::World;
+
+use crate::components::{Body, Position, Velocity};
+use crate::enemy::Enemy;
+use crate::saw::{Saw, SawInfo};
+use crate::tuning::PhysicsTuning;
+use crate::waypoint::Follow;
+
+/// Moves everything following a path along it: saws at their speed from
+/// `saws`, the saws of the level info, and flying enemies at their flight
+/// speed.
+pub fn run(world: &mut World, saws: &[SawInfo], tuning: &PhysicsTuning) {
+    let query = world.query_mut::<(&mut Position, Option<&mut Velocity>, &Body, &mut Follow, Option<&mut Enemy>, Option<&Saw>)>();
+    for (position, velocity, body, follow, enemy, saw) in query {
+        follow.travelled += match (&enemy, saw) {
+            (Some(enemy), _) => { tuning.flight_speed * enemy.speed }
+            (None, Some(saw)) => { saws.get(saw.index).and_then(|info| info.speed).unwrap_or(tuning.saw_speed) }
+            (None, None) => { tuning.saw_speed }
+        };
+        let (x, y) = follow.point_at(follow.travelled);
+        let moved = Velocity { x: x - body.width / 2.0 - position.x, y: y - body.height / 2.0 - position.y };
+        position.x += moved.x;
+        position.y += moved.y;
+        if let Some(enemy) = enemy.filter(|_| moved.x != 0.0) {
+            enemy.direction = moved.x.signum();
+        }
+        if let Some(velocity) = velocity {
+            *velocity = moved;
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! Text typed through SDL text input, including what an input method is
+//! still composing. Used by the console and by screens that ask for a
+//! name, such as save slots or high-score initials.
+
+#[derive(Clone, Debug, Default)]
+pub struct TextEntry {
+    pub text: String,
+    /// Text an input method is composing, replaced once it is committed.
+    pub composition: String,
+    /// Most characters accepted, `None` for no limit.
+    pub max_len: Option<usize>,
+    /// Whether letters are stored in upper case, as for initials.
+    pub uppercase: bool,
+    /// Characters never accepted, such as the key that toggles the console.
+    pub rejected: Vec<char>,
+}
+
+impl TextEntry {
+    pub fn new() -> Self {
+        TextEntry::default()
+    }
+
+    /// Appends committed text from a text input event.
+    pub fn insert(&mut self, text: &str) {
+        self.composition.clear();
+        for code in text.chars() {
+            if code.is_control() || self.rejected.contains(&code) {
+                continue;
+            }
+            if self.max_len.is_some_and(|max_len| self.text.chars().count() >= max_len) {
+                break;
+            }
+            if self.uppercase {
+                self.text.extend(code.to_uppercase());
+            } else {
+                self.text.push(code);
+            }
+        }
+    }
+
+    /// Replaces the composition from a text editing event.
+    pub fn compose(&mut self, text: &str) {
+        self.composition = text.to_string();
+    }
+
+    pub fn backspace(&mut self) {
+        self.text.pop();
+    }
+
+    pub fn set(&mut self, text: &str) {
+        self.text = text.to_string();
+        self.composition.clear();
+    }
+
+    /// Returns the text typed so far and starts over.
+    pub fn take(&mut self) -> String {
+        self.composition.clear();
+        std::mem::take(&mut self.text)
+    }
+
+    /// The text followed by the composition in progress, for display.
+    pub fn display(&self) -> String {
+        format!("{}{}", self.text, self.composition)
+    }
+}
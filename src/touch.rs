@@ -0,0 +1,70 @@
+//! On-screen controls for touch screens: arrows in the bottom left corner,
+//! jump and shoot buttons in the bottom right and a pause button in the
+//! top right.
+//! A finger resting on a button holds its action like a key would.
+
+use std::collections::HashMap;
+
+use crate::bindings::Action;
+use crate::renderer::Rect;
+use crate::settings::TouchMode;
+
+pub struct TouchControls {
+    pub mode: TouchMode,
+    pub visible: bool,
+    /// Action under every finger currently touching a button.
+    fingers: HashMap<i64, Action>,
+}
+
+impl TouchControls {
+    pub fn new(mode: TouchMode) -> Self {
+        TouchControls {
+            mode,
+            visible: mode == TouchMode::Always,
+            fingers: HashMap::new(),
+        }
+    }
+
+    /// Every button with its label and where it is on a screen of `size`.
+    pub fn buttons(size: (u32, u32)) -> [(Action, &'static str, Rect); 5] {
+        let button = size.0.min(size.1) / 6;
+        let margin = button as i32 / 3;
+        let bottom = size.1 as i32 - margin - button as i32;
+        let right = size.0 as i32 - margin - button as i32;
+        [
+            (Action::MoveLeft, "<", Rect::new(margin, bottom, button, button)),
+            (Action::MoveRight, ">", Rect::new(margin * 2 + button as i32, bottom, button, button)),
+            (Action::Jump, "JUMP", Rect::new(right, bottom, button, button)),
+            (Action::Shoot, "FIRE", Rect::new(right - margin - button as i32, bottom, button, button)),
+            (Action::Pause, "PAUSE", Rect::new(right, margin, button, button / 2)),
+        ]
+    }
+
+    /// Moves `finger` to `(x, y)`, given as a fraction of a screen of `size`.
+    /// Returns the action when the finger lands on a button it was not
+    /// already on.
+    pub fn touch(&mut self, finger: i64, x: f32, y: f32, size: (u32, u32)) -> Option<Action> {
+        if self.mode == TouchMode::Never {
+            return None;
+        }
+        self.visible = true;
+        let point = ((x * size.0 as f32) as i32, (y * size.1 as f32) as i32);
+        let action = TouchControls::buttons(size)
+            .into_iter()
+            .find(|(_, _, rect)| rect.contains_point(point))
+            .map(|(action, _, _)| action);
+        let previous = match action {
+            Some(action) => { self.fingers.insert(finger, action) }
+            None => { self.fingers.remove(&finger) }
+        };
+        action.filter(|action| previous != Some(*action))
+    }
+
+    pub fn release(&mut self, finger: i64) {
+        self.fingers.remove(&finger);
+    }
+
+    pub fn is_held(&self, action: Action) -> bool {
+        self.fingers.values().any(|held| *held == action)
+    }
+}
@@ -0,0 +1,49 @@
+//! Spikes hanging from the ceiling that drop on players walking under
+//! them. A trap shakes for a moment once it sees a player below, then
+//! falls until it hits a player or shatters on the ground.
+
+use hecs::{Entity, World};
+use serde::{Deserialize, Serialize};
+
+use crate::collision::Collider;
+use crate::components::{Body, Position, Sprite};
+
+pub const TRAP_CODE: char = 'V';
+pub const TRAP_WIDTH: f32 = 0.6;
+pub const TRAP_HEIGHT: f32 = 0.8;
+pub const TRAP_COLOR: u32 = 0xB0B0C0;
+/// Ticks a trap shakes before it falls.
+pub const SHAKE_TICKS: u32 = 30;
+/// Tiles a shaking trap moves to either side.
+pub const SHAKE_OFFSET: f32 = 0.05;
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TrapState {
+    /// Waiting for a player to walk underneath.
+    Armed,
+    /// About to fall, with the ticks left.
+    Shaking(u32),
+    /// Falling through physics.
+    Falling,
+}
+
+/// Marks a falling spike trap. It only gets a velocity once it falls, so
+/// physics leaves it hanging until then.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Trap {
+    pub state: TrapState,
+    /// Where the left edge of the trap hangs, which shaking moves it around.
+    pub x: f32,
+}
+
+/// Spawns a trap hanging from the top of the tile at `tile`.
+pub fn spawn(world: &mut World, tile: (usize, usize)) -> Entity {
+    let x = tile.0 as f32 + (1.0 - TRAP_WIDTH) / 2.0;
+    world.spawn((
+        Trap { state: TrapState::Armed, x },
+        Position { x, y: tile.1 as f32 },
+        Body::new(TRAP_WIDTH, TRAP_HEIGHT),
+        Sprite { color: TRAP_COLOR },
+        Collider::HAZARD,
+    ))
+}
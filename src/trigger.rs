@@ -0,0 +1,62 @@
+//! Trigger zones: rectangles of tiles set in a level's info file that
+//! raise events as players enter, stay in and leave them. A zone can also
+//! act on its own when entered, showing a line of text, playing a
+//! cutscene, opening gates, switching the music or saving a checkpoint,
+//! and level scripts hear of it through `on_trigger`.
+
+use serde::{Deserialize, Serialize};
+
+use crate::components::Bounds;
+
+/// Ticks a line shown by a trigger stays up.
+pub const MESSAGE_TICKS: u32 = 180;
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Trigger {
+    /// Name it is known by in scripts.
+    pub name: String,
+    /// Top left tile of the rectangle.
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+    /// Fires only the first time a player enters it.
+    pub once: bool,
+    /// What happens when a player enters it.
+    #[serde(rename = "do")]
+    pub action: Option<Action>,
+}
+
+impl Trigger {
+    pub fn bounds(&self) -> Bounds {
+        Bounds { x: self.x as f32, y: self.y as f32, width: self.width as f32, height: self.height as f32 }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// Shows a line of text for a few seconds.
+    Say(String),
+    /// Plays the cutscene of the level info with this index, unless it
+    /// already played.
+    Cutscene(usize),
+    /// Opens the gates of `link` for `seconds`, like a button.
+    OpenGates { link: u32, seconds: f32 },
+    /// Switches the music of the level to this track for the rest of it,
+    /// e.g. as a boss arena is entered.
+    Music(String),
+    /// Makes the bottom left tile of the zone where players come back
+    /// after losing a life, in place of the start of the level.
+    Checkpoint,
+}
+
+/// Where the players are with a trigger.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TriggerState {
+    /// Whether a player is inside.
+    pub occupied: bool,
+    /// Whether a trigger firing once already did.
+    pub spent: bool,
+}
@@ -0,0 +1,183 @@
+//! Gameplay constants read from `tuning.toml`. The file is watched while
+//! the game runs and changes are applied on the next tick, so the feel of
+//! the game can be adjusted without restarting it.
+
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_TUNING: &str = "tuning.toml";
+
+/// How often the watched file is checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Tuning {
+    pub physics: PhysicsTuning,
+    pub animation: AnimationTuning,
+}
+
+/// Speeds in tiles per tick.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PhysicsTuning {
+    pub gravity: f32,
+    /// Gravity for bodies in water.
+    pub water_gravity: f32,
+    pub max_fall_speed: f32,
+    pub move_speed: f32,
+    pub jump_speed: f32,
+    /// Upward speed of a player holding jump in water.
+    pub swim_speed: f32,
+    /// Upward speed of a player stomping an enemy or breaking a crate.
+    pub bounce_speed: f32,
+    pub enemy_speed: f32,
+    pub chase_speed: f32,
+    pub flight_speed: f32,
+    pub saw_speed: f32,
+    pub companion_speed: f32,
+    pub cart_speed: f32,
+    /// Speed a player pushing left or right adds to their swing each tick.
+    pub swing_push: f32,
+    /// Farthest a chaser sees, in tiles.
+    pub sight_range: f32,
+    pub projectile_speed: f32,
+    /// Farthest a player can be from a spawner for it to keep spawning,
+    /// in tiles.
+    pub spawner_range: f32,
+    /// Farthest a turret shoots at, in tiles.
+    pub turret_range: f32,
+    pub turret_shot_speed: f32,
+    /// How much faster players run with a speed power-up.
+    pub speed_boost: f32,
+    /// How much faster players take off with a jump power-up.
+    pub jump_boost: f32,
+    /// Ticks a speed or jump power-up lasts.
+    pub power_up_ticks: u32,
+    /// Ticks a star lasts.
+    pub star_ticks: u32,
+}
+
+impl Default for PhysicsTuning {
+    fn default() -> Self {
+        PhysicsTuning {
+            gravity: 0.015,
+            water_gravity: 0.004,
+            max_fall_speed: 0.5,
+            move_speed: 0.15,
+            jump_speed: 0.3,
+            swim_speed: 0.08,
+            bounce_speed: 0.2,
+            enemy_speed: 0.04,
+            chase_speed: 0.08,
+            flight_speed: 0.05,
+            saw_speed: 0.05,
+            companion_speed: 0.2,
+            cart_speed: 0.15,
+            swing_push: 0.004,
+            sight_range: 10.0,
+            projectile_speed: 0.3,
+            spawner_range: 16.0,
+            turret_range: 12.0,
+            turret_shot_speed: 0.12,
+            speed_boost: 1.5,
+            jump_boost: 1.3,
+            power_up_ticks: 600,
+            star_ticks: 480,
+        }
+    }
+}
+
+/// How effects look and how long they last. None of it changes how a
+/// level plays.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnimationTuning {
+    /// Milliseconds time stands still when a tile is destroyed.
+    pub hit_stop_ms: u64,
+    /// How fast time runs while a player's death plays out, and for how
+    /// many milliseconds.
+    pub death_slow_motion: f32,
+    pub death_slow_motion_ms: u64,
+    /// Pull on particles, in tiles per tick per tick.
+    pub particle_gravity: f32,
+    /// How fast the lava surface waves and its glow throbs, in radians per tick.
+    pub lava_wave_speed: f32,
+    pub lava_glow_speed: f32,
+}
+
+impl Default for AnimationTuning {
+    fn default() -> Self {
+        AnimationTuning {
+            hit_stop_ms: 80,
+            death_slow_motion: 0.25,
+            death_slow_motion_ms: 1500,
+            particle_gravity: 0.01,
+            lava_wave_speed: 0.08,
+            lava_glow_speed: 0.05,
+        }
+    }
+}
+
+/// Keeps the tuning in sync with a file that may not exist.
+pub struct TuningWatcher {
+    path: String,
+    modified: Option<SystemTime>,
+    next_poll: Instant,
+    pub tuning: Tuning,
+}
+
+impl TuningWatcher {
+    pub fn new(path: &str) -> Self {
+        let mut watcher = TuningWatcher {
+            path: path.to_string(),
+            modified: None,
+            next_poll: Instant::now(),
+            tuning: Tuning::default(),
+        };
+        watcher.reload();
+        watcher
+    }
+
+    /// Reloads the file if it changed since the last check. A file that
+    /// fails to parse is reported and the previous values are kept.
+    pub fn poll(&mut self) -> bool {
+        let now = Instant::now();
+        if now < self.next_poll {
+            return false;
+        }
+        self.next_poll = now + POLL_INTERVAL;
+        if modified_time(&self.path) == self.modified {
+            return false;
+        }
+        self.reload()
+    }
+
+    fn reload(&mut self) -> bool {
+        self.modified = modified_time(&self.path);
+        if self.modified.is_none() {
+            return false;
+        }
+        let parsed = std::fs::read_to_string(&self.path)
+            .map_err(|error| error.to_string())
+            .and_then(|contents| toml::from_str::<Tuning>(&contents).map_err(|error| error.to_string()));
+        match parsed {
+            Ok(tuning) => {
+                info!("Loaded tuning from '{}'", self.path);
+                self.tuning = tuning;
+                true
+            }
+            Err(error) => {
+                warn!("Ignoring '{}': {}", self.path, error);
+                false
+            }
+        }
+    }
+}
+
+fn modified_time(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(Path::new(path)).and_then(|metadata| metadata.modified()).ok()
+}
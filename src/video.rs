@@ -0,0 +1,146 @@
+//! Video screen: the window's resolution, fullscreen and vsync, saved to
+//! the settings as they change. The window follows the resolution and
+//! fullscreen right away; vsync only when the game starts again.
+
+use log::info;
+use sdl2::keyboard::Keycode;
+
+use crate::audio::Music;
+use crate::bindings::{Action, Press};
+use crate::context::Context;
+use crate::error::Result;
+use crate::mouse::Mouse;
+use crate::paths::user_file;
+use crate::renderer::{Color, Renderer};
+use crate::settings::{WindowSettings, DEFAULT_SETTINGS};
+use crate::settings_menu::{Tab, TAB_BAR};
+use crate::state::State;
+use crate::text::text_size;
+
+const PIXEL: u32 = 3;
+const LABELS: [&str; 3] = ["RESOLUTION", "FULLSCREEN", "VSYNC"];
+/// Window sizes the resolution row goes through.
+const RESOLUTIONS: [(u32, u32); 5] = [(800, 600), (1024, 768), (1280, 720), (1600, 900), (1920, 1080)];
+
+pub struct VideoState {
+    selected: usize,
+    /// The window settings, refreshed after each change for rendering.
+    settings: WindowSettings,
+    /// Screen it was opened from.
+    back: Box<dyn State>,
+}
+
+impl VideoState {
+    pub fn new(context: &Context, back: Box<dyn State>) -> Self {
+        VideoState { selected: 0, settings: context.settings.window.clone(), back }
+    }
+
+    /// Changes the selected row: the resolution goes to the next or
+    /// previous size, the other rows flip.
+    fn adjust(&mut self, context: &mut Context, up: bool) -> Result<()> {
+        let window = &mut context.settings.window;
+        match self.selected {
+            0 => {
+                let current = RESOLUTIONS.iter().position(|size| *size == (window.width, window.height));
+                let index = match (current, up) {
+                    (Some(index), true) => { (index + 1) % RESOLUTIONS.len() }
+                    (Some(index), false) => { (index + RESOLUTIONS.len() - 1) % RESOLUTIONS.len() }
+                    (None, _) => { 0 }
+                };
+                (window.width, window.height) = RESOLUTIONS[index];
+                info!("Resized the window to {}x{}", window.width, window.height);
+            }
+            1 => { window.fullscreen = !window.fullscreen }
+            _ => { window.vsync = !window.vsync }
+        }
+        context.window_changed = true;
+        self.settings = context.settings.window.clone();
+        context.settings.save(&user_file(DEFAULT_SETTINGS))
+    }
+}
+
+/// Top of the screen's contents and the height of one line on a screen of
+/// `height`, under the bar of tabs.
+fn layout(height: u32) -> (i32, i32) {
+    let height = height as i32 - TAB_BAR;
+    let rows = LABELS.len() as i32 + 3;
+    let line_height = (text_size("X", PIXEL).1 as i32 * 2).min(height / (rows + 1)).max(1);
+    let top = TAB_BAR + (height - line_height * rows) / 2;
+    (top, line_height)
+}
+
+impl State for VideoState {
+    fn name(&self) -> &'static str {
+        "video"
+    }
+
+    fn music(&self) -> Music {
+        self.back.music()
+    }
+
+    fn handle_press(mut self: Box<Self>, context: &mut Context, press: &Press) -> Result<Box<dyn State>> {
+        if let Some(tab) = Tab::Video.switch(press) {
+            return Ok(tab.open(context, self.back));
+        }
+        if press.is(Action::Up) {
+            self.selected = (self.selected + LABELS.len() - 1) % LABELS.len();
+        } else if press.is(Action::Down) {
+            self.selected = (self.selected + 1) % LABELS.len();
+        } else if press.is(Action::MoveLeft) || press.keycode == Some(Keycode::Left) {
+            self.adjust(context, false)?;
+        } else if press.is(Action::MoveRight) || press.keycode == Some(Keycode::Right) || press.is(Action::Confirm) {
+            self.adjust(context, true)?;
+        } else if press.is(Action::Back) {
+            return Ok(self.back);
+        }
+        Ok(self)
+    }
+
+    /// Clicking a row selects and changes it.
+    fn handle_mouse(mut self: Box<Self>, context: &mut Context, mouse: &Mouse) -> Result<Box<dyn State>> {
+        if let Some(tab) = Tab::clicked(mouse) {
+            return Ok(tab.open(context, self.back));
+        }
+        if !mouse.is_click() {
+            return Ok(self);
+        }
+        let (top, line_height) = layout(mouse.screen.1);
+        let row = (mouse.y - top).div_euclid(line_height) - 2;
+        if row >= 0 && (row as usize) < LABELS.len() {
+            self.selected = row as usize;
+            self.adjust(context, true)?;
+        }
+        Ok(self)
+    }
+
+    fn tick(self: Box<Self>, _context: &mut Context) -> Result<Box<dyn State>> {
+        Ok(self)
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer) -> Result<()> {
+        Tab::Video.render_bar(renderer)?;
+        let (width, height) = renderer.output_size()?;
+        let (top, line_height) = layout(height);
+        let title = "VIDEO";
+        renderer.draw_text(title, (width as i32 - text_size(title, PIXEL).0 as i32) / 2, top, PIXEL, Color::WHITE)?;
+
+        let label_x = width as i32 / 2 - text_size("RESOLUTION  ", PIXEL).0 as i32;
+        let value_x = width as i32 / 2;
+        let switch = |on: bool| if on { "ON" } else { "OFF" }.to_string();
+        let values = [
+            format!("{}X{}", self.settings.width, self.settings.height),
+            switch(self.settings.fullscreen),
+            format!("{} ON RESTART", switch(self.settings.vsync)),
+        ];
+        for (index, (label, value)) in LABELS.iter().zip(values).enumerate() {
+            let y = top + line_height * (index as i32 + 2);
+            let color = if index == self.selected { Color::YELLOW } else { Color::GRAY };
+            renderer.draw_text(label, label_x, y, PIXEL, color)?;
+            renderer.draw_text(&value, value_x, y, PIXEL, color)?;
+        }
+
+        let hint = "LEFT AND RIGHT TO CHANGE, TAB FOR MORE, BACKSPACE TO RETURN";
+        let hint_y = top + line_height * (LABELS.len() as i32 + 3);
+        renderer.draw_text(hint, (width as i32 - text_size(hint, 2).0 as i32) / 2, hint_y, 2, Color::GRAY)
+    }
+}
@@ -0,0 +1,184 @@
+//! Sound screen: sliders for the master, music and sound effect volumes,
+//! a row muting everything and one showing captions for sounds, saved to
+//! the settings as they change.
+
+use sdl2::keyboard::Keycode;
+
+use crate::audio::Music;
+use crate::bindings::{Action, Press};
+use crate::context::Context;
+use crate::error::Result;
+use crate::mouse::Mouse;
+use crate::paths::user_file;
+use crate::renderer::{Color, Rect, Renderer};
+use crate::settings::{AudioSettings, DEFAULT_SETTINGS};
+use crate::settings_menu::{Tab, TAB_BAR};
+use crate::state::State;
+use crate::text::text_size;
+
+const PIXEL: u32 = 3;
+const LABELS: [&str; 5] = ["MASTER", "MUSIC", "EFFECTS", "MUTE", "CAPTIONS"];
+/// How much a volume changes per press.
+const STEP: u8 = 10;
+/// Width of a slider as a share of the screen.
+const SLIDER_SHARE: u32 = 3;
+
+pub struct VolumeState {
+    selected: usize,
+    /// The audio settings, refreshed after each change for rendering.
+    settings: AudioSettings,
+    /// Screen it was opened from.
+    back: Box<dyn State>,
+}
+
+impl VolumeState {
+    pub fn new(context: &Context, back: Box<dyn State>) -> Self {
+        VolumeState { selected: 0, settings: context.settings.audio.clone(), back }
+    }
+
+    /// Volume on the slider of `row`, none for the rows turning something
+    /// on or off.
+    fn slider(settings: &mut AudioSettings, row: usize) -> Option<&mut u8> {
+        match row {
+            0 => { Some(&mut settings.volume) }
+            1 => { Some(&mut settings.music) }
+            2 => { Some(&mut settings.effects) }
+            _ => { None }
+        }
+    }
+
+    /// Turns muting or captions on or off, by `row`.
+    fn flip(settings: &mut AudioSettings, row: usize) {
+        if row == 3 {
+            settings.muted = !settings.muted;
+        } else {
+            settings.captions = !settings.captions;
+        }
+    }
+
+    /// Applies `change` to the audio settings, then saves them.
+    fn change(&mut self, context: &mut Context, change: impl FnOnce(&mut AudioSettings)) -> Result<()> {
+        change(&mut context.settings.audio);
+        context.apply_audio_settings();
+        self.settings = context.settings.audio.clone();
+        context.settings.save(&user_file(DEFAULT_SETTINGS))
+    }
+
+    /// Moves the selected slider a step up or down, or flips its row.
+    fn adjust(&mut self, context: &mut Context, up: bool) -> Result<()> {
+        let row = self.selected;
+        self.change(context, |settings| match VolumeState::slider(settings, row) {
+            Some(volume) if up => { *volume = volume.saturating_add(STEP).min(100) }
+            Some(volume) => { *volume = volume.saturating_sub(STEP) }
+            None => { VolumeState::flip(settings, row) }
+        })
+    }
+}
+
+/// Top of the screen's contents and the height of one line on a screen of
+/// `height`, under the bar of tabs.
+fn layout(height: u32) -> (i32, i32) {
+    let height = height as i32 - TAB_BAR;
+    let rows = LABELS.len() as i32 + 3;
+    let line_height = (text_size("X", PIXEL).1 as i32 * 2).min(height / (rows + 1)).max(1);
+    let top = TAB_BAR + (height - line_height * rows) / 2;
+    (top, line_height)
+}
+
+/// Left edge and width of the sliders on a screen of `width`.
+fn slider_span(width: u32) -> (i32, i32) {
+    (width as i32 / 2, (width / SLIDER_SHARE) as i32)
+}
+
+impl State for VolumeState {
+    fn name(&self) -> &'static str {
+        "sound"
+    }
+
+    fn music(&self) -> Music {
+        self.back.music()
+    }
+
+    fn handle_press(mut self: Box<Self>, context: &mut Context, press: &Press) -> Result<Box<dyn State>> {
+        if let Some(tab) = Tab::Sound.switch(press) {
+            return Ok(tab.open(context, self.back));
+        }
+        if press.is(Action::Up) {
+            self.selected = (self.selected + LABELS.len() - 1) % LABELS.len();
+        } else if press.is(Action::Down) {
+            self.selected = (self.selected + 1) % LABELS.len();
+        } else if press.is(Action::MoveLeft) || press.keycode == Some(Keycode::Left) {
+            self.adjust(context, false)?;
+        } else if press.is(Action::MoveRight) || press.keycode == Some(Keycode::Right) || press.is(Action::Confirm) {
+            self.adjust(context, true)?;
+        } else if press.is(Action::Back) {
+            return Ok(self.back);
+        }
+        Ok(self)
+    }
+
+    /// Clicking a slider sets its volume to where it was clicked, clicking
+    /// another row flips it.
+    fn handle_mouse(mut self: Box<Self>, context: &mut Context, mouse: &Mouse) -> Result<Box<dyn State>> {
+        if let Some(tab) = Tab::clicked(mouse) {
+            return Ok(tab.open(context, self.back));
+        }
+        if !mouse.is_click() {
+            return Ok(self);
+        }
+        let (top, line_height) = layout(mouse.screen.1);
+        let row = (mouse.y - top).div_euclid(line_height) - 2;
+        if row < 0 || row as usize >= LABELS.len() {
+            return Ok(self);
+        }
+        self.selected = row as usize;
+        let (left, width) = slider_span(mouse.screen.0);
+        let volume = ((mouse.x - left) * 100 / width.max(1)).clamp(0, 100) as u8;
+        let row = self.selected;
+        self.change(context, |settings| match VolumeState::slider(settings, row) {
+            Some(slider) => { *slider = volume }
+            None => { VolumeState::flip(settings, row) }
+        })?;
+        Ok(self)
+    }
+
+    fn tick(self: Box<Self>, _context: &mut Context) -> Result<Box<dyn State>> {
+        Ok(self)
+    }
+
+    fn render(&self, renderer: &mut dyn Renderer) -> Result<()> {
+        Tab::Sound.render_bar(renderer)?;
+        let (width, height) = renderer.output_size()?;
+        let (top, line_height) = layout(height);
+        let title = "SOUND";
+        renderer.draw_text(title, (width as i32 - text_size(title, PIXEL).0 as i32) / 2, top, PIXEL, Color::WHITE)?;
+
+        let label_x = width as i32 / 2 - text_size("CAPTIONS  ", PIXEL).0 as i32;
+        let (slider_x, slider_width) = slider_span(width);
+        let slider_height = text_size("X", PIXEL).1;
+        let volumes = [Some(self.settings.volume), Some(self.settings.music), Some(self.settings.effects), None, None];
+        let switches = [false, false, false, self.settings.muted, self.settings.captions];
+        for (index, ((label, volume), on)) in LABELS.iter().zip(volumes).zip(switches).enumerate() {
+            let y = top + line_height * (index as i32 + 2);
+            let color = if index == self.selected { Color::YELLOW } else { Color::GRAY };
+            renderer.draw_text(label, label_x, y, PIXEL, color)?;
+            match volume {
+                Some(volume) => {
+                    let filled = slider_width * volume as i32 / 100;
+                    renderer.draw_rect(Rect::new(slider_x, y, slider_width as u32, slider_height), color)?;
+                    if filled > 0 {
+                        renderer.fill_rect(Rect::new(slider_x, y, filled as u32, slider_height), color)?;
+                    }
+                }
+                None => {
+                    let value = if on { "ON" } else { "OFF" };
+                    renderer.draw_text(value, slider_x, y, PIXEL, color)?;
+                }
+            }
+        }
+
+        let hint = "LEFT AND RIGHT TO CHANGE, TAB FOR MORE, BACKSPACE TO RETURN";
+        let hint_y = top + line_height * (LABELS.len() as i32 + 3);
+        renderer.draw_text(hint, (width as i32 - text_size(hint, 2).0 as i32) / 2, hint_y, 2, Color::GRAY)
+    }
+}
@@ -0,0 +1,26 @@
+//! Water, `w` in the map, that bodies sink slowly through and players
+//! swim up in by holding jump. Rectangles of water can carry a current
+//! that pushes everything swimming in it, set in the level's info file,
+//! see [`crate::level::LevelInfo`].
+
+use serde::{Deserialize, Serialize};
+
+/// A rectangle of tiles whose water flows one way.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Current {
+    /// Top left tile of the rectangle.
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+    /// Speed added to what swims in it, in tiles per tick.
+    pub push: (f32, f32),
+}
+
+impl Current {
+    /// Whether the point `(x, y)`, in tile units, is inside the rectangle.
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.x as f32 && x < (self.x + self.width) as f32
+            && y >= self.y as f32 && y < (self.y + self.height) as f32
+    }
+}
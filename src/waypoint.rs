@@ -0,0 +1,198 @@
+//! Named paths of waypoints declared in a map below its rows, one per
+//! line: `path`, the name, how it is followed and the tiles it goes
+//! through, e.g. `path ridge mode=loop 2,3 9,3 9,6`. Saws and flying
+//! enemies listed with `path=ridge` follow it instead of their rail or
+//! row, going once, back and forth or round and round.
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{GameError, Result};
+use crate::level;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PathMode {
+    /// From the first waypoint to the last, stopping there.
+    Linear,
+    /// Back and forth between the first waypoint and the last.
+    #[default]
+    PingPong,
+    /// On from the last waypoint back to the first, round and round.
+    Loop,
+}
+
+impl PathMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PathMode::Linear => { "linear" }
+            PathMode::PingPong => { "ping-pong" }
+            PathMode::Loop => { "loop" }
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<PathMode> {
+        [PathMode::Linear, PathMode::PingPong, PathMode::Loop].into_iter().find(|mode| mode.name() == name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WaypointPath {
+    pub name: String,
+    pub mode: PathMode,
+    /// Tiles the path goes through, in order.
+    pub points: Vec<(usize, usize)>,
+}
+
+impl WaypointPath {
+    /// Parses the words of a path line after `path`; `line` is its number,
+    /// for errors.
+    pub fn parse(words: &str, line: usize) -> Result<WaypointPath> {
+        let error = |message: String| GameError::InvalidMap(format!("line {}: {}", line, message));
+        let mut words = words.split_whitespace();
+        let name = words.next().ok_or_else(|| error("path has no name".to_string()))?.to_string();
+        let mut path = WaypointPath { name, mode: PathMode::default(), points: Vec::new() };
+        for word in words {
+            if let Some(mode) = word.strip_prefix("mode=") {
+                path.mode = PathMode::from_name(mode).ok_or_else(|| error(format!("unknown path mode '{}'", mode)))?;
+                continue;
+            }
+            let point = level::parse_tile(word)
+                .ok_or_else(|| error(format!("expected a tile like 3,4, found '{}'", word)))?;
+            path.points.push(point);
+        }
+        if path.points.is_empty() {
+            return Err(error(format!("path {} has no waypoints", path.name)));
+        }
+        Ok(path)
+    }
+
+    /// Follows the path from its first waypoint.
+    pub fn follow(&self) -> Follow {
+        Follow {
+            points: self.points.iter().map(|(x, y)| (*x as f32 + 0.5, *y as f32 + 0.5)).collect(),
+            mode: self.mode,
+            travelled: 0.0,
+        }
+    }
+}
+
+impl std::fmt::Display for WaypointPath {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "path {} mode={}", self.name, self.mode.name())?;
+        for (x, y) in &self.points {
+            write!(formatter, " {},{}", x, y)?;
+        }
+        Ok(())
+    }
+}
+
+/// Makes an entity move along a path of waypoints on its own, rather than
+/// through physics or its usual movement.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Follow {
+    /// Where the center of the entity passes, in tile units.
+    pub points: Vec<(f32, f32)>,
+    pub mode: PathMode,
+    /// Tiles travelled since the level started.
+    pub travelled: f32,
+}
+
+impl Follow {
+    /// Every leg of the path, in order.
+    fn legs(&self) -> impl Iterator<Item = ((f32, f32), (f32, f32))> + '_ {
+        let closing = (self.mode == PathMode::Loop && self.points.len() > 2)
+            .then(|| (self.points[self.points.len() - 1], self.points[0]));
+        self.points.windows(2).map(|pair| (pair[0], pair[1])).chain(closing)
+    }
+
+    /// Tiles from the first waypoint to the last, or back to the first
+    /// when the path loops.
+    fn length(&self) -> f32 {
+        self.legs().map(|(from, to)| (to.0 - from.0).hypot(to.1 - from.1)).sum()
+    }
+
+    /// Where the center of the entity is once it has travelled `distance`
+    /// tiles.
+    pub fn point_at(&self, distance: f32) -> (f32, f32) {
+        let length = self.length();
+        let Some(first) = self.points.first().copied() else { return (0.0, 0.0) };
+        if length <= 0.0 {
+            return first;
+        }
+        let mut distance = match self.mode {
+            PathMode::Linear => { distance.clamp(0.0, length) }
+            PathMode::PingPong => {
+                let distance = distance.rem_euclid(2.0 * length);
+                if distance > length { 2.0 * length - distance } else { distance }
+            }
+            PathMode::Loop => { distance.rem_euclid(length) }
+        };
+        let mut last = first;
+        for (from, to) in self.legs() {
+            let leg = (to.0 - from.0).hypot(to.1 - from.1);
+            if distance <= leg && leg > 0.0 {
+                let along = distance / leg;
+                return (from.0 + (to.0 - from.0) * along, from.1 + (to.1 - from.1) * along);
+            }
+            distance -= leg;
+            last = to;
+        }
+        last
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn follow(words: &str) -> Follow {
+        WaypointPath::parse(words, 1).unwrap().follow()
+    }
+
+    fn assert_near(point: (f32, f32), expected: (f32, f32)) {
+        assert!((point.0 - expected.0).abs() < 1e-4 && (point.1 - expected.1).abs() < 1e-4, "{:?} is not {:?}", point, expected);
+    }
+
+    #[test]
+    fn parses_and_writes_paths() {
+        let path = WaypointPath::parse("ridge mode=loop 2,3 9,3 9,6", 1).unwrap();
+        assert_eq!(path, WaypointPath { name: "ridge".to_string(), mode: PathMode::Loop, points: vec![(2, 3), (9, 3), (9, 6)] });
+        assert_eq!(path.to_string(), "path ridge mode=loop 2,3 9,3 9,6");
+        assert_eq!(WaypointPath::parse("drop 1,1", 1).unwrap().mode, PathMode::PingPong);
+    }
+
+    #[test]
+    fn rejects_bad_paths() {
+        let error = |words: &str| WaypointPath::parse(words, 7).unwrap_err().to_string();
+        assert_eq!(error(""), "invalid map: line 7: path has no name");
+        assert_eq!(error("ridge"), "invalid map: line 7: path ridge has no waypoints");
+        assert_eq!(error("ridge mode=zigzag 1,1"), "invalid map: line 7: unknown path mode 'zigzag'");
+        assert_eq!(error("ridge 1;1"), "invalid map: line 7: expected a tile like 3,4, found '1;1'");
+    }
+
+    #[test]
+    fn linear_paths_stop_at_the_ends() {
+        let follow = follow("a mode=linear 0,0 2,0");
+        assert_near(follow.point_at(-1.0), (0.5, 0.5));
+        assert_near(follow.point_at(1.0), (1.5, 0.5));
+        assert_near(follow.point_at(5.0), (2.5, 0.5));
+    }
+
+    #[test]
+    fn ping_pong_paths_come_back() {
+        let follow = follow("a 0,0 2,0");
+        assert_near(follow.point_at(3.0), (1.5, 0.5));
+        assert_near(follow.point_at(4.0), (0.5, 0.5));
+        assert_near(follow.point_at(5.0), (1.5, 0.5));
+    }
+
+    #[test]
+    fn loops_close_back_to_the_first_waypoint() {
+        let follow = follow("a mode=loop 0,0 2,0 2,2");
+        let length = 4.0 + 8.0f32.sqrt();
+        assert_near(follow.point_at(4.0), (2.5, 2.5));
+        assert_near(follow.point_at(4.0 + 2.0f32.sqrt()), (1.5, 1.5));
+        assert_near(follow.point_at(length + 1.0), (1.5, 0.5));
+        assert_near(self::follow("b 3,4").point_at(10.0), (3.5, 4.5));
+    }
+}
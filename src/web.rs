@@ -0,0 +1,30 @@
+//! Glue for running in the browser through emscripten, where the game
+//! cannot own the main loop and must hand the browser a frame callback.
+
+use std::cell::RefCell;
+use std::os::raw::c_int;
+
+extern "C" {
+    fn emscripten_set_main_loop(func: extern "C" fn(), fps: c_int, simulate_infinite_loop: c_int);
+    fn emscripten_cancel_main_loop();
+}
+
+thread_local! {
+    static FRAME: RefCell<Option<Box<dyn FnMut() -> bool>>> = RefCell::new(None);
+}
+
+extern "C" fn call_frame() {
+    let running = FRAME.with(|frame| frame.borrow_mut().as_mut().is_some_and(|frame| frame()));
+    if !running {
+        FRAME.with(|frame| frame.borrow_mut().take());
+        unsafe { emscripten_cancel_main_loop() };
+    }
+}
+
+/// Calls `frame` once per browser animation frame until it returns false.
+/// Never returns: emscripten unwinds the stack to give control to the
+/// browser, and the callback owns everything the game needs.
+pub fn set_main_loop(frame: impl FnMut() -> bool + 'static) {
+    FRAME.with(|slot| *slot.borrow_mut() = Some(Box::new(frame)));
+    unsafe { emscripten_set_main_loop(call_frame, 0, 1) };
+}